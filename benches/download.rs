@@ -0,0 +1,83 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Benchmarks writing and hashing a downloaded installer.
+//!
+//! The chunks are served from memory, so only the writer is measured:
+//! the one writing and hashing every chunk on the runtime, as before [`InstallerWriter`],
+//! and [`InstallerWriter`], which does both on a blocking worker.
+//!
+//! Run with `cargo bench --bench download`, the size in MiB can be given with `STREMIO_SERVICE_BENCH_MIB`.
+
+use std::path::Path;
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use sha2::{Digest, Sha256};
+use stremio_service::updater::InstallerWriter;
+use tokio::io::AsyncWriteExt;
+
+/// The size of the chunks returned by reqwest
+const CHUNK_SIZE: usize = 16 * 1024;
+
+async fn per_chunk(file: &Path, chunks: &[Bytes]) -> String {
+    let mut sha256 = Sha256::new();
+    let mut file = tokio::fs::File::create(file).await.unwrap();
+    for chunk in chunks {
+        file.write_all(chunk).await.unwrap();
+        sha256.update(chunk);
+    }
+    file.flush().await.unwrap();
+
+    format!("{:x}", sha256.finalize())
+}
+
+async fn installer_writer(file: &Path, chunks: &[Bytes]) -> String {
+    let writer = InstallerWriter::create(file.to_owned());
+    for chunk in chunks {
+        writer.write(chunk.clone()).await.unwrap();
+    }
+
+    writer.finish().await.unwrap()
+}
+
+fn writers(c: &mut Criterion) {
+    // `cargo test --all-targets` runs the benchmarks once with `--test`
+    let default_mib = match std::env::args().any(|arg| arg == "--test") {
+        true => 1,
+        false => 128,
+    };
+    let mib = std::env::var("STREMIO_SERVICE_BENCH_MIB")
+        .ok()
+        .and_then(|mib| mib.parse::<usize>().ok())
+        .unwrap_or(default_mib);
+    let contents = Bytes::from(
+        (0..mib * 1024 * 1024)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>(),
+    );
+    let chunks = contents
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| contents.slice_ref(chunk))
+        .collect::<Vec<_>>();
+    let expected = hex::encode(Sha256::digest(&contents));
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("installer");
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group(format!("write {mib} MiB in {CHUNK_SIZE} byte chunks"));
+    group
+        .throughput(Throughput::Bytes(contents.len() as u64))
+        .sample_size(10);
+    group.bench_function("per chunk", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { assert_eq!(expected, per_chunk(&file, &chunks).await) })
+    });
+    group.bench_function("InstallerWriter", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { assert_eq!(expected, installer_writer(&file, &chunks).await) })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, writers);
+criterion_main!(benches);
@@ -1,9 +1,19 @@
 #!/bin/sh
+cargo run --bin bundle-flatpak
+
 python flatpak/flatpak-builder-tools/cargo/flatpak-cargo-generator.py Cargo.lock -o flatpak/cargo-sources.json
 
 server_version=$(sed -n '/package.metadata.server/,$p' Cargo.toml | grep -m 1 'version =' | awk -F'"' '{print $2}')
 server_file_url=https://dl.strem.io/server/$server_version/desktop/server.js
-sha256_checksum=$(curl -sL $server_file_url | shasum -a 256 | awk '{print $1}')
+# the pinned or the published checksum, verified by flatpak-builder after the download
+sha256_checksum=$(sed -n '/package.metadata.server/,/^\[/p' Cargo.toml | grep -m 1 '^sha256 =' | awk -F'"' '{print $2}')
+if [ -z "$sha256_checksum" ]; then
+  sha256_checksum=$(curl -sfL $server_file_url.sha256 | awk '{print $1}')
+fi
+if [ -z "$sha256_checksum" ]; then
+  echo "Failed to get the SHA-256 checksum of $server_file_url" >&2
+  exit 1
+fi
 server_source_template='{
   "type": "file",
   "url": "{server_file_url}",
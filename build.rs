@@ -1,7 +1,8 @@
-use std::{env::consts::OS, error::Error, fs, path::PathBuf};
+use std::{error::Error, fs, path::PathBuf, process::Command};
 
 use once_cell::sync::Lazy;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use url::Url;
 
 #[cfg(target_os = "windows")]
@@ -18,12 +19,27 @@ struct ServerMetadata {
     ///
     /// It can be semantic versioning or other
     version: String,
+    /// Pins the SHA-256 checksum of server.js.
+    ///
+    /// Without it the checksum published next to server.js (`server.js.sha256`) is used.
+    sha256: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct UpdaterMetadata {
+    /// The minisign public key the updates are signed with, the builds without it refuse the updates
+    /// and the `bundled` builds fail without it.
+    ///
+    /// `STREMIO_SERVICE_UPDATE_PUBLIC_KEY` takes precedence.
+    public_key: Option<String>,
 }
 
 /// Cargo.toml metadata which we're interested in
 #[derive(Clone, Debug, Deserialize)]
 struct Metadata {
     server: ServerMetadata,
+    #[serde(default)]
+    updater: UpdaterMetadata,
 }
 
 const SUPPORTED_OS: &[&str] = &["linux", "macos", "windows"];
@@ -31,37 +47,82 @@ const SUPPORTED_OS: &[&str] = &["linux", "macos", "windows"];
 fn main() -> Result<(), Box<dyn Error>> {
     println!("cargo:rerun-if-changed=src/");
 
-    if !SUPPORTED_OS.contains(&OS) {
+    // the build script runs on the host, the resources are selected for the target when cross-compiling
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS")?;
+    if !SUPPORTED_OS.contains(&target_os.as_str()) {
         panic!(
             "OS {} not supported, supported OSes are: {:?}",
-            OS, SUPPORTED_OS
+            target_os, SUPPORTED_OS
         )
     }
 
     let current_dir = std::env::current_dir()?;
     let resources = current_dir.join("resources");
-    let platform_bins = resources.join("bin").join(OS);
+    let platform_bins = resources.join("bin").join(&target_os);
 
-    #[cfg(not(feature = "offline-build"))]
-    {
-        let server_js_target = platform_bins.join("server.js");
-        // keeps track of the server.js version in order to update it if versions mismatch
-        let server_js_version_file = platform_bins.join("server_version.txt");
+    let Metadata {
+        server:
+            ServerMetadata {
+                version: manifest_version,
+                sha256: manifest_sha256,
+            },
+        updater,
+    } = {
+        let manifest_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+            .expect("Cannot read the manifest metadata");
 
-        let manifest_version = {
-            let manifest_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
-            let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
-                .expect("Cannot read the manifest metadata");
+        manifest
+            .package
+            .expect("Failed to parse package")
+            .metadata
+            .expect("Failed to parse manifest.package.metadata")
+    };
 
-            let server_metadata = manifest
-                .package
-                .expect("Failed to parse package")
-                .metadata
-                .expect("Failed to parse manifest.package.metadata")
-                .server;
+    println!("cargo:rerun-if-env-changed=STREMIO_SERVICE_UPDATE_PUBLIC_KEY");
+    let update_public_key = std::env::var("STREMIO_SERVICE_UPDATE_PUBLIC_KEY")
+        .ok()
+        .or(updater.public_key)
+        .unwrap_or_default();
+    // the installed builds refuse the updates without a key, they'd never update
+    if cfg!(feature = "bundled") && update_public_key.trim().is_empty() {
+        return Err(
+            "The `bundled` builds need the update public key, set `public_key` of \
+            `[package.metadata.updater]` or `STREMIO_SERVICE_UPDATE_PUBLIC_KEY`"
+                .into(),
+        );
+    }
+    if !update_public_key.trim().is_empty() {
+        check_public_key(&update_public_key)
+            .map_err(|err| format!("Invalid update public key: {err}"))?;
+    }
+    println!(
+        "cargo:rustc-env=STREMIO_SERVICE_UPDATE_PUBLIC_KEY={}",
+        update_public_key.trim()
+    );
 
-            server_metadata.version
-        };
+    embed_build_info(&current_dir, &manifest_version);
+
+    // the build of Stremio Web embedded by `rust-embed`, it's read at compile time
+    println!("cargo:rerun-if-env-changed=STREMIO_WEB_BUILD");
+    if cfg!(feature = "embedded-web") {
+        let web_build = std::env::var_os("STREMIO_WEB_BUILD").map(PathBuf::from);
+        if !web_build.is_some_and(|dir| dir.join("index.html").is_file()) {
+            return Err("The `embedded-web` builds need `STREMIO_WEB_BUILD`, \
+                the `build` directory of a Stremio Web `npm run build`"
+                .into());
+        }
+    }
+
+    // the system server.js is used without the bundled one
+    if !cfg!(any(
+        feature = "offline-build",
+        feature = "no-bundled-server"
+    )) {
+        let server_js_target = platform_bins.join("server.js");
+        // keeps track of the server.js version and its verified checksum
+        // in order to update it if versions mismatch or the file was changed
+        let server_js_version_file = platform_bins.join("server_version.txt");
 
         let download_server_js = || -> Result<(), Box<dyn Error>> {
             let version_url = STREMIO_SERVER_URL
@@ -69,34 +130,72 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .join(&format!("{manifest_version}/desktop/server.js"))
                 .expect("Should never fail");
 
+            let expected_sha256 = match &manifest_sha256 {
+                Some(sha256) => sha256.to_lowercase(),
+                None => fetch_published_sha256(&version_url)?,
+            };
+
             let server_js_file = reqwest::blocking::get(version_url)?
                 .error_for_status()?
                 .bytes()?;
 
+            let sha256 = hex::encode(Sha256::digest(&server_js_file));
+            if sha256 != expected_sha256 {
+                return Err(format!(
+                    "server.js {manifest_version} failed the integrity check, expected SHA-256 {expected_sha256} but got {sha256}"
+                )
+                .into());
+            }
+
             fs::write(&server_js_target, server_js_file)?;
             // replace content in the version file
-            fs::write(&server_js_version_file, &manifest_version)?;
+            fs::write(
+                &server_js_version_file,
+                format!("{manifest_version} {sha256}"),
+            )?;
             Ok(())
         };
 
-        match (
-            server_js_target.exists(),
-            fs::read_to_string(&server_js_version_file).ok(),
-        ) {
-            // if server.js does not exist (no matter if the version file exist)
-            // or if the server.js file exist but we don't have a version file.
-            (false, _) | (true, None) => download_server_js()?,
-            (true, Some(version)) => {
-                if manifest_version != version {
-                    download_server_js()?
+        // server.js is downloaded again if it doesn't exist,
+        // the version file is missing or from before the integrity checks,
+        // or if the version or the checksum have changed
+        let is_up_to_date = match fs::read_to_string(&server_js_version_file) {
+            Ok(contents) => match contents.trim().split_once(' ') {
+                Some((version, sha256)) => {
+                    version == manifest_version
+                        && manifest_sha256.as_ref().is_none_or(|manifest_sha256| {
+                            manifest_sha256.eq_ignore_ascii_case(sha256)
+                        })
+                        && fs::read(&server_js_target).is_ok_and(|server_js_file| {
+                            hex::encode(Sha256::digest(server_js_file)) == sha256
+                        })
                 }
-                // else do nothing, we have the same version
-            }
+                None => false,
+            },
+            Err(_) => false,
+        };
+        if !is_up_to_date {
+            download_server_js()?
         }
     }
 
+    // the verified checksum of the bundled server.js, it's repaired by the service when it doesn't match
+    let server_sha256 = if cfg!(feature = "no-bundled-server") {
+        String::new()
+    } else {
+        fs::read_to_string(platform_bins.join("server_version.txt"))
+            .ok()
+            .and_then(|contents| {
+                let (version, sha256) = contents.trim().split_once(' ')?;
+                (version == manifest_version).then(|| sha256.to_owned())
+            })
+            .unwrap_or_default()
+    };
+    println!("cargo:rustc-env=STREMIO_SERVICE_SERVER_SHA256={server_sha256}");
+
+    // the resources are edited with the Windows tools, so only when building for Windows on Windows
     #[cfg(target_os = "windows")]
-    {
+    if target_os == "windows" {
         let now = Local::now();
         let copyright = format!("Copyright © {} Smart Code OOD", now.year());
         let description =
@@ -130,6 +229,96 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Embeds the build details read by `stremio_service::build_info`.
+///
+/// The build date respects `SOURCE_DATE_EPOCH` for reproducible builds
+/// and the commit can be set with `STREMIO_SERVICE_GIT_COMMIT` when building outside of the git repository.
+fn embed_build_info(current_dir: &std::path::Path, server_version: &str) {
+    // rebuild on new commits and checkouts
+    let git_dir = current_dir.join(".git");
+    let head = git_dir.join("HEAD");
+    if head.exists() {
+        println!("cargo:rerun-if-changed={}", head.display());
+        if let Some(reference) = fs::read_to_string(&head)
+            .ok()
+            .and_then(|head| head.trim().strip_prefix("ref: ").map(str::to_owned))
+        {
+            println!(
+                "cargo:rerun-if-changed={}",
+                git_dir.join(reference).display()
+            );
+        }
+    }
+    println!("cargo:rerun-if-env-changed=STREMIO_SERVICE_GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let git_commit = std::env::var("STREMIO_SERVICE_GIT_COMMIT")
+        .ok()
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "--short=10", "HEAD"])
+                .current_dir(current_dir)
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+                .map(|commit| commit.trim().to_owned())
+        })
+        .unwrap_or_else(|| "unknown".to_owned());
+
+    let build_date = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .and_then(|epoch| chrono::NaiveDateTime::from_timestamp_opt(epoch, 0))
+        .map(|date| chrono::DateTime::<chrono::Utc>::from_utc(date, chrono::Utc))
+        .unwrap_or_else(chrono::Utc::now);
+
+    println!("cargo:rustc-env=STREMIO_SERVICE_GIT_COMMIT={git_commit}");
+    println!(
+        "cargo:rustc-env=STREMIO_SERVICE_BUILD_DATE={}",
+        build_date.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    );
+    println!(
+        "cargo:rustc-env=STREMIO_SERVICE_TARGET={}",
+        std::env::var("TARGET").expect("Cargo always sets the target")
+    );
+    println!("cargo:rustc-env=STREMIO_SERVICE_SERVER_VERSION={server_version}");
+}
+
+/// Checks the minisign public key like `stremio_service::minisign::PublicKey::parse`,
+/// the binary parses the embedded key on every start.
+fn check_public_key(public_key: &str) -> Result<(), Box<dyn Error>> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let encoded = public_key
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+        .ok_or("Empty public key")?;
+    let decoded = STANDARD.decode(encoded)?;
+    if decoded.len() != 42 || !decoded.starts_with(b"Ed") {
+        return Err("Not a minisign Ed25519 public key".into());
+    }
+    ed25519_dalek::VerifyingKey::from_bytes(decoded[10..].try_into()?)?;
+
+    Ok(())
+}
+
+/// Fetches the SHA-256 checksum published next to the file, in the `sha256sum` format.
+fn fetch_published_sha256(file_url: &Url) -> Result<String, Box<dyn Error>> {
+    let checksum_url = Url::parse(&format!("{file_url}.sha256"))?;
+    let checksum = reqwest::blocking::get(checksum_url.clone())?
+        .error_for_status()?
+        .text()?;
+
+    match checksum.split_whitespace().next() {
+        Some(sha256) if sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(sha256.to_lowercase())
+        }
+        _ => Err(format!("Invalid SHA-256 checksum at {checksum_url}").into()),
+    }
+}
+
 #[cfg(target_os = "windows")]
 fn edit_exe_resources(
     file_path: &PathBuf,
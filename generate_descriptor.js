@@ -1,6 +1,6 @@
 #!/usr/bin/env node
 
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
 // This script generates an auto update descriptor for a given tag.
 // It will get the files for the tag, calculate their hashes, and upload
@@ -21,6 +21,9 @@ const DOWNLOAD_ENDPOINT = "https://dl.strem.io/stremio-service/";
 const OS_EXT = {
     ".exe": "windows",
     ".dmg": "macos",
+    // the AppImage and the tarball (`.tar.gz`) are updated by the service itself
+    ".AppImage": "linux",
+    ".gz": "linux",
 };
 const VERSION_REGEX = /^v(\d+\.\d+\.\d+).*$/;
 
@@ -160,6 +163,24 @@ const s3Hash = (path) => {
     });
 };
 
+// Downloads the minisign signature published next to the file, undefined when it's not signed
+const fetchSignature = (url) => {
+    return new Promise((resolve, reject) => {
+        https
+            .get(`${url}.minisig`, (res) => {
+                if (res.statusCode !== 200) {
+                    res.resume();
+                    return resolve();
+                }
+                let signature = "";
+                res.setEncoding("utf8");
+                res.on("data", (chunk) => (signature += chunk));
+                res.on("end", () => resolve(signature));
+            })
+            .on("error", reject);
+    });
+};
+
 // Parses a line from the S3 listing
 const parseS3Listing = (tag) => (line) => {
     const path = (
@@ -191,9 +212,12 @@ const calculateFileChecksums = async (files) =>
         files.map(async (file) => {
             const checksum = await s3Hash(file.url);
             log("The hash for", file.name, "is", checksum);
+            const signature = await fetchSignature(file.url);
+            if (!signature) log("No signature found for", file.name);
             return {
                 ...file,
                 checksum,
+                signature,
             };
         })
     );
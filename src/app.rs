@@ -1,302 +1,3148 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, ensure, Context, Error};
 use fslock::LockFile;
-use log::{error, info};
+use log::{error, info, warn};
 use rand::Rng;
 use rust_embed::RustEmbed;
-#[cfg(all(feature = "bundled", any(target_os = "linux", target_os = "macos")))]
-use std::path::Path;
-use std::path::PathBuf;
+use semver::Version;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 use tao::{
-    event::Event,
-    event_loop::{ControlFlow, EventLoop},
-    menu::{ContextMenu, MenuId, MenuItemAttributes},
-    system_tray::{SystemTray, SystemTrayBuilder},
+    event::{Event, StartCause},
+    event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget},
+    menu::{ContextMenu, CustomMenuItem, MenuId, MenuItemAttributes},
+    platform::run_return::EventLoopExtRunReturn,
+    system_tray::{Icon, SystemTray, SystemTrayBuilder},
     TrayId,
 };
+use tokio::sync::watch;
 use url::Url;
 
+/// Stops [`Application::run_headless`], re-exported for the embedders
+pub use tokio_util::sync::CancellationToken;
+
 use crate::{
     args::Args,
+    autostart,
+    browser::{self, Browser},
+    build_info::BUILD_INFO,
     constants::{STREMIO_URL, UPDATE_ENDPOINT},
+    control::ControlApi,
+    crash,
+    deeplink::{is_stremio_host, is_web_ui_link, sanitize, WebUiLink, MAX_URL_LENGTH},
+    diagnostics::{self, Diagnostics},
+    directories::Directories,
+    discovery::{self, Discovery},
+    error::ServiceError,
+    https::{HttpsConfig, HttpsProxy},
+    instance::{self, Message},
+    link_history::{LinkAction, LinkHistory, LinkRecord},
+    notifications::{Notification, Notifier},
+    pool::{PooledServer, ServerPool},
+    pressure::{Pressure, PressureChange, PressureMonitor},
+    profile::Profile,
+    protocol::{Magnet, Route, ServicePage},
+    qr::QrCode,
+    rollback::Versions,
+    sandbox::Sandbox,
+    secrets::{self, Secrets},
     server::Server,
-    updater::Updater,
-    util::load_icon,
+    server_settings::{self, ServerSettings},
+    settings::{AdditionalServer, SettingDescription, Settings, WebUi},
+    staging::Staging,
+    status::{ServerInfo, ServerTrayStatus, TrayStatus},
+    systemd,
+    throttle::WriteLimit,
+    tls::{self, Roots},
+    transcoding::{self, HwAccel, HwTranscoding},
+    updater::{
+        self, Channel, DownloadProgress, Installation, ProgressHandler, ServerUpdate, Update,
+        UpdateMode, Updater,
+    },
+    util::{create_dir_if_does_not_exists, load_icon},
+    web::LocalWebUi,
 };
-use urlencoding::encode;
 
 use crate::server;
 
-/// Updater is supported only for non-linux operating systems.
-#[cfg(not(target_os = "linux"))]
-pub static IS_UPDATER_SUPPORTED: bool = true;
-/// Updater is supported only for non-linux operating systems.
-#[cfg(target_os = "linux")]
-pub static IS_UPDATER_SUPPORTED: bool = false;
-
 #[derive(RustEmbed)]
 #[folder = "icons"]
 struct Icons;
 
+/// The icon of the tray
+const TRAY_ICON: &str = "icon.png";
+/// The icon of the tray when server.js doesn't respond or it's restarted after a crash
+const TRAY_ICON_WARNING: &str = "icon-warning.png";
+/// The icon of the tray when a downloaded update waits to be installed
+const TRAY_ICON_UPDATING: &str = "icon-updating.png";
+/// The icon of the tray when server.js is stopped or it crashed
+const TRAY_ICON_STOPPED: &str = "icon-stopped.png";
+
 pub struct Application {
     /// The video server process
     server: Server,
+    /// The additional server.js processes, started and stopped with the main one
+    pool: ServerPool,
     config: Config,
+    /// Opens Stremio Web and the service pages
+    opener: Arc<dyn Opener>,
+    notifier: Notifier,
+    /// Set from the tray to install the downloaded update right away, see [`Application::spawn_updates`]
+    install_update: watch::Sender<bool>,
+    /// The channel the updates are checked on, it's changed from the tray
+    update_channel: watch::Sender<Channel>,
+    /// The browser the web pages are opened in, it's changed from the tray, see [`BrowserOpener`]
+    browser: watch::Sender<Browser>,
+    /// The installer waiting for the service to quit, see [`UpdateMode::Quit`]
+    staged_update: Arc<Mutex<Option<(Version, PathBuf)>>>,
+    /// The progress of the installer being downloaded, for the tray and the control API,
+    /// it's changed only when the percent does, see [`Application::spawn_updates`]
+    update_progress: Arc<watch::Sender<Option<DownloadProgress>>>,
+    /// The download limits of server.js and the pool before the streaming engine was paused from the tray,
+    /// the main server.js first, `None` while it's not paused, see [`set_download_limits`]
+    paused: Mutex<Option<Vec<(Server, u64)>>>,
+    shutdown: ShutdownHandle,
+}
+
+/// Stops a running [`Application`] from outside of it, e.g. from a signal handler or a test.
+///
+/// Both [`Application::run`] and [`Application::run_headless`] stop server.js,
+/// flush the logs and resolve once it's triggered, just like on the tray Quit item.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle(CancellationToken);
+
+impl ShutdownHandle {
+    /// Triggers the shutdown, it's a no-op when it's already triggered.
+    pub fn shutdown(&self) {
+        self.0.cancel()
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.0.is_cancelled()
+    }
+
+    /// Resolves once the shutdown is triggered.
+    pub async fn wait(&self) {
+        self.0.cancelled().await
+    }
 }
 
+/// How long to wait for server.js before opening the deep links anyway
+const SERVER_READY_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the running server.js is checked to respond, see [`Application::spawn_health_check`]
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// The consecutive unhealthy checks after which the unresponsive server.js is restarted
+const MAX_FAILED_HEALTH_CHECKS: u32 = 3;
+/// The choices of the "Download limit" submenu, in bytes per second
+const DOWNLOAD_LIMITS: &[(&str, Option<u64>)] = &[
+    ("No limit", None),
+    ("1 MiB/s", Some(1 << 20)),
+    ("2 MiB/s", Some(2 << 20)),
+    ("5 MiB/s", Some(5 << 20)),
+    ("10 MiB/s", Some(10 << 20)),
+];
+/// The limit lifting the one of the running server.js, it keeps its last limit otherwise
+const NO_DOWNLOAD_LIMIT: u64 = 1 << 40;
+/// How often server.js is checked to be idle while an update waits to be installed, see [`wait_until_idle`]
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How long server.js of an updated version has to get ready, see [`Application::spawn_post_update_check`]
+const POST_UPDATE_TIMEOUT: Duration = Duration::from_secs(120);
+/// How often the pressure of the system is sampled, see [`Application::spawn_pressure_watchdog`]
+const PRESSURE_INTERVAL: Duration = Duration::from_secs(5);
+/// The download limit of server.js while the system is under pressure, in bytes per second
+const THROTTLED_DOWNLOAD_LIMIT: u64 = 1 << 20;
+/// The download limit of server.js while the streaming engine is paused from the tray, in bytes per second.
+///
+/// server.js has no way to pause its torrents, they're limited to the smallest speed instead.
+const PAUSED_DOWNLOAD_LIMIT: u64 = 1;
+
 #[derive(Debug, Clone)]
 pub struct Config {
-    /// The Home directory of the user running the service
-    /// used to make the application an autostart one (on `*nix` systems)
-    #[cfg_attr(any(not(feature = "bundled"), target_os = "windows"), allow(dead_code))]
-    home_dir: PathBuf,
+    /// The directories used by the service
+    pub directories: Directories,
 
-    /// The lockfile that guards against running multiple instances of the service.
-    lockfile: PathBuf,
+    /// Whether the service should register itself to start on login (on `*nix` systems), see [`autostart`]
+    autostart: bool,
 
     /// The server configuration
     server: server::Config,
+    /// The named configs of the additional server.js, see [`ServerPool`]
+    pub server_pool: Vec<(String, server::Config)>,
+    /// The endpoint without the channel, see [`Channel::endpoint`]
     pub updater_endpoint: Url,
+    /// The release channel, it can be changed from the tray
+    pub update_channel: Channel,
+    /// When the downloaded updates are installed
+    pub update_mode: UpdateMode,
+    /// How often the updates are checked after the check on start, `None` only checks on start
+    pub update_check_interval: Option<Duration>,
+    /// The hour of the local time the [`UpdateMode::Scheduled`] updates are installed at
+    pub update_hour: u32,
     pub skip_update: bool,
     pub force_update: bool,
+    /// The roots trusted by the HTTPS clients besides the OS certificate store
+    pub tls_roots: Roots,
+    /// The address of the control API, `None` when it's disabled
+    pub control_api: Option<SocketAddr>,
+    /// Advertise server.js on the local network
+    pub lan_discovery: bool,
+    /// Serve server.js over HTTPS on the local network, `None` without a certificate
+    pub https: Option<HttpsConfig>,
+    /// The local build of Stremio Web, it's the Web UI opened by default unless there's a `--web-url`
+    pub local_web_ui: Option<LocalWebUi>,
+    /// Where the crash reports are sent, `None` when they're disabled or kept locally
+    pub crash_report_endpoint: Option<Url>,
+    /// Whether server.js transcodes with the hardware encoders
+    pub hardware_transcoding: HwTranscoding,
+    /// Throttle the downloads of server.js while the system is under pressure
+    pub pressure_watchdog: bool,
+    /// Show the desktop notifications, see [`notifications`](crate::notifications)
+    pub notifications: bool,
+
+    /// The Web UI opened by default
+    pub web_ui: Url,
+    /// All the saved Web UIs
+    pub web_uis: Vec<WebUi>,
+    /// The `--open` url, opened once server.js is ready
+    pub open_url: Option<String>,
+    /// Open the default Web UI once server.js is ready, unless there's an `--open` url
+    pub open_on_start: bool,
+    /// Open the Web UIs from the tray in the app window, only with the `webview` feature
+    pub open_in_window: bool,
+    /// The browser Stremio Web is opened in, it's chosen from the tray too
+    pub browser: Browser,
+    /// The program and the arguments of [`Browser::Custom`], `None` without a `browserCommand`
+    pub browser_command: Option<Vec<String>>,
+    /// Allow addon installation links from any host
+    pub allow_any_addon_host: bool,
 }
 
 impl Config {
     /// Try to create by validating the application configuration.
     ///
-    /// It will initialize the server [`server::Config`] and if it fails it will return an error.
+    /// The persisted [`Settings`] are loaded from the config directory and the given [`Args`] take precedence over them.
+    /// On first run the settings are initialized from the platform [`Profile`](crate::profile::Profile).
     ///
-    /// If `self_update` is `true` and it is a supported platform for the updater (see [`IS_UPDATER_SUPPORTED`])
-    /// it will check for the existence of the `updater` binary at the given location.
+    /// The given server [`server::Config`] is completed with the experiments of the settings.
     pub fn new(
         args: Args,
-        home_dir: PathBuf,
-        cache_dir: PathBuf,
-        service_bins_dir: PathBuf,
-    ) -> Result<Self, Error> {
-        let server =
-            server::Config::new(service_bins_dir).context("Server configuration failed")?;
-
-        let lockfile = cache_dir.join("lock");
-
-        let updater_endpoint = if let Some(endpoint) = args.updater_endpoint {
-            endpoint
-        } else {
-            let mut url = Url::parse(Self::get_random_updater_endpoint().as_str())?;
-            if args.release_candidate {
-                url.query_pairs_mut().append_pair("rc", "true");
-            }
-            url
+        directories: Directories,
+        server: server::Config,
+    ) -> Result<Self, ServiceError> {
+        directories.create_all()?;
+        let settings_file = directories.settings_file();
+        let mut settings = Settings::load(&settings_file)?;
+        if !settings_file.exists() {
+            // first run, persist the platform profile defaults
+            settings.save(&settings_file)?;
+        }
+
+        let updater_endpoint =
+            if let Some(endpoint) = args.updater_endpoint.or(settings.updater_endpoint.clone()) {
+                endpoint
+            } else {
+                match (
+                    settings.pin_updater_endpoint,
+                    settings.pinned_updater_endpoint.clone(),
+                ) {
+                    (true, Some(pinned)) => pinned,
+                    (true, None) => {
+                        let url = Self::get_random_updater_endpoint();
+                        settings.pinned_updater_endpoint = Some(url.clone());
+                        settings.save(&settings_file)?;
+                        info!("Pinned updater endpoint {url}");
+
+                        url
+                    }
+                    (false, _) => Self::get_random_updater_endpoint(),
+                }
+            };
+        let update_channel = match args.channel {
+            Some(channel) => channel,
+            None if args.release_candidate => Channel::Beta,
+            None => settings.update_channel(),
+        };
+
+        if settings.update_hour > 23 {
+            return Err(ServiceError::Config(anyhow!(
+                "`updateHour` is {}, it should be from 0 to 23",
+                settings.update_hour
+            )));
+        }
+
+        let mut tls_roots =
+            Roots::load(args.ca_bundle.as_deref().or(settings.ca_bundle.as_deref()))?;
+        if !tls_roots.is_empty() {
+            info!("Trusting {} extra certificate authorities", tls_roots.len());
+        }
+        if let Some(proxy) = args.proxy.as_ref().or(settings.proxy.as_ref()) {
+            let password = match settings.proxy_username {
+                Some(_) => Secrets::new(directories.secrets_file())
+                    .get(secrets::PROXY_PASSWORD)
+                    .map_err(ServiceError::Config)?,
+                None => None,
+            };
+            if settings.proxy_username.is_some() && password.is_none() {
+                warn!("There's no password of the proxy, set it with `--set-proxy-password`");
+            }
+            let credentials = settings
+                .proxy_username
+                .as_deref()
+                .map(|username| (username, password.as_deref().unwrap_or_default()));
+            tls_roots = tls_roots.with_proxy(proxy, credentials)?;
+            info!("Using the proxy {}", tls::redacted(proxy));
+        }
+
+        let hardware_transcoding = args
+            .hardware_transcoding
+            .unwrap_or(settings.hardware_transcoding);
+        let mut server_settings = settings.server_settings.clone();
+        server_settings.extend(
+            [
+                // `auto` is set once the encoders are probed
+                (
+                    server_settings::HARDWARE_ACCEL,
+                    (hardware_transcoding != HwTranscoding::Auto)
+                        .then(|| Value::from(hardware_transcoding.is_enabled(&[]))),
+                ),
+                (
+                    server_settings::CACHE_SIZE,
+                    args.cache_size.map(Value::from),
+                ),
+                (
+                    server_settings::TRANSCODE_PROFILE,
+                    args.transcode_profile.map(Value::from),
+                ),
+                (
+                    server_settings::DOWNLOAD_SPEED_LIMIT,
+                    args.download_speed_limit.map(Value::from),
+                ),
+                (
+                    server_settings::REMOTE_HTTPS,
+                    args.remote_https.map(Value::from),
+                ),
+            ]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_owned(), value?)))
+            .chain(args.server_setting),
+        );
+        let mut server = server
+            .with_experiments(settings.experiments_env())
+            .with_settings(ServerSettings::new(
+                &directories.server_data(),
+                server_settings,
+            ))
+            .with_port(args.server_port.unwrap_or(settings.server_port))?;
+        if args.sandbox_server || settings.sandbox_server {
+            server = server.with_sandbox(Sandbox::for_server(directories.server_data()));
+        }
+        if directories.profile.is_some() {
+            server = server.with_data_dir(directories.server_data());
+        }
+        if let Some(rate) = args
+            .cache_write_limit
+            .or(settings.cache_write_limit.clone())
+        {
+            server = server.with_write_limit(WriteLimit::new(directories.server_data(), &rate)?);
+        }
+        if !cfg!(feature = "no-bundled-server") {
+            if let Some((server_js, update)) = ServerUpdate::load(&directories.server_update()) {
+                info!(
+                    "Running server.js {} of a server-only update",
+                    update.version
+                );
+                server = server.with_server_js(server_js, update.version);
+            }
+        }
+
+        let https = match (
+            args.https_certificate
+                .or(settings.https_certificate.clone()),
+            args.https_key.or(settings.https_key.clone()),
+        ) {
+            (Some(certificate), Some(key)) => Some(HttpsConfig {
+                certificate,
+                key,
+                port: args.https_port.unwrap_or(settings.https_port),
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(ServiceError::Config(anyhow!(
+                    "Serving over HTTPS requires both `httpsCertificate` and `httpsKey`"
+                )))
+            }
+        };
+
+        let local_web_ui = args
+            .local_web_ui
+            .or(settings.local_web_ui.clone())
+            .map(|dir| LocalWebUi::new(dir, settings.local_web_ui_port, settings.local_web_ui_lan))
+            .transpose()?;
+        // the embedded build is served unless another one is given
+        #[cfg(feature = "embedded-web")]
+        let local_web_ui = local_web_ui.or_else(|| {
+            Some(LocalWebUi::embedded(
+                settings.local_web_ui_port,
+                settings.local_web_ui_lan,
+            ))
+        });
+        let mut web_uis = settings.web_uis.clone();
+        if let Some(local_web_ui) = &local_web_ui {
+            web_uis.push(WebUi {
+                name: "Local Stremio Web".to_owned(),
+                url: local_web_ui.url(),
+                default: false,
+            });
+        }
+
+        let web_ui = match args.web_url.as_deref() {
+            Some(web_url) => match settings.find_web_ui(web_url) {
+                Some(web_ui) => web_ui.url.clone(),
+                None => Url::parse(web_url)
+                    .context(format!(
+                        "`{web_url}` is neither a saved Web UI nor a valid URL"
+                    ))
+                    .map_err(ServiceError::Config)?,
+            },
+            None => match &local_web_ui {
+                Some(local_web_ui) => local_web_ui.url(),
+                None => settings.default_web_ui().url,
+            },
         };
+        // it's opened in the browser, like the links resolved to it
+        let web_ui = sanitize(web_ui)
+            .context("The Web UI should be an `http(s)://` url")
+            .map_err(ServiceError::Config)?;
+
+        let browser_command = settings
+            .browser_command
+            .as_deref()
+            .map(|command| {
+                shlex::split(command)
+                    .filter(|command| !command.is_empty())
+                    .ok_or_else(|| {
+                        ServiceError::Config(anyhow!(
+                            "The `browserCommand` `{command}` is not a valid command"
+                        ))
+                    })
+            })
+            .transpose()?;
+        if settings.browser == Browser::Custom && browser_command.is_none() {
+            return Err(ServiceError::Config(anyhow!(
+                "The `custom` browser requires a `browserCommand`"
+            )));
+        }
+
+        if settings.open_in_window && !cfg!(feature = "webview") {
+            warn!(
+                "`openInWindow` is ignored, this build has no webview, opening the browser instead"
+            );
+        }
+
+        let control_api = settings
+            .control_api_address()
+            .filter(|_| !args.no_control_api);
+        let mut ports = [
+            Some(server.port()),
+            https.as_ref().map(|https| https.port),
+            control_api.map(|address| address.port()),
+            local_web_ui.as_ref().map(LocalWebUi::port),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        let mut server_pool = vec![];
+        for AdditionalServer { name, port } in &settings.additional_servers {
+            if ports.contains(port) {
+                return Err(ServiceError::Config(anyhow!(
+                    "The port {port} of the server `{name}` is already used by the service"
+                )));
+            }
+            ports.push(*port);
+            let data_dir = directories.additional_server_data(*port);
+            server_pool.push((name.clone(), server.for_instance(*port, data_dir)?));
+        }
 
         Ok(Self {
             updater_endpoint,
-            home_dir,
-            lockfile,
+            update_channel,
+            update_mode: args.update_mode.unwrap_or(settings.update_mode),
+            update_hour: settings.update_hour,
+            update_check_interval: (settings.update_check_interval > 0)
+                .then(|| Duration::from_secs(u64::from(settings.update_check_interval) * 60 * 60)),
+            directories,
+            autostart: settings.autostart,
             server,
-            skip_update: args.skip_updater,
+            server_pool,
+            skip_update: args.skip_updater || settings.skip_updater,
             force_update: args.force_update,
+            tls_roots,
+            control_api,
+            lan_discovery: args.lan_discovery || settings.lan_discovery,
+            https,
+            local_web_ui,
+            crash_report_endpoint: settings
+                .crash_report_endpoint
+                .filter(|_| settings.crash_reports),
+            hardware_transcoding,
+            pressure_watchdog: args.pressure_watchdog || settings.pressure_watchdog,
+            notifications: settings.notifications,
+            web_ui,
+            web_uis,
+            open_url: args.open.filter(|open_url| !open_url.is_empty()),
+            open_on_start: settings.open_on_start,
+            open_in_window: settings.open_in_window && cfg!(feature = "webview"),
+            browser: settings.browser,
+            browser_command,
+            allow_any_addon_host: settings.allow_any_addon_host,
         })
     }
-    fn get_random_updater_endpoint() -> String {
+
+    /// Describe every configuration key with its type, default value and help text.
+    ///
+    /// See [`Settings::describe`].
+    pub fn describe() -> Vec<SettingDescription> {
+        Settings::describe()
+    }
+
+    /// The addresses of server.js for the other devices on the local network, over HTTPS too when it's enabled.
+    pub fn lan_urls(&self) -> Vec<Url> {
+        discovery::lan_addresses()
+            .into_iter()
+            .flat_map(|ip| {
+                let http = format!("http://{ip}:{}", self.server.port());
+                let https = self
+                    .https
+                    .as_ref()
+                    .map(|https| format!("https://{ip}:{}", https.port));
+                [Some(http), https].into_iter().flatten()
+            })
+            .map(|url| url.parse().expect("Should be a valid url"))
+            .collect()
+    }
+
+    fn get_random_updater_endpoint() -> Url {
         let mut rng = rand::thread_rng();
         let index = rng.gen_range(0..UPDATE_ENDPOINT.len());
-        UPDATE_ENDPOINT[index].to_string()
+        UPDATE_ENDPOINT[index]
+            .parse()
+            .expect("The updater endpoints should be valid")
     }
 }
 
 impl Application {
     pub fn new(config: Config) -> Self {
+        let browser = watch::channel(config.browser).0;
+        let opener = BrowserOpener::new(
+            browser.subscribe(),
+            config.browser_command.clone().unwrap_or_default(),
+        );
+
         Self {
             server: Server::new(config.server.clone()),
+            pool: ServerPool::new(config.server_pool.clone()),
+            notifier: Notifier::new(config.notifications),
+            install_update: watch::channel(false).0,
+            update_channel: watch::channel(config.update_channel).0,
+            browser,
+            staged_update: Arc::default(),
+            update_progress: Arc::new(watch::channel(None).0),
+            paused: Mutex::default(),
             config,
+            opener: Arc::new(opener),
+            shutdown: ShutdownHandle::default(),
+        }
+    }
+
+    /// Opens the links and the files with the given opener instead of the [`BrowserOpener`].
+    pub fn with_opener(mut self, opener: impl Opener + 'static) -> Self {
+        self.opener = Arc::new(opener);
+        self
+    }
+
+    /// The handle stopping this application.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// The status of server.js, it's sent on every change, see [`Server::subscribe`].
+    pub fn subscribe_status(&self) -> watch::Receiver<ServerInfo> {
+        self.server.subscribe()
+    }
+
+    /// The status of server.js right now, see [`Server::info`]
+    pub async fn server_info(&self) -> ServerInfo {
+        self.server.info().await
+    }
+
+    /// The environment of the service for the support requests, shown by the "About" item of the tray.
+    ///
+    /// The versions of node and ffmpeg are read by running them.
+    pub fn diagnostics(&self) -> Diagnostics {
+        let directories = &self.config.directories;
+        let server = &self.config.server;
+
+        Diagnostics {
+            build: BUILD_INFO,
+            profile: Profile::current_name(),
+            server_version: ServerUpdate::installed_version(&directories.server_update()),
+            node_version: diagnostics::version_of(server.node(), "--version"),
+            ffmpeg_version: diagnostics::version_of(server.ffmpeg(), "-version"),
+            update_channel: *self.update_channel.borrow(),
+            server_running: self.server.is_running(),
+            server_port: server.port(),
+            https_port: self.config.https.as_ref().map(|https| https.port),
+            control_api: self.config.control_api,
+            lan_addresses: discovery::lan_addresses(),
+            paths: vec![
+                ("settings", directories.settings_file()),
+                ("logs", directories.logs()),
+                ("state", directories.state.clone()),
+                ("cache", directories.cache.clone()),
+                ("server.js data", directories.server_data()),
+                ("node path", server.node().to_owned()),
+                ("ffmpeg path", server.ffmpeg().to_owned()),
+            ],
         }
     }
 
-    pub async fn run(&self) -> Result<(), anyhow::Error> {
-        let mut lockfile = LockFile::open(&self.config.lockfile)?;
+    /// Runs the service with the system tray, the protocol handler and the deep links,
+    /// until the tray Quit item is clicked or the [`ShutdownHandle`] is triggered.
+    pub async fn run(&self) -> Result<(), ServiceError> {
+        let history = LinkHistory::new(self.config.directories.link_history());
+        let deep_links =
+            DeepLinks::new(&self.config, history.clone()).with_opener(self.opener.clone());
+
+        let Some(_lockfile) = self.lock()? else {
+            if let Some(open_url) = self.config.open_url.as_ref() {
+                let link = Message::Link(open_url.clone());
+                match instance::forward(&self.config.directories.instance_socket(), &link).await {
+                    Ok(()) => {
+                        info!("Exiting, the link was forwarded to the running instance.");
+
+                        return Ok(());
+                    }
+                    Err(err) => warn!("{err:#}, opening the link"),
+                }
+                // the running instance might have just started
+                if !server::wait_until_ready(&self.server.url(), SERVER_READY_TIMEOUT).await {
+                    warn!("server.js is not ready after {SERVER_READY_TIMEOUT:?}, opening the link anyway");
+                }
+                deep_links.open(open_url);
+                info!("Exiting, another instance is running.");
+
+                return Ok(());
+            }
 
-        if !lockfile.try_lock()? {
-            info!("Exiting, another instance is running.");
+            // it's likely started again from its icon, so it's not exiting silently
+            match instance::forward(
+                &self.config.directories.instance_socket(),
+                &Message::Activate,
+            )
+            .await
+            {
+                Ok(()) => info!("Exiting, the running instance opens Stremio Web."),
+                Err(err) => {
+                    warn!("{err:#}");
+                    info!("Exiting, another instance is running.");
+                    let notifier = self.notifier;
+                    let _ = tokio::task::spawn_blocking(move || {
+                        notifier.notify_blocking(Notification::AlreadyRunning)
+                    })
+                    .await;
+                }
+            }
 
             return Ok(());
+        };
+        let updated = self.notify_updated();
+
+        #[cfg(feature = "bundled")]
+        if let Err(err) = crate::protocol_handler::register() {
+            error!("Failed to register the stremio:// protocol handler: {err:?}");
         }
 
+        // on Windows it's up to the installer, the entry starts the default profile
         #[cfg(all(feature = "bundled", any(target_os = "linux", target_os = "macos")))]
-        make_it_autostart(self.config.home_dir.clone());
+        if self.config.autostart && self.config.directories.profile.is_none() {
+            if let Err(err) = autostart::enable(&self.config.directories.home) {
+                error!("Failed to start on login: {err:#}");
+            }
+        }
+
+        if let Some(open_url) = self.config.open_url.clone() {
+            deep_links.handle(open_url);
+        }
 
         // NOTE: we do not need to run the Fruitbasket event loop but we do need to keep `app` in-scope for the full lifecycle of the app
         #[cfg(target_os = "macos")]
-        let _fruit_app = register_apple_event_callbacks();
+        let _fruit_app = register_apple_event_callbacks(deep_links.clone());
 
         // Showing the system tray icon as soon as possible to give the user a feedback
-        let event_loop = EventLoop::new();
-        let (mut system_tray, open_item_id, quit_item_id) = create_system_tray(&event_loop)?;
-
-        let current_version = env!("CARGO_PKG_VERSION")
-            .parse()
-            .expect("Should always be valid");
-        let updater = Updater::new(current_version, &self.config);
-        let updated = updater.prompt_and_update().await;
+        let mut event_loop = EventLoop::<TrayEvent>::with_user_event();
+        deep_links.notify(event_loop.create_proxy());
+        self.spawn_instance_socket(deep_links.clone(), event_loop.create_proxy());
+        let home_dir = self.config.directories.home.clone();
+        let versions = Versions::new(self.config.directories.versions());
+        let server_status = ServerTrayStatus::Starting;
+        let mut throttled = false;
+        let state = TrayState {
+            // only the bundled service of the default profile starts itself on login
+            start_on_login: (cfg!(feature = "bundled")
+                && self.config.directories.profile.is_none())
+            .then(|| autostart::is_enabled(&home_dir).unwrap_or(self.config.autostart)),
+            // there's nothing to choose when the updater is skipped
+            update_channel: (!self.config.skip_update).then_some(self.config.update_channel),
+            download_limit: self
+                .server
+                .setting(server_settings::DOWNLOAD_SPEED_LIMIT)
+                .and_then(|limit| limit.as_u64())
+                .filter(|limit| *limit != NO_DOWNLOAD_LIMIT),
+            paused: false,
+            browser: self.config.browser,
+            // the custom one only with its command
+            browsers: Browser::ALL
+                .into_iter()
+                .filter(|browser| {
+                    *browser != Browser::Custom || self.config.browser_command.is_some()
+                })
+                .collect(),
+            status: status_label(server_status, throttled),
+            pool_status: self
+                .pool
+                .servers()
+                .iter()
+                .map(|PooledServer { name, server }| {
+                    pool_status_label(name, server.port(), ServerTrayStatus::Starting)
+                })
+                .collect(),
+            transcoding: transcoding_label(self.config.hardware_transcoding, None),
+            pending_update: None,
+            update_progress: None,
+            revert_to: versions
+                .previous(&BUILD_INFO.version.parse().expect("Should always be valid")),
+        };
+        let lan_urls = self.config.lan_urls();
+        let (menu, tray_menu) =
+            create_tray_menu(&self.config.web_uis, &lan_urls, &history.recent(), &state);
+        let mut tray_icons = TrayIcons::default();
+        let tooltip = format!("Stremio Service, streaming on {}", self.server.url());
+        let system_tray = create_system_tray(&event_loop, menu, &tooltip, &mut tray_icons)
+            .map_err(ServiceError::Other)?;
+        let mut tray = Tray {
+            system_tray,
+            icons: tray_icons,
+            menu: tray_menu,
+            state,
+            server_status,
+            web_uis: self.config.web_uis.clone(),
+            lan_urls,
+            history,
+        };
 
+        // streaming doesn't wait for the update check
+        if let Err(err) = self.start_server().await {
+            // the update is offered to be reverted then, see below
+            if !updated {
+                return Err(err);
+            }
+            error!("{err}");
+        }
         if updated {
-            // Exit current process as the updater has spawn the
-            // new version in a separate process.
-            // We haven't started the server.js in this instance yet
-            // so it is safe to run the second service by the updater
-            return Ok(());
+            self.spawn_post_update_check(true);
         }
+        self.spawn_control_api();
+        self.spawn_https_proxy();
+        self.spawn_local_web_ui();
+        self.spawn_discovery();
+        let tray_alive = Arc::new(AtomicBool::new(true));
+        self.spawn_status_events(Some(event_loop.create_proxy()));
+        self.spawn_health_check(Some(TrayHandle {
+            proxy: event_loop.create_proxy(),
+            alive: tray_alive.clone(),
+        }));
+        self.spawn_transcoding_probe(Some(event_loop.create_proxy()));
+        self.spawn_pressure_watchdog(Some(event_loop.create_proxy()));
+        self.spawn_updates(Some(event_loop.create_proxy()));
+        let server_url = self.server.url();
+        let open_web_ui = (self.config.open_on_start && self.config.open_url.is_none())
+            .then(|| self.config.web_ui.clone());
+        let proxy = event_loop.create_proxy();
+        tokio::spawn(async move {
+            if !server::wait_until_ready(&server_url, SERVER_READY_TIMEOUT).await {
+                warn!("server.js is not ready after {SERVER_READY_TIMEOUT:?}, opening the queued links anyway");
+            }
+            deep_links.replay();
+            if let Some(web_ui) = open_web_ui {
+                // maybe in the app window, which is created by the event loop
+                let _ = proxy.send_event(TrayEvent::OpenWebUi(web_ui));
+            }
+        });
+        let shutdown = self.shutdown_handle();
+        let proxy = event_loop.create_proxy();
+        tokio::spawn(async move {
+            shutdown.wait().await;
+            let _ = proxy.send_event(TrayEvent::Shutdown);
+        });
+        let mut web_ui_opener = WebUiOpener::new(self.opener.clone(), &self.config);
+        let hardware_transcoding = self.config.hardware_transcoding;
+        let pool = self.pool.clone();
 
-        self.server.start().context("Failed to start server.js")?;
-        // cheap to clone and interior mutability
-        let mut server = self.server.clone();
-
-        event_loop.run(move |event, _event_loop, control_flow| {
+        event_loop.run_return(move |event, window_target, control_flow| {
             *control_flow = ControlFlow::Wait;
+            web_ui_opener.handle(&event);
 
-            match event {
-                Event::MenuEvent { menu_id, .. } => {
-                    if menu_id == open_item_id {
-                        open_stremio_web(None);
+            let rebuild_menu = match event {
+                Event::MenuEvent { menu_id, .. } => self.handle_menu_event(
+                    menu_id,
+                    &mut tray,
+                    &mut web_ui_opener,
+                    window_target,
+                    control_flow,
+                ),
+                Event::NewEvents(StartCause::Init) => {
+                    self.send_crash_reports();
+                    false
+                }
+                Event::UserEvent(TrayEvent::LinksChanged) => true,
+                Event::UserEvent(TrayEvent::StatusChanged(new_status)) => {
+                    tray.server_status = new_status;
+                    tray.state.status = status_label(tray.server_status, throttled);
+                    tray.menu.status.set_title(&tray.state.status);
+                    set_tray_icon(
+                        &mut tray.system_tray,
+                        &mut tray.icons,
+                        tray_icon(tray.server_status, tray.state.pending_update.is_some()),
+                    );
+                    false
+                }
+                Event::UserEvent(TrayEvent::PoolStatusChanged(index, new_status)) => {
+                    if let (Some(PooledServer { name, server }), Some(label), Some(item)) = (
+                        pool.servers().get(index),
+                        tray.state.pool_status.get_mut(index),
+                        tray.menu.pool_status.get_mut(index),
+                    ) {
+                        *label = pool_status_label(name, server.port(), new_status);
+                        item.set_title(label);
                     }
-                    if menu_id == quit_item_id {
-                        *control_flow = ControlFlow::Exit;
+                    false
+                }
+                Event::UserEvent(TrayEvent::Throttled(is_throttled)) => {
+                    throttled = is_throttled;
+                    tray.state.status = status_label(tray.server_status, throttled);
+                    tray.menu.status.set_title(&tray.state.status);
+                    false
+                }
+                Event::UserEvent(TrayEvent::TranscodingProbed(available)) => {
+                    tray.state.transcoding =
+                        transcoding_label(hardware_transcoding, Some(&available));
+                    tray.menu.transcoding.set_title(&tray.state.transcoding);
+                    false
+                }
+                Event::UserEvent(TrayEvent::UpdateProgress(progress)) => {
+                    tray.state.update_progress = progress.as_ref().map(update_progress_label);
+                    match (&mut tray.menu.update_progress, &tray.state.update_progress) {
+                        (Some(item), Some(label)) => {
+                            item.set_title(label);
+                            false
+                        }
+                        // the item is added or removed
+                        _ => true,
                     }
                 }
+                Event::UserEvent(TrayEvent::UpdateReady(version)) => {
+                    tray.state.pending_update = Some(version);
+                    tray.state.update_progress = None;
+                    set_tray_icon(
+                        &mut tray.system_tray,
+                        &mut tray.icons,
+                        tray_icon(tray.server_status, true),
+                    );
+                    true
+                }
+                Event::UserEvent(TrayEvent::OpenWebUi(web_ui)) => {
+                    web_ui_opener.open(window_target, &web_ui);
+                    false
+                }
+                Event::UserEvent(TrayEvent::Heartbeat) => {
+                    tray_alive.store(true, Ordering::SeqCst);
+                    false
+                }
+                Event::UserEvent(TrayEvent::Shutdown) => {
+                    info!("Shutting down");
+                    *control_flow = ControlFlow::Exit;
+                    false
+                }
                 Event::LoopDestroyed => {
-                    system_tray.take();
-                    if let Err(err) = server.stop() {
-                        error!("{err}")
-                    }
+                    tray.system_tray.take();
+                    false
                 }
-                _ => (),
+                _ => false,
+            };
+            if rebuild_menu {
+                tray.rebuild_menu();
             }
         });
+        // server.js keeps its settings, it's not paused on its next start
+        let paused = self.paused.lock().ok().and_then(|mut paused| paused.take());
+        if let Some(limits) = paused {
+            set_download_limits(limits).await;
+        }
+        if let Err(err) = self.server.stop().await {
+            error!("{err}")
+        }
+        self.pool.stop().await;
+        self.install_staged_update();
+        log::logger().flush();
+
+        Ok(())
     }
-}
 
-fn create_system_tray(
-    event_loop: &EventLoop<()>,
-) -> Result<(Option<SystemTray>, MenuId, MenuId), anyhow::Error> {
-    let mut tray_menu = ContextMenu::new();
-    let open_item = tray_menu.add_item(MenuItemAttributes::new("Open Stremio Web"));
-    let quit_item = tray_menu.add_item(MenuItemAttributes::new("Quit"));
+    /// Handles a click on an item of the tray menu, returns whether the menu has to be rebuilt.
+    fn handle_menu_event(
+        &self,
+        menu_id: MenuId,
+        tray: &mut Tray,
+        web_ui_opener: &mut WebUiOpener,
+        window_target: &EventLoopWindowTarget<TrayEvent>,
+        control_flow: &mut ControlFlow,
+    ) -> bool {
+        let directories = &self.config.directories;
+        let settings_file = directories.settings_file();
 
-    let version_item_label = format!("v{}", env!("CARGO_PKG_VERSION"));
-    let version_item = MenuItemAttributes::new(version_item_label.as_str()).with_enabled(false);
-    tray_menu.add_item(version_item);
+        if menu_id == tray.menu.open {
+            web_ui_opener.open(window_target, &self.config.web_ui);
+        }
+        if let Some((_, url)) = tray.menu.web_uis.iter().find(|(id, _)| *id == menu_id) {
+            web_ui_opener.open(window_target, url);
+        }
+        if let Some((_, record)) = tray.menu.recent_links.iter().find(|(id, _)| *id == menu_id) {
+            show_link_record(record);
+        }
+        if let Some((_, url)) = tray.menu.lan_urls.iter().find(|(id, _)| *id == menu_id) {
+            if let Err(err) = show_qr_code(&self.opener, &directories.qr_code(), url) {
+                show_error(&ServiceError::Other(err));
+            }
+        }
+        if menu_id == tray.menu.open_logs {
+            let logs = directories.logs();
+            create_dir_if_does_not_exists(&logs);
+            if let Err(err) = self.opener.open(logs.as_os_str()) {
+                error!("Failed to open {}: {err}", logs.display());
+            }
+        }
+        if menu_id == tray.menu.show_server_log {
+            if let Err(err) = show_server_log(&self.opener, &directories.server_log(), &self.server)
+            {
+                show_error(&ServiceError::Other(err));
+            }
+        }
+        if menu_id == tray.menu.export_settings {
+            export_settings(&settings_file);
+        }
+        if menu_id == tray.menu.import_settings {
+            import_settings(&settings_file);
+        }
+        if Some(menu_id) == tray.menu.start_on_login {
+            if let Some(enabled) = tray.state.start_on_login.map(|enabled| !enabled) {
+                match set_start_on_login(&settings_file, &directories.home, enabled) {
+                    Ok(()) => tray.state.start_on_login = Some(enabled),
+                    Err(err) => show_error(&err),
+                }
+                return true;
+            }
+        }
+        if let Some(&(_, channel)) = tray
+            .menu
+            .update_channels
+            .iter()
+            .find(|(id, _)| *id == menu_id)
+        {
+            match set_update_channel(&settings_file, channel) {
+                Ok(()) => {
+                    tray.state.update_channel = Some(channel);
+                    // the update of the previous channel is dropped
+                    tray.state.pending_update = None;
+                    self.install_update.send_replace(false);
+                    if let Ok(mut staged) = self.staged_update.lock() {
+                        staged.take();
+                    }
+                    self.update_channel.send_replace(channel);
+                    set_tray_icon(
+                        &mut tray.system_tray,
+                        &mut tray.icons,
+                        tray_icon(tray.server_status, false),
+                    );
+                }
+                Err(err) => show_error(&err),
+            }
+            return true;
+        }
+        if let Some(&(_, browser)) = tray.menu.browsers.iter().find(|(id, _)| *id == menu_id) {
+            match set_browser(&settings_file, browser) {
+                Ok(()) => {
+                    tray.state.browser = browser;
+                    self.browser.send_replace(browser);
+                }
+                Err(err) => show_error(&err),
+            }
+            return true;
+        }
+        if let Some(&(_, limit)) = tray
+            .menu
+            .download_limits
+            .iter()
+            .find(|(id, _)| *id == menu_id)
+        {
+            match set_download_limit(&settings_file, limit) {
+                Ok(()) => {
+                    tray.state.download_limit = limit;
+                    let mut limits =
+                        vec![(self.server.clone(), limit.unwrap_or(NO_DOWNLOAD_LIMIT))];
+                    // the paused server.js gets it once it's resumed
+                    if let Ok(mut paused) = self.paused.lock() {
+                        if let Some(paused) = paused.as_mut() {
+                            paused[0] = limits.remove(0);
+                        }
+                    }
+                    tokio::spawn(set_download_limits(limits));
+                }
+                Err(err) => show_error(&err),
+            }
+            return true;
+        }
+        if menu_id == tray.menu.pause {
+            if let Ok(mut paused) = self.paused.lock() {
+                let limits = match paused.take() {
+                    Some(limits) => {
+                        info!("Resuming the streaming engine");
+                        limits
+                    }
+                    None => {
+                        info!("Pausing the streaming engine");
+                        let servers = std::iter::once(&self.server)
+                            .chain(self.pool.servers().iter().map(|pooled| &pooled.server));
+                        let limits = servers
+                            .map(|server| {
+                                let limit = server
+                                    .setting(server_settings::DOWNLOAD_SPEED_LIMIT)
+                                    .and_then(|limit| limit.as_u64())
+                                    .unwrap_or(NO_DOWNLOAD_LIMIT);
+                                (server.clone(), limit)
+                            })
+                            .collect::<Vec<_>>();
+                        *paused = Some(limits.clone());
 
-    let icon_file = Icons::get("icon.png").ok_or_else(|| anyhow!("Failed to get icon file"))?;
-    let icon = load_icon(icon_file.data.as_ref());
+                        limits
+                            .into_iter()
+                            .map(|(server, _)| (server, PAUSED_DOWNLOAD_LIMIT))
+                            .collect()
+                    }
+                };
+                tray.state.paused = paused.is_some();
+                tokio::spawn(set_download_limits(limits));
+            }
+            return true;
+        }
+        if Some(menu_id) == tray.menu.install_update {
+            self.install_update.send_replace(true);
+        }
+        if let Some(previous) = tray
+            .state
+            .revert_to
+            .clone()
+            .filter(|_| Some(menu_id) == tray.menu.revert_update)
+        {
+            let revert = rfd::MessageDialog::new()
+                .set_title("Stremio Service")
+                .set_description(&format!(
+                    "Revert to v{previous}? The service restarts and this version isn't installed again."
+                ))
+                .set_buttons(rfd::MessageButtons::YesNo)
+                .show();
+            if revert {
+                let server = self.server.clone();
+                let pool = self.pool.clone();
+                let versions = Versions::new(directories.versions());
+                let staging = Staging::new(directories.staging());
+                let notifier = self.notifier;
+                let shutdown = self.shutdown_handle();
+                tokio::spawn(async move {
+                    if revert_update(&server, &pool, &versions, &staging, &previous, notifier).await
+                    {
+                        shutdown.shutdown();
+                    }
+                });
+            }
+        }
+        if menu_id == tray.menu.about {
+            let diagnostics = self.diagnostics();
+            if let Err(err) =
+                show_diagnostics(&self.opener, &directories.about_page(), &diagnostics)
+            {
+                error!("{err:#}");
+                show_about(&diagnostics);
+            }
+        }
+        if menu_id == tray.menu.quit {
+            self.shutdown.shutdown();
+            *control_flow = ControlFlow::Exit;
+        }
 
-    let system_tray = SystemTrayBuilder::new(icon, Some(tray_menu))
-        .with_id(TrayId::new("main"))
-        .build(event_loop)
-        .context("Failed to build the application system tray")?;
+        false
+    }
 
-    Ok((Some(system_tray), open_item.id(), quit_item.id()))
-}
+    /// Runs the service without any GUI until the `shutdown` token is cancelled
+    /// or the [`ShutdownHandle`] of the application is triggered.
+    ///
+    /// Only server.js and the updater are managed, the desktop integration
+    /// (the tray, the protocol handler, the autostart and the deep links) is left to the embedder.
+    ///
+    /// # Errors
+    ///
+    /// When another instance is running or server.js fails to start.
+    pub async fn run_headless(&self, shutdown: CancellationToken) -> Result<(), ServiceError> {
+        let lockfile = self.start_headless().await?;
+        tokio::select! {
+            _ = shutdown.cancelled() => {}
+            _ = self.shutdown.wait() => {}
+        }
 
-/// Handles `stremio://` urls by replacing the custom scheme with `https://`
-/// and opening it.
-/// Either opens the Addon installation link or the Web UI url
-pub fn handle_stremio_protocol(open_url: String) {
-    if open_url.starts_with("stremio://") {
-        let url = open_url.replace("stremio://", "https://");
-        open_stremio_web(Some(url));
+        self.stop_headless(lockfile).await
     }
-}
 
-fn open_stremio_web(addon_manifest_url: Option<String>) {
-    let mut url = STREMIO_URL.to_string();
-    if let Some(p) = addon_manifest_url {
-        url = format!("{}/#/addons?addon={}", STREMIO_URL, &encode(&p));
+    /// Starts server.js and the tasks supervising and updating it, without the tray.
+    ///
+    /// The returned lockfile is held until [`Application::stop_headless`], the tasks stop on the shutdown.
+    pub(crate) async fn start_headless(&self) -> Result<LockFile, ServiceError> {
+        let Some(lockfile) = self.lock()? else {
+            return Err(ServiceError::LockHeld);
+        };
+        let updated = self.notify_updated();
+
+        if let Err(err) = self.start_server().await {
+            // the update is reverted then, see below
+            if !updated {
+                return Err(err);
+            }
+            error!("{err}");
+        }
+        if updated {
+            self.spawn_post_update_check(false);
+        }
+        self.spawn_control_api();
+        self.spawn_https_proxy();
+        self.spawn_local_web_ui();
+        self.spawn_discovery();
+        self.spawn_status_events(None);
+        self.spawn_health_check(None);
+        self.spawn_transcoding_probe(None);
+        self.spawn_pressure_watchdog(None);
+        self.spawn_updates(None);
+
+        Ok(lockfile)
     }
 
-    match open::that(url) {
-        Ok(_) => info!("Opened Stremio Web in the browser"),
-        Err(e) => error!("Failed to open Stremio Web: {}", e),
+    /// Stops what [`Application::start_headless`] started and installs the staged update, if any.
+    pub(crate) async fn stop_headless(&self, lockfile: LockFile) -> Result<(), ServiceError> {
+        self.shutdown.shutdown();
+        info!("Shutting down");
+
+        let stopped = self.server.stop().await;
+        self.pool.stop().await;
+        self.install_staged_update();
+        log::logger().flush();
+        drop(lockfile);
+
+        stopped
+    }
+
+    /// Runs the installer staged by an update in the [`UpdateMode::Quit`], once server.js is stopped.
+    ///
+    /// The updated version isn't started, it starts like this one did, e.g. on the next login.
+    fn install_staged_update(&self) {
+        let Some((version, file)) = self
+            .staged_update
+            .lock()
+            .ok()
+            .and_then(|mut staged| staged.take())
+        else {
+            return;
+        };
+        let current_version = BUILD_INFO.version.parse().expect("Should always be valid");
+        info!("Installing the staged update {}", file.display());
+        let updater = Updater::new(current_version, &self.config);
+        updater.keep_current_version(&version, &file);
+        updater.run_installer(&version, file, false);
     }
-}
 
-/// Only for Linux and MacOS
-#[cfg(all(feature = "bundled", any(target_os = "linux", target_os = "macos")))]
-fn make_it_autostart(home_dir: impl AsRef<Path>) {
-    #[cfg(target_os = "linux")]
-    {
-        use crate::{
-            constants::{AUTOSTART_CONFIG_PATH, DESKTOP_FILE_NAME, DESKTOP_FILE_PATH},
-            util::create_dir_if_does_not_exists,
+    /// Tells the user when this version is newer than the one run last, i.e. an update was completed,
+    /// and returns whether it was.
+    fn notify_updated(&self) -> bool {
+        let current_version = BUILD_INFO.version.parse().expect("Should always be valid");
+        let Some(last_version) =
+            updater::record_version(&self.config.directories.last_version(), &current_version)
+        else {
+            return false;
         };
+        info!("Updated from v{last_version}");
+        self.notifier.notify(Notification::Updated {
+            version: current_version.to_string(),
+        });
 
-        create_dir_if_does_not_exists(&home_dir.as_ref().join(AUTOSTART_CONFIG_PATH));
+        true
+    }
 
-        let from = PathBuf::from(DESKTOP_FILE_PATH).join(DESKTOP_FILE_NAME);
-        let to = home_dir
-            .as_ref()
-            .join(AUTOSTART_CONFIG_PATH)
-            .join(DESKTOP_FILE_NAME);
+    /// Checks that server.js of the updated version gets ready within [`POST_UPDATE_TIMEOUT`],
+    /// when there's a previous version to revert to.
+    ///
+    /// When it doesn't, the previous version is restored right away without the tray,
+    /// the user is told to revert it from the tray otherwise.
+    fn spawn_post_update_check(&self, tray: bool) {
+        let current_version = BUILD_INFO.version.parse().expect("Should always be valid");
+        let versions = Versions::new(self.config.directories.versions());
+        let Some(previous) = versions.previous(&current_version) else {
+            return;
+        };
+        let staging = Staging::new(self.config.directories.staging());
+        let server = self.server.clone();
+        let pool = self.pool.clone();
+        let notifier = self.notifier;
+        let shutdown = self.shutdown_handle();
 
-        if !to.exists() {
-            if let Err(e) = std::fs::copy(from, to) {
-                error!("Failed to copy desktop file to autostart location: {}", e);
+        tokio::spawn(async move {
+            if server::wait_until_ready(&server.url(), POST_UPDATE_TIMEOUT).await {
+                return;
             }
-        }
+            warn!("server.js is not ready {POST_UPDATE_TIMEOUT:?} after the update");
+            if tray {
+                notifier.notify(Notification::UpdateFailed {
+                    version: current_version.to_string(),
+                    previous: previous.to_string(),
+                });
+            } else if revert_update(&server, &pool, &versions, &staging, &previous, notifier).await
+            {
+                shutdown.shutdown();
+            }
+        });
+    }
+
+    /// Restores the previous version kept by the last update and starts it, see [`rollback`](crate::rollback).
+    ///
+    /// # Errors
+    ///
+    /// When another instance is running or there's no previous version to restore.
+    pub async fn rollback(&self) -> Result<(), ServiceError> {
+        let Some(_lockfile) = self.lock()? else {
+            return Err(ServiceError::LockHeld);
+        };
+        let current_version = BUILD_INFO.version.parse().expect("Should always be valid");
+        let versions = Versions::new(self.config.directories.versions());
+        let Some(previous) = versions.previous(&current_version) else {
+            return Err(ServiceError::Update(anyhow!(
+                "No previous version is kept in {}",
+                self.config.directories.versions().display()
+            )));
+        };
+        info!("Reverting to v{previous}");
+
+        skip_current_version(&versions);
+        versions
+            .restore(
+                &Installation::current(),
+                &Staging::new(self.config.directories.staging()),
+                &previous,
+            )
+            .map_err(ServiceError::Update)
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        use crate::{
-            constants::{APP_IDENTIFIER, APP_NAME, LAUNCH_AGENTS_PATH},
-            util::create_dir_if_does_not_exists,
+    /// Installs the files of the update staged by the last run, or completes their swap interrupted before,
+    /// see [`staging`](crate::staging). Returns the updated executable to run instead of this one.
+    ///
+    /// Nothing is installed while another instance holds the lockfile, the failures are only logged
+    /// and the installed version keeps running then.
+    pub fn swap_staged_update(&self) -> Option<PathBuf> {
+        let _lockfile = match self.lock() {
+            Ok(Some(lockfile)) => lockfile,
+            Ok(None) => return None,
+            Err(err) => {
+                error!("{err}");
+                return None;
+            }
         };
 
-        let plist_launch_agent = format!("
-            <?xml version=\"1.0\" encoding=\"UTF-8\"?>
-            <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
-            <plist version=\"1.0\">
-            <dict>  
-                <key>Label</key>
-                <string>{}</string>
-                <key>ProgramArguments</key>
-                <array>
-                    <string>/usr/bin/open</string>
-                    <string>-a</string>
-                    <string>{}</string>
-                </array>
-                <key>RunAtLoad</key>
-                <true/>
-            </dict>
-            </plist>
-        ", APP_IDENTIFIER, APP_NAME);
+        Staging::new(self.config.directories.staging())
+            .apply()
+            .unwrap_or_else(|err| {
+                error!("Failed to install the staged update: {err:#}");
+                None
+            })
+    }
 
-        let launch_agents_path = home_dir.as_ref().join(LAUNCH_AGENTS_PATH);
-        create_dir_if_does_not_exists(&launch_agents_path);
+    /// Installs the update from a local installer, descriptor or bundle, e.g. on an air-gapped machine,
+    /// see [`updater::offline_provider`].
+    ///
+    /// It's verified and installed like the downloaded updates, the updated version is started then.
+    ///
+    /// # Errors
+    ///
+    /// When the service is running, as the update replaces its files, or the update fails to be verified.
+    pub async fn apply_update(
+        &self,
+        file: &Path,
+        version: Option<Version>,
+        sha256: Option<String>,
+    ) -> Result<(), ServiceError> {
+        let Some(_lockfile) = self.lock()? else {
+            return Err(ServiceError::LockHeld);
+        };
+        let current_version = BUILD_INFO.version.parse().expect("Should always be valid");
+        let mut updater =
+            Updater::new(current_version, &self.config).with_progress(ProgressHandler::terminal());
+        updater.provider =
+            updater::offline_provider(file, version, sha256, &self.config.directories.updates())
+                .map_err(ServiceError::Update)?;
+        info!("Applying the update {}", file.display());
 
-        let plist_path = launch_agents_path.join(format!("{}.plist", APP_IDENTIFIER));
-        if !plist_path.exists() {
-            if let Err(e) = std::fs::write(plist_path, plist_launch_agent.as_bytes()) {
-                error!("Failed to create a plist file in LaunchAgents dir: {}", e);
+        match updater.autoupdate().await? {
+            Some(Update::Installer { version, file }) => {
+                updater.keep_current_version(&version, &file);
+                if !updater.run_updater_setup(&version, file) {
+                    return Err(ServiceError::Update(anyhow!(
+                        "The installer of v{version} couldn't be started"
+                    )));
+                }
             }
+            Some(Update::Server { version, .. }) => info!("Installed server.js {version}"),
+            None => {}
         }
+
+        Ok(())
     }
-}
 
-#[cfg(target_os = "macos")]
-fn register_apple_event_callbacks() -> fruitbasket::FruitApp<'static> {
-    use fruitbasket::{FruitApp, FruitCallbackKey};
+    /// Locks the lockfile of the instance, `None` when another instance holds it.
+    ///
+    /// The lock is released when the returned [`LockFile`] is dropped.
+    fn lock(&self) -> Result<Option<LockFile>, ServiceError> {
+        let lockfile = self.config.directories.lockfile();
+        let lock = || {
+            let mut lockfile = LockFile::open(&lockfile)?;
 
-    let mut app = FruitApp::new();
+            Ok::<_, std::io::Error>(lockfile.try_lock()?.then_some(lockfile))
+        };
 
-    app.register_apple_event(fruitbasket::kInternetEventClass, fruitbasket::kAEGetURL);
-    app.register_callback(
-        FruitCallbackKey::Method("handleEvent:withReplyEvent:"),
-        Box::new(move |event| {
-            let open_url: String = fruitbasket::parse_url_event(event);
-            handle_stremio_protocol(open_url);
-        }),
-    );
+        lock().map_err(|err| {
+            ServiceError::Other(anyhow!("Failed to lock {}: {err}", lockfile.display()))
+        })
+    }
 
-    app
+    /// Starts server.js and the ones of the pool, after repairing it when it's corrupted.
+    ///
+    /// # Errors
+    ///
+    /// When the main server.js fails to start, the ones of the pool failing to start are only logged.
+    async fn start_server(&self) -> Result<(), ServiceError> {
+        // a broken server.js is still started, it's reported by the crash
+        if let Err(err) = self.config.server.repair(&self.config.tls_roots).await {
+            error!("{err}");
+        }
+
+        let started = self.server.start();
+        self.pool.start();
+
+        started
+    }
+
+    /// Asks to send the crash reports of the previous runs, when there's an endpoint for them,
+    /// and sends them or dismisses them.
+    fn send_crash_reports(&self) {
+        let Some(endpoint) = self.config.crash_report_endpoint.clone() else {
+            return;
+        };
+        let reports = crash::pending(&self.config.directories.crash_reports());
+        if reports.is_empty() {
+            return;
+        }
+
+        let send = rfd::MessageDialog::new()
+            .set_title("Stremio Service")
+            .set_description(&format!(
+                "Stremio Service crashed, send the {} crash report(s) to {}?\n\nThey have the backtrace, the versions and the last lines of the log.",
+                reports.len(),
+                endpoint.host_str().unwrap_or(endpoint.as_str())
+            ))
+            .set_buttons(rfd::MessageButtons::YesNo)
+            .show();
+
+        let tls_roots = self.config.tls_roots.clone();
+        tokio::spawn(async move {
+            for report in reports {
+                let result = match send {
+                    true => crash::send(&report, &endpoint, &tls_roots).await,
+                    false => crash::dismiss(&report),
+                };
+                match result {
+                    Ok(()) if send => info!("Sent the crash report {}", report.display()),
+                    Ok(()) => {}
+                    Err(err) => warn!("{err:#}"),
+                }
+            }
+        });
+    }
+
+    /// Serves the control API until the application shuts down, when it's enabled.
+    fn spawn_control_api(&self) {
+        let Some(address) = self.config.control_api else {
+            return;
+        };
+
+        // the `--web-url` may not be a saved one, e.g. a self-hosted Stremio Web
+        let web_uis = std::iter::once(&self.config.web_ui)
+            .chain(self.config.web_uis.iter().map(|web_ui| &web_ui.url));
+        let current_version = BUILD_INFO.version.parse().expect("Should always be valid");
+        let control_api = ControlApi::new(address, self.server.clone(), web_uis)
+            .with_updater(Updater::new(current_version, &self.config))
+            .with_update_progress(self.update_progress.subscribe())
+            .with_link_history(LinkHistory::new(self.config.directories.link_history()));
+        let shutdown = self.shutdown_handle();
+        tokio::spawn(async move {
+            // the service works without it, e.g. when another program uses the port
+            if let Err(err) = control_api.serve(shutdown.wait()).await {
+                error!("{err}");
+            }
+        });
+    }
+
+    /// Serves server.js over HTTPS on the local network until the application shuts down, when there's a certificate.
+    fn spawn_https_proxy(&self) {
+        let Some(https) = &self.config.https else {
+            return;
+        };
+
+        let proxy = match HttpsProxy::new(https, self.config.server.port()) {
+            Ok(proxy) => proxy,
+            Err(err) => {
+                error!("{err}");
+                return;
+            }
+        };
+        let shutdown = self.shutdown_handle();
+        tokio::spawn(async move {
+            // server.js is still reachable over HTTP
+            if let Err(err) = proxy.serve(shutdown.wait()).await {
+                error!("{err}");
+            }
+        });
+    }
+
+    /// Serves the local Stremio Web until the application shuts down, when it's enabled.
+    fn spawn_local_web_ui(&self) {
+        let Some(local_web_ui) = self.config.local_web_ui.clone() else {
+            return;
+        };
+
+        let shutdown = self.shutdown_handle();
+        tokio::spawn(async move {
+            // the other Web UIs can still be opened
+            if let Err(err) = local_web_ui.serve(shutdown.wait()).await {
+                error!("{err}");
+            }
+        });
+    }
+
+    /// Advertises server.js on the local network until the application shuts down, when it's enabled.
+    fn spawn_discovery(&self) {
+        if !self.config.lan_discovery {
+            return;
+        }
+
+        let mut discovery = Discovery::new(self.config.server.port());
+        if let Some(https) = &self.config.https {
+            discovery = discovery.with_https_port(https.port);
+        }
+        let shutdown = self.shutdown_handle();
+        tokio::spawn(async move {
+            // streaming works without it, the address can still be typed
+            if let Err(err) = discovery.advertise(shutdown.wait()).await {
+                error!("{err}");
+            }
+        });
+    }
+
+    /// Handles the links of the other instances and opens Stremio Web when they start without one,
+    /// until the application shuts down, see [`instance`].
+    fn spawn_instance_socket(&self, deep_links: DeepLinks, tray: EventLoopProxy<TrayEvent>) {
+        let endpoint = self.config.directories.instance_socket();
+        let web_ui = self.config.web_ui.clone();
+        let shutdown = self.shutdown_handle();
+        tokio::spawn(async move {
+            let on_message = move |message| match message {
+                Message::Link(link) => deep_links.handle(link),
+                // maybe in the app window, which is created by the event loop
+                Message::Activate => {
+                    let _ = tray.send_event(TrayEvent::OpenWebUi(web_ui.clone()));
+                }
+            };
+            // the other instances open their links themselves without it
+            if let Err(err) = instance::listen(&endpoint, on_message, shutdown.wait()).await {
+                error!("{err:#}");
+            }
+        });
+    }
+
+    /// Probes the hardware encoders with the ffmpeg of server.js, unless hardware transcoding is off,
+    /// and sends them to the tray, when there's one.
+    ///
+    /// With `auto` the hardware acceleration of the running server.js is enabled only when one of them works.
+    fn spawn_transcoding_probe(&self, tray: Option<EventLoopProxy<TrayEvent>>) {
+        let mode = self.config.hardware_transcoding;
+        if mode == HwTranscoding::Off {
+            info!("Hardware transcoding is disabled");
+            return;
+        }
+        let ffmpeg = self.config.server.ffmpeg().to_owned();
+        let server = self.server.clone();
+
+        tokio::spawn(async move {
+            let available = transcoding::probe(&ffmpeg).await;
+            match available.is_empty() {
+                true => info!("No hardware encoder works with {}", ffmpeg.display()),
+                false => info!(
+                    "Hardware encoders: {}",
+                    available
+                        .iter()
+                        .map(HwAccel::label)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+            if mode == HwTranscoding::Auto {
+                // it's kept for the next start otherwise
+                server::wait_until_ready(&server.url(), SERVER_READY_TIMEOUT).await;
+                let values = BTreeMap::from([(
+                    server_settings::HARDWARE_ACCEL.to_owned(),
+                    Value::from(mode.is_enabled(&available)),
+                )]);
+                if let Err(err) = server.update_settings(values).await {
+                    warn!("{err}");
+                }
+            }
+
+            if let Some(tray) = tray {
+                let _ = tray.send_event(TrayEvent::TranscodingProbed(available));
+            }
+        });
+    }
+
+    /// Samples the memory and I/O pressure of the system every [`PRESSURE_INTERVAL`], when the watchdog is enabled,
+    /// and limits the downloads of server.js to [`THROTTLED_DOWNLOAD_LIMIT`] while it's high.
+    ///
+    /// The tray is told whether the downloads are throttled, when there's one.
+    fn spawn_pressure_watchdog(&self, tray: Option<EventLoopProxy<TrayEvent>>) {
+        if !self.config.pressure_watchdog {
+            return;
+        }
+        if Pressure::read().is_none() {
+            warn!(
+                "The pressure watchdog is not supported, the kernel doesn't provide /proc/pressure"
+            );
+            return;
+        }
+        let server = self.server.clone();
+        let notifier = self.notifier;
+        let shutdown = self.shutdown_handle();
+
+        tokio::spawn(async move {
+            let watchdog = async {
+                let mut interval = tokio::time::interval(PRESSURE_INTERVAL);
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                let mut monitor = PressureMonitor::default();
+                // the limits before and while throttling
+                let mut limits = None;
+                loop {
+                    interval.tick().await;
+
+                    let Some(pressure) = Pressure::read() else {
+                        continue;
+                    };
+                    let current_limit = server
+                        .setting(server_settings::DOWNLOAD_SPEED_LIMIT)
+                        .and_then(|limit| limit.as_u64())
+                        .unwrap_or(NO_DOWNLOAD_LIMIT);
+                    let limit = match monitor.sample(pressure) {
+                        Some(PressureChange::High) => {
+                            warn!(
+                                "The system is under pressure (memory {:.1}%, I/O {:.1}%), throttling the downloads of server.js",
+                                pressure.memory, pressure.io
+                            );
+                            notifier.notify(Notification::Throttled);
+                            let throttled_limit = current_limit.min(THROTTLED_DOWNLOAD_LIMIT);
+                            limits = Some((current_limit, throttled_limit));
+
+                            Some(throttled_limit)
+                        }
+                        Some(PressureChange::Relieved) => {
+                            info!("The pressure of the system is relieved, restoring the downloads of server.js");
+                            // unless the limit was changed from the tray meanwhile
+                            limits
+                                .take()
+                                .filter(|(_, throttled_limit)| *throttled_limit == current_limit)
+                                .map(|(previous_limit, _)| previous_limit)
+                        }
+                        None => continue,
+                    };
+
+                    if let Some(limit) = limit {
+                        let values = BTreeMap::from([(
+                            server_settings::DOWNLOAD_SPEED_LIMIT.to_owned(),
+                            Value::from(limit),
+                        )]);
+                        if let Err(err) = server.update_settings(values).await {
+                            warn!("{err}");
+                        }
+                    }
+                    if let Some(tray) = &tray {
+                        let _ = tray.send_event(TrayEvent::Throttled(monitor.is_high()));
+                    }
+                }
+            };
+
+            tokio::select! {
+                _ = watchdog => {}
+                _ = shutdown.wait() => {}
+            }
+        });
+    }
+
+    /// Follows the status of server.js until the application shuts down, see [`Server::subscribe`].
+    ///
+    /// Its changes are logged and sent to the tray, when there's one, the user is told about the crashes
+    /// and systemd is told the service is ready once server.js responds.
+    /// The changes of the ones of the pool are only logged and sent to the tray.
+    fn spawn_status_events(&self, tray: Option<EventLoopProxy<TrayEvent>>) {
+        for (index, PooledServer { name, server }) in self.pool.servers().iter().enumerate() {
+            let name = name.clone();
+            let mut status = server.subscribe();
+            let tray = tray.clone();
+            let shutdown = self.shutdown_handle();
+
+            tokio::spawn(async move {
+                let status_events = async {
+                    loop {
+                        let current = status.borrow_and_update().status;
+                        info!("Server `{name}` status: {current}");
+                        if let Some(tray) = &tray {
+                            let _ = tray.send_event(TrayEvent::PoolStatusChanged(index, current));
+                        }
+
+                        if status.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                };
+
+                tokio::select! {
+                    _ = status_events => {}
+                    _ = shutdown.wait() => {}
+                }
+            });
+        }
+
+        let mut status = self.server.subscribe();
+        let notifier = self.notifier;
+        let shutdown = self.shutdown_handle();
+
+        tokio::spawn(async move {
+            let status_events = async {
+                let mut is_ready = false;
+                loop {
+                    let current = status.borrow_and_update().status;
+                    info!("Server status: {current}");
+                    match current {
+                        ServerTrayStatus::Restarting { attempt } => {
+                            notifier.notify(Notification::ServerRestarting { attempt })
+                        }
+                        ServerTrayStatus::Crashed { exit_code } => {
+                            notifier.notify(Notification::ServerCrashed { exit_code })
+                        }
+                        _ => {}
+                    }
+                    if let Some(tray) = &tray {
+                        let _ = tray.send_event(TrayEvent::StatusChanged(current));
+                    }
+                    if !is_ready && current.is_running() {
+                        is_ready = true;
+                        systemd::notify_ready();
+                    }
+
+                    if status.changed().await.is_err() {
+                        break;
+                    }
+                }
+            };
+
+            tokio::select! {
+                _ = status_events => {}
+                _ = shutdown.wait() => {}
+            }
+        });
+    }
+
+    /// Checks server.js every [`HEALTH_CHECK_INTERVAL`] until the application shuts down
+    /// and restarts it once it has been unhealthy for [`MAX_FAILED_HEALTH_CHECKS`] checks in a row.
+    ///
+    /// The status it finds is sent to the subscribers of the server, see [`Application::spawn_status_events`].
+    ///
+    /// The systemd watchdog, if enabled, is pinged after every check while the tray event loop
+    /// answers the [`TrayEvent::Heartbeat`]s.
+    fn spawn_health_check(&self, tray: Option<TrayHandle>) {
+        // the ones of the pool are checked and restarted the same way
+        let servers = std::iter::once(("server.js".to_owned(), self.server.clone()))
+            .chain(
+                self.pool
+                    .servers()
+                    .iter()
+                    .map(|PooledServer { name, server }| {
+                        (format!("server.js `{name}`"), server.clone())
+                    }),
+            )
+            .collect::<Vec<_>>();
+        let shutdown = self.shutdown_handle();
+        let watchdog = systemd::Watchdog::from_env();
+        if let Some(watchdog) = watchdog {
+            info!(
+                "Pinging the systemd watchdog, its timeout is {:?}",
+                watchdog.timeout()
+            );
+            if watchdog.timeout() < 2 * HEALTH_CHECK_INTERVAL {
+                warn!("The systemd watchdog timeout is shorter than 2 health checks, it may restart the service while it's running");
+            }
+        }
+
+        tokio::spawn(async move {
+            let health_check = async {
+                let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                let mut failed_checks = vec![0; servers.len()];
+                loop {
+                    interval.tick().await;
+
+                    for ((name, server), failed_checks) in servers.iter().zip(&mut failed_checks) {
+                        if server.info().await.status == ServerTrayStatus::Unhealthy {
+                            *failed_checks += 1;
+                        } else {
+                            *failed_checks = 0;
+                        }
+                        if *failed_checks >= MAX_FAILED_HEALTH_CHECKS {
+                            warn!("{name} is not responding for {failed_checks} checks in a row, restarting it");
+                            *failed_checks = 0;
+                            if let Err(err) = server.restart().await {
+                                error!("{err}");
+                            }
+                        }
+                    }
+
+                    if let Some(watchdog) = &watchdog {
+                        // the tray answered the previous heartbeat
+                        let is_tray_alive = tray
+                            .as_ref()
+                            .is_none_or(|tray| tray.alive.swap(false, Ordering::SeqCst));
+                        match is_tray_alive {
+                            true => watchdog.ping(),
+                            false => warn!(
+                                "The tray is not responding, the systemd watchdog is not pinged"
+                            ),
+                        }
+                        if let Some(tray) = &tray {
+                            let _ = tray.proxy.send_event(TrayEvent::Heartbeat);
+                        }
+                    }
+                }
+            };
+
+            tokio::select! {
+                _ = health_check => {}
+                _ = shutdown.wait() => {}
+            }
+        });
+    }
+
+    /// Updates the service from the channel while server.js is running, the application shuts down
+    /// once the updated version was started in place of this instance.
+    ///
+    /// The updates are checked on start, then every [`Config::update_check_interval`] and right away
+    /// when the channel is changed from the tray, which drops the update of the previous channel.
+    /// The downloaded installer is run as the [`UpdateMode`] says, or right away when it's requested from the tray,
+    /// which is told about the update when there's one.
+    fn spawn_updates(&self, tray: Option<EventLoopProxy<TrayEvent>>) {
+        let current_version: Version = BUILD_INFO.version.parse().expect("Should always be valid");
+        let config = self.config.clone();
+        // there's nothing to check again when the updater is skipped
+        let interval = config.update_check_interval.filter(|_| !config.skip_update);
+        let server = self.server.clone();
+        let pool = self.pool.clone();
+        let notifier = self.notifier;
+        let install = self.install_update.subscribe();
+        let mut channel = self.update_channel.subscribe();
+        let staged = self.staged_update.clone();
+        let shutdown = self.shutdown_handle();
+        let update_progress = self.update_progress.clone();
+        let progress = ProgressHandler::new(move |progress| {
+            // the percent, or the MiB without the size of the installer
+            let step = |progress: Option<DownloadProgress>| {
+                progress.map(|progress| progress.percent().unwrap_or(progress.downloaded >> 20))
+            };
+            update_progress.send_if_modified(|current| {
+                let changed = step(*current) != step(progress);
+                *current = progress;
+
+                changed
+            });
+        });
+        if let Some(tray) = tray.clone() {
+            let mut update_progress = self.update_progress.subscribe();
+            tokio::spawn(async move {
+                while update_progress.changed().await.is_ok() {
+                    let progress = *update_progress.borrow_and_update();
+                    if tray
+                        .send_event(TrayEvent::UpdateProgress(progress))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+
+        tokio::spawn(async move {
+            loop {
+                let update_channel = *channel.borrow_and_update();
+                let updater = Updater::new(
+                    current_version.clone(),
+                    &Config {
+                        update_channel,
+                        ..config.clone()
+                    },
+                )
+                .with_progress(progress.clone());
+                tokio::select! {
+                    installed = update(&updater, &server, &pool, notifier, tray.clone(), install.clone(), &staged) => {
+                        if installed {
+                            shutdown.shutdown();
+                            return;
+                        }
+                    }
+                    Ok(()) = channel.changed() => continue,
+                }
+
+                let next_check = async {
+                    match interval {
+                        Some(interval) => tokio::time::sleep(interval).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    _ = next_check => {}
+                    Ok(()) = channel.changed() => {}
+                }
+            }
+        });
+    }
+}
+
+/// Runs the installer of the downloaded update and returns whether the updated version was started.
+///
+/// The installer waits until server.js is idle, at the install hour of [`UpdateMode::Scheduled`],
+/// or until `install` is set, as it stops the streams. In the [`UpdateMode::Quit`] it's `staged` to be run
+/// when the service quits instead, unless `install` is set before.
+/// server.js and the ones of the pool are stopped only when there's an update to install, as the installer
+/// replaces their files, and they're started again when the installer fails.
+/// A server-only update just restarts them and the service keeps running.
+async fn update(
+    updater: &Updater,
+    server: &Server,
+    pool: &ServerPool,
+    notifier: Notifier,
+    tray: Option<EventLoopProxy<TrayEvent>>,
+    mut install: watch::Receiver<bool>,
+    staged: &Mutex<Option<(Version, PathBuf)>>,
+) -> bool {
+    let update = match updater.fetch_update().await {
+        Some(Update::Installer { version, file }) => {
+            notifier.notify(Notification::UpdateAvailable {
+                version: version.to_string(),
+                mode: updater.mode,
+            });
+            if let Some(tray) = &tray {
+                let _ = tray.send_event(TrayEvent::UpdateReady(version.clone()));
+            }
+            let requested = async {
+                while !*install.borrow_and_update() {
+                    if install.changed().await.is_err() {
+                        std::future::pending::<()>().await;
+                    }
+                }
+            };
+            let scheduled = async {
+                match updater.mode {
+                    UpdateMode::Idle => {}
+                    UpdateMode::Scheduled => wait_until_hour(updater.install_hour).await,
+                    UpdateMode::Quit => {
+                        info!("Installing v{version} when the service quits");
+                        if let Ok(mut staged) = staged.lock() {
+                            *staged = Some((version.clone(), file.clone()));
+                        }
+                        std::future::pending::<()>().await
+                    }
+                }
+                wait_until_idle(server, pool).await
+            };
+            tokio::select! {
+                _ = requested => info!("Installing v{version}, requested from the tray"),
+                _ = scheduled => info!("Installing v{version} while server.js is idle"),
+            }
+            // it's not installed again on quit
+            if let Ok(mut staged) = staged.lock() {
+                staged.take();
+            }
+
+            (version, file)
+        }
+        Some(Update::Server { version, file }) => {
+            pool.set_server_js(&file, &version).await;
+            match server.set_server_js(file, version.clone()).await {
+                Ok(()) => notifier.notify(Notification::ServerUpdated { version }),
+                Err(err) => error!("{err}"),
+            }
+            return false;
+        }
+        None => return false,
+    };
+
+    let (version, file) = update;
+    if let Err(err) = server.stop().await {
+        error!("{err}");
+    }
+    pool.stop().await;
+    updater.keep_current_version(&version, &file);
+    if updater.run_updater_setup(&version, file) {
+        return true;
+    }
+
+    info!("Keep running the current version");
+    if let Err(err) = server.start() {
+        error!("{err}");
+    }
+    pool.start();
+
+    false
+}
+
+/// Stops server.js and the ones of the pool and restores the `previous` version in place of this one,
+/// returns whether it was started.
+///
+/// server.js is started again when it fails, the user is told about it.
+async fn revert_update(
+    server: &Server,
+    pool: &ServerPool,
+    versions: &Versions,
+    staging: &Staging,
+    previous: &Version,
+    notifier: Notifier,
+) -> bool {
+    info!("Reverting to v{previous}");
+    if let Err(err) = server.stop().await {
+        error!("{err}");
+    }
+    pool.stop().await;
+    skip_current_version(versions);
+    match versions.restore(&Installation::current(), staging, previous) {
+        Ok(()) => return true,
+        Err(err) => error!("Failed to revert to v{previous}: {err:#}"),
+    }
+    notifier.notify(Notification::RevertFailed {
+        previous: previous.to_string(),
+    });
+    if let Err(err) = server.start() {
+        error!("{err}");
+    }
+    pool.start();
+
+    false
+}
+
+/// Skips this version in the updates, before it's reverted as the Windows installer stops it.
+///
+/// It doesn't change what's updated to while this version is running, in case the revert fails.
+fn skip_current_version(versions: &Versions) {
+    let current_version = BUILD_INFO.version.parse().expect("Should always be valid");
+    if let Err(err) = versions.skip(&current_version) {
+        warn!("{err:#}");
+    }
+}
+
+/// Resolves once server.js and the ones of the pool are not downloading anything, or when they can't tell,
+/// e.g. when they're stopped.
+///
+/// It's checked every [`IDLE_CHECK_INTERVAL`], see [`server::is_idle`].
+async fn wait_until_idle(server: &Server, pool: &ServerPool) {
+    let urls = std::iter::once(server.url())
+        .chain(pool.servers().iter().map(|pooled| pooled.server.url()))
+        .collect::<Vec<_>>();
+    'busy: loop {
+        for url in &urls {
+            if server::is_idle(url).await == Some(false) {
+                tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+                continue 'busy;
+            }
+        }
+
+        return;
+    }
+}
+
+/// Resolves at the next `hour` of the local time, right away during it.
+///
+/// The time is checked again every [`IDLE_CHECK_INTERVAL`], as the timers don't count the suspended time.
+async fn wait_until_hour(hour: u32) {
+    loop {
+        let until = updater::until_hour(chrono::Local::now().naive_local(), hour);
+        if until.is_zero() {
+            return;
+        }
+        tokio::time::sleep(until.min(IDLE_CHECK_INTERVAL)).await;
+    }
+}
+
+/// The events sent to the event loop from other threads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayEvent {
+    /// A deep link was handled, the recent links changed
+    LinksChanged,
+    /// server.js is in another state, see [`Application::spawn_status_events`]
+    StatusChanged(ServerTrayStatus),
+    /// The server.js of the pool at the index is in another state, see [`Application::spawn_status_events`]
+    PoolStatusChanged(usize, ServerTrayStatus),
+    /// The working hardware encoders were probed, see [`Application::spawn_transcoding_probe`]
+    TranscodingProbed(Vec<HwAccel>),
+    /// The downloads of server.js are throttled or not anymore, see [`Application::spawn_pressure_watchdog`]
+    Throttled(bool),
+    /// The installer of an update is downloaded, `None` once it's not anymore, see [`Application::spawn_updates`]
+    UpdateProgress(Option<DownloadProgress>),
+    /// The installer of the version was downloaded, it waits to be installed, see [`Application::spawn_updates`]
+    UpdateReady(Version),
+    /// The Web UI is opened, on start once server.js is ready or when another instance is started
+    OpenWebUi(Url),
+    /// Sent by the health check to the event loop, which answers by setting [`TrayHandle::alive`]
+    Heartbeat,
+    /// The [`ShutdownHandle`] was triggered
+    Shutdown,
+}
+
+/// The event loop of the tray, as seen by the health check
+struct TrayHandle {
+    proxy: EventLoopProxy<TrayEvent>,
+    /// Set on every [`TrayEvent::Heartbeat`] and cleared by the health check
+    alive: Arc<AtomicBool>,
+}
+
+/// The ids of the system tray menu items
+struct TrayMenu {
+    /// `None` without a downloaded update
+    install_update: Option<MenuId>,
+    /// `None` without a previous version to revert to
+    revert_update: Option<MenuId>,
+    open: MenuId,
+    /// The saved Web UIs submenu items
+    web_uis: Vec<(MenuId, Url)>,
+    /// The browser submenu items
+    browsers: Vec<(MenuId, Browser)>,
+    /// The recent links submenu items
+    recent_links: Vec<(MenuId, LinkRecord)>,
+    /// The "Connect a device" submenu items
+    lan_urls: Vec<(MenuId, Url)>,
+    open_logs: MenuId,
+    show_server_log: MenuId,
+    export_settings: MenuId,
+    import_settings: MenuId,
+    /// `None` when the service can't start itself on login
+    start_on_login: Option<MenuId>,
+    /// The update channel submenu items, empty when the updater is skipped
+    update_channels: Vec<(MenuId, Channel)>,
+    /// The download limit submenu items, see [`DOWNLOAD_LIMITS`]
+    download_limits: Vec<(MenuId, Option<u64>)>,
+    /// The checked item while the streaming engine is paused
+    pause: MenuId,
+    about: MenuId,
+    quit: MenuId,
+    /// The disabled item showing the progress of the update download, see [`update_progress_label`]
+    update_progress: Option<CustomMenuItem>,
+    /// The disabled item showing the status of server.js
+    status: CustomMenuItem,
+    /// The disabled items of the "Servers" submenu showing the status of the ones of the pool
+    pool_status: Vec<CustomMenuItem>,
+    /// The disabled item showing the hardware encoders of server.js
+    transcoding: CustomMenuItem,
+}
+
+/// The tray of the event loop, with what its menu shows
+struct Tray {
+    /// `None` once the event loop is destroyed
+    system_tray: Option<SystemTray>,
+    icons: TrayIcons,
+    menu: TrayMenu,
+    state: TrayState,
+    /// The state of server.js, the menu shows it with the pressure, see [`status_label`]
+    server_status: ServerTrayStatus,
+    web_uis: Vec<WebUi>,
+    lan_urls: Vec<Url>,
+    history: LinkHistory,
+}
+
+impl Tray {
+    /// Replaces the menu with one of the current state, see [`set_tray_menu`].
+    fn rebuild_menu(&mut self) {
+        self.menu = set_tray_menu(
+            &mut self.system_tray,
+            &self.web_uis,
+            &self.lan_urls,
+            &self.history.recent(),
+            &self.state,
+        );
+    }
+}
+
+/// What the tray menu shows
+#[derive(Debug, Clone)]
+struct TrayState {
+    /// `None` when the service can't start itself on login
+    start_on_login: Option<bool>,
+    /// `None` when the updater is skipped
+    update_channel: Option<Channel>,
+    /// The download speed limit of server.js in bytes per second, `None` without a limit
+    download_limit: Option<u64>,
+    /// Whether the downloads are paused, see [`PAUSED_DOWNLOAD_LIMIT`]
+    paused: bool,
+    /// The browser Stremio Web is opened in
+    browser: Browser,
+    /// The browsers which can be chosen
+    browsers: Vec<Browser>,
+    /// The text of the status item, see [`status_label`]
+    status: String,
+    /// The texts of the status items of the pool, see [`pool_status_label`]
+    pool_status: Vec<String>,
+    /// The text of the hardware transcoding item, see [`transcoding_label`]
+    transcoding: String,
+    /// The version of the downloaded update waiting to be installed
+    pending_update: Option<Version>,
+    /// The text of the item showing the update download, `None` when no update is downloaded
+    update_progress: Option<String>,
+    /// The previous version kept by the last update, see [`rollback`](crate::rollback)
+    revert_to: Option<Version>,
+}
+
+/// Creates the tray menu, with the checked "Start on login" item when it can be chosen
+/// and the "Update channel" submenu when the updater is not skipped.
+fn create_tray_menu(
+    web_uis: &[WebUi],
+    lan_urls: &[Url],
+    recent_links: &[LinkRecord],
+    state: &TrayState,
+) -> (ContextMenu, TrayMenu) {
+    let mut tray_menu = ContextMenu::new();
+    // the first item, so it stands out
+    let install_update_item = state.pending_update.as_ref().map(|version| {
+        tray_menu
+            .add_item(MenuItemAttributes::new(&format!(
+                "Install update v{version}"
+            )))
+            .id()
+    });
+    let update_progress_item = state
+        .update_progress
+        .as_ref()
+        .filter(|_| state.pending_update.is_none())
+        .map(|label| tray_menu.add_item(MenuItemAttributes::new(label).with_enabled(false)));
+    let open_item = tray_menu.add_item(MenuItemAttributes::new("Open Stremio Web"));
+
+    // only worth a submenu when there's more than the default Web UI
+    let mut web_ui_items = vec![];
+    if web_uis.len() > 1 {
+        let mut web_uis_menu = ContextMenu::new();
+        for web_ui in web_uis {
+            let item = web_uis_menu.add_item(MenuItemAttributes::new(&web_ui.name));
+            web_ui_items.push((item.id(), web_ui.url.clone()));
+        }
+        tray_menu.add_submenu("Open Web UI", true, web_uis_menu);
+    }
+    let mut browser_items = vec![];
+    let mut browser_menu = ContextMenu::new();
+    for &browser in &state.browsers {
+        let item = browser_menu.add_item(
+            MenuItemAttributes::new(browser.label()).with_selected(browser == state.browser),
+        );
+        browser_items.push((item.id(), browser));
+    }
+    tray_menu.add_submenu("Browser", true, browser_menu);
+
+    let mut recent_link_items = vec![];
+    let mut recent_links_menu = ContextMenu::new();
+    for record in recent_links {
+        let label = format!(
+            "{} {}",
+            record
+                .timestamp
+                .with_timezone(&chrono::Local)
+                .format("%H:%M"),
+            record.url.chars().take(60).collect::<String>()
+        );
+        let item = recent_links_menu.add_item(MenuItemAttributes::new(&label));
+        recent_link_items.push((item.id(), record.clone()));
+    }
+    tray_menu.add_submenu("Recent links", !recent_links.is_empty(), recent_links_menu);
+
+    // the QR code of an address is shown when it's clicked
+    let mut lan_url_items = vec![];
+    let mut lan_urls_menu = ContextMenu::new();
+    for url in lan_urls {
+        let label = url.as_str().trim_end_matches('/');
+        let item = lan_urls_menu.add_item(MenuItemAttributes::new(label));
+        lan_url_items.push((item.id(), url.clone()));
+    }
+    tray_menu.add_submenu("Connect a device", !lan_urls.is_empty(), lan_urls_menu);
+
+    let open_logs_item = tray_menu.add_item(MenuItemAttributes::new("Open logs folder"));
+    let show_server_log_item = tray_menu.add_item(MenuItemAttributes::new("Show server log"));
+    let export_settings_item = tray_menu.add_item(MenuItemAttributes::new("Export settings"));
+    let import_settings_item = tray_menu.add_item(MenuItemAttributes::new("Import settings"));
+    let start_on_login_item = state.start_on_login.map(|selected| {
+        tray_menu
+            .add_item(MenuItemAttributes::new("Start on login").with_selected(selected))
+            .id()
+    });
+    let mut update_channel_items = vec![];
+    if let Some(update_channel) = state.update_channel {
+        let mut update_channel_menu = ContextMenu::new();
+        for &channel in <Channel as clap::ValueEnum>::value_variants() {
+            let item = update_channel_menu.add_item(
+                MenuItemAttributes::new(channel.label()).with_selected(channel == update_channel),
+            );
+            update_channel_items.push((item.id(), channel));
+        }
+        tray_menu.add_submenu("Update channel", true, update_channel_menu);
+    }
+    let revert_update_item = state.revert_to.as_ref().map(|version| {
+        tray_menu
+            .add_item(MenuItemAttributes::new(&format!(
+                "Revert last update (v{version})"
+            )))
+            .id()
+    });
+    let mut download_limit_items = vec![];
+    let mut download_limit_menu = ContextMenu::new();
+    for &(label, limit) in DOWNLOAD_LIMITS {
+        let item = download_limit_menu
+            .add_item(MenuItemAttributes::new(label).with_selected(limit == state.download_limit));
+        download_limit_items.push((item.id(), limit));
+    }
+    tray_menu.add_submenu("Download limit", true, download_limit_menu);
+    let pause_item = tray_menu
+        .add_item(MenuItemAttributes::new("Pause streaming engine").with_selected(state.paused));
+    let about_item = tray_menu.add_item(MenuItemAttributes::new("About"));
+    let quit_item = tray_menu.add_item(MenuItemAttributes::new("Quit"));
+
+    let status_item =
+        tray_menu.add_item(MenuItemAttributes::new(&state.status).with_enabled(false));
+    // only with a pool, its items only show the status
+    let mut pool_status_items = vec![];
+    if !state.pool_status.is_empty() {
+        let mut pool_status_menu = ContextMenu::new();
+        for label in &state.pool_status {
+            pool_status_items.push(
+                pool_status_menu.add_item(MenuItemAttributes::new(label).with_enabled(false)),
+            );
+        }
+        tray_menu.add_submenu("Servers", true, pool_status_menu);
+    }
+    let transcoding_item =
+        tray_menu.add_item(MenuItemAttributes::new(&state.transcoding).with_enabled(false));
+
+    let version_item_label = BUILD_INFO.short();
+    let version_item = MenuItemAttributes::new(version_item_label.as_str()).with_enabled(false);
+    tray_menu.add_item(version_item);
+
+    let menu_ids = TrayMenu {
+        install_update: install_update_item,
+        revert_update: revert_update_item,
+        open: open_item.id(),
+        web_uis: web_ui_items,
+        browsers: browser_items,
+        recent_links: recent_link_items,
+        lan_urls: lan_url_items,
+        open_logs: open_logs_item.id(),
+        show_server_log: show_server_log_item.id(),
+        export_settings: export_settings_item.id(),
+        import_settings: import_settings_item.id(),
+        start_on_login: start_on_login_item,
+        update_channels: update_channel_items,
+        download_limits: download_limit_items,
+        pause: pause_item.id(),
+        about: about_item.id(),
+        quit: quit_item.id(),
+        update_progress: update_progress_item,
+        status: status_item,
+        pool_status: pool_status_items,
+        transcoding: transcoding_item,
+    };
+
+    (tray_menu, menu_ids)
+}
+
+/// Replaces the menu of the tray, see [`create_tray_menu`].
+fn set_tray_menu(
+    system_tray: &mut Option<SystemTray>,
+    web_uis: &[WebUi],
+    lan_urls: &[Url],
+    recent_links: &[LinkRecord],
+    state: &TrayState,
+) -> TrayMenu {
+    let (menu, tray_menu) = create_tray_menu(web_uis, lan_urls, recent_links, state);
+    if let Some(system_tray) = system_tray.as_mut() {
+        system_tray.set_menu(&menu);
+    }
+
+    tray_menu
+}
+
+/// The text of the tray item of the update download, e.g. `Downloading update 42%`
+fn update_progress_label(progress: &DownloadProgress) -> String {
+    match progress.percent() {
+        Some(percent) => format!("Downloading update {percent}%"),
+        None => format!("Downloading update {} MiB", progress.downloaded >> 20),
+    }
+}
+
+/// The text of the tray status item, e.g. `Server: Running`, or `Server: Running (throttled)`
+/// while the downloads are throttled by the pressure watchdog
+fn status_label(server: ServerTrayStatus, throttled: bool) -> String {
+    let label = TrayStatus {
+        version: BUILD_INFO.version.to_owned(),
+        server,
+    }
+    .label();
+
+    match throttled {
+        true => format!("{label} (throttled)"),
+        false => label,
+    }
+}
+
+/// The text of a status item of the "Servers" submenu, e.g. `Bedroom TV (11490): Running`
+fn pool_status_label(name: &str, port: u16, status: ServerTrayStatus) -> String {
+    format!("{name} ({port}): {status}")
+}
+
+/// The text of the tray transcoding item, e.g. `Hardware transcoding: VAAPI`, `available` is `None` until it's probed
+fn transcoding_label(mode: HwTranscoding, available: Option<&[HwAccel]>) -> String {
+    let encoders = match (mode, available) {
+        (HwTranscoding::Off, _) => "Off".to_owned(),
+        (_, None) => "Probing…".to_owned(),
+        (HwTranscoding::Auto, Some([])) => "Not available".to_owned(),
+        (HwTranscoding::On, Some([])) => "On, none found".to_owned(),
+        (_, Some(available)) => available
+            .iter()
+            .map(HwAccel::label)
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+
+    format!("Hardware transcoding: {encoders}")
+}
+
+/// Decodes the embedded tray icons only when they're shown.
+///
+/// Only the name of the icon in use is kept, the tray holds the icon itself,
+/// so neither the PNG nor the RGBA of the other icons stay in the memory of the idling service.
+#[derive(Debug, Default)]
+struct TrayIcons {
+    current: Option<&'static str>,
+}
+
+impl TrayIcons {
+    /// Decodes the icon, `None` when it's already the one in use.
+    fn icon(&mut self, name: &'static str) -> Result<Option<Icon>, anyhow::Error> {
+        if self.current == Some(name) {
+            return Ok(None);
+        }
+
+        // borrowed from the binary in the release builds, read from the disk in the debug ones
+        let file = Icons::get(name).ok_or_else(|| anyhow!("Failed to get icon file {name}"))?;
+        let icon = load_icon(&file.data)?;
+        self.current = Some(name);
+
+        Ok(Some(icon))
+    }
+}
+
+fn create_system_tray(
+    event_loop: &EventLoop<TrayEvent>,
+    tray_menu: ContextMenu,
+    tooltip: &str,
+    tray_icons: &mut TrayIcons,
+) -> Result<Option<SystemTray>, anyhow::Error> {
+    let icon = tray_icons
+        .icon(TRAY_ICON)?
+        .context("The icons should be of a new tray")?;
+
+    let system_tray = SystemTrayBuilder::new(icon, Some(tray_menu))
+        .with_id(TrayId::new("main"))
+        .with_tooltip(tooltip)
+        .build(event_loop)
+        .context("Failed to build the application system tray")?;
+
+    Ok(Some(system_tray))
+}
+
+/// The tray icon of the state of server.js, with a badge when it can't stream or when an update waits.
+fn tray_icon(server: ServerTrayStatus, pending_update: bool) -> &'static str {
+    match server {
+        ServerTrayStatus::Stopped | ServerTrayStatus::Crashed { .. } => TRAY_ICON_STOPPED,
+        ServerTrayStatus::Unhealthy | ServerTrayStatus::Restarting { .. } => TRAY_ICON_WARNING,
+        ServerTrayStatus::Starting | ServerTrayStatus::Running if pending_update => {
+            TRAY_ICON_UPDATING
+        }
+        ServerTrayStatus::Starting | ServerTrayStatus::Running => TRAY_ICON,
+    }
+}
+
+/// Shows the icon in the tray, unless it's the one shown already.
+fn set_tray_icon(
+    system_tray: &mut Option<SystemTray>,
+    tray_icons: &mut TrayIcons,
+    name: &'static str,
+) {
+    match tray_icons.icon(name) {
+        Ok(Some(icon)) => {
+            if let Some(system_tray) = system_tray.as_mut() {
+                system_tray.set_icon(icon);
+            }
+        }
+        Ok(None) => {}
+        Err(err) => error!("{err:#}"),
+    }
+}
+
+/// Shows the details of a handled link.
+fn show_link_record(record: &LinkRecord) {
+    let action = match &record.action {
+        LinkAction::Queued => "Queued until the server is ready".to_owned(),
+        LinkAction::Opened { target } => format!("Opened {target}"),
+        LinkAction::Refused { reason } => format!("Refused: {reason}"),
+    };
+
+    rfd::MessageDialog::new()
+        .set_title("Stremio Service")
+        .set_description(&format!(
+            "{}\n\n{}\n\n{action}",
+            record.timestamp.with_timezone(&chrono::Local).to_rfc2822(),
+            record.url
+        ))
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+/// Opens the QR code of the address of server.js in the image viewer, for the TVs and the phones to scan it.
+fn show_qr_code(opener: &dyn Opener, file: &Path, url: &Url) -> Result<(), Error> {
+    let url = url.as_str().trim_end_matches('/');
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    }
+    QrCode::encode(url.as_bytes())?.save_png(file, 8)?;
+    opener
+        .open(file.as_os_str())
+        .context(format!("Failed to open the QR code of {url}"))?;
+
+    info!("Showing the QR code of {url}");
+
+    Ok(())
+}
+
+/// Opens the last lines of the server.js output in the text editor, the ones of the crashed processes included.
+fn show_server_log(opener: &dyn Opener, file: &Path, server: &Server) -> Result<(), Error> {
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    }
+    let mut log = server.log_tail(server::LOG_TAIL_LINES).join("\n");
+    log.push('\n');
+    std::fs::write(file, log).context(format!("Failed to write {}", file.display()))?;
+    opener
+        .open(file.as_os_str())
+        .context(format!("Failed to open {}", file.display()))?;
+
+    Ok(())
+}
+
+/// Opens the page of the diagnostics in the browser, to be copied in the support requests.
+fn show_diagnostics(
+    opener: &dyn Opener,
+    file: &Path,
+    diagnostics: &Diagnostics,
+) -> Result<(), Error> {
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(file, diagnostics.html())
+        .context(format!("Failed to write {}", file.display()))?;
+    opener
+        .open(file.as_os_str())
+        .context(format!("Failed to open {}", file.display()))?;
+
+    Ok(())
+}
+
+/// Shows the diagnostics in a dialog, when their page can't be opened.
+fn show_about(diagnostics: &Diagnostics) {
+    rfd::MessageDialog::new()
+        .set_title("About Stremio Service")
+        .set_description(&diagnostics.to_string())
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+/// Asks the user where to export the settings and exports them.
+fn export_settings(settings_file: &Path) {
+    let destination = rfd::FileDialog::new()
+        .set_file_name("stremio-service-settings.json")
+        .add_filter("Settings", &["json"])
+        .save_file();
+
+    if let Some(destination) = destination {
+        match Settings::export(settings_file, &destination) {
+            Ok(()) => info!("Settings exported to {}", destination.display()),
+            Err(err) => error!("Failed to export settings: {err:?}"),
+        }
+    }
+}
+
+/// Starts the service on login or stops doing so, the choice is kept in the settings.
+fn set_start_on_login(
+    settings_file: &Path,
+    home_dir: &Path,
+    enabled: bool,
+) -> Result<(), ServiceError> {
+    autostart::set(home_dir, enabled).map_err(ServiceError::Other)?;
+    let mut settings = Settings::load(settings_file)?;
+    settings.autostart = enabled;
+    settings.save(settings_file)?;
+    info!(
+        "Start on login {}",
+        if enabled { "enabled" } else { "disabled" }
+    );
+
+    Ok(())
+}
+
+/// Updates from the channel from now on, the choice is kept in the settings.
+fn set_update_channel(settings_file: &Path, channel: Channel) -> Result<(), ServiceError> {
+    let mut settings = Settings::load(settings_file)?;
+    settings.update_channel = channel;
+    // it would keep the beta channel
+    settings.release_candidate = false;
+    settings.save(settings_file)?;
+    info!("Update channel {channel}");
+
+    Ok(())
+}
+
+/// Opens the web pages in the browser from now on, it's kept in the settings.
+fn set_browser(settings_file: &Path, browser: Browser) -> Result<(), ServiceError> {
+    let mut settings = Settings::load(settings_file)?;
+    settings.browser = browser;
+    settings.save(settings_file)?;
+    info!("Browser {}", browser.label());
+
+    Ok(())
+}
+
+/// Limits the download speed of server.js from now on, the limit is kept in the settings.
+fn set_download_limit(settings_file: &Path, limit: Option<u64>) -> Result<(), ServiceError> {
+    let mut settings = Settings::load(settings_file)?;
+    let key = server_settings::DOWNLOAD_SPEED_LIMIT.to_owned();
+    match limit {
+        Some(limit) => settings.server_settings.insert(key, limit.into()),
+        None => settings.server_settings.remove(&key),
+    };
+    settings.save(settings_file)?;
+    match limit {
+        Some(limit) => info!("Download limit {limit} B/s"),
+        None => info!("Download limit removed"),
+    }
+
+    Ok(())
+}
+
+/// Changes the download limits of the running server.js instances at once, the failures are logged.
+async fn set_download_limits(limits: Vec<(Server, u64)>) {
+    for (server, limit) in limits {
+        let values = BTreeMap::from([(
+            server_settings::DOWNLOAD_SPEED_LIMIT.to_owned(),
+            Value::from(limit),
+        )]);
+        if let Err(err) = server.update_settings(values).await {
+            warn!("{err}");
+        }
+    }
+}
+
+fn show_error(err: &ServiceError) {
+    error!("{err}");
+
+    rfd::MessageDialog::new()
+        .set_title("Stremio Service")
+        .set_description(&err.to_string())
+        .set_level(rfd::MessageLevel::Error)
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+/// Asks the user for a settings file and imports it.
+///
+/// The imported settings will be used the next time the service starts.
+fn import_settings(settings_file: &Path) {
+    let source = rfd::FileDialog::new()
+        .add_filter("Settings", &["json"])
+        .pick_file();
+
+    if let Some(source) = source {
+        match Settings::import(settings_file, &source) {
+            Ok(_) => {
+                info!("Settings imported from {}", source.display());
+
+                rfd::MessageDialog::new()
+                    .set_title("Stremio Service")
+                    .set_description("Settings imported, restart Stremio Service to apply them.")
+                    .set_buttons(rfd::MessageButtons::Ok)
+                    .show();
+            }
+            Err(err) => error!("Failed to import settings: {err:?}"),
+        }
+    }
+}
+
+/// The deep links handled by the service.
+///
+/// The links received before server.js is ready are queued,
+/// otherwise Stremio Web would fail to reach the server.
+#[derive(Debug, Clone)]
+pub struct DeepLinks {
+    web_ui: Url,
+    /// Allow addon installation links from any host, not only the Stremio ones
+    allow_any_addon_host: bool,
+    /// For the links to the service pages, see [`ServicePage`]
+    directories: Directories,
+    history: LinkHistory,
+    /// Notifies the tray when the recent links change
+    tray: Arc<Mutex<Option<EventLoopProxy<TrayEvent>>>>,
+    /// `None` once the queued links have been replayed
+    queue: Arc<Mutex<Option<Vec<String>>>>,
+    opener: Arc<dyn Opener>,
+    /// The server.js the torrents of the magnet links are created on
+    server_url: String,
+}
+
+impl DeepLinks {
+    pub fn new(config: &Config, history: LinkHistory) -> Self {
+        Self {
+            web_ui: config.web_ui.clone(),
+            allow_any_addon_host: config.allow_any_addon_host,
+            directories: config.directories.clone(),
+            history,
+            tray: Default::default(),
+            queue: Arc::new(Mutex::new(Some(vec![]))),
+            opener: Arc::new(SystemOpener),
+            server_url: config.server.url(),
+        }
+    }
+
+    /// Opens the links with the given opener instead of the [`SystemOpener`].
+    pub fn with_opener(mut self, opener: impl Opener + 'static) -> Self {
+        self.opener = Arc::new(opener);
+        self
+    }
+
+    /// Opens the link, or queues it if server.js is not ready yet.
+    pub fn handle(&self, open_url: String) {
+        let mut queue = self.queue.lock().expect("Should not be poisoned");
+        match queue.as_mut() {
+            Some(queue) => {
+                info!("Queued a link until server.js is ready");
+                self.record(&open_url, LinkAction::Queued);
+                queue.push(open_url);
+            }
+            None => {
+                drop(queue);
+                self.open(&open_url)
+            }
+        }
+    }
+
+    /// Notify the tray about the handled links from now on.
+    pub fn notify(&self, tray: EventLoopProxy<TrayEvent>) {
+        *self.tray.lock().expect("Should not be poisoned") = Some(tray);
+    }
+
+    /// Opens the queued links, the links handled afterwards are opened right away.
+    pub fn replay(&self) {
+        let queued = self
+            .queue
+            .lock()
+            .expect("Should not be poisoned")
+            .take()
+            .unwrap_or_default();
+
+        for open_url in queued {
+            self.open(&open_url);
+        }
+    }
+
+    /// Handles `stremio://` and `magnet:` urls by opening the matching Stremio Web route.
+    /// Either opens the Addon installation link, the player for magnet links
+    /// or the Web UI page (detail, search, etc.), see [`Route`].
+    ///
+    /// Every route is opened in the configured Web UI,
+    /// including the pages of `https://web.stremio.com` links.
+    ///
+    /// The `stremio://service/...` links open the service pages, see [`ServicePage`].
+    pub fn open(&self, open_url: &str) {
+        if let Some(page) = ServicePage::parse(open_url) {
+            let target = self.open_service_page(page);
+            return self.record(open_url, LinkAction::Opened { target });
+        }
+
+        match self.resolve(open_url) {
+            Ok(url) => {
+                if let Some(Route::Magnet(magnet)) = Route::parse(open_url) {
+                    self.create_torrent(magnet);
+                }
+                open_stremio_web(&self.opener, &url, None);
+                self.record(
+                    open_url,
+                    LinkAction::Opened {
+                        target: url.to_string(),
+                    },
+                );
+            }
+            Err(err) => {
+                warn!("Refused to open the link: {err}");
+                self.record(
+                    open_url,
+                    LinkAction::Refused {
+                        reason: err.to_string(),
+                    },
+                );
+            }
+        }
+    }
+
+    fn record(&self, open_url: &str, action: LinkAction) {
+        self.history.record(open_url, action);
+
+        if let Some(tray) = self.tray.lock().expect("Should not be poisoned").as_ref() {
+            // the event loop is closed only when quitting
+            let _ = tray.send_event(TrayEvent::LinksChanged);
+        }
+    }
+
+    /// Creates the torrent of the magnet link on server.js in the background, see [`server::create_torrent`].
+    fn create_torrent(&self, magnet: Magnet) {
+        // e.g. in the tests without a runtime
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let server_url = self.server_url.clone();
+        runtime.spawn(async move {
+            // the player still fetches it, only later
+            match server::create_torrent(&server_url, &magnet).await {
+                Ok(()) => info!("Created the torrent {} on server.js", magnet.info_hash),
+                Err(err) => warn!("{err:#}"),
+            }
+        });
+    }
+
+    /// Opens the service page and returns what was opened.
+    fn open_service_page(&self, page: ServicePage) -> String {
+        let path = match page {
+            ServicePage::Settings => self.directories.settings_file(),
+            ServicePage::Logs => {
+                let logs = self.directories.logs();
+                create_dir_if_does_not_exists(&logs);
+                logs
+            }
+            ServicePage::Server => {
+                let url = WebUiLink::new(&self.web_ui)
+                    .fragment(&Route::Settings.fragment())
+                    .build();
+                open_stremio_web(&self.opener, &url, None);
+
+                return url.to_string();
+            }
+        };
+
+        match self.opener.open(path.as_os_str()) {
+            Ok(_) => info!("Opened {}", path.display()),
+            Err(e) => error!("Failed to open {}: {}", path.display(), e),
+        }
+
+        path.display().to_string()
+    }
+
+    /// The sanitized Web UI url to open for the link.
+    fn resolve(&self, open_url: &str) -> Result<Url, Error> {
+        ensure!(
+            open_url.len() <= MAX_URL_LENGTH,
+            "The url is longer than {MAX_URL_LENGTH} characters"
+        );
+
+        if let Some(route) = Route::parse(open_url) {
+            if let Route::AddonInstall(manifest_url) = &route {
+                ensure!(
+                    self.allow_any_addon_host
+                        || manifest_url.host_str().is_some_and(is_stremio_host),
+                    "Addon host {:?} is not a Stremio host, set `allowAnyAddonHost` in the settings to install addons from any host",
+                    manifest_url.host_str().unwrap_or_default()
+                );
+            }
+
+            let url = WebUiLink::new(&self.web_ui)
+                .fragment(&route.fragment())
+                .build();
+            return sanitize(url);
+        }
+
+        let stremio_web = Url::parse(STREMIO_URL).expect("Valid Stremio Web url");
+        match Url::parse(open_url) {
+            Ok(url) if is_web_ui_link(&url, &self.web_ui) => sanitize(url),
+            Ok(url) if is_web_ui_link(&url, &stremio_web) => {
+                let mut web_ui_url = self.web_ui.to_owned();
+                web_ui_url.set_fragment(url.fragment());
+
+                sanitize(web_ui_url)
+            }
+            _ => bail!("Unsupported url: {open_url}"),
+        }
+    }
+}
+
+/// Opens the links and the files of the service, e.g. Stremio Web in the browser.
+///
+/// Replaced in the tests, to check what would be opened, and by the frontends opening the links themselves.
+pub trait Opener: std::fmt::Debug + Send + Sync {
+    fn open(&self, target: &OsStr) -> std::io::Result<()>;
+}
+
+impl<T: Opener + ?Sized> Opener for Arc<T> {
+    fn open(&self, target: &OsStr) -> std::io::Result<()> {
+        (**self).open(target)
+    }
+}
+
+/// Opens with the default application of the system, see [`open::that`], without a shell on Windows
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemOpener;
+
+impl Opener for SystemOpener {
+    fn open(&self, target: &OsStr) -> std::io::Result<()> {
+        #[cfg(target_os = "windows")]
+        return crate::browser::shell_open(target);
+        #[cfg(not(target_os = "windows"))]
+        open::that(target)
+    }
+}
+
+/// Opens the Web UIs of the tray in the app window with `openInWindow`, in the browser otherwise, see [`window`](crate::window)
+struct WebUiOpener {
+    opener: Arc<dyn Opener>,
+    /// `None` when they're opened in the browser
+    #[cfg(feature = "webview")]
+    window: Option<crate::window::WebUiWindow>,
+}
+
+impl WebUiOpener {
+    #[cfg_attr(not(feature = "webview"), allow(unused_variables))]
+    fn new(opener: Arc<dyn Opener>, config: &Config) -> Self {
+        Self {
+            opener,
+            #[cfg(feature = "webview")]
+            window: config
+                .open_in_window
+                .then(|| crate::window::WebUiWindow::new(config.directories.window_geometry())),
+        }
+    }
+
+    #[cfg_attr(not(feature = "webview"), allow(unused_variables))]
+    fn open(&mut self, window_target: &EventLoopWindowTarget<TrayEvent>, web_ui: &Url) {
+        #[cfg(feature = "webview")]
+        if let Some(window) = &mut self.window {
+            match window.open(window_target, &WebUiLink::new(web_ui).build()) {
+                Ok(()) => {
+                    info!("Opened Stremio Web in the app window");
+                    return;
+                }
+                // e.g. without the webview of the system
+                Err(err) => error!("{err:#}, opening the browser instead"),
+            }
+        }
+
+        open_stremio_web(&self.opener, web_ui, None);
+    }
+
+    #[cfg_attr(not(feature = "webview"), allow(unused_variables))]
+    fn handle(&mut self, event: &Event<TrayEvent>) {
+        #[cfg(feature = "webview")]
+        if let Some(window) = &mut self.window {
+            window.handle(event);
+        }
+    }
+}
+
+/// Opens the web pages in the [`Browser`] chosen in the tray, the files like the [`SystemOpener`]
+#[derive(Debug, Clone)]
+pub struct BrowserOpener {
+    browser: watch::Receiver<Browser>,
+    /// The program and the arguments of [`Browser::Custom`]
+    custom_command: Vec<String>,
+}
+
+impl BrowserOpener {
+    pub fn new(browser: watch::Receiver<Browser>, custom_command: Vec<String>) -> Self {
+        Self {
+            browser,
+            custom_command,
+        }
+    }
+}
+
+impl Opener for BrowserOpener {
+    fn open(&self, target: &OsStr) -> std::io::Result<()> {
+        let browser = *self.browser.borrow();
+        match target.to_str() {
+            Some(url) if browser::is_web_page(target) => browser.open(url, &self.custom_command),
+            _ => SystemOpener.open(target),
+        }
+    }
+}
+
+fn open_stremio_web(opener: &dyn Opener, web_ui: &Url, route: Option<Route>) {
+    let link = WebUiLink::new(web_ui);
+    let url = match route {
+        Some(route) => link.fragment(&route.fragment()).build(),
+        None => link.build(),
+    };
+
+    match opener.open(url.as_str().as_ref()) {
+        Ok(_) => info!("Opened Stremio Web in the browser"),
+        Err(e) => error!("Failed to open Stremio Web: {}", e),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn register_apple_event_callbacks(deep_links: DeepLinks) -> fruitbasket::FruitApp<'static> {
+    use fruitbasket::{FruitApp, FruitCallbackKey};
+
+    let mut app = FruitApp::new();
+
+    app.register_apple_event(fruitbasket::kInternetEventClass, fruitbasket::kAEGetURL);
+    app.register_callback(
+        FruitCallbackKey::Method("handleEvent:withReplyEvent:"),
+        Box::new(move |event| {
+            let open_url: String = fruitbasket::parse_url_event(event);
+            deep_links.handle(open_url);
+        }),
+    );
+
+    app
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_tray_icon_is_decoded_once() {
+        let mut tray_icons = TrayIcons::default();
+
+        assert!(tray_icons.icon(TRAY_ICON).expect("Should decode").is_some());
+        assert!(tray_icons.icon(TRAY_ICON).expect("Should decode").is_none());
+        assert!(tray_icons.icon("missing.png").is_err());
+        assert_eq!(Some(TRAY_ICON), tray_icons.current);
+    }
+
+    #[test]
+    fn test_tray_icon_follows_the_server_status() {
+        assert_eq!(TRAY_ICON, tray_icon(ServerTrayStatus::Running, false));
+        assert_eq!(
+            TRAY_ICON_UPDATING,
+            tray_icon(ServerTrayStatus::Running, true)
+        );
+        assert_eq!(
+            TRAY_ICON_WARNING,
+            tray_icon(ServerTrayStatus::Restarting { attempt: 1 }, true)
+        );
+        assert_eq!(
+            TRAY_ICON_WARNING,
+            tray_icon(ServerTrayStatus::Unhealthy, false)
+        );
+        assert_eq!(
+            TRAY_ICON_STOPPED,
+            tray_icon(ServerTrayStatus::Crashed { exit_code: None }, false)
+        );
+
+        let mut tray_icons = TrayIcons::default();
+        for name in [TRAY_ICON_WARNING, TRAY_ICON_UPDATING, TRAY_ICON_STOPPED] {
+            assert!(tray_icons.icon(name).expect("Should decode").is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_handle_is_shared_by_clones() {
+        let handle = ShutdownHandle::default();
+        let waiting = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.wait().await }
+        });
+        assert!(!handle.is_shutdown());
+
+        handle.clone().shutdown();
+        assert!(handle.is_shutdown());
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("Should resolve once triggered")
+            .expect("Should not panic");
+    }
+
+    /// Records what would be opened
+    #[derive(Debug, Default)]
+    struct RecordingOpener(Mutex<Vec<String>>);
+
+    impl Opener for RecordingOpener {
+        fn open(&self, target: &OsStr) -> std::io::Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .push(target.to_string_lossy().into_owned());
+            Ok(())
+        }
+    }
+
+    /// The deep links in a temporary home, removed with the returned directory
+    fn deep_links() -> (DeepLinks, Arc<RecordingOpener>, TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let directories = Directories {
+            home: dir.path().to_path_buf(),
+            config: dir.path().join("config"),
+            state: dir.path().join("state"),
+            cache: dir.path().join("cache"),
+            profile: None,
+        };
+        let opener = Arc::new(RecordingOpener::default());
+        let deep_links = DeepLinks {
+            web_ui: Url::parse("https://web.stremio.com/").unwrap(),
+            allow_any_addon_host: false,
+            history: LinkHistory::new(directories.link_history()),
+            directories,
+            tray: Default::default(),
+            queue: Arc::new(Mutex::new(Some(vec![]))),
+            opener: Arc::new(SystemOpener),
+            server_url: crate::constants::SERVER_URL.to_owned(),
+        }
+        .with_opener(opener.clone());
+
+        (deep_links, opener, dir)
+    }
+
+    fn opened(opener: &RecordingOpener) -> Vec<String> {
+        opener.0.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn test_deep_links_open_the_web_ui() {
+        let (deep_links, opener, _dir) = deep_links();
+
+        deep_links.handle("stremio:///detail/movie/tt0032138".to_owned());
+        assert!(opened(&opener).is_empty(), "Should be queued");
+        deep_links.replay();
+        deep_links.open("https://web.stremio.com/#/search?search=oz");
+
+        assert_eq!(
+            vec![
+                "https://web.stremio.com/#/detail/movie/tt0032138".to_owned(),
+                "https://web.stremio.com/#/search?search=oz".to_owned(),
+            ],
+            opened(&opener)
+        );
+    }
+
+    #[test]
+    fn test_deep_links_open_a_self_hosted_web_ui() {
+        let (mut deep_links, opener, _dir) = deep_links();
+        deep_links.web_ui = Url::parse("http://192.168.1.10:8080/stremio/").unwrap();
+        deep_links.replay();
+
+        deep_links.open("stremio:///detail/movie/tt0032138");
+        deep_links.open("https://web.stremio.com/#/search?search=oz");
+        deep_links.open("http://192.168.1.10:8080/stremio/#/library");
+
+        assert_eq!(
+            vec![
+                "http://192.168.1.10:8080/stremio/#/detail/movie/tt0032138".to_owned(),
+                "http://192.168.1.10:8080/stremio/#/search?search=oz".to_owned(),
+                "http://192.168.1.10:8080/stremio/#/library".to_owned(),
+            ],
+            opened(&opener)
+        );
+    }
+
+    #[test]
+    fn test_deep_links_refuse_addons_from_other_hosts() {
+        let (deep_links, opener, _dir) = deep_links();
+
+        deep_links.open("stremio://example.com/manifest.json");
+        deep_links.open("https://example.com/");
+        assert!(opened(&opener).is_empty());
+
+        deep_links.open("stremio://v3-cinemeta.strem.io/manifest.json");
+        assert_eq!(
+            vec!["https://web.stremio.com/#/addons?addon=https%3A%2F%2Fv3-cinemeta.strem.io%2Fmanifest.json".to_owned()],
+            opened(&opener)
+        );
+    }
+
+    #[test]
+    fn test_deep_links_open_the_service_pages() {
+        let (deep_links, opener, _dir) = deep_links();
+
+        deep_links.open("stremio://service/logs");
+        deep_links.open("stremio://service/settings");
+        assert_eq!(
+            vec![
+                deep_links.directories.logs().display().to_string(),
+                deep_links.directories.settings_file().display().to_string(),
+            ],
+            opened(&opener)
+        );
+        assert!(deep_links.directories.logs().is_dir());
+    }
 }
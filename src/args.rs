@@ -1,11 +1,27 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
 use url::Url;
 
+use crate::{
+    directories, server_settings,
+    transcoding::HwTranscoding,
+    updater::{Channel, UpdateMode},
+};
+
 #[derive(Parser, Debug, Clone)]
-#[command(author, version, about, long_about = None)]
+#[command(author, about, long_about = None)]
 pub struct Args {
+    /// Print the version and the build details and exit
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
+    /// Print the version and the build details as JSON
+    #[arg(long, requires = "version")]
+    pub json: bool,
+
     /// Whether or not to skip the updater
     ///
     /// This options is not used for `*nix` systems
@@ -23,21 +39,314 @@ pub struct Args {
 
     /// The endpoint to use for the updater
     ///
-    /// Overrides the default endpoint
+    /// Overrides the default endpoint, e.g. with a mirror or a `file://` update descriptor
     #[clap(short, long)]
     #[arg(group = "endpoint")]
     pub updater_endpoint: Option<Url>,
 
     /// Updates the app to the latest release candidate
     ///
-    /// This option is ignored when `--updater-endpoint` is set
+    /// The same as `--channel beta`, kept for the existing shortcuts
     #[clap(short, long)]
-    #[arg(group = "endpoint")]
+    #[arg(group = "endpoint", conflicts_with = "channel")]
     pub release_candidate: bool,
 
-    /// Open an URL with a custom `stremio://` scheme.
+    /// The release channel to update from
+    ///
+    /// Overrides the channel from the settings, it's appended to the query of the updater endpoint
+    #[clap(long, value_enum)]
+    pub channel: Option<Channel>,
+
+    /// When the downloaded update is installed, overrides the `updateMode` setting
+    #[clap(long, value_enum, value_name = "MODE")]
+    pub update_mode: Option<UpdateMode>,
+
+    /// Run without the tray, e.g. without a desktop session
+    ///
+    /// The service runs until it's interrupted, the tray items are left to the control API.
+    #[clap(long)]
+    pub headless: bool,
+
+    /// Open an URL with a custom `stremio://` scheme, a `magnet:` link
+    /// or a `https://web.stremio.com` link.
     ///
     /// If empty URL or no url is provided, the service will skip this argument.
     #[clap(short, long)]
     pub open: Option<String>,
+
+    /// The Web UI to open, either the name of a saved Web UI or an URL.
+    ///
+    /// Overrides the default Web UI from the settings.
+    #[clap(long, value_name = "NAME|URL")]
+    pub web_url: Option<String>,
+
+    /// Serve a build of Stremio Web and open it instead of the Web UIs, overrides the `localWebUi` setting
+    ///
+    /// It's the `build` directory of `npm run build` in stremio-web, served on `http://127.0.0.1:11473/web/`.
+    #[clap(long, value_name = "DIR")]
+    pub local_web_ui: Option<PathBuf>,
+
+    /// Run an independent instance with its own settings, lockfile, cache and data of server.js
+    ///
+    /// Its directories are named `stremio-service-<NAME>` instead, e.g. for one instance per family member.
+    /// The directories given explicitly override the ones of the profile.
+    #[clap(long, value_name = "NAME", value_parser = directories::parse_profile)]
+    pub profile: Option<String>,
+
+    /// Overrides the directory of the service configuration
+    #[clap(long, value_name = "DIR")]
+    pub config_dir: Option<PathBuf>,
+
+    /// Overrides the directory of the service state (lockfile, logs)
+    #[clap(long, value_name = "DIR")]
+    pub state_dir: Option<PathBuf>,
+
+    /// Overrides the directory of the service cache (downloaded updates)
+    #[clap(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Remove the `stremio://` protocol handler registered by the service and exit.
+    ///
+    /// Used when uninstalling the service.
+    #[clap(long)]
+    pub unregister_protocol: bool,
+
+    /// Register the service as a Windows Service started on boot, without a logged in user, and exit.
+    ///
+    /// Requires an administrator.
+    #[clap(long, conflicts_with = "uninstall_service")]
+    pub install_service: bool,
+
+    /// Stop and remove the Windows Service registered with `--install-service` and exit.
+    ///
+    /// Requires an administrator.
+    #[clap(long)]
+    pub uninstall_service: bool,
+
+    /// Restore the version run before the last update, start it and exit
+    ///
+    /// The reverted version isn't installed again, only the newer ones.
+    /// The running service has to be quit first.
+    #[clap(long)]
+    pub rollback: bool,
+
+    /// Read the password of the `proxyUsername` from the standard input, keep it in the OS keychain and exit
+    ///
+    /// An empty password removes it.
+    #[clap(long)]
+    pub set_proxy_password: bool,
+
+    /// Run as the Windows Service, used by the Service Control Manager.
+    #[clap(long, hide = true)]
+    pub run_service: bool,
+
+    /// Export the service settings to a portable file and exit.
+    #[clap(long, value_name = "FILE")]
+    #[arg(group = "settings")]
+    pub export_settings: Option<PathBuf>,
+
+    /// Import the service settings from a portable file and exit.
+    ///
+    /// The imported settings replace the current ones.
+    #[clap(long, value_name = "FILE")]
+    #[arg(group = "settings")]
+    pub import_settings: Option<PathBuf>,
+
+    /// Confine server.js to its own data directory
+    ///
+    /// Only supported on Linux with Landlock and on Windows, otherwise server.js runs unconfined
+    #[clap(long)]
+    pub sandbox_server: bool,
+
+    /// A PEM bundle of certificate authorities to trust besides the OS certificate store
+    #[clap(long, value_name = "FILE")]
+    pub ca_bundle: Option<PathBuf>,
+
+    /// The HTTP proxy of the requests to the internet, e.g. `http://proxy.example.com:3128`, overrides `proxy`
+    #[clap(long, value_name = "URL")]
+    pub proxy: Option<Url>,
+
+    /// Limit the disk writes of server.js to its cache, in bytes per second, e.g. `20M`
+    ///
+    /// Only supported on Linux with systemd
+    #[clap(long, value_name = "RATE")]
+    pub cache_write_limit: Option<String>,
+
+    /// Throttle the downloads of server.js while the system is stalled on the memory or the disk
+    ///
+    /// Only supported on Linux
+    #[clap(long)]
+    pub pressure_watchdog: bool,
+
+    /// The torrent cache size of server.js, e.g. `10G`, `0` disables the cache
+    #[clap(long, value_name = "SIZE", value_parser = server_settings::parse_size)]
+    pub cache_size: Option<u64>,
+
+    /// The transcoding profile of server.js
+    #[clap(long, value_name = "PROFILE")]
+    pub transcode_profile: Option<String>,
+
+    /// Whether server.js transcodes with the GPU, `auto` only when one of its encoders works
+    #[clap(long, value_enum, value_name = "MODE")]
+    pub hardware_transcoding: Option<HwTranscoding>,
+
+    /// The maximum download speed of the torrents of server.js in bytes per second, e.g. `2M`
+    #[clap(long, value_name = "RATE", value_parser = server_settings::parse_size)]
+    pub download_speed_limit: Option<u64>,
+
+    /// The local address server.js is reachable on over HTTPS by the other devices
+    #[clap(long, value_name = "ADDRESS")]
+    pub remote_https: Option<String>,
+
+    /// Any other setting of server.js, e.g. `btMaxConnections=55`, the value is parsed as JSON
+    ///
+    /// Can be given multiple times, the settings override the `serverSettings` of the settings file
+    #[clap(long, value_name = "KEY=VALUE", value_parser = server_settings::parse_setting)]
+    pub server_setting: Vec<(String, serde_json::Value)>,
+
+    /// The port server.js listens on, by default 11470
+    #[clap(long, value_name = "PORT")]
+    pub server_port: Option<u16>,
+
+    /// The log filter, e.g. `debug` or `info,server.js=warn`
+    ///
+    /// Overrides the one of the settings, while `RUST_LOG` takes precedence
+    #[clap(long, value_name = "FILTER")]
+    pub log_level: Option<String>,
+
+    /// Don't serve the local HTTP API controlling the service
+    #[clap(long)]
+    pub no_control_api: bool,
+
+    /// Advertise server.js on the local network with mDNS, for the TVs and the phones to find it
+    #[clap(long)]
+    pub lan_discovery: bool,
+
+    /// A PEM certificate for serving server.js over HTTPS on the local network
+    #[clap(long, value_name = "FILE", requires = "https_key")]
+    pub https_certificate: Option<PathBuf>,
+
+    /// The PEM PKCS#8 private key of the HTTPS certificate
+    #[clap(long, value_name = "FILE", requires = "https_certificate")]
+    pub https_key: Option<PathBuf>,
+
+    /// The port server.js is served over HTTPS on, by default 11472
+    #[clap(long, value_name = "PORT")]
+    pub https_port: Option<u16>,
+
+    /// The server.js to run, defaults to the one set with `STREMIO_SERVICE_SERVER_JS` at build time
+    #[cfg(feature = "no-bundled-server")]
+    #[clap(long, env = "STREMIO_SERVICE_SERVER_JS", value_name = "FILE")]
+    pub server_js: Option<PathBuf>,
+
+    /// The Node.js runtime of server.js, defaults to the `node` in `PATH`
+    #[cfg(feature = "no-bundled-server")]
+    #[clap(long, env = "STREMIO_SERVICE_NODE", value_name = "FILE")]
+    pub node: Option<PathBuf>,
+
+    /// The ffmpeg of server.js, defaults to the one in `PATH`
+    #[cfg(feature = "no-bundled-server")]
+    #[clap(long, env = "STREMIO_SERVICE_FFMPEG", value_name = "FILE")]
+    pub ffmpeg: Option<PathBuf>,
+
+    /// The ffprobe of server.js, defaults to the one in `PATH`
+    #[cfg(feature = "no-bundled-server")]
+    #[clap(long, env = "STREMIO_SERVICE_FFPROBE", value_name = "FILE")]
+    pub ffprobe: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Run the service, the default without a command
+    ///
+    /// The options of the service go before the command, e.g. `stremio-service --headless run`.
+    Run,
+    /// Show the status of server.js run by the running service
+    Status {
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Start, stop or restart server.js of the running service
+    Server {
+        #[command(subcommand)]
+        command: ServerCommand,
+    },
+    /// Check the updates of the running service
+    Update {
+        #[command(subcommand)]
+        command: UpdateCommand,
+    },
+    /// Show the current service settings
+    Config {
+        /// List all settings keys with their types, defaults and descriptions
+        #[arg(long)]
+        list: bool,
+
+        /// Print the list as a Markdown table
+        #[arg(long, requires = "list")]
+        markdown: bool,
+    },
+    /// Manage the service started by the init system
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerCommand {
+    /// Start server.js, unless it's running
+    Start,
+    /// Stop server.js, it's started again only by `server start` or a restart of the service
+    Stop,
+    /// Stop server.js, when it's running, and start it again
+    Restart,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum UpdateCommand {
+    /// Check whether there's a newer release on the update channel, without installing it
+    Check {
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Install an update from a local file, e.g. on a machine without internet access
+    ///
+    /// The file is an installer, an update descriptor or a `.zip` with the `descriptor.json`
+    /// and the installers it lists. It's verified like a downloaded update, the service must not be running.
+    Apply {
+        /// The installer, the `.json` descriptor or the `.zip` bundle
+        file: PathBuf,
+
+        /// The version of the installer, required for an installer
+        #[arg(long)]
+        version: Option<semver::Version>,
+
+        /// The SHA-256 checksum of the installer, required for an installer
+        #[arg(long, value_name = "CHECKSUM")]
+        sha256: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ServiceCommand {
+    /// Install a systemd user unit starting the service on login, enable and start it
+    ///
+    /// It replaces the autostart desktop entry, "Start on login" enables and disables the unit then.
+    InstallSystemd {
+        /// Start the service without the tray, with the user manager instead of the graphical session
+        #[arg(long)]
+        headless: bool,
+
+        /// Only print the unit, e.g. to install it from the host of a Flatpak
+        #[arg(long)]
+        print: bool,
+    },
+    /// Stop, disable and remove the systemd user unit
+    UninstallSystemd,
 }
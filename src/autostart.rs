@@ -0,0 +1,327 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Starting the service on login for the current user, toggled from the tray.
+//!
+//! On Linux the desktop entry is copied to the autostart directory (inside Flatpak the Background portal is asked instead)
+//! or, once it's installed, the systemd user unit is enabled (see [`systemd`](crate::systemd)),
+//! on macOS a LaunchAgent is installed and on Windows the `Run` registry value of the user is set.
+
+use std::path::Path;
+
+use anyhow::Error;
+
+/// Starts the service on login or stops doing so.
+pub fn set(home_dir: &Path, enabled: bool) -> Result<(), Error> {
+    if enabled {
+        enable(home_dir)
+    } else {
+        disable(home_dir)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn autostart_entry(home_dir: &Path) -> std::path::PathBuf {
+    use crate::constants::{AUTOSTART_CONFIG_PATH, DESKTOP_FILE_NAME};
+
+    home_dir.join(AUTOSTART_CONFIG_PATH).join(DESKTOP_FILE_NAME)
+}
+
+/// Whether the service starts on login, `None` inside Flatpak where only the portal knows it.
+#[cfg(target_os = "linux")]
+pub fn is_enabled(home_dir: &Path) -> Option<bool> {
+    if crate::profile::Profile::is_flatpak() {
+        return None;
+    }
+    if crate::systemd::is_installed(home_dir) {
+        return Some(crate::systemd::is_enabled());
+    }
+
+    Some(autostart_entry(home_dir).exists())
+}
+
+/// Copies the desktop entry to the autostart directory, unless it's already there.
+#[cfg(target_os = "linux")]
+pub fn enable(home_dir: &Path) -> Result<(), Error> {
+    use std::path::PathBuf;
+
+    use anyhow::Context;
+
+    use crate::{
+        constants::{DESKTOP_FILE_NAME, DESKTOP_FILE_PATH, SNAP_DESKTOP_FILE_PATH},
+        profile::Profile,
+    };
+
+    // the host autostart directory is outside of the sandbox
+    if Profile::is_flatpak() {
+        return request_background(true);
+    }
+    if crate::systemd::is_installed(home_dir) {
+        return crate::systemd::set_enabled(true);
+    }
+
+    // the home of a snap is its own, snapd starts the desktop files from its autostart directory
+    let from = match std::env::var_os("SNAP") {
+        Some(snap) if Profile::is_snap() => PathBuf::from(snap).join(SNAP_DESKTOP_FILE_PATH),
+        _ => PathBuf::from(DESKTOP_FILE_PATH).join(DESKTOP_FILE_NAME),
+    };
+    let to = autostart_entry(home_dir);
+    if to.exists() {
+        return Ok(());
+    }
+
+    if let Some(autostart_dir) = to.parent() {
+        std::fs::create_dir_all(autostart_dir)
+            .context(format!("Failed to create {}", autostart_dir.display()))?;
+    }
+    std::fs::copy(&from, &to).context(format!(
+        "Failed to copy the desktop file {} to the autostart location",
+        from.display()
+    ))?;
+
+    Ok(())
+}
+
+/// Removes the desktop entry from the autostart directory.
+#[cfg(target_os = "linux")]
+pub fn disable(home_dir: &Path) -> Result<(), Error> {
+    if crate::profile::Profile::is_flatpak() {
+        return request_background(false);
+    }
+    if crate::systemd::is_installed(home_dir) {
+        return crate::systemd::set_enabled(false);
+    }
+
+    remove_desktop_entry(home_dir)
+}
+
+/// Removes the desktop entry from the autostart directory, also when the systemd unit replaces it.
+#[cfg(target_os = "linux")]
+pub fn remove_desktop_entry(home_dir: &Path) -> Result<(), Error> {
+    use anyhow::Context;
+
+    let entry = autostart_entry(home_dir);
+    match std::fs::remove_file(&entry) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).context(format!("Failed to remove {}", entry.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Asks the Background portal to start the service on login or not, the user is prompted only the first time.
+#[cfg(target_os = "linux")]
+fn request_background(autostart: bool) -> Result<(), Error> {
+    use anyhow::{bail, Context};
+    use log::info;
+
+    let options = format!("{{'reason': <'Start Stremio Service on login'>, 'autostart': <{autostart}>, 'commandline': <['stremio-service']>}}");
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.portal.Desktop",
+            "--object-path",
+            "/org/freedesktop/portal/desktop",
+            "--method",
+            "org.freedesktop.portal.Background.RequestBackground",
+            "",
+            &options,
+        ])
+        .output()
+        .context("Failed to request autostart from the Background portal")?;
+    if !output.status.success() {
+        bail!(
+            "The Background portal refused the autostart: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    info!("Requested autostart {autostart} from the Background portal");
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn launch_agent(home_dir: &Path) -> std::path::PathBuf {
+    use crate::constants::{APP_IDENTIFIER, LAUNCH_AGENTS_PATH};
+
+    home_dir
+        .join(LAUNCH_AGENTS_PATH)
+        .join(format!("{APP_IDENTIFIER}.plist"))
+}
+
+/// Whether the LaunchAgent is installed
+#[cfg(target_os = "macos")]
+pub fn is_enabled(home_dir: &Path) -> Option<bool> {
+    Some(launch_agent(home_dir).exists())
+}
+
+/// Installs the LaunchAgent opening the app, unless it's already installed.
+#[cfg(target_os = "macos")]
+pub fn enable(home_dir: &Path) -> Result<(), Error> {
+    use anyhow::Context;
+
+    use crate::constants::{APP_IDENTIFIER, APP_NAME};
+
+    let plist_launch_agent = format!("
+            <?xml version=\"1.0\" encoding=\"UTF-8\"?>
+            <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+            <plist version=\"1.0\">
+            <dict>
+                <key>Label</key>
+                <string>{}</string>
+                <key>ProgramArguments</key>
+                <array>
+                    <string>/usr/bin/open</string>
+                    <string>-a</string>
+                    <string>{}</string>
+                </array>
+                <key>RunAtLoad</key>
+                <true/>
+            </dict>
+            </plist>
+        ", APP_IDENTIFIER, APP_NAME);
+
+    let plist_path = launch_agent(home_dir);
+    if plist_path.exists() {
+        return Ok(());
+    }
+
+    if let Some(launch_agents_path) = plist_path.parent() {
+        std::fs::create_dir_all(launch_agents_path)
+            .context(format!("Failed to create {}", launch_agents_path.display()))?;
+    }
+    std::fs::write(&plist_path, plist_launch_agent.as_bytes())
+        .context("Failed to create a plist file in LaunchAgents dir")?;
+
+    Ok(())
+}
+
+/// Removes the LaunchAgent.
+#[cfg(target_os = "macos")]
+pub fn disable(home_dir: &Path) -> Result<(), Error> {
+    use anyhow::Context;
+
+    let plist_path = launch_agent(home_dir);
+    match std::fs::remove_file(&plist_path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).context(format!("Failed to remove {}", plist_path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(target_os = "windows")]
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// Whether the `Run` value is set for the user or, by a machine-wide installation, for all the users
+#[cfg(target_os = "windows")]
+pub fn is_enabled(_home_dir: &Path) -> Option<bool> {
+    use winreg::{
+        enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE},
+        RegKey,
+    };
+
+    use crate::constants::APP_NAME;
+
+    Some(
+        [HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE]
+            .into_iter()
+            .any(|hkey| {
+                RegKey::predef(hkey)
+                    .open_subkey(RUN_KEY)
+                    .and_then(|key| key.get_value::<String, _>(APP_NAME))
+                    .is_ok()
+            }),
+    )
+}
+
+/// Sets the `Run` value of the user to the current executable, the same one as the installer's.
+#[cfg(target_os = "windows")]
+pub fn enable(_home_dir: &Path) -> Result<(), Error> {
+    use anyhow::Context;
+    use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+    use crate::constants::APP_NAME;
+
+    let current_exe = std::env::current_exe().context("Failed to get current executable")?;
+    let (key, _) = RegKey::predef(HKEY_CURRENT_USER)
+        .create_subkey(RUN_KEY)
+        .context("Failed to open the Run registry key")?;
+    key.set_value(APP_NAME, &format!("\"{}\"", current_exe.display()))
+        .context("Failed to set the Run registry value")?;
+
+    Ok(())
+}
+
+/// Removes the `Run` value of the user.
+///
+/// # Errors
+///
+/// Also when a machine-wide installation starts the service for all the users,
+/// as only an administrator can change that.
+#[cfg(target_os = "windows")]
+pub fn disable(home_dir: &Path) -> Result<(), Error> {
+    use anyhow::{bail, Context};
+    use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+    use crate::constants::APP_NAME;
+
+    if let Ok(key) = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey_with_flags(RUN_KEY, winreg::enums::KEY_SET_VALUE)
+    {
+        match key.delete_value(APP_NAME) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                return Err(err).context("Failed to remove the Run registry value");
+            }
+            _ => {}
+        }
+    }
+
+    if is_enabled(home_dir) == Some(true) {
+        bail!("The service is started on login for all the users, an administrator can disable it in the Task Manager");
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn is_enabled(_home_dir: &Path) -> Option<bool> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn enable(_home_dir: &Path) -> Result<(), Error> {
+    anyhow::bail!(
+        "Starting on login is not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn disable(_home_dir: &Path) -> Result<(), Error> {
+    anyhow::bail!(
+        "Starting on login is not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_autostart_entry_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let home_dir = dir.path().join("home");
+        let entry = autostart_entry(&home_dir);
+        std::fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        std::fs::write(&entry, "[Desktop Entry]\n").unwrap();
+        assert_eq!(Some(true), is_enabled(&home_dir));
+
+        set(&home_dir, false).expect("Should remove the entry");
+        assert_eq!(Some(false), is_enabled(&home_dir));
+        set(&home_dir, false).expect("Should do nothing");
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use env_logger::Env;
+use flate2::{write::GzEncoder, Compression};
+use log::info;
+use serde::Deserialize;
+
+#[path = "bundle/linux.rs"]
+mod linux;
+#[path = "bundle/target.rs"]
+mod target;
+
+use linux::LinuxMetadata;
+use target::Target;
+
+/// Bundles the `.deb` package
+#[derive(Parser, Debug)]
+struct Args {
+    #[command(flatten)]
+    target: Target,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DebMetadata {
+    copyright: String,
+    extended_description: String,
+    depends: String,
+    section: String,
+    priority: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    linux: Option<LinuxMetadata>,
+    deb: Option<DebMetadata>,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    args.target.ensure_os("linux")?;
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let package = manifest.package.context("Failed to parse package")?;
+    let Metadata { linux, deb } = package
+        .metadata
+        .clone()
+        .context("Failed to parse manifest.package.metadata")?;
+    let linux = linux.context("Failed to parse manifest.package.metadata.linux")?;
+    let metadata = deb.context("Failed to parse manifest.package.metadata.deb")?;
+
+    let arch = match args.target.arch() {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        arch => bail!("Architecture {arch} is not supported"),
+    };
+
+    // data.tar.gz - the installed files
+    let mut files = vec![];
+    let mut directories = std::collections::BTreeSet::new();
+    for asset in linux.assets(&manifest_dir, &args.target)? {
+        let contents = std::fs::read(&asset.source)
+            .context(format!("Failed to read {}", asset.source.display()))?;
+        let destination = asset.destination.trim_start_matches('/').to_owned();
+
+        // every parent directory has to be in the archive as well
+        directories.extend(parent_directories(&destination));
+
+        info!("Adding {} as /{destination}", asset.source.display());
+        files.push((destination, asset.mode, contents));
+    }
+    let copyright_file = format!("usr/share/doc/{}/copyright", package.name);
+    directories.extend(parent_directories(&copyright_file));
+    files.push((
+        copyright_file,
+        0o644,
+        format!(
+            "{}\nLicense: {}\n",
+            metadata.copyright,
+            package.license().unwrap_or_default()
+        )
+        .into_bytes(),
+    ));
+
+    let mut data = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::best()));
+    // sorted, so the parents are always added before their children
+    for directory in directories {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        header.set_mtime(0);
+        header.set_cksum();
+        data.append_data(
+            &mut header,
+            format!("./{}/", directory.display()),
+            std::io::empty(),
+        )?;
+    }
+    let installed_size = files
+        .iter()
+        .map(|(_, _, contents)| contents.len() as u64)
+        .sum::<u64>();
+    for (destination, mode, contents) in files {
+        append_file(&mut data, &destination, mode, &contents)?;
+    }
+    let data = data.into_inner()?.finish()?;
+
+    // control.tar.gz - the package description and the maintainer scripts
+    let description = format!(
+        "{}\n {}",
+        package.description().unwrap_or_default(),
+        metadata.extended_description
+    );
+    let control = format!(
+        "Package: {name}\n\
+        Version: {version}\n\
+        Architecture: {arch}\n\
+        Maintainer: {maintainer}\n\
+        Installed-Size: {installed_size}\n\
+        Depends: {depends}\n\
+        Section: {section}\n\
+        Priority: {priority}\n\
+        Homepage: {homepage}\n\
+        Description: {description}\n",
+        name = package.name,
+        version = env!("CARGO_PKG_VERSION"),
+        maintainer = package.authors().join(", "),
+        installed_size = installed_size.div_ceil(1024),
+        depends = metadata.depends,
+        section = metadata.section,
+        priority = metadata.priority,
+        homepage = package.homepage().unwrap_or_default(),
+    );
+    let mut control_tar = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::best()));
+    append_file(&mut control_tar, "control", 0o644, control.as_bytes())?;
+
+    for script in linux::SCRIPTS {
+        if let Some(contents) = linux.script(&manifest_dir, script)? {
+            info!("Adding the {script} script");
+            append_file(&mut control_tar, script, 0o755, contents.as_bytes())?;
+        }
+    }
+    let control_tar = control_tar.into_inner()?.finish()?;
+
+    let target_path = manifest_dir.join("target").join("debian");
+    std::fs::create_dir_all(&target_path).context(format!(
+        "Failed to create target dir: {}",
+        target_path.display()
+    ))?;
+    let deb_path = target_path.join(format!(
+        "{}_{}_{arch}.deb",
+        package.name,
+        env!("CARGO_PKG_VERSION")
+    ));
+
+    let deb_file = std::fs::File::create(&deb_path)
+        .context(format!("Failed to create {}", deb_path.display()))?;
+    let mut deb = ar::Builder::new(deb_file);
+    for (name, contents) in [
+        ("debian-binary", b"2.0\n".as_slice()),
+        ("control.tar.gz", &control_tar),
+        ("data.tar.gz", &data),
+    ] {
+        let mut header = ar::Header::new(name.as_bytes().to_vec(), contents.len() as u64);
+        header.set_mode(0o100644);
+        deb.append(&header, contents)
+            .context(format!("Failed to write {name}"))?;
+    }
+    info!("Finished bundling {}", deb_path.display());
+
+    Ok(())
+}
+
+fn append_file<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    mode: u32,
+    contents: &[u8],
+) -> anyhow::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(mode);
+    header.set_size(contents.len() as u64);
+    header.set_mtime(0);
+    header.set_cksum();
+
+    archive
+        .append_data(&mut header, format!("./{path}"), contents)
+        .context(format!("Failed to add {path} to the archive"))
+}
+
+/// Every parent directory of the relative path, e.g. `usr/share` and `usr` for `usr/share/file`
+fn parent_directories(path: &str) -> impl Iterator<Item = PathBuf> + '_ {
+    Path::new(path)
+        .ancestors()
+        .skip(1)
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+}
@@ -0,0 +1,134 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use env_logger::Env;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct FlatpakMetadata {
+    app_id: String,
+    runtime: String,
+    runtime_version: String,
+    sdk: String,
+    sdk_extensions: Vec<String>,
+    command: String,
+    finish_args: Vec<String>,
+    sources: Vec<String>,
+    modules: Vec<String>,
+    /// `[source, destination, mode]`
+    assets: Vec<[String; 3]>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    flatpak: Option<FlatpakMetadata>,
+}
+
+/// The `flatpak-builder` manifest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Manifest {
+    app_id: String,
+    runtime: String,
+    runtime_version: String,
+    sdk: String,
+    sdk_extensions: Vec<String>,
+    command: String,
+    finish_args: Vec<String>,
+    build_options: serde_json::Value,
+    modules: Vec<Module>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Module {
+    name: String,
+    buildsystem: String,
+    build_options: serde_json::Value,
+    build_commands: Vec<String>,
+    post_install: Vec<String>,
+    sources: Vec<serde_json::Value>,
+    modules: Vec<String>,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let metadata = manifest
+        .package
+        .context("Failed to parse package")?
+        .metadata
+        .context("Failed to parse manifest.package.metadata")?
+        .flatpak
+        .context("Failed to parse manifest.package.metadata.flatpak")?;
+
+    // the sources are the whole repository, the downloaded crates and server.js
+    let sources = std::iter::once(serde_json::json!({ "type": "dir", "path": "." }))
+        .chain(metadata.sources.into_iter().map(serde_json::Value::from))
+        .collect();
+
+    // flatpak-builder doesn't pass the environment to the build, the bundled build fails without the key
+    let mut build_env = serde_json::json!({ "CARGO_HOME": "/run/build/service/cargo" });
+    if let Ok(public_key) = std::env::var("STREMIO_SERVICE_UPDATE_PUBLIC_KEY") {
+        build_env["STREMIO_SERVICE_UPDATE_PUBLIC_KEY"] = public_key.trim().into();
+    }
+
+    let flatpak_manifest = Manifest {
+        app_id: metadata.app_id.clone(),
+        runtime: metadata.runtime,
+        runtime_version: metadata.runtime_version,
+        sdk: metadata.sdk,
+        sdk_extensions: metadata.sdk_extensions,
+        command: metadata.command,
+        finish_args: metadata.finish_args,
+        build_options: serde_json::json!({ "append-path": "/usr/lib/sdk/rust-stable/bin" }),
+        modules: vec![Module {
+            name: "service".into(),
+            buildsystem: "simple".into(),
+            build_options: serde_json::json!({ "env": build_env }),
+            build_commands: vec![
+                "cargo --offline fetch --manifest-path Cargo.toml --verbose".into(),
+                "cargo --offline build --release --features=bundled,offline-build --verbose".into(),
+            ],
+            post_install: metadata
+                .assets
+                .iter()
+                .map(|[source, destination, mode]| {
+                    format!("install -Dm{mode} ./{source} {destination}")
+                })
+                .collect(),
+            sources,
+            modules: metadata.modules,
+        }],
+    };
+
+    // the same indentation as the rest of the flatpak manifests
+    let mut contents = vec![];
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut contents, formatter);
+    flatpak_manifest
+        .serialize(&mut serializer)
+        .context("Failed to serialize the Flatpak manifest")?;
+    contents.push(b'\n');
+
+    let flatpak_manifest_path = manifest_dir.join(format!("{}.json", metadata.app_id));
+    std::fs::write(&flatpak_manifest_path, contents).context(format!(
+        "Failed to write {}",
+        flatpak_manifest_path.display()
+    ))?;
+    info!(
+        "Finished the Flatpak manifest {}",
+        flatpak_manifest_path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::{Path, PathBuf};
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::os::unix::fs::PermissionsExt;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use env_logger::Env;
+use log::info;
+use serde::Deserialize;
+
+#[path = "bundle/fs.rs"]
+mod fs;
+#[path = "bundle/target.rs"]
+mod target;
+
+use fs::create_dir;
+use target::Target;
+
+/// Bundles the AppImage
+#[derive(Parser, Debug)]
+struct Args {
+    #[command(flatten)]
+    target: Target,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct AppImageMetadata {
+    name: String,
+    desktop_file: String,
+    metainfo: String,
+    icon: Vec<String>,
+    executable: String,
+    bins: Vec<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    appimage: Option<AppImageMetadata>,
+}
+
+/// The directory inside the AppDir where the service and its binaries are installed
+const INSTALL_DIR: &str = "usr/share/stremio-service";
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    args.target.ensure_os("linux")?;
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let metadata = manifest
+        .package
+        .context("Failed to parse package")?
+        .metadata
+        .context("Failed to parse manifest.package.metadata")?
+        .appimage
+        .context("Failed to parse manifest.package.metadata.appimage")?;
+
+    let target_path = manifest_dir.join("target").join("linux");
+    std::fs::create_dir_all(target_path.clone()).context(format!(
+        "Failed to create target dir: {}",
+        target_path.display()
+    ))?;
+
+    let app_dir = target_path.join(format!("{}.AppDir", metadata.name));
+    if app_dir.exists() {
+        std::fs::remove_dir_all(app_dir.clone()).context("Failed to remove old AppDir")?;
+    }
+
+    let install_path = app_dir.join(INSTALL_DIR);
+    create_dir(&install_path)?;
+
+    for bin in metadata.bins.iter() {
+        let source = args.target.source(&manifest_dir, &bin[0]);
+        info!("Copying {source} to {}", bin[1]);
+        std::fs::copy(manifest_dir.join(&source), install_path.join(&bin[1]))
+            .context(format!("Failed to copy {source} to {}", bin[1]))?;
+        make_executable(&install_path.join(&bin[1]))?;
+    }
+    info!("All files copied");
+
+    let app_run = format!(
+        "#!/bin/sh\n\
+        HERE=\"$(dirname \"$(readlink -f \"$0\")\")\"\n\
+        exec \"$HERE/{INSTALL_DIR}/{executable}\" \"$@\"\n",
+        executable = metadata.executable
+    );
+    std::fs::write(app_dir.join("AppRun"), app_run).context("Failed to write AppRun")?;
+    make_executable(&app_dir.join("AppRun"))?;
+
+    // The AppImage runs the executable from the AppRun instead of the installed location
+    let desktop_file_name = Path::new(&metadata.desktop_file)
+        .file_name()
+        .context("Invalid desktop file")?;
+    let desktop_file = std::fs::read_to_string(manifest_dir.join(&metadata.desktop_file))
+        .context(format!("Failed to read {}", metadata.desktop_file))?
+        .lines()
+        .map(|line| match line.starts_with("Exec=") {
+            true => format!("Exec={} -o %u", metadata.executable),
+            false => line.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    for desktop_file_path in [
+        app_dir.join(desktop_file_name),
+        app_dir
+            .join("usr/share/applications")
+            .join(desktop_file_name),
+    ] {
+        create_dir(desktop_file_path.parent().expect("Should have a parent"))?;
+        std::fs::write(&desktop_file_path, &desktop_file).context(format!(
+            "Failed to write the desktop file to {}",
+            desktop_file_path.display()
+        ))?;
+    }
+
+    let metainfo_name = Path::new(&metadata.metainfo)
+        .file_name()
+        .context("Invalid metainfo file")?;
+    let metainfo_dir = app_dir.join("usr/share/metainfo");
+    create_dir(&metainfo_dir)?;
+    std::fs::copy(
+        manifest_dir.join(&metadata.metainfo),
+        metainfo_dir.join(metainfo_name),
+    )
+    .context(format!("Failed to copy {}", metadata.metainfo))?;
+
+    let icons_dir = app_dir.join("usr/share/icons/hicolor/scalable/apps");
+    create_dir(&icons_dir)?;
+    for icon_path in [
+        app_dir.join(&metadata.icon[1]),
+        app_dir.join(".DirIcon"),
+        icons_dir.join(&metadata.icon[1]),
+    ] {
+        std::fs::copy(manifest_dir.join(&metadata.icon[0]), &icon_path).context(format!(
+            "Failed to copy {} to {}",
+            metadata.icon[0],
+            icon_path.display()
+        ))?;
+    }
+    info!("Finished the AppDir {}", app_dir.display());
+
+    let arch = args.target.arch();
+    let appimage_path = target_path.join(format!("{}-{arch}.AppImage", metadata.name));
+    let appimagetool = std::env::var("APPIMAGETOOL").unwrap_or_else(|_| "appimagetool".into());
+    let status = std::process::Command::new(&appimagetool)
+        .env("ARCH", arch)
+        .arg(&app_dir)
+        .arg(&appimage_path)
+        .status()
+        .context(format!("Failed to run {appimagetool}"))?;
+    if !status.success() {
+        bail!("{appimagetool} exited with {status}");
+    }
+    info!("Finished bundling {}", appimage_path.display());
+
+    Ok(())
+}
+
+fn make_executable(path: &Path) -> anyhow::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .context(format!("Failed to set permissions for {}", path.display()))?;
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    let _ = path;
+
+    Ok(())
+}
@@ -1,15 +1,50 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
-use std::path::PathBuf;
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 use std::os::unix::fs::PermissionsExt;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
+use clap::Parser;
 use env_logger::Env;
-use log::info;
+use log::{info, warn};
 use serde::Deserialize;
 
+#[path = "bundle/target.rs"]
+mod target;
+
+use target::Target;
+
+/// Bundles the MacOS `.app` and optionally signs, notarizes and packages it in a `.dmg`
+#[derive(Parser, Debug)]
+struct Args {
+    /// The identity to codesign the `.app` with, using the hardened runtime
+    #[arg(long, value_name = "IDENTITY")]
+    sign: Option<String>,
+    /// The `notarytool` keychain profile to notarize the signed `.app` with
+    #[arg(long, value_name = "PROFILE", requires = "sign")]
+    notarize: Option<String>,
+    /// Package the `.app` in a `.dmg` with the `/Applications` link, the license and the background
+    #[arg(long)]
+    dmg: bool,
+    /// Create universal binaries from the builds for every architecture, when available
+    #[arg(long, conflicts_with = "target")]
+    universal: bool,
+    #[command(flatten)]
+    target: Target,
+}
+
+/// The architectures of the universal binaries with their Rust target triples
+const ARCHITECTURES: [(&str, &str); 2] = [
+    ("x86_64", "x86_64-apple-darwin"),
+    ("arm64", "aarch64-apple-darwin"),
+];
+
 #[derive(Clone, Debug, Deserialize)]
 struct MacOSMetadata {
     name: String,
@@ -17,11 +52,25 @@ struct MacOSMetadata {
     identifier: String,
     icon: Vec<String>,
     copyright: String,
-    url_scheme: String,
+    url_schemes: Vec<String>,
     executable: String,
+    entitlements: String,
+    dmg: DmgMetadata,
     bins: Vec<Vec<String>>,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+struct DmgMetadata {
+    /// The name of the volume
+    title: String,
+    background: String,
+    icon_size: u32,
+    app_position: [u32; 2],
+    applications_position: [u32; 2],
+    license: String,
+    license_position: [u32; 2],
+}
+
 #[derive(Clone, Debug, Deserialize)]
 struct Metadata {
     macos: Option<MacOSMetadata>,
@@ -29,6 +78,8 @@ struct Metadata {
 
 fn main() -> anyhow::Result<()> {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    args.target.ensure_os("macos")?;
 
     let manifest_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
     let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
@@ -50,7 +101,7 @@ fn main() -> anyhow::Result<()> {
         target_path.display()
     ))?;
 
-    let bundle_path = target_path.join(metadata.name + ".app");
+    let bundle_path = target_path.join(format!("{}.app", metadata.name));
 
     if bundle_path.exists() {
         std::fs::remove_dir_all(bundle_path.clone()).context("Failed to remove old bundle")?;
@@ -89,7 +140,7 @@ fn main() -> anyhow::Result<()> {
                         <string>{url_name}</string>
                         <key>CFBundleURLSchemes</key>
                         <array>
-                            <string>{url_scheme}</string>
+                            {url_schemes}
                         </array>
                     </dict>
                 </array>
@@ -103,7 +154,12 @@ fn main() -> anyhow::Result<()> {
         executable = metadata.executable,
         copyright = metadata.copyright,
         url_name = metadata.display_name,
-        url_scheme = metadata.url_scheme
+        url_schemes = metadata
+            .url_schemes
+            .iter()
+            .map(|url_scheme| format!("<string>{url_scheme}</string>"))
+            .collect::<Vec<_>>()
+            .join("\n")
     );
     std::fs::write(contents_path.join("Info.plist"), info_plist).context(format!(
         "Failed to write Info.plist to {}",
@@ -116,11 +172,36 @@ fn main() -> anyhow::Result<()> {
         bins_path.to_str().unwrap()
     ))?;
 
-    for bin in metadata.bins {
-        info!("Copying {} to {}", bin[0], bin[1]);
-        let target_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(bin[0].clone());
-        std::fs::copy(target_path, bins_path.join(bin[1].clone()))
-            .context(format!("Failed to copy {} to {}", bin[0], bin[1]))?;
+    for bin in metadata.bins.iter() {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let source = args.target.source(&manifest_dir, &bin[0]);
+        let target_path = manifest_dir.join(&source);
+        let builds = architecture_builds(&bin[0]);
+        if args.universal && builds.iter().all(|build| build.exists()) {
+            info!("Creating the universal {} from {:?}", bin[1], builds);
+            run(Command::new("lipo")
+                .arg("-create")
+                .arg("-output")
+                .arg(bins_path.join(&bin[1]))
+                .args(&builds))?;
+        } else {
+            if args.universal && bin[0].starts_with("target/") {
+                bail!(
+                    "Missing the builds of {} for every architecture: {:?}",
+                    bin[0],
+                    builds
+                );
+            }
+            if args.universal && is_mach_o(&target_path)? {
+                warn!(
+                    "Missing the builds of {} for every architecture, copying it as it is",
+                    bin[0]
+                );
+            }
+            info!("Copying {source} to {}", bin[1]);
+            std::fs::copy(target_path, bins_path.join(bin[1].clone()))
+                .context(format!("Failed to copy {source} to {}", bin[1]))?;
+        }
         #[cfg(any(target_os = "linux", target_os = "macos"))]
         // Make the file executable
         std::fs::set_permissions(
@@ -146,5 +227,278 @@ fn main() -> anyhow::Result<()> {
     });
     info!("Finished bundling Macos .app");
 
+    if let Some(identity) = args.sign.as_deref() {
+        let entitlements = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(&metadata.entitlements);
+
+        // The nested binaries are signed first as the signature of the bundle covers them
+        for bin in metadata.bins.iter() {
+            let bin_path = bins_path.join(&bin[1]);
+            if bin[1] != metadata.executable && is_mach_o(&bin_path)? {
+                info!("Signing {}", bin[1]);
+                codesign(identity, &bin_path, &entitlements)?;
+            }
+        }
+        info!("Signing {}", bundle_path.display());
+        codesign(identity, &bundle_path, &entitlements)?;
+        run(Command::new("codesign")
+            .args(["--verify", "--strict", "--verbose=2"])
+            .arg(&bundle_path))?;
+    }
+
+    if let Some(keychain_profile) = args.notarize.as_deref() {
+        let zip_path = target_path.join(format!("{}.app.zip", metadata.name));
+        run(Command::new("ditto")
+            .args(["-c", "-k", "--sequesterRsrc", "--keepParent"])
+            .arg(&bundle_path)
+            .arg(&zip_path))?;
+
+        info!("Notarizing {}", bundle_path.display());
+        run(Command::new("xcrun")
+            .args(["notarytool", "submit"])
+            .arg(&zip_path)
+            .args(["--keychain-profile", keychain_profile, "--wait"]))?;
+        // Gatekeeper can verify the stapled ticket even when the user is offline
+        run(Command::new("xcrun")
+            .args(["stapler", "staple"])
+            .arg(&bundle_path))?;
+        std::fs::remove_file(&zip_path)
+            .context(format!("Failed to remove {}", zip_path.display()))?;
+    }
+
+    if args.dmg {
+        let dmg_path = target_path.join(format!("{}.dmg", metadata.name));
+        if dmg_path.exists() {
+            std::fs::remove_file(&dmg_path).context("Failed to remove old dmg")?;
+        }
+        create_dmg(
+            &metadata.dmg,
+            &bundle_path,
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(&metadata.icon[0]),
+            &dmg_path,
+        )?;
+
+        if let Some(identity) = args.sign.as_deref() {
+            run(Command::new("codesign")
+                .args(["--force", "--timestamp", "--sign", identity])
+                .arg(&dmg_path))?;
+        }
+        info!("Finished bundling {}", dmg_path.display());
+    }
+
+    Ok(())
+}
+
+/// Creates the compressed `.dmg` with `hdiutil`.
+///
+/// The contents are laid out in a read-write image first, where Finder saves the window layout in the `.DS_Store`.
+fn create_dmg(
+    metadata: &DmgMetadata,
+    bundle_path: &Path,
+    volume_icon: &Path,
+    dmg_path: &Path,
+) -> anyhow::Result<()> {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let target_path = dmg_path.parent().context("Invalid dmg path")?;
+    let app_name = bundle_path
+        .file_name()
+        .context("Invalid bundle path")?
+        .to_string_lossy()
+        .into_owned();
+    let background_path = manifest_dir.join(&metadata.background);
+    let background_name = background_path
+        .file_name()
+        .context("Invalid background")?
+        .to_string_lossy()
+        .into_owned();
+    let license_name = Path::new(&metadata.license)
+        .file_name()
+        .context("Invalid license")?
+        .to_string_lossy()
+        .into_owned();
+
+    let contents_path = target_path.join("dmg");
+    if contents_path.exists() {
+        std::fs::remove_dir_all(&contents_path).context("Failed to remove old dmg contents")?;
+    }
+    std::fs::create_dir_all(contents_path.join(".background"))
+        .context("Failed to create the dmg contents")?;
+    // ditto keeps the signature and the stapled ticket of the bundle
+    run(Command::new("ditto")
+        .arg(bundle_path)
+        .arg(contents_path.join(&app_name)))?;
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    std::os::unix::fs::symlink("/Applications", contents_path.join("Applications"))
+        .context("Failed to link /Applications")?;
+    std::fs::copy(
+        manifest_dir.join(&metadata.license),
+        contents_path.join(&license_name),
+    )
+    .context(format!("Failed to copy {}", metadata.license))?;
+    std::fs::copy(
+        &background_path,
+        contents_path.join(".background").join(&background_name),
+    )
+    .context(format!("Failed to copy {}", metadata.background))?;
+    std::fs::copy(volume_icon, contents_path.join(".VolumeIcon.icns"))
+        .context(format!("Failed to copy {}", volume_icon.display()))?;
+
+    let image_path = target_path.join(format!("{}.rw.dmg", metadata.title));
+    run(Command::new("hdiutil")
+        .arg("create")
+        .args(["-volname", &metadata.title])
+        .arg("-srcfolder")
+        .arg(&contents_path)
+        .args(["-fs", "HFS+", "-format", "UDRW", "-ov"])
+        .arg(&image_path))?;
+
+    let mount_path = target_path.join("dmg-mount");
+    run(Command::new("hdiutil")
+        .args(["attach", "-readwrite", "-noverify", "-noautoopen"])
+        .arg("-mountpoint")
+        .arg(&mount_path)
+        .arg(&image_path))?;
+    let layout = layout_dmg(
+        metadata,
+        &mount_path,
+        &app_name,
+        &background_name,
+        &license_name,
+    );
+    // the image must be detached even when the layout fails
+    let detached = run(Command::new("hdiutil").arg("detach").arg(&mount_path)).or_else(|_| {
+        run(Command::new("hdiutil")
+            .args(["detach", "-force"])
+            .arg(&mount_path))
+    });
+    layout?;
+    detached?;
+
+    run(Command::new("hdiutil")
+        .arg("convert")
+        .arg(&image_path)
+        .args(["-format", "UDZO", "-imagekey", "zlib-level=9", "-o"])
+        .arg(dmg_path))?;
+
+    std::fs::remove_file(&image_path)
+        .context(format!("Failed to remove {}", image_path.display()))?;
+    std::fs::remove_dir_all(&contents_path)
+        .context(format!("Failed to remove {}", contents_path.display()))?;
+
+    Ok(())
+}
+
+/// Lays out the Finder window of the mounted read-write image
+fn layout_dmg(
+    metadata: &DmgMetadata,
+    mount_path: &Path,
+    app_name: &str,
+    background_name: &str,
+    license_name: &str,
+) -> anyhow::Result<()> {
+    let (width, height) = image::image_dimensions(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(&metadata.background),
+    )
+    .context(format!("Failed to read {}", metadata.background))?;
+    let position = |[x, y]: [u32; 2]| format!("{{{x}, {y}}}");
+
+    let script = format!(
+        "tell application \"Finder\"
+    tell disk \"{title}\"
+        open
+        set current view of container window to icon view
+        set toolbar visible of container window to false
+        set statusbar visible of container window to false
+        set the bounds of container window to {{100, 100, {right}, {bottom}}}
+        set viewOptions to the icon view options of container window
+        set arrangement of viewOptions to not arranged
+        set icon size of viewOptions to {icon_size}
+        set background picture of viewOptions to file \".background:{background_name}\"
+        set position of item \"{app_name}\" of container window to {app_position}
+        set position of item \"Applications\" of container window to {applications_position}
+        set position of item \"{license_name}\" of container window to {license_position}
+        close
+        open
+        update without registering applications
+        delay 2
+        close
+    end tell
+end tell
+",
+        title = metadata.title,
+        right = 100 + width,
+        bottom = 100 + height,
+        icon_size = metadata.icon_size,
+        app_position = position(metadata.app_position),
+        applications_position = position(metadata.applications_position),
+        license_position = position(metadata.license_position),
+    );
+    info!("Laying out the dmg window");
+    run(Command::new("osascript").arg("-e").arg(script))?;
+
+    // show the `.VolumeIcon.icns` as the icon of the volume
+    if let Err(err) = run(Command::new("SetFile").args(["-a", "C"]).arg(mount_path)) {
+        warn!("Failed to set the volume icon: {err}");
+    }
+    let _ = std::fs::remove_dir_all(mount_path.join(".fseventsd"));
+
+    Ok(())
+}
+
+/// The builds of the binary for each of the [`ARCHITECTURES`].
+///
+/// The binaries built by cargo are in the `--target` directories, e.g. `target/aarch64-apple-darwin/release`,
+/// the rest in directories named after the architecture, e.g. `resources/bin/macos/arm64`.
+fn architecture_builds(source: &str) -> Vec<PathBuf> {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    ARCHITECTURES
+        .iter()
+        .map(|(arch, triple)| match source.strip_prefix("target/") {
+            Some(target_source) => manifest_dir.join("target").join(triple).join(target_source),
+            None => {
+                let source = Path::new(source);
+                manifest_dir
+                    .join(source.parent().unwrap_or(Path::new("")))
+                    .join(arch)
+                    .join(source.file_name().unwrap_or_default())
+            }
+        })
+        .collect()
+}
+
+/// Signs with the hardened runtime required for the notarization
+fn codesign(identity: &str, path: &Path, entitlements: &Path) -> anyhow::Result<()> {
+    run(Command::new("codesign")
+        .args(["--force", "--timestamp", "--options", "runtime"])
+        .arg("--entitlements")
+        .arg(entitlements)
+        .args(["--sign", identity])
+        .arg(path))
+}
+
+/// Whether the file is an executable or a library, thin or universal
+fn is_mach_o(path: &Path) -> anyhow::Result<bool> {
+    let mut magic = [0; 4];
+    let mut file =
+        std::fs::File::open(path).context(format!("Failed to open {}", path.display()))?;
+    if file.read_exact(&mut magic).is_err() {
+        return Ok(false);
+    }
+
+    Ok(matches!(
+        u32::from_be_bytes(magic),
+        0xfeedface | 0xfeedfacf | 0xcefaedfe | 0xcffaedfe | 0xcafebabe
+    ))
+}
+
+fn run(command: &mut Command) -> anyhow::Result<()> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .context(format!("Failed to run {program}"))?;
+    if !status.success() {
+        bail!("{program} exited with {status}");
+    }
+
     Ok(())
 }
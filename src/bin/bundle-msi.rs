@@ -0,0 +1,213 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use env_logger::Env;
+use log::info;
+use serde::Deserialize;
+
+#[path = "bundle/target.rs"]
+mod target;
+
+use target::Target;
+
+/// Bundles the MSI package with the WiX Toolset
+#[derive(Parser, Debug)]
+struct Args {
+    #[command(flatten)]
+    target: Target,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct MsiMetadata {
+    name: String,
+    display_name: String,
+    manufacturer: String,
+    upgrade_code: String,
+    url: String,
+    icon: String,
+    executable: String,
+    firewall_exception: String,
+    bins: Vec<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    msi: Option<MsiMetadata>,
+}
+
+/// The WiX extension adding the firewall rule
+const FIREWALL_EXTENSION: &str = "WixToolset.Firewall.wixext";
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    args.target.ensure_os("windows")?;
+    let arch = match args.target.arch() {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        arch => bail!("Architecture {arch} is not supported"),
+    };
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let metadata = manifest
+        .package
+        .context("Failed to parse package")?
+        .metadata
+        .context("Failed to parse manifest.package.metadata")?
+        .msi
+        .context("Failed to parse manifest.package.metadata.msi")?;
+
+    let target_path = manifest_dir.join("target").join("windows");
+    std::fs::create_dir_all(&target_path).context(format!(
+        "Failed to create target dir: {}",
+        target_path.display()
+    ))?;
+
+    let mut files = vec![];
+    for bin in metadata.bins.iter() {
+        let source = manifest_dir.join(args.target.source(&manifest_dir, &bin[0]));
+        if !source.exists() {
+            bail!("Missing {}", source.display());
+        }
+        info!("Adding {} as {}", source.display(), bin[1]);
+        files.push(format!(
+            "<Component><File Id=\"{id}\" Name=\"{name}\" Source=\"{source}\" KeyPath=\"yes\" /></Component>",
+            id = file_id(&bin[1]),
+            name = bin[1],
+            source = source.display(),
+        ));
+    }
+    for referenced in [&metadata.executable, &metadata.firewall_exception] {
+        if !metadata.bins.iter().any(|bin| &bin[1] == referenced) {
+            bail!("{referenced} is not in the bins");
+        }
+    }
+
+    let wxs = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<Wix xmlns=\"http://wixtoolset.org/schemas/v4/wxs\" xmlns:fire=\"http://wixtoolset.org/schemas/v4/wxs/firewall\">
+    <!-- Installed for the current user by default, ALLUSERS=1 installs it for every user of the machine -->
+    <Package Name=\"{display_name}\" Manufacturer=\"{manufacturer}\" Version=\"{version}\" UpgradeCode=\"{upgrade_code}\" Scope=\"perUserOrMachine\">
+        <MajorUpgrade DowngradeErrorMessage=\"A newer version of [ProductName] is already installed.\" />
+        <MediaTemplate EmbedCab=\"yes\" />
+
+        <Icon Id=\"ProductIcon\" SourceFile=\"{icon}\" />
+        <Property Id=\"ARPPRODUCTICON\" Value=\"ProductIcon\" />
+        <Property Id=\"ARPURLINFOABOUT\" Value=\"{url}\" />
+
+        <!-- AUTOSTART=0 skips starting the service on login -->
+        <Property Id=\"AUTOSTART\" Value=\"1\" Secure=\"yes\" />
+        <!-- MAGNET=1 opens the magnet: links with the service -->
+        <Property Id=\"MAGNET\" Secure=\"yes\" />
+
+        <StandardDirectory Id=\"ProgramFiles64Folder\">
+            <Directory Id=\"INSTALLFOLDER\" Name=\"{name}\" />
+        </StandardDirectory>
+        <StandardDirectory Id=\"ProgramMenuFolder\" />
+
+        <ComponentGroup Id=\"Files\" Directory=\"INSTALLFOLDER\">
+            {files}
+        </ComponentGroup>
+
+        <ComponentGroup Id=\"Integration\" Directory=\"INSTALLFOLDER\">
+            <Component Id=\"StremioProtocol\">
+                <RegistryKey Root=\"HKMU\" Key=\"Software\\Classes\\stremio\">
+                    <RegistryValue Type=\"string\" Value=\"URL:Stremio Protocol\" KeyPath=\"yes\" />
+                    <RegistryValue Name=\"URL Protocol\" Type=\"string\" Value=\"\" />
+                    <RegistryValue Key=\"DefaultIcon\" Type=\"string\" Value=\"&quot;[#{executable}]&quot;,0\" />
+                    <RegistryValue Key=\"shell\\open\\command\" Type=\"string\" Value=\"&quot;[#{executable}]&quot; -o &quot;%1&quot;\" />
+                </RegistryKey>
+            </Component>
+            <Component Id=\"MagnetProtocol\" Condition=\"MAGNET = 1\">
+                <RegistryKey Root=\"HKMU\" Key=\"Software\\Classes\\magnet\">
+                    <RegistryValue Type=\"string\" Value=\"URL:Magnet Link\" KeyPath=\"yes\" />
+                    <RegistryValue Name=\"URL Protocol\" Type=\"string\" Value=\"\" />
+                    <RegistryValue Key=\"DefaultIcon\" Type=\"string\" Value=\"&quot;[#{executable}]&quot;,0\" />
+                    <RegistryValue Key=\"shell\\open\\command\" Type=\"string\" Value=\"&quot;[#{executable}]&quot; -o &quot;%1&quot;\" />
+                </RegistryKey>
+            </Component>
+            <Component Id=\"Autostart\" Condition=\"AUTOSTART = 1\">
+                <RegistryValue Root=\"HKMU\" Key=\"Software\\Microsoft\\Windows\\CurrentVersion\\Run\" Name=\"{name}\" Type=\"string\" Value=\"&quot;[#{executable}]&quot;\" KeyPath=\"yes\" />
+            </Component>
+            <!-- Adding a firewall rule needs the elevation of the installation for every user -->
+            <Component Id=\"FirewallException\" Condition=\"ALLUSERS = 1\">
+                <fire:FirewallException Id=\"ServerFirewallException\" Name=\"{display_name}\" Program=\"[#{firewall_exception}]\" Scope=\"any\" IgnoreFailure=\"yes\" />
+                <RegistryValue Root=\"HKMU\" Key=\"Software\\{manufacturer}\\{name}\" Name=\"FirewallException\" Type=\"integer\" Value=\"1\" KeyPath=\"yes\" />
+            </Component>
+            <Component Id=\"StartMenuShortcut\" Directory=\"ProgramMenuFolder\">
+                <Shortcut Id=\"StartMenuShortcut\" Name=\"{display_name}\" Target=\"[#{executable}]\" WorkingDirectory=\"INSTALLFOLDER\" Icon=\"ProductIcon\" />
+                <RegistryValue Root=\"HKMU\" Key=\"Software\\{manufacturer}\\{name}\" Name=\"StartMenuShortcut\" Type=\"integer\" Value=\"1\" KeyPath=\"yes\" />
+            </Component>
+        </ComponentGroup>
+
+        <!-- Remove the stremio: protocol handler registered by the service itself -->
+        <CustomAction Id=\"UnregisterProtocol\" FileRef=\"{executable}\" ExeCommand=\"--unregister-protocol\" Execute=\"deferred\" Impersonate=\"yes\" Return=\"ignore\" />
+        <InstallExecuteSequence>
+            <Custom Action=\"UnregisterProtocol\" Before=\"RemoveFiles\" Condition=\"REMOVE = &quot;ALL&quot; AND NOT UPGRADINGPRODUCTCODE\" />
+        </InstallExecuteSequence>
+
+        <Feature Id=\"Main\">
+            <ComponentGroupRef Id=\"Files\" />
+            <ComponentGroupRef Id=\"Integration\" />
+        </Feature>
+    </Package>
+</Wix>
+",
+        display_name = metadata.display_name,
+        manufacturer = metadata.manufacturer,
+        version = env!("CARGO_PKG_VERSION"),
+        upgrade_code = metadata.upgrade_code,
+        icon = manifest_dir.join(&metadata.icon).display(),
+        url = metadata.url,
+        name = metadata.name,
+        files = files.join("\n            "),
+        executable = file_id(&metadata.executable),
+        firewall_exception = file_id(&metadata.firewall_exception),
+    );
+    let wxs_path = target_path.join(format!("{}.wxs", metadata.name));
+    std::fs::write(&wxs_path, wxs).context(format!("Failed to write {}", wxs_path.display()))?;
+    info!("Finished the WiX source {}", wxs_path.display());
+
+    let msi_path = target_path.join(format!("{}.msi", metadata.name));
+    build_msi(&wxs_path, &msi_path, arch)?;
+    info!("Finished bundling {}", msi_path.display());
+
+    Ok(())
+}
+
+/// Runs the WiX Toolset v4 `wix build` command, set with the `WIXTOOL` environment variable or from the `PATH`
+fn build_msi(wxs_path: &Path, msi_path: &Path, arch: &str) -> anyhow::Result<()> {
+    let wix = std::env::var("WIXTOOL").unwrap_or_else(|_| "wix".into());
+    let status = std::process::Command::new(&wix)
+        .arg("build")
+        .args(["-arch", arch])
+        .args(["-ext", FIREWALL_EXTENSION])
+        .arg("-o")
+        .arg(msi_path)
+        .arg(wxs_path)
+        .status()
+        .context(format!("Failed to run {wix}"))?;
+    if !status.success() {
+        bail!("{wix} exited with {status}");
+    }
+
+    Ok(())
+}
+
+/// WiX identifiers can only contain letters, digits, underscores and periods
+fn file_id(file_name: &str) -> String {
+    file_name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '.' {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
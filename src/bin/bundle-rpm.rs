@@ -0,0 +1,124 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use env_logger::Env;
+use log::info;
+use serde::Deserialize;
+
+#[path = "bundle/linux.rs"]
+mod linux;
+#[path = "bundle/target.rs"]
+mod target;
+
+use linux::LinuxMetadata;
+use target::Target;
+
+/// Bundles the `.rpm` package
+#[derive(Parser, Debug)]
+struct Args {
+    #[command(flatten)]
+    target: Target,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RpmMetadata {
+    release: String,
+    group: String,
+    requires: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    linux: Option<LinuxMetadata>,
+    rpm: Option<RpmMetadata>,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    args.target.ensure_os("linux")?;
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let package = manifest.package.context("Failed to parse package")?;
+    let Metadata { linux, rpm } = package
+        .metadata
+        .clone()
+        .context("Failed to parse manifest.package.metadata")?;
+    let linux = linux.context("Failed to parse manifest.package.metadata.linux")?;
+    let metadata = rpm.context("Failed to parse manifest.package.metadata.rpm")?;
+
+    let arch = match args.target.arch() {
+        arch @ ("x86_64" | "aarch64") => arch,
+        arch => bail!("Architecture {arch} is not supported"),
+    };
+
+    let mut builder = rpm::PackageBuilder::new(
+        &package.name,
+        env!("CARGO_PKG_VERSION"),
+        package.license().unwrap_or_default(),
+        arch,
+        package.description().unwrap_or_default(),
+    );
+    builder
+        .release(&metadata.release)
+        .group(&metadata.group)
+        .url(package.homepage().unwrap_or_default())
+        .vendor(package.authors().join(", "))
+        .description(package.description().unwrap_or_default());
+
+    for asset in linux.assets(&manifest_dir, &args.target)? {
+        info!("Adding {} as {}", asset.source.display(), asset.destination);
+        builder
+            .with_file(
+                &asset.source,
+                rpm::FileOptions::new(&asset.destination).permissions(asset.mode as u16),
+            )
+            .context(format!("Failed to add {}", asset.source.display()))?;
+    }
+
+    for requirement in metadata.requires.iter() {
+        builder.requires(rpm::Dependency::any(requirement));
+    }
+
+    // The Debian maintainer scripts map to the rpm scriptlets,
+    // they are written without relying on the arguments that differ between the two
+    for script in linux::SCRIPTS {
+        if let Some(contents) = linux.script(&manifest_dir, script)? {
+            info!("Adding the {script} script");
+            match script {
+                "preinst" => builder.pre_install_script(contents),
+                "postinst" => builder.post_install_script(contents),
+                "prerm" => builder.pre_uninstall_script(contents),
+                "postrm" => builder.post_uninstall_script(contents),
+                script => bail!("Unknown script {script}"),
+            };
+        }
+    }
+
+    let package_file = builder.build().context("Failed to build the rpm package")?;
+
+    let target_path = manifest_dir.join("target").join("generate-rpm");
+    std::fs::create_dir_all(&target_path).context(format!(
+        "Failed to create target dir: {}",
+        target_path.display()
+    ))?;
+    let rpm_path = target_path.join(format!(
+        "{}-{}-{}.{arch}.rpm",
+        package.name,
+        env!("CARGO_PKG_VERSION"),
+        metadata.release
+    ));
+    package_file
+        .write_file(&rpm_path)
+        .context(format!("Failed to write {}", rpm_path.display()))?;
+    info!("Finished bundling {}", rpm_path.display());
+
+    Ok(())
+}
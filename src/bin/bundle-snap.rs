@@ -0,0 +1,226 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::{Path, PathBuf};
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::os::unix::fs::PermissionsExt;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use env_logger::Env;
+use log::info;
+use serde::Deserialize;
+
+#[path = "bundle/fs.rs"]
+mod fs;
+// the snap has no maintainer scripts
+#[allow(dead_code)]
+#[path = "bundle/linux.rs"]
+mod linux;
+#[path = "bundle/target.rs"]
+mod target;
+
+use fs::create_dir;
+use linux::LinuxMetadata;
+use target::Target;
+
+/// Bundles the snap
+#[derive(Parser, Debug)]
+struct Args {
+    #[command(flatten)]
+    target: Target,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SnapMetadata {
+    base: String,
+    grade: String,
+    confinement: String,
+    desktop_file: String,
+    icon: String,
+    /// The installed path of the service executable
+    executable: String,
+    plugs: Vec<String>,
+    stage_packages: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DebMetadata {
+    extended_description: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    linux: Option<LinuxMetadata>,
+    snap: Option<SnapMetadata>,
+    deb: Option<DebMetadata>,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    args.target.ensure_os("linux")?;
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let package = manifest.package.context("Failed to parse package")?;
+    let Metadata { linux, snap, deb } = package
+        .metadata
+        .clone()
+        .context("Failed to parse manifest.package.metadata")?;
+    let linux = linux.context("Failed to parse manifest.package.metadata.linux")?;
+    let metadata = snap.context("Failed to parse manifest.package.metadata.snap")?;
+    let deb = deb.context("Failed to parse manifest.package.metadata.deb")?;
+
+    let arch = snap_arch(args.target.arch())?;
+    let host_arch = snap_arch(std::env::consts::ARCH)?;
+
+    let target_path = manifest_dir.join("target").join("snap");
+    if target_path.exists() {
+        std::fs::remove_dir_all(&target_path).context("Failed to remove old snap layout")?;
+    }
+
+    // the prebuilt files, dumped in the snap as they are
+    let root_path = target_path.join("root");
+    for asset in linux.assets(&manifest_dir, &args.target)? {
+        let destination = root_path.join(asset.destination.trim_start_matches('/'));
+        create_dir(destination.parent().expect("Should have a parent"))?;
+        info!(
+            "Copying {} to {}",
+            asset.source.display(),
+            asset.destination
+        );
+        std::fs::copy(&asset.source, &destination)
+            .context(format!("Failed to copy {}", asset.source.display()))?;
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        std::fs::set_permissions(&destination, std::fs::Permissions::from_mode(asset.mode))
+            .context(format!(
+                "Failed to set permissions for {}",
+                destination.display()
+            ))?;
+    }
+
+    // snapd exports the desktop files and icons from `meta/gui`
+    let gui_path = target_path.join("snap").join("gui");
+    create_dir(&gui_path)?;
+    let icon_name = Path::new(&metadata.icon)
+        .file_name()
+        .context("Invalid icon")?
+        .to_string_lossy()
+        .into_owned();
+    std::fs::copy(manifest_dir.join(&metadata.icon), gui_path.join(&icon_name))
+        .context(format!("Failed to copy {}", metadata.icon))?;
+    let desktop_file = std::fs::read_to_string(manifest_dir.join(&metadata.desktop_file))
+        .context(format!("Failed to read {}", metadata.desktop_file))?
+        .lines()
+        .map(|line| {
+            if line.starts_with("Exec=") {
+                format!("Exec={} -o %u", package.name)
+            } else if line.starts_with("Icon=") {
+                format!("Icon=${{SNAP}}/meta/gui/{icon_name}")
+            } else {
+                line.to_owned()
+            }
+        })
+        .map(|line| line + "\n")
+        .collect::<String>();
+    let desktop_file_path = gui_path.join(format!("{}.desktop", package.name));
+    std::fs::write(&desktop_file_path, desktop_file).context(format!(
+        "Failed to write the desktop file to {}",
+        desktop_file_path.display()
+    ))?;
+
+    let desktop_file_name = Path::new(&metadata.desktop_file)
+        .file_name()
+        .context("Invalid desktop file")?
+        .to_string_lossy()
+        .into_owned();
+    let snapcraft = format!(
+        "name: {name}
+base: {base}
+version: '{version}'
+summary: {summary}
+description: |
+  {description}
+license: {license}
+grade: {grade}
+confinement: {confinement}
+architectures:
+  - build-on: {host_arch}
+    build-for: {arch}
+
+apps:
+  {name}:
+    command: {command}
+    extensions: [gnome]
+    # the service copies its desktop file in the autostart directory of the snap
+    autostart: {desktop_file_name}
+    plugs:
+{plugs}
+
+parts:
+  {name}:
+    plugin: dump
+    source: root
+    stage-packages:
+{stage_packages}
+",
+        name = package.name,
+        base = metadata.base,
+        version = env!("CARGO_PKG_VERSION"),
+        summary = package.description().unwrap_or_default(),
+        description = deb.extended_description.trim().replace('\n', "\n  "),
+        license = package.license().unwrap_or_default(),
+        grade = metadata.grade,
+        confinement = metadata.confinement,
+        command = metadata.executable.trim_start_matches('/'),
+        plugs = yaml_list(&metadata.plugs, 6),
+        stage_packages = yaml_list(&metadata.stage_packages, 6),
+    );
+    let snapcraft_path = target_path.join("snap").join("snapcraft.yaml");
+    std::fs::write(&snapcraft_path, snapcraft)
+        .context(format!("Failed to write {}", snapcraft_path.display()))?;
+    info!("Finished the snap layout {}", target_path.display());
+
+    let snapcraft = std::env::var("SNAPCRAFT").unwrap_or_else(|_| "snapcraft".into());
+    let status = std::process::Command::new(&snapcraft)
+        .arg("pack")
+        .current_dir(&target_path)
+        .status()
+        .context(format!("Failed to run {snapcraft}"))?;
+    if !status.success() {
+        bail!("{snapcraft} exited with {status}");
+    }
+    info!(
+        "Finished bundling {}",
+        target_path
+            .join(format!(
+                "{}_{}_{arch}.snap",
+                package.name,
+                env!("CARGO_PKG_VERSION")
+            ))
+            .display()
+    );
+
+    Ok(())
+}
+
+fn snap_arch(arch: &str) -> anyhow::Result<&'static str> {
+    match arch {
+        "x86_64" => Ok("amd64"),
+        "aarch64" => Ok("arm64"),
+        arch => bail!("Architecture {arch} is not supported"),
+    }
+}
+
+fn yaml_list(items: &[String], indent: usize) -> String {
+    items
+        .iter()
+        .map(|item| format!("{:indent$}- {item}", ""))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,12 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The file system helpers shared by the `bundle-linux` and `bundle-snap` bins.
+
+use std::path::Path;
+
+use anyhow::Context;
+
+/// Creates the directory and its parents, unless they exist
+pub fn create_dir(path: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(path).context(format!("Failed to create directory: {}", path.display()))
+}
@@ -0,0 +1,61 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The layout of the Linux packages, shared by the `bundle-deb`, `bundle-rpm` and `bundle-snap` bins.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::target::Target;
+
+/// The scripts run by the package manager, named after the Debian maintainer scripts
+pub const SCRIPTS: [&str; 4] = ["preinst", "postinst", "prerm", "postrm"];
+
+/// `package.metadata.linux`
+#[derive(Clone, Debug, Deserialize)]
+pub struct LinuxMetadata {
+    /// The directory with the [`SCRIPTS`]
+    pub scripts: String,
+    /// `[source, destination, mode]`
+    pub assets: Vec<[String; 3]>,
+}
+
+/// A file installed by the package
+#[derive(Clone, Debug)]
+pub struct Asset {
+    pub source: PathBuf,
+    /// The absolute installation path
+    pub destination: String,
+    pub mode: u32,
+}
+
+impl LinuxMetadata {
+    /// The files installed by the package, the sources are relative to the manifest directory
+    /// and resolved for the target, see [`Target::source`].
+    pub fn assets(&self, manifest_dir: &Path, target: &Target) -> anyhow::Result<Vec<Asset>> {
+        self.assets
+            .iter()
+            .map(|[source, destination, mode]| {
+                Ok(Asset {
+                    source: manifest_dir.join(target.source(manifest_dir, source)),
+                    destination: destination.to_owned(),
+                    mode: u32::from_str_radix(mode, 8)
+                        .context(format!("Invalid mode for {source}"))?,
+                })
+            })
+            .collect()
+    }
+
+    /// The contents of one of the [`SCRIPTS`], if the package has it.
+    pub fn script(&self, manifest_dir: &Path, name: &str) -> anyhow::Result<Option<String>> {
+        let script_path = manifest_dir.join(&self.scripts).join(name);
+        if !script_path.exists() {
+            return Ok(None);
+        }
+
+        std::fs::read_to_string(&script_path)
+            .map(Some)
+            .context(format!("Failed to read {}", script_path.display()))
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The platform of the bundled build, shared by the bundler bins.
+//!
+//! The bundlers run on the host, while the service may be cross-compiled with `cargo build --target`.
+
+use std::path::Path;
+
+use anyhow::bail;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct Target {
+    /// The target triple the service was cross-compiled for, its build is bundled from `target/<TRIPLE>`
+    #[arg(long, value_name = "TRIPLE")]
+    pub target: Option<String>,
+    /// Overrides the operating system of the target, otherwise the one of the triple or the host
+    #[arg(long, value_name = "OS")]
+    pub target_os: Option<String>,
+}
+
+impl Target {
+    /// The operating system, as in [`std::env::consts::OS`]
+    pub fn os(&self) -> &str {
+        if let Some(os) = self.target_os.as_deref() {
+            return os;
+        }
+
+        match self.target.as_deref() {
+            Some(triple) if triple.contains("-windows") => "windows",
+            Some(triple) if triple.contains("-apple-darwin") => "macos",
+            Some(triple) if triple.contains("-linux") => "linux",
+            Some(triple) => triple.split('-').nth(2).unwrap_or(triple),
+            None => std::env::consts::OS,
+        }
+    }
+
+    /// The architecture, as in [`std::env::consts::ARCH`]
+    pub fn arch(&self) -> &str {
+        match self.target.as_deref() {
+            Some(triple) => triple.split('-').next().unwrap_or(triple),
+            None => std::env::consts::ARCH,
+        }
+    }
+
+    /// Fails when the bundler doesn't package builds for the target.
+    pub fn ensure_os(&self, os: &str) -> anyhow::Result<()> {
+        if self.os() != os {
+            bail!(
+                "Only {os} builds can be bundled, the target OS is {}",
+                self.os()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The source of a bundled file for the target, relative to the manifest directory.
+    ///
+    /// The cargo builds are in the `--target` directory, e.g. `target/aarch64-unknown-linux-gnu/release`,
+    /// and the runtime binaries for the architecture are preferred when they exist,
+    /// e.g. `resources/bin/linux/arm64/ffmpeg` (see `fetch-bins --arch-dir`).
+    pub fn source(&self, manifest_dir: &Path, source: &str) -> String {
+        if let (Some(triple), Some(build)) =
+            (self.target.as_deref(), source.strip_prefix("target/"))
+        {
+            return format!("target/{triple}/{build}");
+        }
+
+        let source_path = Path::new(source);
+        if source.starts_with("resources/bin/") {
+            let arch_dir = match self.arch() {
+                "aarch64" => "arm64",
+                arch => arch,
+            };
+            let arch_source = source_path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(arch_dir)
+                .join(source_path.file_name().unwrap_or_default());
+            if manifest_dir.join(&arch_source).exists() {
+                return arch_source.to_string_lossy().into_owned();
+            }
+        }
+
+        source.to_owned()
+    }
+}
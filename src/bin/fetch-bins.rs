@@ -0,0 +1,270 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    path::{Component, Path, PathBuf},
+};
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::os::unix::fs::PermissionsExt;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use env_logger::Env;
+use log::{info, warn};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// Downloads the pinned runtime binaries (stremio-runtime, ffmpeg, ffprobe) in `resources/bin/<os>`
+#[derive(Parser, Debug)]
+struct Args {
+    /// The operating system of the binaries
+    #[arg(long, default_value_t = std::env::consts::OS.to_owned())]
+    os: String,
+    /// The architecture of the binaries
+    #[arg(long, default_value_t = std::env::consts::ARCH.to_owned())]
+    arch: String,
+    /// Save them in a directory named after the architecture, e.g. `resources/bin/macos/arm64`,
+    /// for the universal binaries of `bundle-macos --universal`
+    #[arg(long)]
+    arch_dir: bool,
+    /// Download them even if they already exist
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Download {
+    url: Url,
+    sha256: String,
+    /// `[path in the archive, file name]`, a download that is not an archive has a single file
+    /// and the path in the archive is ignored
+    files: Vec<[String; 2]>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    /// The downloads for every `<os>-<arch>`
+    bins: Option<HashMap<String, Vec<Download>>>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let mut bins = manifest
+        .package
+        .context("Failed to parse package")?
+        .metadata
+        .context("Failed to parse manifest.package.metadata")?
+        .bins
+        .context("Failed to parse manifest.package.metadata.bins")?;
+
+    let platform = format!("{}-{}", args.os, args.arch);
+    let downloads = bins
+        .remove(&platform)
+        .context(format!("No binaries are pinned for {platform}"))?;
+
+    let mut bins_path = manifest_dir.join("resources").join("bin").join(&args.os);
+    if args.arch_dir {
+        bins_path = bins_path.join(match args.arch.as_str() {
+            "aarch64" => "arm64",
+            arch => arch,
+        });
+    }
+    std::fs::create_dir_all(&bins_path)
+        .context(format!("Failed to create {}", bins_path.display()))?;
+
+    // every file is staged next to its destination first and they're swapped in together once all are fetched,
+    // an interrupted fetch must not leave a truncated binary or a mix of the old and the new ones behind
+    let mut staged = vec![];
+    let fetched = fetch(&args, &downloads, &bins_path, &manifest_dir, &mut staged).await;
+    if let Err(err) = fetched {
+        for (staged_file, _) in &staged {
+            let _ = std::fs::remove_file(staged_file);
+        }
+        return Err(err);
+    }
+    swap(&staged)?;
+    info!("All binaries for {platform} are in {}", bins_path.display());
+
+    Ok(())
+}
+
+/// Downloads and extracts the files of the downloads, they're staged as `.<file name>.part` in the bins directory
+async fn fetch(
+    args: &Args,
+    downloads: &[Download],
+    bins_path: &Path,
+    manifest_dir: &Path,
+    staged: &mut Vec<(PathBuf, PathBuf)>,
+) -> anyhow::Result<()> {
+    for download in downloads {
+        let is_fetched = download
+            .files
+            .iter()
+            .all(|[_, file_name]| bins_path.join(file_name).exists());
+        if is_fetched && !args.force {
+            info!("Skipping {}, the files already exist", download.url);
+            continue;
+        }
+
+        info!("Downloading {}", download.url);
+        let contents = reqwest::get(download.url.clone())
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+            .context(format!("Failed to download {}", download.url))?;
+
+        let sha256 = hex::encode(Sha256::digest(&contents));
+        if !sha256.eq_ignore_ascii_case(&download.sha256) {
+            bail!(
+                "{} failed the integrity check, expected SHA-256 {} but got {sha256}",
+                download.url,
+                download.sha256
+            );
+        }
+
+        let archive_path = download.url.path().to_owned();
+        let extract_path = manifest_dir.join("target").join("fetch-bins");
+        if extract_path.exists() {
+            std::fs::remove_dir_all(&extract_path)
+                .context("Failed to remove old extracted files")?;
+        }
+        if archive_path.ends_with(".tar.gz") || archive_path.ends_with(".tgz") {
+            tar::Archive::new(flate2::read::GzDecoder::new(Cursor::new(contents)))
+                .unpack(&extract_path)
+                .context(format!("Failed to extract {}", download.url))?;
+        } else if archive_path.ends_with(".tar.xz") {
+            tar::Archive::new(xz::read::XzDecoder::new(Cursor::new(contents)))
+                .unpack(&extract_path)
+                .context(format!("Failed to extract {}", download.url))?;
+        } else if archive_path.ends_with(".zip") {
+            zip_extract::extract(Cursor::new(contents), &extract_path, false)
+                .context(format!("Failed to extract {}", download.url))?;
+        } else {
+            // the download is the file itself
+            let [[archive_file, _]] = download.files.as_slice() else {
+                bail!(
+                    "{} is not an archive, it can only have one file",
+                    download.url
+                );
+            };
+            std::fs::create_dir_all(&extract_path)?;
+            std::fs::write(extract_path.join(archive_file), contents)?;
+        }
+
+        download
+            .files
+            .iter()
+            .try_for_each(|[archive_file, file_name]| {
+                let source = extract_path.join(relative_path(archive_file)?);
+                if !source.is_file() {
+                    bail!("{archive_file} is not in {}", download.url);
+                }
+                let destination = bins_path.join(plain_file_name(file_name)?);
+                let staged_file = bins_path.join(format!(".{file_name}.part"));
+                staged.push((staged_file.clone(), destination));
+                std::fs::copy(&source, &staged_file).context(format!(
+                    "Failed to copy {archive_file} from {} to {}",
+                    download.url,
+                    staged_file.display()
+                ))?;
+                #[cfg(any(target_os = "linux", target_os = "macos"))]
+                std::fs::set_permissions(&staged_file, std::fs::Permissions::from_mode(0o755))
+                    .context(format!("Failed to set permissions for {file_name}"))?;
+
+                Ok(())
+            })?;
+        std::fs::remove_dir_all(&extract_path).context("Failed to remove the extracted files")?;
+    }
+
+    Ok(())
+}
+
+/// Renames the staged files over their destinations, the replaced files are kept as `.<file name>.old`
+/// until all are renamed. When one fails, the renamed files are rolled back and the replaced ones restored.
+fn swap(staged: &[(PathBuf, PathBuf)]) -> anyhow::Result<()> {
+    // the destination with its backup, `None` when it didn't exist
+    let mut replaced: Vec<(&Path, Option<PathBuf>)> = vec![];
+    let swapped = staged.iter().try_for_each(|(staged_file, destination)| {
+        let backup = destination.with_file_name(format!(
+            ".{}.old",
+            destination
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        ));
+        let backup = match destination.exists() {
+            true => {
+                std::fs::rename(destination, &backup)
+                    .context(format!("Failed to back up {}", destination.display()))?;
+                Some(backup)
+            }
+            false => None,
+        };
+        replaced.push((destination.as_path(), backup));
+
+        std::fs::rename(staged_file, destination)
+            .context(format!("Failed to replace {}", destination.display()))
+    });
+
+    if let Err(err) = swapped {
+        for (destination, backup) in replaced.into_iter().rev() {
+            let restored = match backup {
+                Some(backup) => std::fs::rename(&backup, destination),
+                None => std::fs::remove_file(destination),
+            };
+            if let Err(err) = restored {
+                warn!("Failed to restore {}: {err}", destination.display());
+            }
+        }
+        for (staged_file, _) in staged {
+            let _ = std::fs::remove_file(staged_file);
+        }
+        return Err(err);
+    }
+
+    for (destination, backup) in replaced {
+        if let Some(backup) = backup {
+            let _ = std::fs::remove_file(backup);
+        }
+        info!("Saved {}", destination.display());
+    }
+
+    Ok(())
+}
+
+/// The path in the archive, it can't point outside of the extracted files
+fn relative_path(path: &str) -> anyhow::Result<&Path> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        bail!("{path} is not a relative path inside of the archive");
+    }
+
+    Ok(relative)
+}
+
+/// The name of a saved file, it can't point outside of the bins directory
+fn plain_file_name(file_name: &str) -> anyhow::Result<&str> {
+    match Path::new(file_name)
+        .components()
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [Component::Normal(_)] => Ok(file_name),
+        _ => bail!("{file_name} is not a plain file name"),
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use env_logger::Env;
+use log::info;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Generates the Homebrew cask of the released dmg
+#[derive(Parser, Debug)]
+struct Args {
+    /// The released tag, e.g. `v0.1.13`, defaults to the version of the package
+    #[arg(long)]
+    tag: Option<String>,
+    /// The SHA-256 checksum of the published dmg
+    #[arg(long, conflicts_with = "dmg")]
+    sha256: Option<String>,
+    /// The dmg to calculate the checksum of, when it's not given with `--sha256`
+    #[arg(long, default_value = "target/macos/StremioService.dmg")]
+    dmg: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct MacOSMetadata {
+    name: String,
+    display_name: String,
+    identifier: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct HomebrewMetadata {
+    token: String,
+    desc: String,
+    download_url: String,
+    zap: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    macos: Option<MacOSMetadata>,
+    homebrew: Option<HomebrewMetadata>,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let package = manifest.package.context("Failed to parse package")?;
+    let Metadata { macos, homebrew } = package
+        .metadata
+        .clone()
+        .context("Failed to parse manifest.package.metadata")?;
+    let macos = macos.context("Failed to parse manifest.package.metadata.macos")?;
+    let metadata = homebrew.context("Failed to parse manifest.package.metadata.homebrew")?;
+
+    let version = match args.tag {
+        Some(tag) => tag.trim_start_matches('v').to_owned(),
+        None => env!("CARGO_PKG_VERSION").to_owned(),
+    };
+    semver::Version::parse(&version).context(format!("Invalid version {version}"))?;
+
+    let sha256 = match args.sha256 {
+        Some(sha256) => sha256.to_lowercase(),
+        None => {
+            let dmg = manifest_dir.join(&args.dmg);
+            info!("Calculating the checksum of {}", dmg.display());
+            let contents =
+                std::fs::read(&dmg).context(format!("Failed to read {}", dmg.display()))?;
+            hex::encode(Sha256::digest(contents))
+        }
+    };
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Invalid SHA-256 checksum {sha256}");
+    }
+
+    // the url is interpolated by Homebrew, so `brew bump-cask-pr` only has to change the version and checksum
+    let url = ruby_string(&metadata.download_url).replace("{version}", "#{version}");
+    let zap = metadata
+        .zap
+        .iter()
+        .map(|path| format!("    {},", ruby_string(path)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let cask = format!(
+        "cask {token} do
+  version {version}
+  sha256 {sha256}
+
+  url {url}
+  name {name}
+  desc {description}
+  homepage {homepage}
+
+  # the service updates itself
+  auto_updates true
+
+  app {app}
+
+  uninstall quit: {identifier}
+
+  zap trash: [
+{zap}
+  ]
+end
+",
+        token = ruby_string(&metadata.token),
+        version = ruby_string(&version),
+        sha256 = ruby_string(&sha256),
+        name = ruby_string(&macos.display_name),
+        description = ruby_string(&metadata.desc),
+        homepage = ruby_string(package.homepage().unwrap_or_default()),
+        app = ruby_string(&format!("{}.app", macos.name)),
+        identifier = ruby_string(&macos.identifier),
+    );
+
+    let target_path = manifest_dir.join("target").join("homebrew");
+    std::fs::create_dir_all(&target_path).context(format!(
+        "Failed to create target dir: {}",
+        target_path.display()
+    ))?;
+    let cask_path = target_path.join(format!("{}.rb", metadata.token));
+    std::fs::write(&cask_path, cask).context(format!("Failed to write {}", cask_path.display()))?;
+    info!("Finished the Homebrew cask {}", cask_path.display());
+
+    Ok(())
+}
+
+/// Quotes the value as a double-quoted Ruby string
+fn ruby_string(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace("#{", "\\#{")
+    )
+}
@@ -0,0 +1,339 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use env_logger::Env;
+use log::info;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Generates the winget and Chocolatey manifests of the released MSI
+#[derive(Parser, Debug)]
+struct Args {
+    /// The released tag, e.g. `v0.1.13`, defaults to the version of the package
+    #[arg(long)]
+    tag: Option<String>,
+    /// The URL of the published MSI, defaults to `package.metadata.msi.download_url`
+    #[arg(long)]
+    installer_url: Option<String>,
+    /// The SHA-256 checksum of the published MSI
+    #[arg(long, conflicts_with = "installer")]
+    sha256: Option<String>,
+    /// The MSI to calculate the checksum of, when it's not given with `--sha256`
+    #[arg(long, default_value = "target/windows/StremioService.msi")]
+    installer: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct MsiMetadata {
+    display_name: String,
+    manufacturer: String,
+    upgrade_code: String,
+    url: String,
+    download_url: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct WingetMetadata {
+    identifier: String,
+    license_url: String,
+    tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ChocolateyMetadata {
+    id: String,
+    license_url: String,
+    tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Metadata {
+    msi: Option<MsiMetadata>,
+    winget: Option<WingetMetadata>,
+    chocolatey: Option<ChocolateyMetadata>,
+}
+
+/// The version of the winget manifest schema
+const WINGET_MANIFEST_VERSION: &str = "1.6.0";
+
+/// Everything the manifests are generated from
+struct Release {
+    version: String,
+    installer_url: String,
+    sha256: String,
+    name: String,
+    publisher: String,
+    homepage: String,
+    repository: String,
+    license: String,
+    description: String,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let manifest = cargo_toml::Manifest::<Metadata>::from_path_with_metadata(manifest_path)
+        .context("Cannot read the manifest metadata")?;
+
+    let package = manifest.package.context("Failed to parse package")?;
+    let Metadata {
+        msi,
+        winget,
+        chocolatey,
+    } = package
+        .metadata
+        .clone()
+        .context("Failed to parse manifest.package.metadata")?;
+    let msi = msi.context("Failed to parse manifest.package.metadata.msi")?;
+    let winget = winget.context("Failed to parse manifest.package.metadata.winget")?;
+    let chocolatey = chocolatey.context("Failed to parse manifest.package.metadata.chocolatey")?;
+
+    let version = match args.tag {
+        Some(tag) => tag.trim_start_matches('v').to_owned(),
+        None => env!("CARGO_PKG_VERSION").to_owned(),
+    };
+    semver::Version::parse(&version).context(format!("Invalid version {version}"))?;
+
+    let sha256 = match args.sha256 {
+        Some(sha256) => sha256,
+        None => {
+            let installer = manifest_dir.join(&args.installer);
+            info!("Calculating the checksum of {}", installer.display());
+            let contents = std::fs::read(&installer)
+                .context(format!("Failed to read {}", installer.display()))?;
+            hex::encode(Sha256::digest(contents))
+        }
+    };
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Invalid SHA-256 checksum {sha256}");
+    }
+
+    let release = Release {
+        installer_url: args
+            .installer_url
+            .unwrap_or_else(|| msi.download_url.replace("{version}", &version)),
+        version,
+        sha256,
+        name: msi.display_name,
+        publisher: msi.manufacturer,
+        homepage: msi.url,
+        repository: package.repository().unwrap_or_default().to_owned(),
+        license: package.license().unwrap_or_default().to_owned(),
+        description: package.description().unwrap_or_default().to_owned(),
+    };
+
+    let winget_path = manifest_dir.join("target").join("winget");
+    write_winget(&winget_path, &release, &winget, &msi.upgrade_code)?;
+    info!("Finished the winget manifests {}", winget_path.display());
+
+    let chocolatey_path = manifest_dir.join("target").join("chocolatey");
+    write_chocolatey(&chocolatey_path, &release, &chocolatey)?;
+    info!(
+        "Finished the Chocolatey package {}",
+        chocolatey_path.display()
+    );
+
+    Ok(())
+}
+
+/// Writes the multi-file manifest in the `manifests/<letter>/<publisher>/<name>/<version>` layout of `winget-pkgs`
+fn write_winget(
+    target_path: &Path,
+    release: &Release,
+    metadata: &WingetMetadata,
+    upgrade_code: &str,
+) -> anyhow::Result<()> {
+    let identifier = &metadata.identifier;
+    let manifest_path = identifier
+        .split('.')
+        .fold(
+            target_path
+                .join("manifests")
+                .join(identifier[..1].to_lowercase()),
+            |path, part| path.join(part),
+        )
+        .join(&release.version);
+    if manifest_path.exists() {
+        std::fs::remove_dir_all(&manifest_path).context("Failed to remove old manifests")?;
+    }
+    std::fs::create_dir_all(&manifest_path)
+        .context(format!("Failed to create {}", manifest_path.display()))?;
+
+    let header = |manifest_type: &str| {
+        format!(
+            "# yaml-language-server: $schema=https://aka.ms/winget-manifest.{manifest_type}.{WINGET_MANIFEST_VERSION}.schema.json
+
+PackageIdentifier: {identifier}
+PackageVersion: {version}
+",
+            version = release.version,
+        )
+    };
+    let footer = |manifest_type: &str| {
+        format!("ManifestType: {manifest_type}\nManifestVersion: {WINGET_MANIFEST_VERSION}\n")
+    };
+
+    let version_manifest = format!(
+        "{header}DefaultLocale: en-US
+{footer}",
+        header = header("version"),
+        footer = footer("version"),
+    );
+
+    // The MSI installs for the current user by default and for every user with `ALLUSERS=1`
+    let installer_manifest = format!(
+        "{header}InstallerType: wix
+UpgradeBehavior: install
+Protocols:
+- stremio
+AppsAndFeaturesEntries:
+- UpgradeCode: '{{{upgrade_code}}}'
+Installers:
+- Architecture: x64
+  Scope: user
+  InstallerUrl: {installer_url}
+  InstallerSha256: {sha256}
+- Architecture: x64
+  Scope: machine
+  InstallerUrl: {installer_url}
+  InstallerSha256: {sha256}
+  InstallerSwitches:
+    Custom: ALLUSERS=1
+{footer}",
+        header = header("installer"),
+        installer_url = release.installer_url,
+        sha256 = release.sha256.to_uppercase(),
+        footer = footer("installer"),
+    );
+
+    let locale_manifest = format!(
+        "{header}PackageLocale: en-US
+Publisher: {publisher}
+PublisherUrl: {homepage}
+PackageName: {name}
+PackageUrl: {repository}
+License: {license}
+LicenseUrl: {license_url}
+ShortDescription: {description}
+Tags:
+{tags}
+{footer}",
+        header = header("defaultLocale"),
+        publisher = release.publisher,
+        homepage = release.homepage,
+        name = release.name,
+        repository = release.repository,
+        license = release.license,
+        license_url = metadata.license_url,
+        description = release.description,
+        tags = metadata
+            .tags
+            .iter()
+            .map(|tag| format!("- {tag}"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        footer = footer("defaultLocale"),
+    );
+
+    for (file_name, contents) in [
+        (format!("{identifier}.yaml"), version_manifest),
+        (format!("{identifier}.installer.yaml"), installer_manifest),
+        (format!("{identifier}.locale.en-US.yaml"), locale_manifest),
+    ] {
+        let path = manifest_path.join(file_name);
+        std::fs::write(&path, contents).context(format!("Failed to write {}", path.display()))?;
+        info!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Writes the `.nuspec` and the install script of the package, for `choco pack`
+fn write_chocolatey(
+    target_path: &Path,
+    release: &Release,
+    metadata: &ChocolateyMetadata,
+) -> anyhow::Result<()> {
+    if target_path.exists() {
+        std::fs::remove_dir_all(target_path).context("Failed to remove old package")?;
+    }
+    let tools_path = target_path.join("tools");
+    std::fs::create_dir_all(&tools_path)
+        .context(format!("Failed to create {}", tools_path.display()))?;
+
+    let nuspec = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<package xmlns=\"http://schemas.microsoft.com/packaging/2015/06/nuspec.xsd\">
+  <metadata>
+    <id>{id}</id>
+    <version>{version}</version>
+    <title>{name}</title>
+    <authors>{publisher}</authors>
+    <projectUrl>{homepage}</projectUrl>
+    <projectSourceUrl>{repository}</projectSourceUrl>
+    <licenseUrl>{license_url}</licenseUrl>
+    <requireLicenseAcceptance>false</requireLicenseAcceptance>
+    <tags>{tags}</tags>
+    <summary>{description}</summary>
+    <description>{description}</description>
+  </metadata>
+  <files>
+    <file src=\"tools\\**\" target=\"tools\" />
+  </files>
+</package>
+",
+        id = metadata.id,
+        version = release.version,
+        name = xml_escape(&release.name),
+        publisher = xml_escape(&release.publisher),
+        homepage = xml_escape(&release.homepage),
+        repository = xml_escape(&release.repository),
+        license_url = xml_escape(&metadata.license_url),
+        tags = xml_escape(&metadata.tags.join(" ")),
+        description = xml_escape(&release.description),
+    );
+    let nuspec_path = target_path.join(format!("{}.nuspec", metadata.id));
+    std::fs::write(&nuspec_path, nuspec)
+        .context(format!("Failed to write {}", nuspec_path.display()))?;
+    info!("Wrote {}", nuspec_path.display());
+
+    // Chocolatey installs the packages for every user of the machine
+    let install_script = format!(
+        "$ErrorActionPreference = 'Stop'
+
+$packageArgs = @{{
+  packageName    = $env:ChocolateyPackageName
+  fileType       = 'msi'
+  url64bit       = '{installer_url}'
+  checksum64     = '{sha256}'
+  checksumType64 = 'sha256'
+  silentArgs     = \"ALLUSERS=1 /qn /norestart /l*v `\"$($env:TEMP)\\$($env:ChocolateyPackageName).$($env:ChocolateyPackageVersion).MsiInstall.log`\"\"
+  validExitCodes = @(0, 3010, 1641)
+}}
+
+Install-ChocolateyPackage @packageArgs
+",
+        installer_url = release.installer_url,
+        sha256 = release.sha256,
+    );
+    let install_script_path = tools_path.join("chocolateyinstall.ps1");
+    std::fs::write(&install_script_path, install_script)
+        .context(format!("Failed to write {}", install_script_path.display()))?;
+    info!("Wrote {}", install_script_path.display());
+
+    Ok(())
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,302 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The browser Stremio Web is opened in, see the `browser` setting.
+//!
+//! The default browser of the system isn't always the one playing the streams,
+//! e.g. a Chromium build without the proprietary codecs or the DRM module on Linux,
+//! so Chrome, Firefox or a custom command is chosen from the tray instead.
+
+use std::{
+    ffi::OsStr,
+    io,
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Replaced by the url in the `browserCommand`, otherwise the url is its last argument
+pub const URL_PLACEHOLDER: &str = "%u";
+
+/// The browser the web pages are opened in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+    /// The default browser of the system
+    #[default]
+    Default,
+    Chrome,
+    Firefox,
+    /// The `browserCommand`
+    Custom,
+}
+
+impl Browser {
+    pub const ALL: [Self; 4] = [Self::Default, Self::Chrome, Self::Firefox, Self::Custom];
+
+    /// The name shown in the tray
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Default => "System default",
+            Self::Chrome => "Chrome",
+            Self::Firefox => "Firefox",
+            Self::Custom => "Custom command",
+        }
+    }
+
+    /// The commands opening the url, they're tried in order until one of them is found.
+    ///
+    /// The `custom_command` is the program and the arguments of [`Browser::Custom`].
+    pub fn commands(&self, url: &str, custom_command: &[String]) -> Vec<Command> {
+        match self {
+            Self::Default => open::commands(url),
+            Self::Chrome => app_commands(
+                url,
+                "Google Chrome",
+                "chrome",
+                &[
+                    "google-chrome",
+                    "google-chrome-stable",
+                    "chromium",
+                    "chromium-browser",
+                ],
+            ),
+            Self::Firefox => app_commands(url, "Firefox", "firefox", &["firefox"]),
+            Self::Custom => {
+                let Some((program, args)) = custom_command.split_first() else {
+                    return vec![];
+                };
+                let mut command = Command::new(program);
+                match args.iter().any(|arg| arg.contains(URL_PLACEHOLDER)) {
+                    true => command.args(args.iter().map(|arg| arg.replace(URL_PLACEHOLDER, url))),
+                    false => command.args(args).arg(url),
+                };
+
+                vec![command]
+            }
+        }
+    }
+
+    /// Opens the url, the browser runs on its own then.
+    ///
+    /// # Errors
+    ///
+    /// When the browser isn't installed or fails to start.
+    pub fn open(&self, url: &str, custom_command: &[String]) -> io::Result<()> {
+        // it reports the failures of the default application
+        if *self == Self::Default {
+            #[cfg(target_os = "windows")]
+            return shell_open(OsStr::new(url));
+            #[cfg(not(target_os = "windows"))]
+            return open::that(url);
+        }
+
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "No `browserCommand` is set");
+        for mut command in self.commands(url, custom_command) {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            match command.spawn() {
+                Ok(mut child) => {
+                    // it's reaped once the browser exits, e.g. right away when it was running already
+                    std::thread::spawn(move || child.wait());
+
+                    return Ok(());
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    last_error = io::Error::new(
+                        err.kind(),
+                        format!(
+                            "{} is not installed",
+                            command.get_program().to_string_lossy()
+                        ),
+                    );
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+/// The commands of a browser by its name on macOS, its `App Paths` name on Windows or its programs elsewhere
+#[allow(unused_variables)]
+fn app_commands(url: &str, macos_app: &str, windows_app: &str, programs: &[&str]) -> Vec<Command> {
+    #[cfg(target_os = "macos")]
+    {
+        let mut command = Command::new("/usr/bin/open");
+        command.arg("-a").arg(macos_app).arg(url);
+
+        vec![command]
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // the browser is usually not in the PATH, it's run directly without a shell, the url is never parsed by one
+        let program = app_path(windows_app).unwrap_or_else(|| format!("{windows_app}.exe").into());
+        let mut command = Command::new(program);
+        command.arg(url);
+
+        vec![command]
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    programs
+        .iter()
+        .map(|program| {
+            let mut command = Command::new(program);
+            command.arg(url);
+            command
+        })
+        .collect()
+}
+
+/// The executable of the app registered in the `App Paths` of the user or of the machine, e.g. for `chrome`
+#[cfg(target_os = "windows")]
+fn app_path(app: &str) -> Option<std::path::PathBuf> {
+    use winreg::{
+        enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE},
+        RegKey,
+    };
+
+    let key = format!(r"Software\Microsoft\Windows\CurrentVersion\App Paths\{app}.exe");
+    [HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE]
+        .into_iter()
+        .find_map(|hkey| {
+            RegKey::predef(hkey)
+                .open_subkey(&key)
+                .and_then(|key| key.get_value::<String, _>(""))
+                .ok()
+        })
+        .map(|path| path.trim_matches('"').into())
+}
+
+/// Opens the url or the file with its default application by `ShellExecuteW`, unlike [`open::that`]
+/// which runs `cmd /c start` and lets the shell parse the target, e.g. its `%VAR%` and `&`.
+#[cfg(target_os = "windows")]
+pub(crate) fn shell_open(target: &OsStr) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::UI::{Shell::ShellExecuteW, WindowsAndMessaging::SW_SHOWNORMAL};
+
+    let operation = crate::system_service::wide("open");
+    let file = target
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect::<Vec<_>>();
+    // SAFETY: the strings are NUL terminated and outlive the call
+    let instance = unsafe {
+        ShellExecuteW(
+            0,
+            operation.as_ptr(),
+            file.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // the values up to 32 are the errors
+    match instance > 32 {
+        true => Ok(()),
+        false => Err(io::Error::other(format!(
+            "{} can't be opened ({instance})",
+            target.to_string_lossy()
+        ))),
+    }
+}
+
+/// Whether the target is a web page, which is opened in the browser
+pub fn is_web_page(target: &OsStr) -> bool {
+    target
+        .to_str()
+        .is_some_and(|target| target.starts_with("http://") || target.starts_with("https://"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_custom_browser_command() {
+        let url = "https://web.stremio.com/#/library";
+        let flatpak = ["flatpak", "run", "com.google.Chrome"].map(str::to_owned);
+        assert_eq!(
+            vec![["flatpak", "run", "com.google.Chrome", url]
+                .map(str::to_owned)
+                .to_vec()],
+            Browser::Custom
+                .commands(url, &flatpak)
+                .iter()
+                .map(args)
+                .collect::<Vec<_>>()
+        );
+
+        let app_mode = ["brave-browser", "--app=%u", "--new-window"].map(str::to_owned);
+        assert_eq!(
+            vec![
+                "brave-browser".to_owned(),
+                format!("--app={url}"),
+                "--new-window".to_owned()
+            ],
+            args(&Browser::Custom.commands(url, &app_mode)[0])
+        );
+
+        assert!(Browser::Custom.commands(url, &[]).is_empty());
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            Browser::Custom.open(url, &[]).unwrap_err().kind()
+        );
+        assert!(is_web_page(OsStr::new(url)));
+        assert!(!is_web_page(OsStr::new("/home/user/logs")));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_windows_browser_runs_without_a_shell() {
+        let url = "https://web.stremio.com/#/search?search=%PATH%&calc";
+        let commands = Browser::Firefox.commands(url, &[]);
+
+        assert_eq!(1, commands.len());
+        let args = args(&commands[0]);
+        assert!(args[0].to_ascii_lowercase().ends_with("firefox.exe"));
+        assert_eq!(vec![url.to_owned()], args[1..]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_chrome_falls_back_to_chromium() {
+        let commands = Browser::Chrome.commands("https://web.stremio.com/", &[]);
+        let programs = commands
+            .iter()
+            .map(|command| command.get_program().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "google-chrome",
+                "google-chrome-stable",
+                "chromium",
+                "chromium-browser"
+            ],
+            programs
+        );
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            Browser::Custom
+                .open(
+                    "https://web.stremio.com/",
+                    &["/nonexistent/browser".to_owned()]
+                )
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::fmt;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+
+/// The build of the service, embedded by the build script.
+///
+/// Shown by `--version`, the tray and the About dialog and sent with the update checks,
+/// in order to tell apart the reports from different builds of the same version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// The short hash of the built commit or `unknown` when built outside of the git repository
+    pub git_commit: &'static str,
+    /// RFC 3339 date in UTC
+    pub build_date: &'static str,
+    /// The Rust target triple, e.g. `x86_64-pc-windows-msvc`
+    pub target: &'static str,
+    /// The version of the server.js the service was built with
+    pub server_version: &'static str,
+}
+
+/// The build of the running service.
+pub const BUILD_INFO: BuildInfo = BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_commit: env!("STREMIO_SERVICE_GIT_COMMIT"),
+    build_date: env!("STREMIO_SERVICE_BUILD_DATE"),
+    target: env!("STREMIO_SERVICE_TARGET"),
+    server_version: env!("STREMIO_SERVICE_SERVER_VERSION"),
+};
+
+impl BuildInfo {
+    /// The version with the commit, e.g. `v0.1.13 (1beeb6f4c2)`
+    pub fn short(&self) -> String {
+        format!("v{} ({})", self.version, self.git_commit)
+    }
+
+    /// The headers sent to the updater endpoint.
+    pub fn headers(&self) -> HeaderMap {
+        [
+            ("x-stremio-service-version", self.version),
+            ("x-stremio-service-commit", self.git_commit),
+            ("x-stremio-service-build-date", self.build_date),
+            ("x-stremio-service-target", self.target),
+            ("x-stremio-server-version", self.server_version),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            Some((
+                HeaderName::from_static(name),
+                HeaderValue::from_str(value).ok()?,
+            ))
+        })
+        .collect()
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", env!("CARGO_PKG_NAME"), self.version)?;
+        writeln!(f, "commit: {}", self.git_commit)?;
+        writeln!(f, "built: {}", self.build_date)?;
+        writeln!(f, "target: {}", self.target)?;
+        write!(f, "server.js: {}", self.server_version)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build_info_is_embedded() {
+        assert_eq!(env!("CARGO_PKG_VERSION"), BUILD_INFO.version);
+        for value in [
+            BUILD_INFO.git_commit,
+            BUILD_INFO.build_date,
+            BUILD_INFO.target,
+            BUILD_INFO.server_version,
+        ] {
+            assert!(!value.is_empty());
+        }
+        assert!(chrono::DateTime::parse_from_rfc3339(BUILD_INFO.build_date).is_ok());
+
+        let json = serde_json::to_value(BUILD_INFO).expect("Should serialize");
+        for key in [
+            "version",
+            "gitCommit",
+            "buildDate",
+            "target",
+            "serverVersion",
+        ] {
+            assert!(json.get(key).is_some(), "Missing {key}");
+        }
+        assert_eq!(5, BUILD_INFO.headers().len());
+    }
+}
@@ -1,12 +1,36 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 
 pub const STREMIO_URL: &str = "https://web.stremio.com";
+/// The local address server.js listens on by default
+pub const SERVER_URL: &str = "http://127.0.0.1:11470";
+/// The port of [`SERVER_URL`]
+pub const SERVER_PORT: u16 = 11470;
+/// The local address of the control API, see [`ControlApi`](crate::control::ControlApi)
+pub const CONTROL_API_ADDRESS: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, CONTROL_API_PORT));
+/// The port of [`CONTROL_API_ADDRESS`]
+pub const CONTROL_API_PORT: u16 = 11471;
+/// The port server.js is served over HTTPS on by default, see [`HttpsProxy`](crate::https::HttpsProxy)
+pub const HTTPS_PORT: u16 = 11472;
+/// The port the local Stremio Web is served on by default, see [`LocalWebUi`](crate::web::LocalWebUi)
+pub const LOCAL_WEB_UI_PORT: u16 = 11473;
+/// The releases of server.js, e.g. `<version>/desktop/server.js`
+pub const SERVER_DOWNLOAD_URL: &str = "https://dl.strem.io/server/";
 pub const APP_IDENTIFIER: &str = "com.stremio.service";
 pub const APP_NAME: &str = "StremioService";
 
+pub const CONFIG_DIR_NAME: &str = "stremio-service";
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+
 pub const DESKTOP_FILE_PATH: &str = "/usr/share/applications";
 pub const DESKTOP_FILE_NAME: &str = "com.stremio.service.desktop";
+/// The desktop file of the snap, relative to `$SNAP`
+pub const SNAP_DESKTOP_FILE_PATH: &str = "meta/gui/stremio-service.desktop";
 pub const AUTOSTART_CONFIG_PATH: &str = ".config/autostart";
+/// The systemd user units directory, when `XDG_CONFIG_HOME` isn't set
+pub const SYSTEMD_USER_UNITS_PATH: &str = ".config/systemd/user";
 pub const LAUNCH_AGENTS_PATH: &str = "Library/LaunchAgents";
 
 pub const UPDATE_ENDPOINT: [&str; 3] = [
@@ -0,0 +1,730 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The local HTTP API controlling the service, for the dashboards, the scripts and the Stremio Web settings.
+//!
+//! It listens only on the loopback interface, by default on [`CONTROL_API_ADDRESS`](crate::constants::CONTROL_API_ADDRESS):
+//!
+//! | Endpoint | Response |
+//! |---|---|
+//! | `GET /status` | The [`ServerInfo`] of server.js as JSON |
+//! | `GET /status/events` | The [`ServerInfo`] of server.js on every change, as [Server-Sent Events](https://html.spec.whatwg.org/multipage/server-sent-events.html) |
+//! | `GET /version` | The [`BuildInfo`](crate::build_info::BuildInfo) of the service as JSON |
+//! | `GET /logs/tail?lines=100` | The last lines of the server.js output as text |
+//! | `POST /start-server` | Starts server.js unless it's running, responds with its [`ServerInfo`] |
+//! | `POST /restart-server` | Restarts server.js, responds with its [`ServerInfo`] |
+//! | `POST /stop-server` | Stops server.js, responds with its [`ServerInfo`] |
+//! | `GET /update` | Checks for a newer release, responds with the [`UpdateCheck`] |
+//! | `GET /update/progress` | The [`DownloadProgress`] of the installer being downloaded, `null` otherwise |
+//! | `GET /links` | The [`LinkRecord`]s of the recently handled deep links, newest first |
+//!
+//! The failures are responded with `{ "error": <message> }`.
+//! The commands of the CLI (e.g. `stremio-service status`) use the API through a [`ControlClient`].
+//! Any local program can use the API, while the browsers only from the origins of the saved Web UIs:
+//! the requests of any other origin and the ones for another host (DNS rebinding) are forbidden.
+//!
+//! [`ServerInfo`]: crate::status::ServerInfo
+//! [`UpdateCheck`]: crate::updater::UpdateCheck
+//! [`DownloadProgress`]: crate::updater::DownloadProgress
+//! [`LinkRecord`]: crate::link_history::LinkRecord
+
+use std::{convert::Infallible, future::Future, net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use hyper::{
+    header::{self, HeaderValue},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use log::{error, info};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::{
+    build_info::BUILD_INFO,
+    error::ServiceError,
+    link_history::LinkHistory,
+    server::{Server, LOG_TAIL_LINES},
+    status::ServerInfo,
+    updater::{DownloadProgress, UpdateCheck, Updater},
+};
+
+/// The lines of `/logs/tail` without the `lines` parameter
+const DEFAULT_TAIL_LINES: usize = 100;
+/// How long a [`ControlClient`] waits for a response, stopping server.js may take a while
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Serves the control API of a [`Server`]
+#[derive(Debug, Clone)]
+pub struct ControlApi {
+    address: SocketAddr,
+    server: Server,
+    /// The origins allowed to use the API from a browser, e.g. `https://web.stremio.com`
+    origins: Vec<String>,
+    /// Checks for the updates of `GET /update`, `None` when there's no updater
+    updater: Option<Arc<Updater>>,
+    /// The progress of `GET /update/progress`, see [`ControlApi::with_update_progress`]
+    update_progress: watch::Receiver<Option<DownloadProgress>>,
+    /// The deep links of `GET /links`, `None` when there's no history
+    link_history: Option<LinkHistory>,
+    /// Ends the event streams once the API stops, they'd keep it from stopping otherwise
+    closing: CancellationToken,
+}
+
+impl ControlApi {
+    /// The API listening on `address`, the browsers can use it only from the given Web UIs.
+    pub fn new<'a>(
+        address: SocketAddr,
+        server: Server,
+        web_uis: impl IntoIterator<Item = &'a Url>,
+    ) -> Self {
+        Self {
+            address,
+            server,
+            origins: web_uis
+                .into_iter()
+                .map(|url| url.origin().ascii_serialization())
+                .collect(),
+            updater: None,
+            update_progress: watch::channel(None).1,
+            link_history: None,
+            closing: CancellationToken::new(),
+        }
+    }
+
+    /// Checks for the updates of `GET /update` with the updater.
+    pub fn with_updater(mut self, updater: Updater) -> Self {
+        self.updater = Some(Arc::new(updater));
+        self
+    }
+
+    /// Responds to `GET /update/progress` with the progress of the downloads of the updater of the service.
+    pub fn with_update_progress(
+        mut self,
+        update_progress: watch::Receiver<Option<DownloadProgress>>,
+    ) -> Self {
+        self.update_progress = update_progress;
+        self
+    }
+
+    /// Responds to `GET /links` with the recent links of the history.
+    pub fn with_link_history(mut self, link_history: LinkHistory) -> Self {
+        self.link_history = Some(link_history);
+        self
+    }
+
+    /// Serves the API until the `shutdown` future resolves.
+    ///
+    /// # Errors
+    ///
+    /// When the address can't be listened on, e.g. it's used by another program.
+    pub async fn serve(self, shutdown: impl Future<Output = ()>) -> Result<(), ServiceError> {
+        let address = self.address;
+        let builder = hyper::Server::try_bind(&address)
+            .context(format!("Failed to listen on {address}"))
+            .map_err(ServiceError::Other)?;
+
+        let closing = self.closing.clone();
+        let shutdown = async move {
+            shutdown.await;
+            closing.cancel();
+        };
+        let api = Arc::new(self);
+        let make_service = make_service_fn(move |_| {
+            let api = api.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let api = api.clone();
+                    async move { Ok::<_, Infallible>(api.handle(request).await) }
+                }))
+            }
+        });
+
+        info!("Control API listening on http://{address}");
+        builder
+            .serve(make_service)
+            .with_graceful_shutdown(shutdown)
+            .await
+            .context("The control API failed")
+            .map_err(ServiceError::Other)
+    }
+
+    /// Responds to a request of the API.
+    pub async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        if !self.is_allowed_host(&request) {
+            return error(StatusCode::FORBIDDEN, "Forbidden host");
+        }
+        let origin = request.headers().get(header::ORIGIN).cloned();
+        if let Some(origin) = &origin {
+            if !self.is_allowed_origin(origin) {
+                return error(StatusCode::FORBIDDEN, "Forbidden origin");
+            }
+        }
+
+        let mut response = match (request.method(), request.uri().path()) {
+            (&Method::OPTIONS, _) => preflight(),
+            (&Method::GET, "/status") => json(StatusCode::OK, self.server.info().await),
+            (&Method::GET, "/status/events") => self.status_events(),
+            (&Method::GET, "/version") => json(StatusCode::OK, BUILD_INFO),
+            (&Method::GET, "/logs/tail") => self.log_tail(request.uri().query()),
+            (&Method::POST, "/start-server") => {
+                info!("Starting server.js from the control API");
+                self.respond_with_info(self.server.start()).await
+            }
+            (&Method::GET, "/update") => self.check_update().await,
+            (&Method::GET, "/update/progress") => {
+                json(StatusCode::OK, *self.update_progress.borrow())
+            }
+            (&Method::GET, "/links") => {
+                let links = self
+                    .link_history
+                    .as_ref()
+                    .map(LinkHistory::recent)
+                    .unwrap_or_default();
+
+                json(StatusCode::OK, links)
+            }
+            (&Method::POST, "/restart-server") => {
+                info!("Restarting server.js from the control API");
+                self.respond_with_info(self.server.restart().await).await
+            }
+            (&Method::POST, "/stop-server") => {
+                info!("Stopping server.js from the control API");
+                self.respond_with_info(self.server.stop().await).await
+            }
+            (
+                _,
+                "/status" | "/status/events" | "/version" | "/logs/tail" | "/start-server"
+                | "/restart-server" | "/stop-server" | "/update" | "/update/progress" | "/links",
+            ) => error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"),
+            (_, path) => error(StatusCode::NOT_FOUND, &format!("No endpoint {path}")),
+        };
+
+        if let Some(origin) = origin {
+            let headers = response.headers_mut();
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+        }
+
+        response
+    }
+
+    /// Whether the request is for the address of the API, any host is allowed without the header
+    fn is_allowed_host(&self, request: &Request<Body>) -> bool {
+        let Some(host) = request.headers().get(header::HOST) else {
+            return true;
+        };
+
+        let port = self.address.port();
+        [
+            format!("{}:{port}", self.address.ip()),
+            format!("localhost:{port}"),
+        ]
+        .iter()
+        .any(|allowed| host.as_bytes().eq_ignore_ascii_case(allowed.as_bytes()))
+    }
+
+    fn is_allowed_origin(&self, origin: &HeaderValue) -> bool {
+        self.origins
+            .iter()
+            .any(|allowed| origin.as_bytes() == allowed.as_bytes())
+    }
+
+    /// The last lines of the server.js output, the `lines` parameter is capped at [`LOG_TAIL_LINES`]
+    fn log_tail(&self, query: Option<&str>) -> Response<Body> {
+        let lines = url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+            .find(|(key, _)| key == "lines")
+            .map(|(_, lines)| lines.parse::<usize>());
+        let lines = match lines {
+            Some(Ok(lines)) => lines.min(LOG_TAIL_LINES),
+            Some(Err(_)) => return error(StatusCode::BAD_REQUEST, "Invalid number of lines"),
+            None => DEFAULT_TAIL_LINES,
+        };
+
+        let mut tail = self.server.log_tail(lines).join("\n");
+        if !tail.is_empty() {
+            tail.push('\n');
+        }
+
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(Body::from(tail))
+            .expect("Should be a valid response")
+    }
+
+    /// Sends the current status of server.js, then every change of it, until the client or the API closes.
+    fn status_events(&self) -> Response<Body> {
+        let mut status = self.server.subscribe();
+        let closing = self.closing.clone();
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            let events = async {
+                loop {
+                    let info = serde_json::to_string(&*status.borrow_and_update())
+                        .expect("Should serialize");
+                    if sender
+                        .send_data(format!("data: {info}\n\n").into())
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                    if status.changed().await.is_err() {
+                        break;
+                    }
+                }
+            };
+
+            tokio::select! {
+                _ = events => {}
+                _ = closing.cancelled() => {}
+            }
+        });
+
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(body)
+            .expect("Should be a valid response")
+    }
+
+    async fn check_update(&self) -> Response<Body> {
+        let Some(updater) = &self.updater else {
+            return error(StatusCode::NOT_FOUND, "The service has no updater");
+        };
+
+        match updater.check().await {
+            Ok(check) => json(StatusCode::OK, check),
+            Err(err) => {
+                error!("{err}");
+                error(StatusCode::BAD_GATEWAY, &err.to_string())
+            }
+        }
+    }
+
+    async fn respond_with_info(&self, result: Result<(), ServiceError>) -> Response<Body> {
+        match result {
+            Ok(()) => json(StatusCode::OK, self.server.info().await),
+            Err(err) => {
+                error!("{err}");
+                error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+            }
+        }
+    }
+}
+
+/// A client of the control API of the running instance, for the commands of the CLI
+#[derive(Debug, Clone)]
+pub struct ControlClient {
+    base: Url,
+    client: reqwest::Client,
+}
+
+impl ControlClient {
+    /// The client of the API listening on `address`
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            base: Url::parse(&format!("http://{address}/")).expect("Should be a valid URL"),
+            client: reqwest::Client::builder()
+                .timeout(CLIENT_TIMEOUT)
+                .no_proxy()
+                .build()
+                .expect("Should build the client"),
+        }
+    }
+
+    /// `GET /status`
+    pub async fn status(&self) -> Result<ServerInfo, ServiceError> {
+        self.request(reqwest::Method::GET, "status").await
+    }
+
+    /// `POST /start-server`
+    pub async fn start_server(&self) -> Result<ServerInfo, ServiceError> {
+        self.request(reqwest::Method::POST, "start-server").await
+    }
+
+    /// `POST /stop-server`
+    pub async fn stop_server(&self) -> Result<ServerInfo, ServiceError> {
+        self.request(reqwest::Method::POST, "stop-server").await
+    }
+
+    /// `POST /restart-server`
+    pub async fn restart_server(&self) -> Result<ServerInfo, ServiceError> {
+        self.request(reqwest::Method::POST, "restart-server").await
+    }
+
+    /// `GET /update`
+    pub async fn check_update(&self) -> Result<UpdateCheck, ServiceError> {
+        self.request(reqwest::Method::GET, "update").await
+    }
+
+    /// Sends the request and parses the response, or the `error` of a failed one.
+    ///
+    /// # Errors
+    ///
+    /// [`ServiceError::NotRunning`] when nothing listens on the address of the API.
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+    ) -> Result<T, ServiceError> {
+        let url = self.base.join(path).expect("Should be a valid path");
+        let response = self
+            .client
+            .request(method, url.clone())
+            .send()
+            .await
+            .map_err(|err| match err.is_connect() {
+                true => ServiceError::NotRunning,
+                false => ServiceError::Other(
+                    anyhow::Error::new(err).context(format!("Failed to request {url}")),
+                ),
+            })?;
+
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .context(format!("Failed to read the response of {url}"))
+            .map_err(ServiceError::Other)?;
+        if !status.is_success() {
+            let message = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| body["error"].as_str().map(ToOwned::to_owned))
+                .unwrap_or_else(|| status.to_string());
+            return Err(ServiceError::Other(anyhow::anyhow!(message)));
+        }
+
+        serde_json::from_slice(&body)
+            .context(format!("Invalid response of {url}"))
+            .map_err(ServiceError::Other)
+    }
+}
+
+/// Allows the requests of the Web UIs, including from a public to the loopback address
+fn preflight() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, POST")
+        .header("Access-Control-Allow-Private-Network", "true")
+        .body(Body::empty())
+        .expect("Should be a valid response")
+}
+
+fn json(status: StatusCode, body: impl Serialize) -> Response<Body> {
+    let body = serde_json::to_vec(&body).expect("Should serialize");
+
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("Should be a valid response")
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json(status, json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use tokio::process::Command;
+
+    use super::*;
+    use crate::{
+        link_history::{LinkAction, LinkRecord},
+        server::{Config, Process, ProcessSpawner},
+        status::{ServerInfo, ServerTrayStatus},
+    };
+
+    /// server.js never starts
+    #[derive(Debug)]
+    struct MissingNode;
+
+    impl ProcessSpawner for MissingNode {
+        fn spawn(&self, _command: &mut Command) -> io::Result<Box<dyn Process>> {
+            Err(io::ErrorKind::NotFound.into())
+        }
+    }
+
+    fn control_api() -> ControlApi {
+        let exe = std::env::current_exe().unwrap();
+        let config = Config::from_paths(exe.clone(), exe.clone(), exe.clone(), exe).unwrap();
+        let web_ui = Url::parse("https://web.stremio.com/").unwrap();
+
+        ControlApi::new(
+            "127.0.0.1:11471".parse().unwrap(),
+            Server::with_spawner(config, MissingNode),
+            [&web_ui],
+        )
+    }
+
+    fn request(method: Method, path: &str) -> hyper::http::request::Builder {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::HOST, "127.0.0.1:11471")
+    }
+
+    async fn body(response: Response<Body>) -> String {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_control_api_endpoints() {
+        let api = control_api();
+
+        let response = api
+            .handle(request(Method::GET, "/status").body(Body::empty()).unwrap())
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let info: ServerInfo = serde_json::from_str(&body(response).await).unwrap();
+        assert_eq!(ServerTrayStatus::Stopped, info.status);
+
+        let response = api
+            .handle(
+                request(Method::GET, "/version")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(
+            serde_json::to_value(BUILD_INFO).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&body(response).await).unwrap()
+        );
+
+        let response = api
+            .handle(
+                request(Method::GET, "/logs/tail?lines=10")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("", body(response).await);
+
+        let response = api
+            .handle(
+                request(Method::GET, "/logs/tail?lines=all")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = api
+            .handle(
+                request(Method::POST, "/stop-server")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = api
+            .handle(
+                request(Method::POST, "/restart-server")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        assert!(body(response).await.contains("Server didn't start"));
+
+        let response = api
+            .handle(
+                request(Method::GET, "/stop-server")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, response.status());
+
+        let response = api
+            .handle(
+                request(Method::GET, "/settings")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+        let progress = DownloadProgress {
+            downloaded: 1 << 20,
+            total: Some(4 << 20),
+        };
+        let (sender, receiver) = watch::channel(None);
+        let api = api.with_update_progress(receiver);
+        let get_progress = || {
+            api.handle(
+                request(Method::GET, "/update/progress")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        assert_eq!("null", body(get_progress().await).await);
+        sender.send_replace(Some(progress));
+        assert_eq!(
+            Some(progress),
+            serde_json::from_str(&body(get_progress().await).await).unwrap()
+        );
+
+        let links = api
+            .handle(request(Method::GET, "/links").body(Body::empty()).unwrap())
+            .await;
+        assert_eq!("[]", body(links).await);
+        let dir = tempfile::tempdir().unwrap();
+        let history = LinkHistory::new(dir.path().join("links.json"));
+        history.record("stremio:///detail/movie/1", LinkAction::Queued);
+        history.record(
+            "stremio://example.com/manifest.json",
+            LinkAction::Refused {
+                reason: "Not a Stremio host".to_owned(),
+            },
+        );
+        let api = api.with_link_history(history);
+        let links = api
+            .handle(request(Method::GET, "/links").body(Body::empty()).unwrap())
+            .await;
+        let links: Vec<LinkRecord> = serde_json::from_str(&body(links).await).unwrap();
+        assert_eq!(
+            vec![
+                "stremio://example.com/manifest.json",
+                "stremio:///detail/movie/1"
+            ],
+            links
+                .iter()
+                .map(|link| link.url.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_events() {
+        use hyper::body::HttpBody;
+
+        let api = control_api();
+        let response = api
+            .handle(
+                request(Method::GET, "/status/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "text/event-stream",
+            response.headers()[header::CONTENT_TYPE]
+        );
+
+        let mut events = response.into_body();
+        let event = events.data().await.expect("Should send").unwrap();
+        let info = std::str::from_utf8(&event)
+            .unwrap()
+            .strip_prefix("data: ")
+            .and_then(|event| event.strip_suffix("\n\n"))
+            .expect("Should be an event");
+        let info: ServerInfo = serde_json::from_str(info).unwrap();
+        assert_eq!(ServerTrayStatus::Stopped, info.status);
+
+        api.closing.cancel();
+        assert!(events.data().await.is_none(), "Should end with the API");
+    }
+
+    #[tokio::test]
+    async fn test_control_client() {
+        let address: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap();
+        let client = ControlClient::new(address);
+        assert!(matches!(
+            client.status().await,
+            Err(ServiceError::NotRunning)
+        ));
+
+        let api = control_api();
+        let api = ControlApi { address, ..api };
+        let (shutdown, stopped) = tokio::sync::oneshot::channel::<()>();
+        let serving = tokio::spawn(api.serve(async {
+            let _ = stopped.await;
+        }));
+        let status = loop {
+            match client.status().await {
+                Err(ServiceError::NotRunning) => tokio::task::yield_now().await,
+                status => break status,
+            }
+        };
+        assert_eq!(ServerTrayStatus::Stopped, status.unwrap().status);
+
+        let err = client
+            .start_server()
+            .await
+            .expect_err("Should fail to start");
+        assert!(err.to_string().contains("Server didn't start"), "{err}");
+        let err = client
+            .check_update()
+            .await
+            .expect_err("Should have no updater");
+        assert_eq!("The service has no updater", err.to_string());
+        assert_eq!(
+            ServerTrayStatus::Stopped,
+            client.stop_server().await.unwrap().status
+        );
+
+        shutdown.send(()).unwrap();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_control_api_allows_only_the_web_uis() {
+        let api = control_api();
+
+        let response = api
+            .handle(
+                request(Method::GET, "/status")
+                    .header(header::ORIGIN, "https://web.stremio.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "https://web.stremio.com",
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN]
+        );
+
+        let response = api
+            .handle(
+                request(Method::POST, "/stop-server")
+                    .header(header::ORIGIN, "https://example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+        let response = api
+            .handle(
+                Request::get("/status")
+                    .header(header::HOST, "rebound.example.com:11471")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+        let response = api
+            .handle(
+                Request::get("/status")
+                    .header(header::HOST, "localhost:11471")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The opt-in crash reports of the service, see the `crashReports` setting.
+//!
+//! A panic of any thread of the service writes a JSON report to the crash reports directory
+//! (see [`Directories::crash_reports`](crate::directories::Directories::crash_reports)) with the backtrace,
+//! the OS, the build details and the last lines of the log. On the next start the user is asked to send
+//! the pending reports to the `crashReportEndpoint`, the declined ones are kept in its `dismissed` directory
+//! to be attached to a bug report instead.
+//!
+//! Only the panics are reported, the native crashes (e.g. of the tray libraries) leave no report.
+
+use std::{
+    backtrace::Backtrace,
+    panic::PanicHookInfo,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    build_info::{BuildInfo, BUILD_INFO},
+    tls::Roots,
+};
+
+/// The lines of the log included in the report
+const LOG_LINES: usize = 200;
+const DISMISSED_DIR_NAME: &str = "dismissed";
+
+/// What's known about a panic
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub build: &'static BuildInfo,
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// The name of the panicked thread, e.g. `main` or `tokio-runtime-worker`
+    pub thread: Option<String>,
+    /// The panic message
+    pub message: String,
+    /// The file, line and column of the panic
+    pub location: Option<String>,
+    pub backtrace: String,
+    /// The last [`LOG_LINES`] lines of the current log file
+    pub log: Vec<String>,
+}
+
+impl CrashReport {
+    fn new(info: &PanicHookInfo, log_file: &Path) -> Self {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_owned());
+
+        Self {
+            timestamp: chrono::Utc::now(),
+            build: &BUILD_INFO,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            thread: std::thread::current().name().map(ToOwned::to_owned),
+            message,
+            location: info.location().map(ToString::to_string),
+            backtrace: Backtrace::force_capture().to_string(),
+            log: last_lines(log_file, LOG_LINES),
+        }
+    }
+}
+
+/// Writes a [`CrashReport`] to `dir` on every panic, before the default hook prints it.
+///
+/// `log_file` is the current log file, see [`logging`](crate::logging).
+pub fn install(dir: PathBuf, log_file: PathBuf) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = CrashReport::new(info, &log_file);
+        match write(&dir, &report) {
+            Ok(file) => log::error!("Crash report written to {}", file.display()),
+            Err(err) => log::error!("Failed to write the crash report: {err:#}"),
+        }
+
+        default_hook(info);
+    }));
+}
+
+fn write(dir: &Path, report: &CrashReport) -> Result<PathBuf, anyhow::Error> {
+    std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    let file = dir.join(format!(
+        "crash-{}.json",
+        report.timestamp.format("%Y%m%dT%H%M%S%.3f")
+    ));
+    std::fs::write(&file, serde_json::to_vec_pretty(report)?)
+        .context(format!("Failed to write {}", file.display()))?;
+
+    Ok(file)
+}
+
+/// The reports neither sent nor dismissed yet, the oldest first
+pub fn pending(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut reports = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("crash-"))
+        })
+        .collect::<Vec<_>>();
+    reports.sort();
+
+    reports
+}
+
+/// Sends the report to the endpoint as it is and removes it.
+pub async fn send(report: &Path, endpoint: &Url, tls_roots: &Roots) -> Result<(), anyhow::Error> {
+    let contents = tokio::fs::read(report)
+        .await
+        .context(format!("Failed to read {}", report.display()))?;
+    tls_roots
+        .client_builder()
+        .default_headers(BUILD_INFO.headers())
+        .build()
+        .context("Failed to build the crash report client")?
+        .post(endpoint.clone())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(contents)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .context(format!("Failed to send the crash report to {endpoint}"))?;
+
+    tokio::fs::remove_file(report)
+        .await
+        .context(format!("Failed to remove {}", report.display()))
+}
+
+/// Moves the report out of the pending ones, to the `dismissed` directory.
+pub fn dismiss(report: &Path) -> Result<(), anyhow::Error> {
+    let (Some(dir), Some(name)) = (report.parent(), report.file_name()) else {
+        anyhow::bail!("Not a crash report {}", report.display());
+    };
+    let dismissed = dir.join(DISMISSED_DIR_NAME);
+    std::fs::create_dir_all(&dismissed)
+        .context(format!("Failed to create {}", dismissed.display()))?;
+    std::fs::rename(report, dismissed.join(name))
+        .context(format!("Failed to move {}", report.display()))
+}
+
+/// The last `count` lines of the file, none when it can't be read
+fn last_lines(file: &Path, count: usize) -> Vec<String> {
+    let Ok(contents) = std::fs::read_to_string(file) else {
+        return vec![];
+    };
+    let lines = contents.lines().collect::<Vec<_>>();
+
+    lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_panic_writes_a_crash_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_file = dir.path().join("service.log");
+        let reports = dir.path().join("crashes");
+        std::fs::write(&log_file, "first\nlast\n").unwrap();
+
+        let default_hook = std::panic::take_hook();
+        install(reports.clone(), log_file);
+        std::thread::Builder::new()
+            .name("crashing".to_owned())
+            .spawn(|| panic!("Something went wrong"))
+            .unwrap()
+            .join()
+            .expect_err("Should panic");
+        std::panic::set_hook(default_hook);
+
+        let pending = pending(&reports);
+        assert_eq!(1, pending.len());
+        let report: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&pending[0]).unwrap()).unwrap();
+        assert_eq!("Something went wrong", report["message"]);
+        assert_eq!("crashing", report["thread"]);
+        assert_eq!(serde_json::json!(["first", "last"]), report["log"]);
+        assert_eq!(BUILD_INFO.version, report["build"]["version"]);
+
+        dismiss(&pending[0]).expect("Should dismiss the report");
+        assert!(super::pending(&reports).is_empty());
+        assert_eq!(
+            1,
+            std::fs::read_dir(reports.join(DISMISSED_DIR_NAME))
+                .unwrap()
+                .count()
+        );
+    }
+}
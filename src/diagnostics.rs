@@ -0,0 +1,223 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The environment of the service shown by the "About" item of the tray, to be copied in the support requests.
+//!
+//! The [`Diagnostics`] are written to a local HTML page opened in the browser, with a "Copy diagnostics" button
+//! copying their plain text, see [`Diagnostics::html`].
+
+use std::{
+    fmt,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::{build_info::BuildInfo, updater::Channel};
+
+/// The versions, paths and addresses of the running service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+    pub build: BuildInfo,
+    /// The platform profile, e.g. `flatpak`, see [`Profile`](crate::profile::Profile)
+    pub profile: &'static str,
+    /// The server.js version, `None` when it's not the bundled one
+    pub server_version: Option<String>,
+    /// The output of `node --version`, `None` when it fails
+    pub node_version: Option<String>,
+    /// The first line of `ffmpeg -version`, `None` when it fails
+    pub ffmpeg_version: Option<String>,
+    pub update_channel: Channel,
+    /// Whether server.js is running
+    pub server_running: bool,
+    pub server_port: u16,
+    /// The HTTPS port of server.js on the local network, when it's enabled
+    pub https_port: Option<u16>,
+    /// The address of the control API, when it's enabled
+    pub control_api: Option<SocketAddr>,
+    pub lan_addresses: Vec<Ipv4Addr>,
+    /// The directories and files of the service, with their names
+    pub paths: Vec<(&'static str, PathBuf)>,
+}
+
+impl Diagnostics {
+    /// The page of the diagnostics, their text is copied by its button.
+    pub fn html(&self) -> String {
+        let text = escape(&self.to_string());
+
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>About Stremio Service</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; color: #222; }}
+pre {{ background: #f4f4f4; padding: 1em; overflow-x: auto; }}
+button {{ font-size: 1em; padding: 0.5em 1em; }}
+</style>
+</head>
+<body>
+<h1>Stremio Service {version}</h1>
+<p>Add these details to the support requests.</p>
+<button id="copy">Copy diagnostics</button> <span id="copied"></span>
+<pre id="diagnostics">{text}</pre>
+<script>
+document.getElementById("copy").addEventListener("click", function () {{
+    var text = document.getElementById("diagnostics").textContent;
+    var copied = function () {{ document.getElementById("copied").textContent = "Copied"; }};
+    var fallback = function () {{
+        var area = document.createElement("textarea");
+        area.value = text;
+        document.body.appendChild(area);
+        area.select();
+        document.execCommand("copy");
+        document.body.removeChild(area);
+        copied();
+    }};
+    if (navigator.clipboard) {{
+        navigator.clipboard.writeText(text).then(copied, fallback);
+    }} else {{
+        fallback();
+    }}
+}});
+</script>
+</body>
+</html>
+"#,
+            version = escape(self.build.version),
+        )
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_unknown = |value: &Option<String>| value.clone().unwrap_or("unknown".to_owned());
+
+        writeln!(f, "{}", self.build)?;
+        writeln!(
+            f,
+            "os: {} {} ({})",
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            self.profile
+        )?;
+        writeln!(f, "update channel: {}", self.update_channel)?;
+        writeln!(
+            f,
+            "installed server.js: {}",
+            or_unknown(&self.server_version)
+        )?;
+        writeln!(f, "node: {}", or_unknown(&self.node_version))?;
+        writeln!(f, "ffmpeg: {}", or_unknown(&self.ffmpeg_version))?;
+        writeln!(
+            f,
+            "server.js status: {}",
+            match self.server_running {
+                true => "running",
+                false => "stopped",
+            }
+        )?;
+        writeln!(f, "server.js port: {}", self.server_port)?;
+        if let Some(https_port) = self.https_port {
+            writeln!(f, "HTTPS port: {https_port}")?;
+        }
+        match self.control_api {
+            Some(address) => writeln!(f, "control API: {address}")?,
+            None => writeln!(f, "control API: disabled")?,
+        }
+        let lan_addresses = self
+            .lan_addresses
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        writeln!(f, "LAN addresses: {}", lan_addresses.join(", "))?;
+        for (name, path) in &self.paths {
+            writeln!(f, "{name}: {}", path.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The first line of the output of `program` with the `argument`, e.g. `--version`.
+pub fn version_of(program: &Path, argument: &str) -> Option<String> {
+    let mut command = Command::new(program);
+    command
+        .arg(argument)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+
+        command.creation_flags(crate::server::CREATE_NO_WINDOW);
+    }
+    let output = command
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_owned())
+        .filter(|line| !line.is_empty())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::build_info::BUILD_INFO;
+
+    use super::*;
+
+    #[test]
+    fn test_diagnostics_page() {
+        let diagnostics = Diagnostics {
+            build: BUILD_INFO,
+            profile: "tarball",
+            server_version: Some("v4.20.8".to_owned()),
+            node_version: Some("v18.20.4".to_owned()),
+            ffmpeg_version: None,
+            update_channel: Channel::Stable,
+            server_running: true,
+            server_port: 11470,
+            https_port: None,
+            control_api: None,
+            lan_addresses: vec![Ipv4Addr::new(192, 168, 1, 2), Ipv4Addr::new(10, 0, 0, 2)],
+            paths: vec![("logs", PathBuf::from("/home/<user>/logs"))],
+        };
+
+        let text = diagnostics.to_string();
+        for line in [
+            "installed server.js: v4.20.8",
+            "node: v18.20.4",
+            "ffmpeg: unknown",
+            "server.js status: running",
+            "control API: disabled",
+            "LAN addresses: 192.168.1.2, 10.0.0.2",
+            "logs: /home/<user>/logs",
+        ] {
+            assert!(text.lines().any(|text| text == line), "No `{line}`");
+        }
+        assert!(!text.contains("HTTPS port"));
+
+        let html = diagnostics.html();
+        assert!(html.contains("logs: /home/&lt;user&gt;/logs"));
+        assert!(!html.contains("<user>"));
+    }
+
+    #[test]
+    fn test_version_of_missing_program() {
+        assert_eq!(
+            None,
+            version_of(Path::new("/nonexistent/stremio-runtime"), "--version")
+        );
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail};
+
+use crate::{
+    constants::{CONFIG_DIR_NAME, SETTINGS_FILE_NAME},
+    error::ServiceError,
+    profile::Profile,
+    server,
+};
+
+/// The directories used by the service.
+///
+/// Each one can be overridden independently, otherwise the platform defaults are used
+/// (see [`dirs`]), with a `stremio-service` sub-directory:
+///
+/// - config - [`dirs::config_dir`]
+/// - state - [`dirs::state_dir`] on Linux and [`dirs::data_local_dir`] on other platforms
+///   (and inside Flatpak versions that don't set `XDG_STATE_HOME`, as the host `~/.local/state` is not available)
+/// - cache - [`dirs::cache_dir`]
+///
+/// With a profile, see `--profile`, the sub-directories are named `stremio-service-<profile>` instead,
+/// so the instances of the profiles have their own lockfile, settings and cache.
+#[derive(Debug, Clone)]
+pub struct Directories {
+    /// The Home directory of the user running the service
+    pub home: PathBuf,
+    /// The user configuration, e.g. the [`Settings`](crate::settings::Settings) file
+    pub config: PathBuf,
+    /// The state of the service which should survive restarts, e.g. the lockfile and logs
+    pub state: PathBuf,
+    /// Files which can be safely removed, e.g. the downloaded updates
+    pub cache: PathBuf,
+    /// The profile of an independent instance of the service, `None` for the default one
+    pub profile: Option<String>,
+}
+
+impl Directories {
+    /// Create the service directories using the platform defaults for every directory that is not overridden.
+    ///
+    /// The defaults are the ones of the `profile`, when it's given, see [`parse_profile`].
+    ///
+    /// # Errors
+    ///
+    /// When there's no default for the platform and no override is given.
+    pub fn new(
+        config: Option<PathBuf>,
+        state: Option<PathBuf>,
+        cache: Option<PathBuf>,
+        profile: Option<String>,
+    ) -> Result<Self, ServiceError> {
+        let missing = |dir: &str| ServiceError::Config(anyhow!("Failed to get {dir} dir"));
+        let home = dirs::home_dir().ok_or_else(|| missing("home"))?;
+        let dir_name = match &profile {
+            Some(profile) => format!("{CONFIG_DIR_NAME}-{profile}"),
+            None => CONFIG_DIR_NAME.to_owned(),
+        };
+
+        let config = match config {
+            Some(config) => config,
+            None => dirs::config_dir()
+                .ok_or_else(|| missing("config"))?
+                .join(&dir_name),
+        };
+        let state = match state {
+            Some(state) => state,
+            None => default_state_dir()
+                .ok_or_else(|| missing("state"))?
+                .join(&dir_name),
+        };
+        let cache = match cache {
+            Some(cache) => cache,
+            None => dirs::cache_dir()
+                .ok_or_else(|| missing("cache"))?
+                .join(&dir_name),
+        };
+
+        Ok(Self {
+            home,
+            config,
+            state,
+            cache,
+            profile,
+        })
+    }
+
+    /// Creates all the directories if they don't exist.
+    pub fn create_all(&self) -> Result<(), ServiceError> {
+        for dir in [&self.config, &self.state, &self.cache] {
+            std::fs::create_dir_all(dir).map_err(|err| {
+                ServiceError::Config(anyhow!("Failed to create {:?}: {err}", dir))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// The persisted [`Settings`](crate::settings::Settings) file
+    pub fn settings_file(&self) -> PathBuf {
+        self.config.join(SETTINGS_FILE_NAME)
+    }
+
+    /// The fallback file for the [`Secrets`](crate::secrets::Secrets)
+    pub fn secrets_file(&self) -> PathBuf {
+        self.config.join("secrets.json")
+    }
+
+    /// The lockfile that guards against running multiple instances of the service.
+    pub fn lockfile(&self) -> PathBuf {
+        self.state.join("lock")
+    }
+
+    /// Where the running instance receives the links of the other ones, see [`instance`](crate::instance).
+    ///
+    /// A Unix socket in the state directory, on Windows a named pipe named after the state directory.
+    pub fn instance_socket(&self) -> PathBuf {
+        if cfg!(windows) {
+            use sha2::{Digest, Sha256};
+
+            let id = Sha256::digest(self.state.as_os_str().as_encoded_bytes());
+            PathBuf::from(format!(
+                r"\\.\pipe\stremio-service-{}",
+                hex::encode(&id[..8])
+            ))
+        } else {
+            self.state.join("instance.sock")
+        }
+    }
+
+    /// The history of the handled deep links, see [`LinkHistory`](crate::link_history::LinkHistory)
+    pub fn link_history(&self) -> PathBuf {
+        self.state.join("links.json")
+    }
+
+    /// The directory for the service log files
+    pub fn logs(&self) -> PathBuf {
+        self.state.join("logs")
+    }
+
+    /// The size and position of the app window, see [`WindowGeometry`](crate::window::WindowGeometry)
+    pub fn window_geometry(&self) -> PathBuf {
+        self.state.join("window.json")
+    }
+
+    /// The QR code of the LAN address of server.js shown from the tray
+    pub fn qr_code(&self) -> PathBuf {
+        self.cache.join("connect.png")
+    }
+
+    /// The diagnostics page shown by the "About" item of the tray, see [`Diagnostics`](crate::diagnostics::Diagnostics)
+    pub fn about_page(&self) -> PathBuf {
+        self.cache.join("about.html")
+    }
+
+    /// The last lines of the server.js output, written when they're shown from the tray
+    pub fn server_log(&self) -> PathBuf {
+        self.logs().join("server.log")
+    }
+
+    /// The pending crash reports, see [`crash`](crate::crash)
+    pub fn crash_reports(&self) -> PathBuf {
+        self.state.join("crashes")
+    }
+
+    /// The directory where the updater downloads the installers
+    pub fn updates(&self) -> PathBuf {
+        self.cache.join("updates")
+    }
+
+    /// The version of the service run last, see [`record_version`](crate::updater::record_version)
+    pub fn last_version(&self) -> PathBuf {
+        self.state.join("last-version")
+    }
+
+    /// The previous version kept by the last update, see [`Versions`](crate::rollback::Versions)
+    pub fn versions(&self) -> PathBuf {
+        self.state.join("versions")
+    }
+
+    /// The updates of the tarball installation staged for the next start, see [`Staging`](crate::staging::Staging)
+    pub fn staging(&self) -> PathBuf {
+        self.state.join("staging")
+    }
+
+    /// The data directory of server.js, of the profile when there's one, see [`server::data_dir`]
+    pub fn server_data(&self) -> PathBuf {
+        server::data_dir(&self.home, self.profile.as_deref())
+    }
+
+    /// The data directory of the additional server.js listening on the port, see [`ServerPool`](crate::pool::ServerPool)
+    pub fn additional_server_data(&self, port: u16) -> PathBuf {
+        let mut data_dir = self.server_data().into_os_string();
+        data_dir.push(format!("-{port}"));
+
+        data_dir.into()
+    }
+
+    /// The directory of the server.js installed by a server-only update,
+    /// see [`ServerUpdate`](crate::updater::ServerUpdate)
+    pub fn server_update(&self) -> PathBuf {
+        self.state.join("server")
+    }
+}
+
+/// Parses the name of a profile, only the ASCII letters, the digits, `-` and `_` are allowed
+/// as it's a part of the directory names.
+pub fn parse_profile(name: &str) -> Result<String, anyhow::Error> {
+    let is_valid = name
+        .chars()
+        .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_');
+    if name.is_empty() || !is_valid {
+        bail!(
+            "`{name}` is not a profile name, only the letters, the digits, `-` and `_` are allowed"
+        );
+    }
+
+    Ok(name.to_owned())
+}
+
+fn default_state_dir() -> Option<PathBuf> {
+    if Profile::is_flatpak() && std::env::var_os("XDG_STATE_HOME").is_none() {
+        return dirs::data_local_dir();
+    }
+
+    // only Linux has a state dir
+    dirs::state_dir().or_else(dirs::data_local_dir)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_profile_directories() {
+        let directories = Directories::new(None, None, None, Some("beta".to_owned())).unwrap();
+        let default = Directories::new(None, None, None, None).unwrap();
+        for (dir, default) in [
+            (&directories.config, &default.config),
+            (&directories.state, &default.state),
+            (&directories.cache, &default.cache),
+        ] {
+            assert_eq!(Some("stremio-service-beta".as_ref()), dir.file_name());
+            assert_eq!(default.parent(), dir.parent());
+        }
+        assert_ne!(default.lockfile(), directories.lockfile());
+        assert_ne!(default.server_data(), directories.server_data());
+
+        let overridden = Directories::new(
+            Some("/tmp/config".into()),
+            None,
+            None,
+            Some("beta".to_owned()),
+        )
+        .unwrap();
+        assert_eq!(PathBuf::from("/tmp/config"), overridden.config);
+
+        assert_eq!("kids_2", parse_profile("kids_2").unwrap());
+        for name in ["", "../beta", "beta profile", "bêta"] {
+            assert!(parse_profile(name).is_err(), "`{name}` should be invalid");
+        }
+    }
+}
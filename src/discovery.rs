@@ -0,0 +1,593 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Advertising server.js on the local network with [mDNS](https://www.rfc-editor.org/rfc/rfc6762) and
+//! [DNS-SD](https://www.rfc-editor.org/rfc/rfc6763), so the TVs and the phones running Stremio Web find it
+//! without typing the address of the computer.
+//!
+//! The service is advertised as `Stremio Service on <host>._stremio-server._tcp.local`,
+//! its SRV record points to the port of server.js on `<host>.local` and its TXT record has the versions:
+//!
+//! ```text
+//! version=<service version>
+//! server=<server.js version>
+//! path=/
+//! ```
+//!
+//! Only the queries for these names are answered, the port is shared with the mDNS responder of the system.
+
+use std::{
+    future::Future,
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::Context;
+use log::{debug, info, warn};
+use tokio::net::UdpSocket;
+
+use crate::{build_info::BUILD_INFO, error::ServiceError};
+
+/// The DNS-SD service type of server.js
+pub const SERVICE_TYPE: &str = "_stremio-server._tcp.local";
+/// The DNS-SD meta query enumerating the service types
+const SERVICES: &str = "_services._dns-sd._udp.local";
+const MDNS_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set in the class of the records only this host answers
+const CACHE_FLUSH: u16 = 0x8000;
+/// Set in the class of the questions asking for a unicast response
+const UNICAST_RESPONSE: u16 = 0x8000;
+/// A response of an authoritative answer
+const RESPONSE_FLAGS: u16 = 0x8400;
+
+/// The TTL of the records of the host, which change with the address
+const HOST_TTL: u32 = 120;
+/// The TTL of the other records, see RFC 6762 section 10
+const SERVICE_TTL: u32 = 4500;
+/// The unsolicited announcements sent on start, a second apart
+const ANNOUNCEMENTS: u32 = 2;
+
+/// The advertisement of server.js
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovery {
+    /// The instance name, e.g. `Stremio Service on laptop`
+    instance: String,
+    /// The host label, resolved as `<host>.local`
+    host: String,
+    /// The port server.js listens on
+    port: u16,
+    /// The `key=value` entries of the TXT record
+    txt: Vec<String>,
+}
+
+impl Discovery {
+    /// The advertisement of the server.js listening on `port` of this computer
+    pub fn new(port: u16) -> Self {
+        let host = hostname();
+
+        Self {
+            instance: format!("Stremio Service on {host}"),
+            host,
+            port,
+            txt: vec![
+                format!("version={}", BUILD_INFO.version),
+                format!("server={}", BUILD_INFO.server_version),
+                "path=/".to_owned(),
+            ],
+        }
+    }
+
+    /// Advertises the port server.js is served over HTTPS on too, see [`HttpsProxy`](crate::https::HttpsProxy)
+    pub fn with_https_port(mut self, port: u16) -> Self {
+        self.txt.push(format!("https={port}"));
+        self
+    }
+
+    /// Answers the mDNS queries until the `shutdown` future resolves, then says goodbye.
+    ///
+    /// # Errors
+    ///
+    /// When the mDNS port can't be listened on.
+    pub async fn advertise(self, shutdown: impl Future<Output = ()>) -> Result<(), ServiceError> {
+        let socket = multicast_socket()
+            .context("Failed to listen for the mDNS queries")
+            .map_err(ServiceError::Other)?;
+        let multicast = SocketAddr::from((MDNS_ADDRESS, MDNS_PORT));
+        info!(
+            "Advertising \"{}\" as {SERVICE_TYPE} on port {}",
+            self.instance, self.port
+        );
+
+        tokio::pin!(shutdown);
+        let mut announcements = tokio::time::interval(Duration::from_secs(1));
+        let mut announced = 0;
+        let mut buffer = vec![0; 9000];
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = announcements.tick(), if announced < ANNOUNCEMENTS => {
+                    announced += 1;
+                    if let Some(announcement) = self.announcement(1) {
+                        send(&socket, &announcement, multicast).await;
+                    }
+                }
+                received = socket.recv_from(&mut buffer) => match received {
+                    Ok((length, source)) => {
+                        if let Some((response, unicast)) = self.respond(&buffer[..length], local_ipv4()) {
+                            // the legacy resolvers query from another port
+                            let unicast = unicast || source.port() != MDNS_PORT;
+                            send(&socket, &response, if unicast { source } else { multicast }).await;
+                        }
+                    }
+                    Err(err) => warn!("Failed to receive an mDNS query: {err}"),
+                },
+            }
+        }
+
+        // the records are removed from the caches of the other devices
+        if let Some(goodbye) = self.announcement(0) {
+            send(&socket, &goodbye, multicast).await;
+        }
+
+        Ok(())
+    }
+
+    /// The unsolicited response with all the records, their TTLs are multiplied by `ttl_factor`.
+    ///
+    /// `None` without an address on the local network.
+    fn announcement(&self, ttl_factor: u32) -> Option<Vec<u8>> {
+        let ip = local_ipv4()?;
+        let answers = [
+            self.ptr(),
+            self.srv(),
+            self.txt(),
+            self.a(ip),
+            self.services_ptr(),
+        ]
+        .map(|record| Record {
+            ttl: record.ttl * ttl_factor,
+            ..record
+        });
+
+        Some(encode_response(0, &answers, &[]))
+    }
+
+    /// The response to the query and whether it's sent back to the querier only, `None` when it's not for this service.
+    fn respond(&self, query: &[u8], ip: Option<Ipv4Addr>) -> Option<(Vec<u8>, bool)> {
+        let header = query.get(..12)?;
+        let id = u16::from_be_bytes([header[0], header[1]]);
+        let flags = u16::from_be_bytes([header[2], header[3]]);
+        // a response of another responder
+        if flags & 0x8000 != 0 {
+            return None;
+        }
+        let questions = u16::from_be_bytes([header[4], header[5]]);
+
+        let mut answers = vec![];
+        let mut additionals = vec![];
+        let mut unicast = true;
+        let mut offset = 12;
+        for _ in 0..questions {
+            let (name, next) = read_name(query, offset)?;
+            let kind = u16::from_be_bytes([*query.get(next)?, *query.get(next + 1)?]);
+            let class = u16::from_be_bytes([*query.get(next + 2)?, *query.get(next + 3)?]);
+            offset = next + 4;
+            if class & !UNICAST_RESPONSE != CLASS_IN && class & !UNICAST_RESPONSE != TYPE_ANY {
+                continue;
+            }
+
+            let (matched, additional) = self.answer(&name.to_ascii_lowercase(), kind, ip);
+            if !matched.is_empty() && class & UNICAST_RESPONSE == 0 {
+                unicast = false;
+            }
+            answers.extend(matched);
+            additionals.extend(additional);
+        }
+        if answers.is_empty() {
+            return None;
+        }
+        answers.dedup();
+        additionals.retain(|record| !answers.contains(record));
+        additionals.dedup();
+
+        debug!("Answering an mDNS query for {SERVICE_TYPE}");
+        // a legacy resolver, e.g. `dig -p 5353`, expects the id of its query
+        Some((encode_response(id, &answers, &additionals), unicast))
+    }
+
+    /// The answers and the additional records for the question
+    fn answer(&self, name: &str, kind: u16, ip: Option<Ipv4Addr>) -> (Vec<Record>, Vec<Record>) {
+        let is = |kinds: &[u16]| kinds.contains(&kind) || kind == TYPE_ANY;
+        let address = ip.map(|ip| self.a(ip));
+
+        if name == SERVICE_TYPE && is(&[TYPE_PTR]) {
+            let additionals = [Some(self.srv()), Some(self.txt()), address];
+            (
+                vec![self.ptr()],
+                additionals.into_iter().flatten().collect(),
+            )
+        } else if name == SERVICES && is(&[TYPE_PTR]) {
+            (vec![self.services_ptr()], vec![])
+        } else if name == self.instance_name().to_ascii_lowercase() {
+            let answers = [
+                is(&[TYPE_SRV]).then(|| self.srv()),
+                is(&[TYPE_TXT]).then(|| self.txt()),
+            ];
+            (
+                answers.into_iter().flatten().collect(),
+                address.into_iter().collect(),
+            )
+        } else if name == self.host_name().to_ascii_lowercase() && is(&[TYPE_A]) {
+            (address.into_iter().collect(), vec![])
+        } else {
+            (vec![], vec![])
+        }
+    }
+
+    fn instance_name(&self) -> String {
+        format!("{}.{SERVICE_TYPE}", self.instance)
+    }
+
+    fn host_name(&self) -> String {
+        format!("{}.local", self.host)
+    }
+
+    fn ptr(&self) -> Record {
+        Record::new(
+            SERVICE_TYPE,
+            TYPE_PTR,
+            false,
+            SERVICE_TTL,
+            encode_name(&self.instance_name()),
+        )
+    }
+
+    fn services_ptr(&self) -> Record {
+        Record::new(
+            SERVICES,
+            TYPE_PTR,
+            false,
+            SERVICE_TTL,
+            encode_name(SERVICE_TYPE),
+        )
+    }
+
+    fn srv(&self) -> Record {
+        // no priority nor weight
+        let mut data = vec![0, 0, 0, 0];
+        data.extend_from_slice(&self.port.to_be_bytes());
+        data.extend(encode_name(&self.host_name()));
+
+        Record::new(&self.instance_name(), TYPE_SRV, true, HOST_TTL, data)
+    }
+
+    fn txt(&self) -> Record {
+        let mut data = vec![];
+        for entry in &self.txt {
+            data.push(entry.len().min(255) as u8);
+            data.extend_from_slice(&entry.as_bytes()[..entry.len().min(255)]);
+        }
+
+        Record::new(&self.instance_name(), TYPE_TXT, true, SERVICE_TTL, data)
+    }
+
+    fn a(&self, ip: Ipv4Addr) -> Record {
+        Record::new(
+            &self.host_name(),
+            TYPE_A,
+            true,
+            HOST_TTL,
+            ip.octets().to_vec(),
+        )
+    }
+}
+
+/// A resource record of a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Record {
+    name: String,
+    kind: u16,
+    /// Whether only this host has the record
+    unique: bool,
+    ttl: u32,
+    data: Vec<u8>,
+}
+
+impl Record {
+    fn new(name: &str, kind: u16, unique: bool, ttl: u32, data: Vec<u8>) -> Self {
+        Self {
+            name: name.to_owned(),
+            kind,
+            unique,
+            ttl,
+            data,
+        }
+    }
+}
+
+fn encode_response(id: u16, answers: &[Record], additionals: &[Record]) -> Vec<u8> {
+    let mut message = vec![];
+    for value in [
+        id,
+        RESPONSE_FLAGS,
+        0,
+        answers.len() as u16,
+        0,
+        additionals.len() as u16,
+    ] {
+        message.extend_from_slice(&value.to_be_bytes());
+    }
+
+    for record in answers.iter().chain(additionals) {
+        message.extend(encode_name(&record.name));
+        let class = if record.unique {
+            CLASS_IN | CACHE_FLUSH
+        } else {
+            CLASS_IN
+        };
+        message.extend_from_slice(&record.kind.to_be_bytes());
+        message.extend_from_slice(&class.to_be_bytes());
+        message.extend_from_slice(&record.ttl.to_be_bytes());
+        message.extend_from_slice(&(record.data.len() as u16).to_be_bytes());
+        message.extend_from_slice(&record.data);
+    }
+
+    message
+}
+
+/// Encodes the dotted name without compression, the first label of an instance name may contain anything but a dot
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut encoded = vec![];
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label);
+    }
+    encoded.push(0);
+
+    encoded
+}
+
+/// Reads the possibly compressed name at `offset`, returns it dotted and the offset after it
+fn read_name(message: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = vec![];
+    let mut next = None;
+    // guards against the pointer loops
+    for _ in 0..128 {
+        let length = *message.get(offset)? as usize;
+        match length {
+            0 => {
+                return Some((labels.join("."), next.unwrap_or(offset + 1)));
+            }
+            length if length & 0xc0 == 0xc0 => {
+                let pointer = ((length & 0x3f) << 8) | *message.get(offset + 1)? as usize;
+                next.get_or_insert(offset + 2);
+                offset = pointer;
+            }
+            length => {
+                let label = message.get(offset + 1..offset + 1 + length)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset += 1 + length;
+            }
+        }
+    }
+
+    None
+}
+
+async fn send(socket: &UdpSocket, message: &[u8], destination: SocketAddr) {
+    if let Err(err) = socket.send_to(message, destination).await {
+        warn!("Failed to send the mDNS response to {destination}: {err}");
+    }
+}
+
+/// The mDNS socket, shared with the other responders of the system
+fn multicast_socket() -> io::Result<UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())?;
+    socket.join_multicast_v4(&MDNS_ADDRESS, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_ttl_v4(255)?;
+    socket.set_nonblocking(true)?;
+
+    UdpSocket::from_std(socket.into())
+}
+
+/// The address of the interface the multicast is sent from, `None` when there's no local network
+fn local_ipv4() -> Option<Ipv4Addr> {
+    // nothing is sent, it only picks the route
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((MDNS_ADDRESS, MDNS_PORT)).ok()?;
+    match socket.local_addr().ok()? {
+        SocketAddr::V4(address) if !address.ip().is_unspecified() => Some(*address.ip()),
+        _ => None,
+    }
+}
+
+/// The IPv4 addresses of the computer on the local networks, the one of [`local_ipv4`] first.
+///
+/// Only that one is known on the platforms without `getifaddrs`.
+pub fn lan_addresses() -> Vec<Ipv4Addr> {
+    let mut addresses = local_ipv4().into_iter().collect::<Vec<_>>();
+    for ip in interface_addresses() {
+        if !ip.is_loopback() && !ip.is_link_local() && !addresses.contains(&ip) {
+            addresses.push(ip);
+        }
+    }
+
+    addresses
+}
+
+/// The IPv4 addresses of the interfaces which are up
+#[cfg(unix)]
+fn interface_addresses() -> Vec<Ipv4Addr> {
+    let mut addresses = vec![];
+    let mut interfaces = std::ptr::null_mut();
+    // SAFETY: the list is only read until it's freed
+    if unsafe { libc::getifaddrs(&mut interfaces) } != 0 {
+        return addresses;
+    }
+
+    let mut interface = interfaces;
+    while let Some(current) = unsafe { interface.as_ref() } {
+        let is_up = current.ifa_flags & libc::IFF_UP as libc::c_uint != 0;
+        if let Some(address) = unsafe { current.ifa_addr.as_ref() } {
+            if is_up && i32::from(address.sa_family) == libc::AF_INET {
+                // SAFETY: the family is checked
+                let address = unsafe { &*(current.ifa_addr as *const libc::sockaddr_in) };
+                addresses.push(Ipv4Addr::from(u32::from_be(address.sin_addr.s_addr)));
+            }
+        }
+        interface = current.ifa_next;
+    }
+    unsafe { libc::freeifaddrs(interfaces) };
+
+    addresses
+}
+
+#[cfg(not(unix))]
+fn interface_addresses() -> Vec<Ipv4Addr> {
+    vec![]
+}
+
+/// The first label of the host name with only the letters, digits and hyphens, `stremio-service` without one
+fn hostname() -> String {
+    #[cfg(unix)]
+    let hostname = {
+        let mut buffer = [0_u8; 256];
+        // SAFETY: the length of the buffer is given
+        let result =
+            unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+        (result == 0).then(|| {
+            let end = buffer.iter().position(|&byte| byte == 0).unwrap_or(0);
+            String::from_utf8_lossy(&buffer[..end]).into_owned()
+        })
+    };
+    #[cfg(not(unix))]
+    let hostname = std::env::var("COMPUTERNAME").ok();
+
+    let label = hostname
+        .unwrap_or_default()
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|char| char.is_ascii_alphanumeric() || *char == '-')
+        .collect::<String>();
+
+    if label.is_empty() {
+        "stremio-service".to_owned()
+    } else {
+        label
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn query(name: &str, kind: u16, class: u16) -> Vec<u8> {
+        let mut query = vec![0x12, 0x34, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        query.extend(encode_name(name));
+        query.extend_from_slice(&kind.to_be_bytes());
+        query.extend_from_slice(&class.to_be_bytes());
+
+        query
+    }
+
+    /// The names and the types of the records of the response
+    fn records(response: &[u8]) -> Vec<(String, u16)> {
+        let count = u16::from_be_bytes([response[6], response[7]])
+            + u16::from_be_bytes([response[10], response[11]]);
+        let mut offset = 12;
+        (0..count)
+            .map(|_| {
+                let (name, next) = read_name(response, offset).unwrap();
+                let kind = u16::from_be_bytes([response[next], response[next + 1]]);
+                let length = u16::from_be_bytes([response[next + 8], response[next + 9]]);
+                offset = next + 10 + length as usize;
+
+                (name, kind)
+            })
+            .collect()
+    }
+
+    fn discovery() -> Discovery {
+        Discovery {
+            instance: "Stremio Service on laptop".to_owned(),
+            host: "laptop".to_owned(),
+            port: 11470,
+            txt: vec!["path=/".to_owned()],
+        }
+    }
+
+    #[test]
+    fn test_service_query_is_answered() {
+        let ip = Some(Ipv4Addr::new(192, 168, 1, 2));
+        let instance = format!("Stremio Service on laptop.{SERVICE_TYPE}");
+
+        let (response, unicast) = discovery()
+            .respond(&query(SERVICE_TYPE, TYPE_PTR, CLASS_IN), ip)
+            .expect("Should answer");
+        assert!(!unicast);
+        assert_eq!(
+            vec![
+                (SERVICE_TYPE.to_owned(), TYPE_PTR),
+                (instance.clone(), TYPE_SRV),
+                (instance.clone(), TYPE_TXT),
+                ("laptop.local".to_owned(), TYPE_A),
+            ],
+            records(&response)
+        );
+        assert!(response.ends_with(&[0, 4, 192, 168, 1, 2]));
+
+        let (response, unicast) = discovery()
+            .respond(
+                &query(
+                    &instance.to_uppercase(),
+                    TYPE_SRV,
+                    CLASS_IN | UNICAST_RESPONSE,
+                ),
+                ip,
+            )
+            .expect("Should answer");
+        assert!(unicast);
+        assert_eq!(
+            vec![(instance, TYPE_SRV), ("laptop.local".to_owned(), TYPE_A)],
+            records(&response)
+        );
+
+        assert_eq!(
+            None,
+            discovery().respond(&query("_http._tcp.local", TYPE_PTR, CLASS_IN), ip)
+        );
+        assert_eq!(None, discovery().respond(&[0; 4], ip));
+    }
+
+    #[test]
+    fn test_compressed_name_is_read() {
+        // `local` and then `laptop.local` pointing to it
+        let message = [
+            5, b'l', b'o', b'c', b'a', b'l', 0, 6, b'l', b'a', b'p', b't', b'o', b'p', 0xc0, 0,
+        ];
+
+        assert_eq!(
+            Some(("laptop.local".to_owned(), 16)),
+            read_name(&message, 7)
+        );
+        // a pointer to itself
+        assert_eq!(None, read_name(&[0xc0, 0], 0));
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::path::PathBuf;
+
+/// The failures of the public API, for the embedders and the CLI to match on.
+///
+/// The underlying causes are kept with their context and included in the messages.
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError {
+    /// The arguments, the directories or the server.js configuration are invalid
+    #[error("Invalid configuration: {0:#}")]
+    Config(anyhow::Error),
+    /// One of the binaries required for running server.js is missing
+    #[error("Failed to locate the file {0:?}")]
+    MissingBinary(PathBuf),
+    /// The settings file can't be read, parsed or written
+    #[error("{0:#}")]
+    Settings(anyhow::Error),
+    /// server.js couldn't be spawned
+    #[error("Server didn't start: {0}")]
+    ServerSpawn(std::io::Error),
+    /// server.js couldn't be killed
+    #[error("Failed to stop the server: {0}")]
+    ServerStop(std::io::Error),
+    /// The corrupted server.js couldn't be downloaded again
+    #[error("Failed to repair server.js: {0:#}")]
+    ServerRepair(anyhow::Error),
+    /// Checking, downloading or verifying the update failed
+    #[error("Failed to update: {0:#}")]
+    Update(anyhow::Error),
+    /// Another instance of the service holds the lockfile
+    #[error("Another instance is running")]
+    LockHeld,
+    /// The control API of the running instance can't be reached, see [`ControlClient`](crate::control::ControlClient)
+    #[error("The service is not running or its control API is disabled")]
+    NotRunning,
+    /// Any other failure, e.g. of the lockfile or the system tray
+    #[error("{0:#}")]
+    Other(anyhow::Error),
+}
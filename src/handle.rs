@@ -0,0 +1,91 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Embedding the service in-process, e.g. in the Stremio desktop shells.
+//!
+//! [`ServiceHandle::start`] starts server.js with its supervision (the restarts and the health check),
+//! the control API, the LAN discovery and the updater on the current tokio runtime and returns once server.js is started,
+//! the embedder keeps the desktop integration (the window, the tray, the deep links) to itself.
+//! It's [`Application::run_headless`] split in two, for the embedders which can't await the service in a task.
+//!
+//! ```no_run
+//! # async fn embed(config: stremio_service::app::Config) -> Result<(), stremio_service::ServiceError> {
+//! use stremio_service::{Application, ServiceHandle};
+//!
+//! let service = ServiceHandle::start(Application::new(config)).await?;
+//! let mut status = service.subscribe_status();
+//! tokio::spawn(async move {
+//!     while status.changed().await.is_ok() {
+//!         println!("server.js: {}", status.borrow().status);
+//!     }
+//! });
+//! // ...
+//! service.stop().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use fslock::LockFile;
+use tokio::sync::watch;
+
+use crate::{
+    app::{Application, ShutdownHandle},
+    error::ServiceError,
+    status::ServerInfo,
+};
+
+/// The running service, it's stopped by [`ServiceHandle::stop`].
+///
+/// A stopped service isn't started again, a new [`Application`] is started instead.
+pub struct ServiceHandle {
+    application: Application,
+    /// Held while the service runs, so the other instances don't start
+    lockfile: LockFile,
+}
+
+impl ServiceHandle {
+    /// Starts the service without the tray, the opener of the application opens its links, see [`Application::with_opener`].
+    ///
+    /// # Errors
+    ///
+    /// When another instance is running or server.js fails to start.
+    pub async fn start(application: Application) -> Result<Self, ServiceError> {
+        let lockfile = application.start_headless().await?;
+
+        Ok(Self {
+            application,
+            lockfile,
+        })
+    }
+
+    /// Stops server.js and the tasks of the service, the update staged for the quit is installed then.
+    ///
+    /// # Errors
+    ///
+    /// When server.js fails to be stopped.
+    pub async fn stop(self) -> Result<(), ServiceError> {
+        self.application.stop_headless(self.lockfile).await
+    }
+
+    /// The status of server.js with its address and process, it's sent on every change, see [`Server::subscribe`].
+    ///
+    /// [`Server::subscribe`]: crate::server::Server::subscribe
+    pub fn subscribe_status(&self) -> watch::Receiver<ServerInfo> {
+        self.application.subscribe_status()
+    }
+
+    /// The status of server.js right now, with its address and process.
+    pub async fn info(&self) -> ServerInfo {
+        self.application.server_info().await
+    }
+
+    /// Triggered when the service asks to stop, e.g. once an update was started in its place,
+    /// [`ServiceHandle::stop`] is called then.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.application.shutdown_handle()
+    }
+
+    /// The running application, e.g. for its [`diagnostics`](Application::diagnostics)
+    pub fn application(&self) -> &Application {
+        &self.application
+    }
+}
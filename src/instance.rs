@@ -0,0 +1,272 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Forwarding the links opened by a second instance to the running one.
+//!
+//! The instance holding the lockfile listens on a Unix socket in its state directory (a named pipe on Windows),
+//! see [`Directories::instance_socket`](crate::directories::Directories::instance_socket).
+//! A second instance started with `--open` writes the link to it, as a single line, instead of exiting,
+//! so the `stremio://` and `magnet:` links clicked while the service runs are handled by the running one.
+//! On macOS they're delivered to the running app by the Apple Events as well.
+//!
+//! A second instance started without a link, e.g. from the icon of the service, asks the running one
+//! to open Stremio Web instead, see [`Message::Activate`].
+
+use std::{future::Future, path::Path, time::Duration};
+
+use anyhow::Context;
+use log::{info, warn};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+
+use crate::deeplink::MAX_URL_LENGTH;
+
+/// How long the second instance tries to reach the running one
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+/// The line of [`Message::Activate`], it's not a link, so the older instances only refuse it
+const ACTIVATE_LINE: &str = "activate";
+
+/// What a second instance asks the running one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Handle the `--open` link
+    Link(String),
+    /// Open Stremio Web, the service was started again without a link
+    Activate,
+}
+
+impl Message {
+    fn parse(line: &str) -> Self {
+        match line {
+            ACTIVATE_LINE => Self::Activate,
+            link => Self::Link(link.to_owned()),
+        }
+    }
+
+    fn line(&self) -> &str {
+        match self {
+            Self::Link(link) => link,
+            Self::Activate => ACTIVATE_LINE,
+        }
+    }
+}
+
+/// Receives the messages of the other instances until the `shutdown` future resolves.
+///
+/// # Errors
+///
+/// When the socket can't be listened on.
+#[cfg(unix)]
+pub async fn listen(
+    endpoint: &Path,
+    on_message: impl Fn(Message) + Clone + Send + 'static,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), anyhow::Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // left by a killed instance, the lockfile keeps the running ones from listening on it
+    let _ = std::fs::remove_file(endpoint);
+    let listener = tokio::net::UnixListener::bind(endpoint)
+        .context(format!("Failed to listen on {}", endpoint.display()))?;
+    std::fs::set_permissions(endpoint, std::fs::Permissions::from_mode(0o600))
+        .context(format!("Failed to restrict {}", endpoint.display()))?;
+    info!(
+        "Receiving the messages of the other instances on {}",
+        endpoint.display()
+    );
+
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(receive(stream, on_message.clone()));
+                }
+                Err(err) => warn!("Failed to accept a connection of another instance: {err}"),
+            },
+        }
+    }
+
+    let _ = std::fs::remove_file(endpoint);
+
+    Ok(())
+}
+
+/// Receives the messages of the other instances until the `shutdown` future resolves.
+///
+/// # Errors
+///
+/// When the named pipe can't be created, e.g. another instance of the same state directory has it.
+#[cfg(windows)]
+pub async fn listen(
+    endpoint: &Path,
+    on_message: impl Fn(Message) + Clone + Send + 'static,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), anyhow::Error> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let create = |first| {
+        ServerOptions::new()
+            .first_pipe_instance(first)
+            .create(endpoint)
+            .context(format!("Failed to create the pipe {}", endpoint.display()))
+    };
+    let mut server = create(true)?;
+    info!(
+        "Receiving the messages of the other instances on {}",
+        endpoint.display()
+    );
+
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            connected = server.connect() => match connected {
+                // the next client connects to a new instance of the pipe
+                Ok(()) => {
+                    let client = std::mem::replace(&mut server, create(false)?);
+                    tokio::spawn(receive(client, on_message.clone()));
+                }
+                Err(err) => warn!("Failed to accept a connection of another instance: {err}"),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub async fn listen(
+    _endpoint: &Path,
+    _on_message: impl Fn(Message) + Clone + Send + 'static,
+    _shutdown: impl Future<Output = ()>,
+) -> Result<(), anyhow::Error> {
+    anyhow::bail!(
+        "Forwarding the links is not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+/// Reads the message of a connected instance
+async fn receive(stream: impl AsyncRead + Unpin, on_message: impl Fn(Message)) {
+    // the longer links are refused anyway
+    let mut reader = BufReader::new(stream.take(MAX_URL_LENGTH as u64 + 1));
+    let mut line = String::new();
+    match reader.read_line(&mut line).await {
+        Ok(_) if !line.trim().is_empty() => {
+            let message = Message::parse(line.trim());
+            match &message {
+                Message::Link(_) => info!("Received a link from another instance"),
+                Message::Activate => info!("Another instance was started"),
+            }
+            on_message(message);
+        }
+        Ok(_) => {}
+        Err(err) => warn!("Failed to read the message of another instance: {err}"),
+    }
+}
+
+/// Sends the message to the instance listening on `endpoint`, see [`listen`].
+///
+/// # Errors
+///
+/// When no instance listens on it, e.g. the running one is an older version.
+pub async fn forward(endpoint: &Path, message: &Message) -> Result<(), anyhow::Error> {
+    let send = async {
+        let mut stream = connect(endpoint).await?;
+        stream
+            .write_all(format!("{}\n", message.line()).as_bytes())
+            .await?;
+        stream.shutdown().await?;
+
+        Ok::<_, std::io::Error>(())
+    };
+
+    tokio::time::timeout(FORWARD_TIMEOUT, send)
+        .await
+        .context("The running instance didn't respond")?
+        .context(format!(
+            "Failed to reach the running instance on {}",
+            endpoint.display()
+        ))
+}
+
+#[cfg(unix)]
+async fn connect(endpoint: &Path) -> std::io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(endpoint).await
+}
+
+#[cfg(windows)]
+async fn connect(
+    endpoint: &Path,
+) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+
+    /// Every instance of the pipe is connected
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    loop {
+        match ClientOptions::new().open(endpoint) {
+            Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            client => return client,
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn connect(_endpoint: &Path) -> std::io::Result<tokio::io::DuplexStream> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_link_is_forwarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let endpoint = dir.path().join("instance");
+        let link = Message::Link("stremio:///detail/movie/tt0032138".to_owned());
+        assert!(forward(&endpoint, &link).await.is_err());
+
+        let (sender, mut messages) = mpsc::unbounded_channel();
+        let shutdown = CancellationToken::new();
+        let listening = {
+            let endpoint = endpoint.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                listen(
+                    &endpoint,
+                    move |message| {
+                        let _ = sender.send(message);
+                    },
+                    shutdown.cancelled(),
+                )
+                .await
+            })
+        };
+        while !endpoint.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        forward(&endpoint, &link)
+            .await
+            .expect("Should forward the link");
+        assert_eq!(Some(link), messages.recv().await);
+        forward(&endpoint, &Message::Activate)
+            .await
+            .expect("Should ask to open Stremio Web");
+        assert_eq!(Some(Message::Activate), messages.recv().await);
+
+        shutdown.cancel();
+        listening
+            .await
+            .unwrap()
+            .expect("Should listen until the shutdown");
+        assert!(!endpoint.exists());
+    }
+}
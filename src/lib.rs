@@ -1,8 +1,51 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
 pub mod app;
 pub mod args;
+pub mod autostart;
+pub mod browser;
+pub mod build_info;
 pub mod constants;
+pub mod control;
+pub mod crash;
+pub mod deeplink;
+pub mod diagnostics;
+pub mod directories;
+pub mod discovery;
+pub mod error;
+pub mod handle;
+pub mod https;
+pub mod instance;
+pub mod link_history;
+pub mod logging;
+pub mod minisign;
+pub mod notifications;
+pub mod pool;
+pub mod pressure;
+pub mod profile;
+pub mod protocol;
+pub mod protocol_handler;
+pub mod qr;
+pub mod rollback;
+pub mod sandbox;
+pub mod secrets;
 pub mod server;
+pub mod server_settings;
+pub mod settings;
+pub mod staging;
+pub mod status;
+pub mod system_service;
+pub mod systemd;
+pub mod throttle;
+pub mod tls;
+pub mod transcoding;
 pub mod updater;
 pub mod util;
+pub mod web;
+pub mod window;
+
+// The API of the embedders, see [`handle`]
+pub use app::{Application, CancellationToken, Config, ShutdownHandle};
+pub use error::ServiceError;
+pub use handle::ServiceHandle;
+pub use status::{ServerInfo, ServerTrayStatus};
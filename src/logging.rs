@@ -0,0 +1,191 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Logging to the console and to the rotating files of the logs directory,
+//! see [`Directories::logs`](crate::directories::Directories::logs).
+//!
+//! The current file is `service.log`, it's rotated to `service.1.log` once it's larger than [`MAX_LOG_SIZE`]
+//! or on the first record of a new day, and only the last [`LOG_FILES`] rotated files are kept.
+//! They're the files asked for in the bug reports, the tray opens their directory.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use chrono::{Local, NaiveDate};
+use env_logger::{Env, Target};
+
+/// The size of a log file before it's rotated
+pub const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+/// The rotated log files kept besides the current one
+pub const LOG_FILES: usize = 5;
+const LOG_FILE_NAME: &str = "service";
+
+/// Sets up the logger with the `log_level` filter, the `RUST_LOG` environment variable takes precedence.
+///
+/// When the log file can't be opened the records are only written to the console, the error is logged then.
+pub fn init(log_level: &str, logs_dir: &Path) {
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or(log_level));
+    let log_file = RotatingFile::open(logs_dir, MAX_LOG_SIZE, LOG_FILES).map(|file| {
+        let path = file.path();
+        builder.target(Target::Pipe(Box::new(Tee { file })));
+
+        path
+    });
+    builder.init();
+
+    match log_file {
+        Ok(path) => log::debug!("Logging to {}", path.display()),
+        Err(err) => log::warn!("Logging only to the console: {err:#}"),
+    }
+}
+
+/// Writes the records to the standard error and to the log file
+struct Tee {
+    file: RotatingFile,
+}
+
+impl Write for Tee {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        // the console output doesn't depend on the file
+        let _ = self.file.write_all(buffer);
+        io::stderr().write_all(buffer)?;
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = self.file.flush();
+        io::stderr().flush()
+    }
+}
+
+/// A log file rotated by its size and by the day
+#[derive(Debug)]
+pub struct RotatingFile {
+    dir: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    /// The size of the current file
+    size: u64,
+    /// The day of the last record
+    day: NaiveDate,
+}
+
+impl RotatingFile {
+    /// Opens the current log file of the directory for appending, creating the directory if needed.
+    pub fn open(dir: &Path, max_size: u64, max_files: usize) -> Result<Self, anyhow::Error> {
+        std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+        let path = log_file(dir, 0);
+        let file = open_append(&path)?;
+        let metadata = file.metadata()?;
+        // a file of an earlier day is rotated on the first record
+        let day = metadata
+            .modified()
+            .map(|modified| chrono::DateTime::<Local>::from(modified).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            max_size,
+            max_files,
+            file,
+            size: metadata.len(),
+            day,
+        })
+    }
+
+    /// The current log file
+    pub fn path(&self) -> PathBuf {
+        log_file(&self.dir, 0)
+    }
+
+    /// Renames `service.<n>.log` to `service.<n + 1>.log`, removing the oldest one, and starts a new file.
+    fn rotate(&mut self) -> io::Result<()> {
+        let _ = std::fs::remove_file(log_file(&self.dir, self.max_files));
+        for index in (0..self.max_files).rev() {
+            let from = log_file(&self.dir, index);
+            if from.exists() {
+                std::fs::rename(from, log_file(&self.dir, index + 1))?;
+            }
+        }
+
+        self.file = open_append(&self.path()).map_err(io::Error::other)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let today = Local::now().date_naive();
+        let is_full = self.size > 0 && self.size + buffer.len() as u64 > self.max_size;
+        if is_full || (self.size > 0 && today != self.day) {
+            self.rotate()?;
+        }
+        self.day = today;
+
+        let written = self.file.write(buffer)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// The current log file of the directory
+pub fn current_file(logs_dir: &Path) -> PathBuf {
+    log_file(logs_dir, 0)
+}
+
+/// `service.log` for the current file, `service.<index>.log` for the rotated ones
+fn log_file(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join(format!("{LOG_FILE_NAME}.log")),
+        index => dir.join(format!("{LOG_FILE_NAME}.{index}.log")),
+    }
+}
+
+fn open_append(path: &Path) -> Result<File, anyhow::Error> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("Failed to open {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_log_files_are_rotated() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = RotatingFile::open(dir.path(), 10, 2).expect("Should open the log file");
+
+        for record in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(record.as_bytes()).unwrap();
+        }
+
+        let read = |index| std::fs::read_to_string(log_file(dir.path(), index)).unwrap();
+        assert_eq!("fourth\n", read(0));
+        assert_eq!("third\n", read(1));
+        assert_eq!("second\n", read(2));
+        assert!(
+            !log_file(dir.path(), 3).exists(),
+            "Only 2 rotated files are kept"
+        );
+
+        // continues the current file
+        let mut file = RotatingFile::open(dir.path(), 100, 2).unwrap();
+        file.write_all(b"fifth\n").unwrap();
+        assert_eq!("fourth\nfifth\n", read(0));
+    }
+}
@@ -1,47 +1,315 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
 #![cfg_attr(
     all(target_os = "windows", feature = "bundled"),
     windows_subsystem = "windows"
 )]
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 
-use anyhow::Context;
 use clap::Parser;
-use env_logger::Env;
 
-use stremio_service::app::{handle_stremio_protocol, Application, Config};
-use stremio_service::args::Args;
+use stremio_service::app::{Application, CancellationToken, Config};
+use stremio_service::args::{Args, Command, ServerCommand, ServiceCommand, UpdateCommand};
+use stremio_service::build_info::BUILD_INFO;
+use stremio_service::control::ControlClient;
+use stremio_service::directories::Directories;
+use stremio_service::secrets::{Secrets, PROXY_PASSWORD};
+use stremio_service::server;
+use stremio_service::settings::Settings;
+use stremio_service::status::ServerInfo;
+use stremio_service::system_service;
+use stremio_service::systemd;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let mut cli = Args::parse();
 
-    let cli = Args::parse();
+    if cli.version {
+        match cli.json {
+            true => println!("{}", serde_json::to_string_pretty(&BUILD_INFO)?),
+            false => println!("{BUILD_INFO}"),
+        }
+
+        return Ok(());
+    }
+
+    let directories = Directories::new(
+        cli.config_dir.clone(),
+        cli.state_dir.clone(),
+        cli.cache_dir.clone(),
+        cli.profile.clone(),
+    )?;
+    let settings_file = directories.settings_file();
+
+    // an invalid settings file is reported once the logger is set up
+    let settings = Settings::load(&settings_file).unwrap_or_default();
+    let log_level = cli.log_level.clone().unwrap_or(settings.log_level.clone());
+    stremio_service::logging::init(&log_level, &directories.logs());
+    if settings.crash_reports {
+        stremio_service::crash::install(
+            directories.crash_reports(),
+            stremio_service::logging::current_file(&directories.logs()),
+        );
+    }
+
+    if cli.unregister_protocol {
+        stremio_service::protocol_handler::unregister()?;
+
+        return Ok(());
+    }
+
+    if cli.set_proxy_password {
+        let mut password = String::new();
+        std::io::stdin().read_line(&mut password)?;
+        let password = password.trim_end_matches(['\r', '\n']);
+        let secrets = Secrets::new(directories.secrets_file());
+        match password.is_empty() {
+            true => {
+                secrets.delete(PROXY_PASSWORD)?;
+                log::info!("Proxy password removed");
+            }
+            false => {
+                secrets.set(PROXY_PASSWORD, password)?;
+                log::info!("Proxy password saved");
+            }
+        }
+
+        return Ok(());
+    }
+
+    if cli.install_service {
+        system_service::install()?;
+
+        return Ok(());
+    }
+
+    if cli.uninstall_service {
+        system_service::uninstall()?;
+
+        return Ok(());
+    }
+
+    if let Some(Command::Config { list, markdown }) = cli.command.as_ref() {
+        match (list, markdown) {
+            (true, true) => print!("{}", Settings::describe_markdown()),
+            (true, false) => {
+                for description in Config::describe() {
+                    println!(
+                        "{} ({}) [default: {}]\n    {}",
+                        description.name, description.kind, description.default, description.help
+                    );
+                }
+            }
+            _ => {
+                let settings = Settings::load(&settings_file)?;
+                println!("# {}", settings_file.display());
+                println!("{}", serde_json::to_string_pretty(&settings)?);
+            }
+        }
+
+        return Ok(());
+    }
 
-    if let Some(url) = cli.open.as_ref() {
-        if !url.is_empty() {
-            handle_stremio_protocol(url.clone());
+    if let Some(Command::Service { command }) = cli.command.as_ref() {
+        match command {
+            ServiceCommand::InstallSystemd {
+                headless,
+                print: true,
+            } => print!("{}", systemd::unit(*headless)?),
+            ServiceCommand::InstallSystemd {
+                headless,
+                print: false,
+            } => systemd::install(&directories.home, *headless)?,
+            ServiceCommand::UninstallSystemd => systemd::uninstall(&directories.home)?,
         }
+
+        return Ok(());
+    }
+
+    if let Some(command) = cli.command.as_ref() {
+        if run_control_command(command, settings.control_api_address()).await? {
+            return Ok(());
+        }
+    }
+
+    if let Some(destination) = cli.export_settings.as_ref() {
+        Settings::export(&settings_file, destination)?;
+        log::info!("Settings exported to {}", destination.display());
+
+        return Ok(());
     }
 
-    let home_dir = dirs::home_dir().context("Failed to get home dir")?;
-    let cache_dir = dirs::cache_dir().context("Failed to get cache dir")?;
+    if let Some(source) = cli.import_settings.as_ref() {
+        Settings::import(&settings_file, source)?;
+        log::info!("Settings imported from {}", source.display());
 
-    #[cfg(feature = "bundled")]
+        return Ok(());
+    }
+
+    #[cfg(all(feature = "bundled", not(feature = "no-bundled-server")))]
     // use the installed dir if we've built the app with `bundled` feature.
     let service_bins_dir = stremio_service::util::get_current_exe_dir();
-    #[cfg(not(feature = "bundled"))]
+    #[cfg(not(any(feature = "bundled", feature = "no-bundled-server")))]
     // use the `resources/bin/{linux|windows|macos}` directory
     let service_bins_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .join("resources")
         .join("bin")
         .join(std::env::consts::OS);
+    #[cfg(not(feature = "no-bundled-server"))]
+    let server = server::Config::new(service_bins_dir);
 
-    let config = Config::new(cli, home_dir, cache_dir, service_bins_dir)?;
+    // use the system binaries given explicitly or found in `PATH`
+    #[cfg(feature = "no-bundled-server")]
+    let server = server::Config::from_system(
+        cli.server_js
+            .clone()
+            .or(option_env!("STREMIO_SERVICE_SERVER_JS").map(Into::into)),
+        cli.node.clone(),
+        cli.ffmpeg.clone(),
+        cli.ffprobe.clone(),
+    );
+
+    let run_service = cli.run_service;
+    // the Windows Service runs the executable of the Program Files, only the installers run by an administrator update it
+    if run_service {
+        cli.skip_updater = true;
+    }
+    let headless = cli.headless;
+    let rollback = cli.rollback;
+    let apply_update = match cli.command.clone() {
+        Some(Command::Update {
+            command:
+                UpdateCommand::Apply {
+                    file,
+                    version,
+                    sha256,
+                },
+        }) => Some((file, version, sha256)),
+        _ => None,
+    };
+    let config = Config::new(cli, directories, server?)?;
     log::info!("Using service configuration: {:#?}", config);
 
     let application = Application::new(config);
+    if rollback {
+        return Ok(application.rollback().await?);
+    }
+    if let Some((file, version, sha256)) = apply_update {
+        return Ok(application.apply_update(&file, version, sha256).await?);
+    }
+    // the update staged by the last run replaces the installed files before anything runs from them
+    if let Some(executable) = (!run_service)
+        .then(|| application.swap_staged_update())
+        .flatten()
+    {
+        log::info!("Starting the updated {}", executable.display());
+        return Ok(stremio_service::staging::restart(&executable)?);
+    }
+    if run_service {
+        // the Service Control Manager stops it instead of the signals
+        let runtime = tokio::runtime::Handle::current();
+        return Ok(tokio::task::block_in_place(|| {
+            system_service::run(application, runtime)
+        })?);
+    }
+
+    let shutdown = application.shutdown_handle();
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        log::info!("Interrupted, shutting down");
+        shutdown.shutdown();
+    });
+
+    match headless {
+        true => Ok(application.run_headless(CancellationToken::new()).await?),
+        false => Ok(application.run().await?),
+    }
+}
+
+/// Runs the commands of the running instance through its control API, returns `false` for the other commands.
+async fn run_control_command(
+    command: &Command,
+    control_api: Option<SocketAddr>,
+) -> Result<bool, anyhow::Error> {
+    if !matches!(
+        command,
+        Command::Status { .. }
+            | Command::Server { .. }
+            | Command::Update {
+                command: UpdateCommand::Check { .. }
+            }
+    ) {
+        return Ok(false);
+    }
+    let Some(control_api) = control_api else {
+        anyhow::bail!("The control API is disabled by the `controlApi` setting");
+    };
+    let client = ControlClient::new(control_api);
+
+    match command {
+        Command::Status { json } => print_server_info(&client.status().await?, *json)?,
+        Command::Server { command } => {
+            let info = match command {
+                ServerCommand::Start => client.start_server().await?,
+                ServerCommand::Stop => client.stop_server().await?,
+                ServerCommand::Restart => client.restart_server().await?,
+            };
+            print_server_info(&info, false)?;
+        }
+        Command::Update {
+            command: UpdateCommand::Check { json },
+        } => {
+            let check = client.check_update().await?;
+            match (json, &check.available_version) {
+                (true, _) => println!("{}", serde_json::to_string_pretty(&check)?),
+                (false, Some(version)) => println!(
+                    "v{version} is available on the {} channel (current v{})",
+                    check.channel, check.current_version
+                ),
+                (false, None) => println!(
+                    "Up to date on the {} channel (v{})",
+                    check.channel, check.current_version
+                ),
+            }
+        }
+        _ => unreachable!("Not a command of the running instance"),
+    }
+
+    Ok(true)
+}
+
+fn print_server_info(info: &ServerInfo, json: bool) -> Result<(), anyhow::Error> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(info)?);
+
+        return Ok(());
+    }
 
-    Ok(application.run().await?)
+    println!("server.js: {}", info.status);
+    println!("URL: {}", info.url);
+    println!("Version: {}", info.version);
+    if let Some(pid) = info.pid {
+        println!("PID: {pid}");
+    }
+
+    Ok(())
+}
+
+/// Resolves on Ctrl+C and, on `*nix` systems, on `SIGTERM`
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
 }
@@ -0,0 +1,258 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Verifying the [minisign](https://jedisct1.github.io/minisign/) signatures of the updates.
+//!
+//! The installers are signed with `minisign -S`, the signature is the Ed25519 signature of the BLAKE2b-512 hash
+//! of the file and the trusted comment is signed along with it. The trusted comment names the file and the version
+//! of its release, e.g. `file:StremioServiceSetup.exe version:1.2.3`, so an older signed installer can't be served
+//! as the one of a newer release.
+//! The legacy signatures of the whole file (`minisign -l`) are not supported, the installers are hashed while they're read.
+
+use std::{io::Read, path::Path};
+
+use anyhow::{bail, ensure, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use blake2::{Blake2b512, Digest};
+use ed25519_dalek::{Signature, VerifyingKey};
+
+/// The public key the updates are signed with, set at build time, empty when the build doesn't verify the signatures
+const EMBEDDED_PUBLIC_KEY: &str = env!("STREMIO_SERVICE_UPDATE_PUBLIC_KEY");
+
+const KEY_ALGORITHM: &[u8; 2] = b"Ed";
+/// The signature of the BLAKE2b-512 hash of the file
+const PREHASHED_ALGORITHM: &[u8; 2] = b"ED";
+const UNTRUSTED_COMMENT: &str = "untrusted comment:";
+const TRUSTED_COMMENT: &str = "trusted comment: ";
+
+/// A minisign public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    key_id: [u8; 8],
+    key: VerifyingKey,
+}
+
+impl PublicKey {
+    /// The key embedded in the binary, `None` when the build has none.
+    pub fn embedded() -> Option<Self> {
+        (!EMBEDDED_PUBLIC_KEY.is_empty()).then(|| {
+            Self::parse(EMBEDDED_PUBLIC_KEY)
+                .expect("The embedded public key is checked by the build")
+        })
+    }
+
+    /// Parses either the contents of the `.pub` file or only its base64 line.
+    pub fn parse(public_key: &str) -> Result<Self, anyhow::Error> {
+        let encoded = public_key
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with(UNTRUSTED_COMMENT))
+            .context("Empty public key")?;
+        let decoded = STANDARD
+            .decode(encoded)
+            .context("The public key is not valid base64")?;
+        ensure!(
+            decoded.len() == 42 && decoded.starts_with(KEY_ALGORITHM),
+            "Not a minisign Ed25519 public key"
+        );
+
+        let key = VerifyingKey::from_bytes(decoded[10..].try_into().expect("Length is checked"))
+            .context("Invalid Ed25519 public key")?;
+
+        Ok(Self {
+            key_id: decoded[2..10].try_into().expect("Length is checked"),
+            key,
+        })
+    }
+
+    /// Verifies the signature of the file (the contents of its `.minisig`) and its trusted comment,
+    /// which has to name the file and the `version` of its release.
+    ///
+    /// The file is read and hashed in the calling thread.
+    pub fn verify(&self, file: &Path, signature: &str, version: &str) -> Result<(), anyhow::Error> {
+        let file_name = file
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .context("Invalid file name")?;
+        let mut lines = signature.lines().map(str::trim_end);
+        let (Some(untrusted_comment), Some(encoded), Some(trusted_comment), Some(global)) =
+            (lines.next(), lines.next(), lines.next(), lines.next())
+        else {
+            bail!("The signature is truncated");
+        };
+        ensure!(
+            untrusted_comment.starts_with(UNTRUSTED_COMMENT),
+            "Not a minisign signature"
+        );
+        let trusted_comment = trusted_comment
+            .strip_prefix(TRUSTED_COMMENT)
+            .context("The signature has no trusted comment")?;
+
+        let decoded = STANDARD
+            .decode(encoded)
+            .context("The signature is not valid base64")?;
+        ensure!(decoded.len() == 74, "Invalid signature length");
+        ensure!(
+            decoded.starts_with(PREHASHED_ALGORITHM),
+            "Only the signatures of the hashed files (the default of `minisign -S`) are supported"
+        );
+        ensure!(
+            decoded[2..10] == self.key_id,
+            "The file is signed with another key"
+        );
+        let signature = Signature::from_slice(&decoded[10..]).context("Invalid signature")?;
+
+        let mut hasher = Blake2b512::new();
+        let mut file =
+            std::fs::File::open(file).context(format!("Failed to open {}", file.display()))?;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        self.key
+            .verify_strict(&hasher.finalize(), &signature)
+            .context("Signature verification failed")?;
+
+        // the trusted comment is signed along with the signature
+        let global = Signature::from_slice(
+            &STANDARD
+                .decode(global)
+                .context("The global signature is not valid base64")?,
+        )
+        .context("Invalid global signature")?;
+        let signed = [signature.to_bytes().as_slice(), trusted_comment.as_bytes()].concat();
+        self.key
+            .verify_strict(&signed, &global)
+            .context("The trusted comment verification failed")?;
+
+        let field = |name: &str| {
+            trusted_comment
+                .split_whitespace()
+                .find_map(|field| field.strip_prefix(name)?.strip_prefix(':'))
+        };
+        ensure!(
+            field("file") == Some(file_name),
+            "The signature is not of {file_name}"
+        );
+        ensure!(
+            field("version").map(|signed| signed.trim_start_matches('v'))
+                == Some(version.trim_start_matches('v')),
+            "The signature is not of v{}",
+            version.trim_start_matches('v')
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    const KEY_ID: [u8; 8] = *b"stremio!";
+
+    pub(crate) fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    pub(crate) fn public_key() -> PublicKey {
+        let encoded = STANDARD.encode(
+            [
+                KEY_ALGORITHM.as_slice(),
+                &KEY_ID,
+                signing_key().verifying_key().as_bytes(),
+            ]
+            .concat(),
+        );
+
+        PublicKey::parse(&format!(
+            "{UNTRUSTED_COMMENT} minisign public key\n{encoded}\n"
+        ))
+        .expect("Should parse the key")
+    }
+
+    /// Signs the contents like `minisign -S -t "timestamp:1792000000 file:<file> version:<version>"`
+    pub(crate) fn sign(contents: &[u8], file: &str, version: &str) -> String {
+        let signing_key = signing_key();
+        let signature = signing_key.sign(&Blake2b512::digest(contents));
+        let trusted_comment = format!("timestamp:1792000000\tfile:{file}\tversion:{version}");
+        let global = signing_key
+            .sign(&[signature.to_bytes().as_slice(), trusted_comment.as_bytes()].concat());
+
+        format!(
+            "{UNTRUSTED_COMMENT} signature from minisign secret key\n{}\n{TRUSTED_COMMENT}{trusted_comment}\n{}\n",
+            STANDARD.encode(
+                [
+                    PREHASHED_ALGORITHM.as_slice(),
+                    &KEY_ID,
+                    &signature.to_bytes()
+                ]
+                .concat()
+            ),
+            STANDARD.encode(global.to_bytes()),
+        )
+    }
+
+    #[test]
+    fn test_signature_verification() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("StremioServiceSetup.exe");
+        std::fs::write(&file, b"installer").unwrap();
+        let public_key = public_key();
+        let signature = sign(b"installer", "StremioServiceSetup.exe", "1.2.3");
+
+        public_key
+            .verify(&file, &signature, "1.2.3")
+            .expect("Should verify the signature");
+        assert!(public_key
+            .verify(
+                &file,
+                &sign(b"another installer", "StremioServiceSetup.exe", "1.2.3"),
+                "1.2.3"
+            )
+            .is_err());
+
+        let tampered = signature.replace("file:StremioServiceSetup.exe", "file:x");
+        assert!(public_key.verify(&file, &tampered, "1.2.3").is_err());
+        assert!(public_key
+            .verify(&file, "not a signature", "1.2.3")
+            .is_err());
+    }
+
+    #[test]
+    fn test_signature_of_another_release_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("StremioServiceSetup.exe");
+        std::fs::write(&file, b"installer").unwrap();
+        let public_key = public_key();
+
+        // an older installer, validly signed, served as the newer release
+        let err = public_key
+            .verify(
+                &file,
+                &sign(b"installer", "StremioServiceSetup.exe", "1.2.2"),
+                "1.2.3",
+            )
+            .expect_err("Should refuse the older release");
+        assert_eq!("The signature is not of v1.2.3", err.to_string());
+
+        let err = public_key
+            .verify(&file, &sign(b"installer", "server.js", "1.2.3"), "1.2.3")
+            .expect_err("Should refuse another file");
+        assert_eq!(
+            "The signature is not of StremioServiceSetup.exe",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_embedded_public_key_is_valid() {
+        // panics on an invalid key
+        PublicKey::embedded();
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The desktop notifications of the service, see the `notifications` setting.
+//!
+//! They tell about the updates and about server.js crashing, without blocking like the dialogs.
+//! They're shown with the tools of the desktop, so no notification library is linked:
+//!
+//! - Linux - the `org.freedesktop.Notifications` D-Bus service, with `gdbus`
+//! - macOS - `osascript`, the notifications are shown as the ones of the Script Editor
+//! - Windows - a toast from PowerShell, shown as the ones of Windows PowerShell as the service has no package identity
+
+use anyhow::{bail, Context, Error};
+use log::warn;
+
+use crate::updater::UpdateMode;
+
+/// What the user is told about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notification {
+    /// A new version was downloaded, it's installed as the [`UpdateMode`] says or from the tray
+    UpdateAvailable { version: String, mode: UpdateMode },
+    /// The service runs a newer version than the last time
+    Updated { version: String },
+    /// A server-only update was installed, see [`ServerUpdate`](crate::updater::ServerUpdate)
+    ServerUpdated { version: String },
+    /// server.js exited on its own and it's restarted after a delay
+    ServerRestarting { attempt: u32 },
+    /// server.js exited on its own and it's not restarted
+    ServerCrashed { exit_code: Option<i32> },
+    /// server.js of the updated version doesn't start, the `previous` version can be restored from the tray
+    UpdateFailed { version: String, previous: String },
+    /// The previous version couldn't be restored, see [`rollback`](crate::rollback)
+    RevertFailed { previous: String },
+    /// The downloads of server.js are throttled, see [`pressure`](crate::pressure)
+    Throttled,
+    /// The service was started again while it runs, but the running instance couldn't be asked to open Stremio Web
+    AlreadyRunning,
+}
+
+impl Notification {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::UpdateAvailable { .. } => "Stremio Service update ready",
+            Self::Updated { .. } => "Stremio Service updated",
+            Self::ServerUpdated { .. } => "Streaming server updated",
+            Self::ServerRestarting { .. } => "Streaming server crashed",
+            Self::ServerCrashed { .. } => "Streaming server stopped",
+            Self::UpdateFailed { .. } => "Stremio Service update failed",
+            Self::RevertFailed { .. } => "Stremio Service not reverted",
+            Self::Throttled => "Downloads throttled",
+            Self::AlreadyRunning => "Stremio Service is already running",
+        }
+    }
+
+    pub fn body(&self) -> String {
+        match self {
+            Self::UpdateAvailable { version, mode } => {
+                format!("Version {version} is installed {}, or now from the tray", mode.when())
+            }
+            Self::Updated { version } => format!("Version {version} is running"),
+            Self::ServerUpdated { version } => format!("server.js {version} is running"),
+            Self::ServerRestarting { attempt } => {
+                format!("Restarting it, attempt {attempt}")
+            }
+            Self::ServerCrashed {
+                exit_code: Some(exit_code),
+            } => format!("It exited with code {exit_code}, see the server log from the tray"),
+            Self::ServerCrashed { exit_code: None } => {
+                "It was terminated, see the server log from the tray".to_owned()
+            }
+            Self::UpdateFailed { version, previous } => format!(
+                "The streaming server of version {version} doesn't start, \"Revert last update\" in the tray restores {previous}"
+            ),
+            Self::RevertFailed { previous } => {
+                format!("Version {previous} couldn't be restored, see the logs from the tray")
+            }
+            Self::Throttled => {
+                "The system is stalled on the memory or the disk, the torrents download slower for now"
+                    .to_owned()
+            }
+            Self::AlreadyRunning => "Stremio Web is opened from its tray icon".to_owned(),
+        }
+    }
+}
+
+/// Shows the notifications, unless they're disabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Notifier {
+    enabled: bool,
+}
+
+impl Notifier {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Shows the notification without waiting for it, the failures are only logged.
+    pub fn notify(&self, notification: Notification) {
+        if !self.enabled {
+            return;
+        }
+
+        let spawned = std::thread::Builder::new()
+            .name("notification".to_owned())
+            .spawn(move || {
+                if let Err(err) = show(notification.title(), &notification.body()) {
+                    warn!("Failed to show the notification: {err:#}");
+                }
+            });
+        if let Err(err) = spawned {
+            warn!("Failed to show the notification: {err}");
+        }
+    }
+
+    /// Shows the notification and waits until it's shown, e.g. before exiting, the failures are only logged.
+    pub fn notify_blocking(&self, notification: Notification) {
+        if !self.enabled {
+            return;
+        }
+
+        if let Err(err) = show(notification.title(), &notification.body()) {
+            warn!("Failed to show the notification: {err:#}");
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn show(title: &str, body: &str) -> Result<(), Error> {
+    use crate::constants::APP_IDENTIFIER;
+
+    // Notify(app_name, replaces_id, app_icon, summary, body, actions, hints, expire_timeout)
+    let output = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.Notifications",
+            "--object-path",
+            "/org/freedesktop/Notifications",
+            "--method",
+            "org.freedesktop.Notifications.Notify",
+            &gvariant_string("Stremio Service"),
+            "uint32 0",
+            &gvariant_string(APP_IDENTIFIER),
+            &gvariant_string(title),
+            &gvariant_string(body),
+            "@as []",
+            "@a{sv} {}",
+            "int32 -1",
+        ])
+        .output()
+        .context("Failed to run gdbus")?;
+    if !output.status.success() {
+        bail!(
+            "The notification service refused it: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// The text of a GVariant string, parsed by `gdbus`
+#[cfg(any(target_os = "linux", test))]
+fn gvariant_string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(target_os = "macos")]
+fn show(title: &str, body: &str) -> Result<(), Error> {
+    let script = format!(
+        "display notification {} with title {}",
+        applescript_string(body),
+        applescript_string(title)
+    );
+    let output = std::process::Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .context("Failed to run osascript")?;
+    if !output.status.success() {
+        bail!(
+            "osascript failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn applescript_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "windows")]
+fn show(title: &str, body: &str) -> Result<(), Error> {
+    use std::os::windows::process::CommandExt;
+
+    /// The application id of Windows PowerShell, the toasts need a registered one
+    const POWERSHELL_APP_ID: &str =
+        r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+    // the text nodes need no XML escaping
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    let script = format!(
+        "$manager = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]
+$xml = $manager::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$text = $xml.GetElementsByTagName('text')
+$text.Item(0).AppendChild($xml.CreateTextNode({title})) > $null
+$text.Item(1).AppendChild($xml.CreateTextNode({body})) > $null
+$manager::CreateToastNotifier({app_id}).Show([Windows.UI.Notifications.ToastNotification]::new($xml))",
+        title = quote(title),
+        body = quote(body),
+        app_id = quote(POWERSHELL_APP_ID),
+    );
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(crate::server::CREATE_NO_WINDOW)
+        .output()
+        .context("Failed to run PowerShell")?;
+    if !output.status.success() {
+        bail!(
+            "PowerShell failed to show the toast: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn show(_title: &str, _body: &str) -> Result<(), Error> {
+    bail!(
+        "Notifications are not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_gvariant_string_is_escaped() {
+        assert_eq!("'Stremio Service'", gvariant_string("Stremio Service"));
+        assert_eq!(r"'It\'s C:\\stremio'", gvariant_string(r"It's C:\stremio"));
+    }
+
+    #[test]
+    fn test_notification_text() {
+        let notification = Notification::ServerCrashed { exit_code: Some(1) };
+        assert_eq!("Streaming server stopped", notification.title());
+        assert_eq!(
+            "It exited with code 1, see the server log from the tray",
+            notification.body()
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The server.js instances run besides the main one, see the `additionalServers` setting.
+//!
+//! Every instance listens on its own port with its own data directory, so the devices connected to different instances,
+//! e.g. several TVs, don't share the cache, the settings and the sessions of server.js.
+//! They're started, stopped and updated with the main server.js and restarted after a crash like it,
+//! while the control API, the LAN discovery and the HTTPS proxy are only the ones of the main server.js.
+
+use std::path::Path;
+
+use log::error;
+
+use crate::server::{Config, Server};
+
+/// A server.js of the [`ServerPool`]
+#[derive(Debug, Clone)]
+pub struct PooledServer {
+    /// Shown in the tray and the log
+    pub name: String,
+    pub server: Server,
+}
+
+/// The additional server.js instances, empty by default
+#[derive(Debug, Clone, Default)]
+pub struct ServerPool {
+    servers: Vec<PooledServer>,
+}
+
+impl ServerPool {
+    /// The pool of the named configs, see [`Config::for_instance`]
+    pub fn new(configs: Vec<(String, Config)>) -> Self {
+        Self {
+            servers: configs
+                .into_iter()
+                .map(|(name, config)| PooledServer {
+                    name,
+                    server: Server::new(config),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn servers(&self) -> &[PooledServer] {
+        &self.servers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// Starts every server.js which isn't running, the ones failing to start are logged.
+    pub fn start(&self) {
+        for PooledServer { name, server } in &self.servers {
+            if let Err(err) = server.start() {
+                error!("Failed to start server.js `{name}`: {err}");
+            }
+        }
+    }
+
+    /// Stops every server.js, the ones failing to stop are logged.
+    pub async fn stop(&self) {
+        for PooledServer { name, server } in &self.servers {
+            if let Err(err) = server.stop().await {
+                error!("Failed to stop server.js `{name}`: {err}");
+            }
+        }
+    }
+
+    /// Runs the server.js of the version at the path in every instance, e.g. the one of a server-only update,
+    /// see [`Server::set_server_js`].
+    pub async fn set_server_js(&self, server_js: &Path, version: &str) {
+        for PooledServer { name, server } in &self.servers {
+            if let Err(err) = server
+                .set_server_js(server_js.to_path_buf(), version.to_owned())
+                .await
+            {
+                error!("Failed to restart server.js `{name}`: {err}");
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The optional watchdog of the memory and I/O pressure of the system, see the `pressureWatchdog` setting.
+//!
+//! The torrent writes of server.js can stall a whole Linux desktop on a slow disk or when the memory is low,
+//! with the page cache of the stream competing with the other apps. The watchdog reads the Pressure Stall
+//! Information of the kernel (`/proc/pressure`), the share of the time all the running tasks were stalled
+//! on the memory or the I/O, and the service throttles the downloads of server.js while it's high.
+//!
+//! It's only available on Linux 4.20 and later, with `CONFIG_PSI`.
+
+use std::path::Path;
+
+/// The `full avg10` percentage from which the system is considered under pressure
+pub const HIGH_PRESSURE: f64 = 20.0;
+/// The `full avg10` percentage below which the pressure is considered relieved
+pub const LOW_PRESSURE: f64 = 5.0;
+/// The samples below [`LOW_PRESSURE`] in a row before the pressure is considered relieved
+pub const RELIEVED_SAMPLES: u32 = 6;
+
+/// The pressure of the system, in percents of the last 10 seconds all the running tasks were stalled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pressure {
+    pub memory: f64,
+    pub io: f64,
+}
+
+impl Pressure {
+    /// The current pressure, `None` when the kernel doesn't provide it
+    pub fn read() -> Option<Self> {
+        Self::read_from(Path::new("/proc/pressure"))
+    }
+
+    fn read_from(dir: &Path) -> Option<Self> {
+        let read = |resource: &str| {
+            let contents = std::fs::read_to_string(dir.join(resource)).ok()?;
+
+            full_avg10(&contents)
+        };
+
+        Some(Self {
+            memory: read("memory")?,
+            io: read("io")?,
+        })
+    }
+
+    /// The highest of the pressures
+    pub fn max(&self) -> f64 {
+        self.memory.max(self.io)
+    }
+}
+
+/// The `avg10` of the `full` line, e.g. of `full avg10=1.50 avg60=0.80 avg300=0.20 total=12345`
+fn full_avg10(contents: &str) -> Option<f64> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("full "))?
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse()
+        .ok()
+}
+
+/// What's done after a sample, see [`PressureMonitor::sample`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureChange {
+    /// The system came under pressure
+    High,
+    /// The pressure was relieved
+    Relieved,
+}
+
+/// Tells when the system comes under pressure and when it's relieved,
+/// only after [`RELIEVED_SAMPLES`] low samples in a row so it doesn't flap.
+#[derive(Debug, Clone, Default)]
+pub struct PressureMonitor {
+    is_high: bool,
+    low_samples: u32,
+}
+
+impl PressureMonitor {
+    /// Whether the system is considered under pressure
+    pub fn is_high(&self) -> bool {
+        self.is_high
+    }
+
+    /// Takes the sample into account, returns whether the pressure changed
+    pub fn sample(&mut self, pressure: Pressure) -> Option<PressureChange> {
+        let pressure = pressure.max();
+        if !self.is_high {
+            if pressure >= HIGH_PRESSURE {
+                self.is_high = true;
+                self.low_samples = 0;
+                return Some(PressureChange::High);
+            }
+            return None;
+        }
+
+        match pressure < LOW_PRESSURE {
+            true => self.low_samples += 1,
+            false => self.low_samples = 0,
+        }
+        if self.low_samples >= RELIEVED_SAMPLES {
+            self.is_high = false;
+            self.low_samples = 0;
+            return Some(PressureChange::Relieved);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pressure_is_read() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("memory"),
+            "some avg10=3.00 avg60=1.00 avg300=0.50 total=100\nfull avg10=1.50 avg60=0.80 avg300=0.20 total=50\n",
+        )
+        .unwrap();
+        assert_eq!(
+            None,
+            Pressure::read_from(dir.path()),
+            "Without the I/O pressure"
+        );
+
+        std::fs::write(
+            dir.path().join("io"),
+            "some avg10=40.00 avg60=20.00 avg300=5.00 total=1000\nfull avg10=32.25 avg60=10.00 avg300=2.00 total=800\n",
+        )
+        .unwrap();
+        let pressure = Pressure::read_from(dir.path()).expect("Should read the pressure");
+        assert_eq!(
+            Pressure {
+                memory: 1.5,
+                io: 32.25
+            },
+            pressure
+        );
+        assert_eq!(32.25, pressure.max());
+    }
+
+    #[test]
+    fn test_monitor_does_not_flap() {
+        let sample = |io| Pressure { memory: 0.0, io };
+        let mut monitor = PressureMonitor::default();
+
+        assert_eq!(None, monitor.sample(sample(10.0)));
+        assert_eq!(Some(PressureChange::High), monitor.sample(sample(25.0)));
+        assert_eq!(None, monitor.sample(sample(30.0)));
+        for _ in 1..RELIEVED_SAMPLES {
+            assert_eq!(None, monitor.sample(sample(1.0)));
+        }
+        // a spike restarts the count
+        assert_eq!(None, monitor.sample(sample(10.0)));
+        for _ in 1..RELIEVED_SAMPLES {
+            assert_eq!(None, monitor.sample(sample(1.0)));
+        }
+        assert_eq!(Some(PressureChange::Relieved), monitor.sample(sample(1.0)));
+        assert!(!monitor.is_high());
+    }
+}
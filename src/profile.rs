@@ -0,0 +1,118 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use rust_embed::RustEmbed;
+use serde::Deserialize;
+
+#[derive(RustEmbed)]
+#[folder = "resources/profiles"]
+struct Profiles;
+
+/// The platform-specific defaults of the service.
+///
+/// Every profile is embedded in the binary and the one for the current platform
+/// is used as defaults for the [`Settings`](crate::settings::Settings) on first run.
+/// The user settings always take precedence over the profile.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Profile {
+    /// The name of the profile, see [`Profile::current_name`].
+    pub name: String,
+    /// Whether the service should register itself to start on login.
+    ///
+    /// On Windows this is handled by the installer
+    /// and inside Flatpak the user is asked for it by the Background portal.
+    pub autostart: bool,
+    /// Whether or not to skip the updater.
+    ///
+    /// On Linux only the AppImage and the release tarball are updated by the service,
+    /// the packages are updated by the package manager.
+    pub skip_updater: bool,
+}
+
+impl Profile {
+    /// Load the profile for the platform the service is running on.
+    pub fn current() -> Self {
+        Self::load(Self::current_name())
+            .expect("Profile for the current platform should be embedded")
+    }
+
+    /// Load an embedded profile by name.
+    pub fn load(name: &str) -> Option<Self> {
+        let file = Profiles::get(&format!("{name}.json"))?;
+
+        Some(serde_json::from_slice(file.data.as_ref()).expect("Embedded profiles should be valid"))
+    }
+
+    /// The name of the profile for the platform the service is running on.
+    ///
+    /// One of `windows`, `macos`, `linux`, `flatpak`, `snap`, `appimage` or `tarball`.
+    pub fn current_name() -> &'static str {
+        match std::env::consts::OS {
+            "linux" if std::env::var_os("FLATPAK_ID").is_some() => "flatpak",
+            "linux" if std::env::var_os("SNAP_NAME").is_some() => "snap",
+            // set by the runtime of the AppImage to its path
+            "linux" if std::env::var_os("APPIMAGE").is_some() => "appimage",
+            "linux" if is_tarball() => "tarball",
+            os => os,
+        }
+    }
+
+    /// Whether the service runs inside the Flatpak sandbox,
+    /// where the host paths like `/usr/share/applications` are not available.
+    pub fn is_flatpak() -> bool {
+        Self::current_name() == "flatpak"
+    }
+
+    /// Whether the service runs confined as a snap, where the host paths are not available either.
+    pub fn is_snap() -> bool {
+        Self::current_name() == "snap"
+    }
+}
+
+/// Whether the service runs from the extracted release tarball,
+/// a directory with server.js and the runtime which is outside of `/usr` and writable by the user.
+#[cfg(target_os = "linux")]
+fn is_tarball() -> bool {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let Ok(current_exe) = std::env::current_exe() else {
+        return false;
+    };
+    let Some(dir) = current_exe.parent() else {
+        return false;
+    };
+    if dir.starts_with("/usr")
+        || !["server.js", "stremio-runtime"]
+            .into_iter()
+            .all(|file| dir.join(file).exists())
+    {
+        return false;
+    }
+    let Ok(dir) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+
+    // SAFETY: the path is NUL terminated
+    unsafe { libc::access(dir.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_tarball() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::Profile;
+
+    #[test]
+    fn test_all_profiles_are_valid() {
+        for name in [
+            "windows", "macos", "linux", "flatpak", "snap", "appimage", "tarball",
+        ] {
+            let profile = Profile::load(name).expect("Profile should exist");
+
+            assert_eq!(name, profile.name);
+        }
+    }
+}
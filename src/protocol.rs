@@ -0,0 +1,377 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::io::Write;
+
+use base64::Engine;
+use flate2::{write::ZlibEncoder, Compression};
+use url::Url;
+
+use crate::deeplink::{addon_manifest_url, Fragment};
+
+/// The custom scheme handled by the service
+pub const STREMIO_SCHEME: &str = "stremio";
+/// The torrent links scheme handled by the service
+pub const MAGNET_SCHEME: &str = "magnet";
+/// The host of the `stremio://` links to the pages of the service itself
+pub const SERVICE_HOST: &str = "service";
+
+/// A Stremio Web route parsed from a `stremio://` url.
+///
+/// The path-based links have an empty host, e.g. `stremio:///detail/movie/tt0032138`,
+/// while the links with a host are addon installation links, e.g. `stremio://example.com/manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    /// `stremio:///` or `stremio:///board`
+    Board,
+    /// `stremio:///discover/...`
+    Discover(Vec<String>),
+    /// `stremio:///library/...`
+    Library(Vec<String>),
+    /// `stremio:///detail/{type}/{id}` or `stremio:///detail/{type}/{id}/{video_id}`
+    Detail {
+        kind: String,
+        id: String,
+        video_id: Option<String>,
+    },
+    /// `stremio:///search?search={query}`
+    Search(String),
+    /// `stremio:///player/...`
+    Player(Vec<String>),
+    /// `stremio:///settings`
+    Settings,
+    /// `stremio:///addons/...`
+    Addons(Vec<String>),
+    /// `stremio://{addon_host}/{path}/manifest.json`, the manifest is served over `https://`
+    AddonInstall(Url),
+    /// `magnet:?xt=urn:btih:{info_hash}`, played directly in the Stremio Web player
+    Magnet(Magnet),
+}
+
+impl Route {
+    /// Parse a `stremio://` or `magnet:` url, returns `None` if it's not a valid or supported link.
+    pub fn parse(open_url: &str) -> Option<Self> {
+        let url = Url::parse(open_url).ok()?;
+        match url.scheme() {
+            STREMIO_SCHEME => {}
+            MAGNET_SCHEME => return Magnet::parse(&url).map(Self::Magnet),
+            _ => return None,
+        }
+
+        match url.host_str() {
+            Some(SERVICE_HOST) => return None,
+            Some(host) if !host.is_empty() => {
+                return addon_manifest_url(&url).map(Self::AddonInstall)
+            }
+            _ => {}
+        }
+
+        let segments = url
+            .path_segments()
+            .map(|segments| {
+                segments
+                    .filter(|segment| !segment.is_empty())
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let Some((route, rest)) = segments.split_first() else {
+            return Some(Self::Board);
+        };
+
+        match (route.as_str(), rest) {
+            ("board", []) => Some(Self::Board),
+            ("discover", rest) => Some(Self::Discover(rest.to_vec())),
+            ("library", rest) => Some(Self::Library(rest.to_vec())),
+            ("detail", [kind, id]) => Some(Self::Detail {
+                kind: kind.to_owned(),
+                id: id.to_owned(),
+                video_id: None,
+            }),
+            ("detail", [kind, id, video_id]) => Some(Self::Detail {
+                kind: kind.to_owned(),
+                id: id.to_owned(),
+                video_id: Some(video_id.to_owned()),
+            }),
+            ("search", []) => url
+                .query_pairs()
+                .find(|(key, _)| key == "search" || key == "query")
+                .map(|(_, query)| Self::Search(query.into_owned())),
+            ("player", rest) if !rest.is_empty() => Some(Self::Player(rest.to_vec())),
+            ("settings", []) => Some(Self::Settings),
+            ("addons", rest) => Some(Self::Addons(rest.to_vec())),
+            _ => None,
+        }
+    }
+
+    /// The Stremio Web url fragment for this route.
+    ///
+    /// Path segments are kept as they came in the `stremio://` url, i.e. already percent-encoded.
+    pub fn fragment(&self) -> Fragment {
+        match self {
+            Self::Board => Fragment::new("/"),
+            Self::Discover(segments) => Fragment::new("/discover").encoded_segments(segments),
+            Self::Library(segments) => Fragment::new("/library").encoded_segments(segments),
+            Self::Detail { kind, id, video_id } => {
+                let fragment = Fragment::new("/detail").encoded_segments(&[kind, id]);
+                match video_id {
+                    Some(video_id) => fragment.encoded_segment(video_id),
+                    None => fragment,
+                }
+            }
+            Self::Search(query) => Fragment::new("/search").query("search", query),
+            Self::Player(segments) => Fragment::new("/player").encoded_segments(segments),
+            Self::Settings => Fragment::new("/settings"),
+            Self::Addons(segments) => Fragment::new("/addons").encoded_segments(segments),
+            Self::AddonInstall(manifest_url) => Fragment::addon_install(manifest_url),
+            Self::Magnet(magnet) => Fragment::new("/player").segment(&magnet.encode_stream()),
+        }
+    }
+}
+
+/// A page of the service itself parsed from a `stremio://service/{page}` url,
+/// so support docs can link straight to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServicePage {
+    /// `stremio://service/settings`, the service [`Settings`](crate::settings::Settings) file
+    Settings,
+    /// `stremio://service/logs`, the directory of the service log files
+    Logs,
+    /// `stremio://service/server`, the streaming server settings in the Web UI
+    Server,
+}
+
+impl ServicePage {
+    /// Parse a `stremio://service/{page}` url, returns `None` for any other url.
+    pub fn parse(open_url: &str) -> Option<Self> {
+        let url = Url::parse(open_url).ok()?;
+        if url.scheme() != STREMIO_SCHEME || url.host_str() != Some(SERVICE_HOST) {
+            return None;
+        }
+
+        match url.path().trim_matches('/') {
+            "settings" => Some(Self::Settings),
+            "logs" => Some(Self::Logs),
+            "server" => Some(Self::Server),
+            _ => None,
+        }
+    }
+}
+
+/// A `magnet:` link to a torrent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Magnet {
+    /// The lowercase, hex-encoded BitTorrent info hash
+    pub info_hash: String,
+    /// The display name (`dn`)
+    pub name: Option<String>,
+    /// The tracker urls (`tr`)
+    pub trackers: Vec<String>,
+}
+
+impl Magnet {
+    /// Parse a magnet link, the info hash can be either hex or base32 encoded.
+    pub fn parse(url: &Url) -> Option<Self> {
+        let mut info_hash = None;
+        let mut name = None;
+        let mut trackers = vec![];
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "xt" => {
+                    info_hash = value
+                        .strip_prefix("urn:btih:")
+                        .and_then(Self::normalize_info_hash)
+                        .or(info_hash)
+                }
+                "dn" => name = Some(value.into_owned()),
+                "tr" => trackers.push(value.into_owned()),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            info_hash: info_hash?,
+            name,
+            trackers,
+        })
+    }
+
+    /// The peer sources of the torrent, as expected by server.js: the trackers and the DHT
+    pub fn announce(&self) -> Vec<String> {
+        self.trackers
+            .iter()
+            .map(|tracker| format!("tracker:{tracker}"))
+            .chain(std::iter::once(format!("dht:{}", self.info_hash)))
+            .collect()
+    }
+
+    /// The torrent stream as expected by the Stremio Web player route:
+    /// the JSON stream compressed with zlib and base64 encoded.
+    pub fn encode_stream(&self) -> String {
+        let mut stream = serde_json::json!({
+            "infoHash": self.info_hash,
+            "announce": self.announce(),
+        });
+        if let Some(name) = self.name.as_ref() {
+            stream["name"] = name.as_str().into();
+        }
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(stream.to_string().as_bytes())
+            .expect("Writing to a Vec should never fail");
+        let compressed = encoder
+            .finish()
+            .expect("Writing to a Vec should never fail");
+
+        base64::engine::general_purpose::STANDARD.encode(compressed)
+    }
+
+    fn normalize_info_hash(info_hash: &str) -> Option<String> {
+        match info_hash.len() {
+            40 if info_hash.chars().all(|char| char.is_ascii_hexdigit()) => {
+                Some(info_hash.to_ascii_lowercase())
+            }
+            32 => {
+                // base32 (RFC 4648) encoded 20 bytes
+                let mut bits = 0_u64;
+                let mut bits_count = 0;
+                let mut hex = String::with_capacity(40);
+                for char in info_hash.chars() {
+                    let value = match char.to_ascii_uppercase() {
+                        char @ 'A'..='Z' => char as u64 - 'A' as u64,
+                        char @ '2'..='7' => char as u64 - '2' as u64 + 26,
+                        _ => return None,
+                    };
+                    bits = (bits << 5) | value;
+                    bits_count += 5;
+                    if bits_count >= 8 {
+                        bits_count -= 8;
+                        hex.push_str(&format!("{:02x}", (bits >> bits_count) & 0xff));
+                    }
+                }
+
+                Some(hex)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Magnet, Route, ServicePage};
+
+    fn fragment(open_url: &str) -> Option<String> {
+        Route::parse(open_url).map(|route| route.fragment().to_string())
+    }
+
+    #[test]
+    fn test_addon_install_route() {
+        assert_eq!(
+            Some("/addons?addon=https%3A%2F%2Fexample.com%2Fmanifest.json".to_owned()),
+            fragment("stremio://example.com/manifest.json")
+        );
+        assert_eq!(
+            Some(
+                "/addons?addon=https%3A%2F%2Fexample.com%3A8080%2Fconfig%2Fmanifest.json%3Fkey%3D1"
+                    .to_owned()
+            ),
+            fragment("stremio://example.com:8080/config/manifest.json?key=1")
+        );
+    }
+
+    #[test]
+    fn test_service_pages() {
+        assert_eq!(
+            Some(ServicePage::Settings),
+            ServicePage::parse("stremio://service/settings")
+        );
+        assert_eq!(
+            Some(ServicePage::Logs),
+            ServicePage::parse("stremio://service/logs/")
+        );
+        assert_eq!(
+            Some(ServicePage::Server),
+            ServicePage::parse("stremio://service/server")
+        );
+        assert_eq!(None, ServicePage::parse("stremio://service/unknown"));
+        assert_eq!(None, ServicePage::parse("stremio:///settings"));
+        // not an addon installation link
+        assert_eq!(None, Route::parse("stremio://service/settings"));
+    }
+
+    #[test]
+    fn test_magnet_route() {
+        let route = Route::parse(
+            "magnet:?xt=urn:btih:08ADA5A7A6183AAE1E09D831DF6748D566095A10&dn=Sintel&tr=udp%3A%2F%2Fexplodie.org%3A6969",
+        );
+        let expected = Magnet {
+            info_hash: "08ada5a7a6183aae1e09d831df6748d566095a10".to_owned(),
+            name: Some("Sintel".to_owned()),
+            trackers: vec!["udp://explodie.org:6969".to_owned()],
+        };
+        assert_eq!(Some(Route::Magnet(expected)), route);
+
+        let base32 = Route::parse("magnet:?xt=urn:btih:BCW2LJ5GDA5K4HQJ3AY56Z2I2VTASWQQ");
+        assert!(
+            matches!(base32, Some(Route::Magnet(magnet)) if magnet.info_hash == "08ada5a7a6183aae1e09d831df6748d566095a10")
+        );
+
+        assert_eq!(None, Route::parse("magnet:?dn=Sintel"));
+    }
+
+    #[test]
+    fn test_detail_route() {
+        assert_eq!(
+            Some("/detail/movie/tt0032138".to_owned()),
+            fragment("stremio:///detail/movie/tt0032138")
+        );
+        assert_eq!(
+            Some("/detail/series/tt0108778/tt0108778%3A1%3A1".to_owned()),
+            fragment("stremio:///detail/series/tt0108778/tt0108778%3A1%3A1")
+        );
+        assert_eq!(None, fragment("stremio:///detail/movie"));
+    }
+
+    #[test]
+    fn test_search_route() {
+        assert_eq!(
+            Some("/search?search=the%20wizard%20of%20oz".to_owned()),
+            fragment("stremio:///search?search=the+wizard%20of%20oz")
+        );
+        assert_eq!(
+            Some("/search?search=oz".to_owned()),
+            fragment("stremio:///search?query=oz")
+        );
+        assert_eq!(None, fragment("stremio:///search"));
+    }
+
+    #[test]
+    fn test_other_routes() {
+        assert_eq!(Some("/".to_owned()), fragment("stremio://"));
+        assert_eq!(Some("/".to_owned()), fragment("stremio:///"));
+        assert_eq!(Some("/".to_owned()), fragment("stremio:///board"));
+        assert_eq!(
+            Some("/settings".to_owned()),
+            fragment("stremio:///settings")
+        );
+        assert_eq!(
+            Some("/discover/community/movie/top".to_owned()),
+            fragment("stremio:///discover/community/movie/top")
+        );
+        assert_eq!(Some("/library".to_owned()), fragment("stremio:///library"));
+        assert_eq!(
+            Some("/player/eAEBRQC6%2F3siaW5mb0hhc2giOiI".to_owned()),
+            fragment("stremio:///player/eAEBRQC6%2F3siaW5mb0hhc2giOiI")
+        );
+        assert_eq!(
+            Some("/addons/movie".to_owned()),
+            fragment("stremio:///addons/movie")
+        );
+        assert_eq!(None, fragment("stremio:///player"));
+        assert_eq!(None, fragment("stremio:///unknown"));
+        assert_eq!(None, fragment("https://web.stremio.com"));
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Registration of the service as the `stremio://` protocol handler for the current user.
+//!
+//! The installers already register the protocol,
+//! this makes portable installs (e.g. the zip release) work as well.
+//!
+//! On Linux the package desktop entry might be missing or not be the default handler,
+//! in which case a user-level desktop entry is installed instead.
+
+use anyhow::Error;
+
+#[cfg(target_os = "windows")]
+const CLASSES_KEY: &str = r"Software\Classes\stremio";
+
+#[cfg(target_os = "linux")]
+const SCHEME_MIME_TYPE: &str = "x-scheme-handler/stremio";
+
+/// Registers the current executable as the `stremio://` handler.
+///
+/// Only `HKCU\Software\Classes\stremio` is written, so no elevated privileges are required.
+#[cfg(target_os = "windows")]
+pub fn register() -> Result<(), Error> {
+    use anyhow::Context;
+    use log::info;
+    use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+    let current_exe = std::env::current_exe().context("Failed to get current executable")?;
+    let open_command = format!("\"{}\" -o \"%1\"", current_exe.display());
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let registered_command = hkcu
+        .open_subkey(format!(r"{CLASSES_KEY}\shell\open\command"))
+        .and_then(|key| key.get_value::<String, _>(""))
+        .ok();
+    if registered_command.as_deref() == Some(open_command.as_str()) {
+        return Ok(());
+    }
+
+    let (key, _) = hkcu
+        .create_subkey(CLASSES_KEY)
+        .context("Failed to create the protocol registry key")?;
+    key.set_value("", &"URL:Stremio Protocol")?;
+    key.set_value("URL Protocol", &"")?;
+
+    let (icon, _) = key.create_subkey("DefaultIcon")?;
+    icon.set_value("", &format!("{},0", current_exe.display()))?;
+
+    let (command, _) = key.create_subkey(r"shell\open\command")?;
+    command.set_value("", &open_command)?;
+
+    info!("Registered stremio:// protocol handler: {open_command}");
+
+    Ok(())
+}
+
+/// Removes the `stremio://` handler registered by [`register`].
+#[cfg(target_os = "windows")]
+pub fn unregister() -> Result<(), Error> {
+    use anyhow::Context;
+    use log::info;
+    use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    match hkcu.delete_subkey_all(CLASSES_KEY) {
+        Ok(()) => info!("Unregistered stremio:// protocol handler"),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).context("Failed to remove the protocol registry key"),
+    }
+
+    Ok(())
+}
+
+/// Makes sure a desktop entry advertising `x-scheme-handler/stremio` is the default handler.
+///
+/// If neither the package nor the user desktop entry advertises the scheme,
+/// a user-level desktop entry for the current executable is installed.
+/// Inside Flatpak and snaps the handler is exported by the sandbox, so nothing is done.
+#[cfg(target_os = "linux")]
+pub fn register() -> Result<(), Error> {
+    use anyhow::Context;
+    use log::info;
+
+    use crate::constants::{DESKTOP_FILE_NAME, DESKTOP_FILE_PATH};
+
+    if crate::profile::Profile::is_flatpak() || crate::profile::Profile::is_snap() {
+        return Ok(());
+    }
+
+    let package_entry = std::path::Path::new(DESKTOP_FILE_PATH).join(DESKTOP_FILE_NAME);
+    let user_entry = user_desktop_entry()?;
+
+    let is_advertised = [&package_entry, &user_entry].iter().any(|entry| {
+        std::fs::read_to_string(entry).is_ok_and(|contents| advertises_scheme(&contents))
+    });
+    if !is_advertised {
+        // the AppImage is mounted at a different location on each run
+        let current_exe = match std::env::var_os("APPIMAGE") {
+            Some(appimage) => std::path::PathBuf::from(appimage),
+            None => std::env::current_exe().context("Failed to get current executable")?,
+        };
+        let contents = format!(
+            "[Desktop Entry]\n\
+            Name=Stremio Service\n\
+            Exec=\"{}\" -o %u\n\
+            MimeType={SCHEME_MIME_TYPE};\n\
+            NoDisplay=true\n\
+            Terminal=false\n\
+            Type=Application\n\
+            Icon=com.stremio.service\n",
+            current_exe.display()
+        );
+
+        if let Some(applications_dir) = user_entry.parent() {
+            std::fs::create_dir_all(applications_dir)
+                .context(format!("Failed to create {:?}", applications_dir))?;
+        }
+        std::fs::write(&user_entry, contents).context(format!(
+            "Failed to write the desktop entry {:?}",
+            user_entry
+        ))?;
+
+        info!("Installed desktop entry {user_entry:?}");
+    }
+
+    let default_handler = std::process::Command::new("xdg-mime")
+        .args(["query", "default", SCHEME_MIME_TYPE])
+        .output()
+        .context("Failed to query the default stremio:// handler")?;
+    if !is_advertised
+        || String::from_utf8_lossy(&default_handler.stdout)
+            .trim()
+            .is_empty()
+    {
+        let status = std::process::Command::new("xdg-mime")
+            .args(["default", DESKTOP_FILE_NAME, SCHEME_MIME_TYPE])
+            .status()
+            .context("Failed to set the default stremio:// handler")?;
+        anyhow::ensure!(status.success(), "xdg-mime exited with {status}");
+
+        info!("Registered stremio:// protocol handler: {DESKTOP_FILE_NAME}");
+    }
+
+    Ok(())
+}
+
+/// Removes the user-level desktop entry installed by [`register`].
+#[cfg(target_os = "linux")]
+pub fn unregister() -> Result<(), Error> {
+    use anyhow::Context;
+    use log::info;
+
+    let user_entry = user_desktop_entry()?;
+    match std::fs::remove_file(&user_entry) {
+        Ok(()) => info!("Removed desktop entry {user_entry:?}"),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).context(format!("Failed to remove {:?}", user_entry)),
+    }
+
+    Ok(())
+}
+
+/// `~/.local/share/applications/com.stremio.service.desktop`
+#[cfg(target_os = "linux")]
+fn user_desktop_entry() -> Result<std::path::PathBuf, Error> {
+    use anyhow::Context;
+
+    use crate::constants::DESKTOP_FILE_NAME;
+
+    Ok(dirs::data_dir()
+        .context("Failed to get data dir")?
+        .join("applications")
+        .join(DESKTOP_FILE_NAME))
+}
+
+/// Whether the `MimeType` key of a desktop entry contains `x-scheme-handler/stremio`.
+#[cfg(target_os = "linux")]
+fn advertises_scheme(desktop_entry: &str) -> bool {
+    desktop_entry
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("MimeType="))
+        .any(|mime_types| mime_types.split(';').any(|mime| mime == SCHEME_MIME_TYPE))
+}
+
+/// Registers the current executable as the `stremio://` handler.
+///
+/// Nothing to do for this platform, the bundle already registers the protocol.
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn register() -> Result<(), Error> {
+    Ok(())
+}
+
+/// Removes the `stremio://` handler registered by [`register`].
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn unregister() -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::advertises_scheme;
+
+    #[test]
+    fn test_advertises_scheme() {
+        let entry = include_str!("../resources/com.stremio.service.desktop");
+        assert!(advertises_scheme(entry));
+
+        assert!(!advertises_scheme(
+            "[Desktop Entry]\nMimeType=x-scheme-handler/magnet;\n"
+        ));
+        assert!(!advertises_scheme(
+            "[Desktop Entry]\nMimeType=x-scheme-handler/stremio-foo;\n"
+        ));
+    }
+}
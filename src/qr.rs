@@ -0,0 +1,451 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The QR codes of the LAN addresses of server.js, for pairing the TVs and the phones.
+//!
+//! Only what's needed for a URL is encoded: the byte mode, the medium error correction level
+//! and the versions 1 to 6 (up to 106 bytes), which have a single alignment pattern and no version information.
+
+use std::path::Path;
+
+use anyhow::Context;
+use image::{GrayImage, Luma};
+
+/// The error correction codewords per block, the blocks and the data codewords per block of the versions 1 to 6
+const BLOCKS: [(usize, usize, usize); 6] = [
+    (10, 1, 16),
+    (16, 1, 28),
+    (26, 1, 44),
+    (18, 2, 32),
+    (24, 2, 43),
+    (16, 4, 27),
+];
+/// The format bits of the medium error correction level
+const ERROR_CORRECTION_MEDIUM: u32 = 0b00;
+/// The light modules around the code, required by the readers
+const QUIET_ZONE: u32 = 4;
+
+/// A QR code, the modules are `true` when dark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encodes the data in the smallest version fitting it.
+    ///
+    /// # Errors
+    ///
+    /// When the data is longer than 106 bytes.
+    pub fn encode(data: &[u8]) -> Result<Self, anyhow::Error> {
+        let (code, is_function) = Self::unmasked(data)?;
+
+        Ok((0..8)
+            .map(|mask| code.masked(mask, &is_function))
+            .min_by_key(Self::penalty)
+            .expect("There are masks"))
+    }
+
+    /// The code before masking and whether each module is a function one
+    fn unmasked(data: &[u8]) -> Result<(Self, Vec<bool>), anyhow::Error> {
+        let (version, &(ecc_len, blocks, data_len)) = BLOCKS
+            .iter()
+            .enumerate()
+            .find(|(_, &(_, blocks, data_len))| 4 + 8 + data.len() * 8 <= blocks * data_len * 8)
+            .context("Too long for a QR code")?;
+        let version = version + 1;
+
+        let codewords = interleave(&data_codewords(data, blocks * data_len), blocks, ecc_len);
+        let (mut code, is_function) = Self::with_function_patterns(version);
+        code.draw_codewords(&codewords, &is_function);
+
+        Ok((code, is_function))
+    }
+
+    /// The code with the mask and its format information
+    fn masked(&self, mask: u8, is_function: &[bool]) -> Self {
+        let mut code = self.clone();
+        code.apply_mask(mask, is_function);
+        code.draw_format(mask);
+
+        code
+    }
+
+    /// The modules per side, without the quiet zone
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module of column `x` and row `y` is dark
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    /// The black on white image of the code with its quiet zone, `scale` pixels per module.
+    pub fn to_image(&self, scale: u32) -> GrayImage {
+        let side = (self.size as u32 + 2 * QUIET_ZONE) * scale;
+        GrayImage::from_fn(side, side, |x, y| {
+            let (x, y) = (x / scale, y / scale);
+            let is_dark = (QUIET_ZONE..QUIET_ZONE + self.size as u32).contains(&x)
+                && (QUIET_ZONE..QUIET_ZONE + self.size as u32).contains(&y)
+                && self.is_dark((x - QUIET_ZONE) as usize, (y - QUIET_ZONE) as usize);
+
+            Luma([if is_dark { 0 } else { 255 }])
+        })
+    }
+
+    /// Saves the image of the code as a PNG, see [`QrCode::to_image`].
+    pub fn save_png(&self, path: &Path, scale: u32) -> Result<(), anyhow::Error> {
+        self.to_image(scale)
+            .save_with_format(path, image::ImageFormat::Png)
+            .context(format!("Failed to save the QR code to {}", path.display()))
+    }
+
+    fn set(&mut self, x: usize, y: usize, is_dark: bool) {
+        self.modules[y * self.size + x] = is_dark;
+    }
+
+    /// The code with only the finder, timing and alignment patterns
+    /// and whether each module is a function one, the format areas included.
+    fn with_function_patterns(version: usize) -> (Self, Vec<bool>) {
+        let size = version * 4 + 17;
+        let mut code = Self {
+            size,
+            modules: vec![false; size * size],
+        };
+        let mut function = vec![false; size * size];
+
+        // separators included
+        for (left, top) in [(0, 0), (size - 7, 0), (0, size - 7)] {
+            for y in top.saturating_sub(1)..(top + 8).min(size) {
+                for x in left.saturating_sub(1)..(left + 8).min(size) {
+                    let (dx, dy) = (x.abs_diff(left + 3), y.abs_diff(top + 3));
+                    code.set(x, y, dx.max(dy) != 2 && dx.max(dy) != 4);
+                    function[y * size + x] = true;
+                }
+            }
+        }
+        for i in 8..size - 8 {
+            code.set(6, i, i % 2 == 0);
+            code.set(i, 6, i % 2 == 0);
+            function[i * size + 6] = true;
+            function[6 * size + i] = true;
+        }
+        if version > 1 {
+            let center = size - 7;
+            for y in center - 2..=center + 2 {
+                for x in center - 2..=center + 2 {
+                    code.set(x, y, x.abs_diff(center).max(y.abs_diff(center)) != 1);
+                    function[y * size + x] = true;
+                }
+            }
+        }
+        // the format information and the dark module
+        for i in 0..9 {
+            function[8 * size + i] = true;
+            function[i * size + 8] = true;
+        }
+        for i in 0..8 {
+            function[8 * size + size - 1 - i] = true;
+            function[(size - 1 - i) * size + 8] = true;
+        }
+
+        (code, function)
+    }
+
+    /// Places the codewords in the two modules wide columns, zigzagging from the bottom right corner
+    fn draw_codewords(&mut self, codewords: &[u8], is_function: &[bool]) {
+        let size = self.size;
+        let mut bit = 0;
+        let mut right = size - 1;
+        loop {
+            // skips the vertical timing pattern
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..size {
+                for x in [right, right - 1] {
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward {
+                        size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    if is_function[y * size + x] {
+                        continue;
+                    }
+                    // the remainder bits are light
+                    let is_dark = codewords
+                        .get(bit / 8)
+                        .is_some_and(|codeword| codeword >> (7 - bit % 8) & 1 == 1);
+                    self.set(x, y, is_dark);
+                    bit += 1;
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8, is_function: &[bool]) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !is_function[y * self.size + x] {
+                    let index = y * self.size + x;
+                    self.modules[index] = !self.modules[index];
+                }
+            }
+        }
+    }
+
+    /// Draws both copies of the format information and the dark module
+    fn draw_format(&mut self, mask: u8) {
+        let data = ERROR_CORRECTION_MEDIUM << 3 | u32::from(mask);
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+        let is_set = |i: usize| bits >> i & 1 == 1;
+
+        let size = self.size;
+        for i in 0..6 {
+            self.set(8, i, is_set(i));
+        }
+        self.set(8, 7, is_set(6));
+        self.set(8, 8, is_set(7));
+        self.set(7, 8, is_set(8));
+        for i in 9..15 {
+            self.set(14 - i, 8, is_set(i));
+        }
+        for i in 0..8 {
+            self.set(size - 1 - i, 8, is_set(i));
+        }
+        for i in 8..15 {
+            self.set(8, size - 15 + i, is_set(i));
+        }
+        self.set(8, size - 8, true);
+    }
+
+    /// The penalty of the masked code, the mask with the lowest one is used
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let row = |y: usize| {
+            (0..size)
+                .map(move |x| self.is_dark(x, y))
+                .collect::<Vec<_>>()
+        };
+        let column = |x: usize| {
+            (0..size)
+                .map(move |y| self.is_dark(x, y))
+                .collect::<Vec<_>>()
+        };
+        let lines = (0..size).map(row).chain((0..size).map(column));
+
+        let mut penalty = 0;
+        for line in lines {
+            // the runs of five or more modules of the same color
+            let mut run = 1;
+            for i in 1..=size {
+                if i < size && line[i] == line[i - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    penalty += run - 2;
+                }
+                run = 1;
+            }
+            // the patterns looking like a finder
+            const FINDER: [bool; 7] = [true, false, true, true, true, false, true];
+            for start in 0..size.saturating_sub(10) {
+                let window = &line[start..start + 11];
+                if (window[..7] == FINDER && window[7..].iter().all(|&dark| !dark))
+                    || (window[4..] == FINDER && window[..4].iter().all(|&dark| !dark))
+                {
+                    penalty += 40;
+                }
+            }
+        }
+        // the 2x2 blocks of the same color
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.is_dark(x, y);
+                if self.is_dark(x + 1, y) == color
+                    && self.is_dark(x, y + 1) == color
+                    && self.is_dark(x + 1, y + 1) == color
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        // far from as many dark modules as light ones
+        let dark = self.modules.iter().filter(|&&dark| dark).count();
+        penalty += (dark * 20).abs_diff(self.modules.len() * 10) / self.modules.len() * 10;
+
+        penalty
+    }
+}
+
+/// The mode, the length, the data and the padding in `len` codewords
+fn data_codewords(data: &[u8], len: usize) -> Vec<u8> {
+    fn push(bits: &mut Vec<bool>, value: usize, count: usize) {
+        bits.extend((0..count).rev().map(|i| value >> i & 1 == 1));
+    }
+
+    let mut bits = Vec::with_capacity(len * 8);
+    // the byte mode
+    push(&mut bits, 0b0100, 4);
+    push(&mut bits, data.len(), 8);
+    for &byte in data {
+        push(&mut bits, byte.into(), 8);
+    }
+    let terminator = (len * 8 - bits.len()).min(4);
+    push(&mut bits, 0, terminator);
+    let padding = (8 - bits.len() % 8) % 8;
+    push(&mut bits, 0, padding);
+
+    let mut codewords = bits
+        .chunks(8)
+        .map(|byte| {
+            byte.iter()
+                .fold(0, |codeword, &bit| codeword << 1 | u8::from(bit))
+        })
+        .collect::<Vec<_>>();
+    for pad in [0xEC, 0x11].into_iter().cycle().take(len - codewords.len()) {
+        codewords.push(pad);
+    }
+
+    codewords
+}
+
+/// Splits the data in the blocks, computes their error correction and interleaves everything
+fn interleave(data: &[u8], blocks: usize, ecc_len: usize) -> Vec<u8> {
+    let data_blocks = data.chunks(data.len() / blocks).collect::<Vec<_>>();
+    let ecc_blocks = data_blocks
+        .iter()
+        .map(|block| reed_solomon(block, ecc_len))
+        .collect::<Vec<_>>();
+
+    let mut codewords = Vec::with_capacity(data.len() + blocks * ecc_len);
+    for i in 0..data.len() / blocks {
+        codewords.extend(data_blocks.iter().map(|block| block[i]));
+    }
+    for i in 0..ecc_len {
+        codewords.extend(ecc_blocks.iter().map(|block| block[i]));
+    }
+
+    codewords
+}
+
+/// The Reed-Solomon error correction codewords of the block
+fn reed_solomon(block: &[u8], len: usize) -> Vec<u8> {
+    // the coefficients of the generator polynomial, without the leading one
+    let mut generator = vec![0; len];
+    generator[len - 1] = 1;
+    let mut root = 1;
+    for _ in 0..len {
+        for i in 0..len {
+            generator[i] = multiply(generator[i], root);
+            if i + 1 < len {
+                generator[i] ^= generator[i + 1];
+            }
+        }
+        root = multiply(root, 0x02);
+    }
+
+    let mut remainder = vec![0; len];
+    for &codeword in block {
+        let factor = codeword ^ remainder.remove(0);
+        remainder.push(0);
+        for (remainder, &coefficient) in remainder.iter_mut().zip(&generator) {
+            *remainder ^= multiply(coefficient, factor);
+        }
+    }
+
+    remainder
+}
+
+/// The product in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn multiply(x: u8, y: u8) -> u8 {
+    let mut product: u16 = 0;
+    for i in (0..8).rev() {
+        product = (product << 1) ^ ((product >> 7) * 0x11D);
+        product ^= u16::from(y >> i & 1) * u16::from(x);
+    }
+
+    product as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reed_solomon() {
+        // `HELLO WORLD` in the version 1 with the medium error correction level
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+
+        assert_eq!(
+            vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23],
+            reed_solomon(&data, 10)
+        );
+    }
+
+    #[test]
+    fn test_qr_code_modules() {
+        // made with the mask 2 by another encoder
+        let expected = [
+            "111111100101101111111",
+            "100000100001101000001",
+            "101110101000101011101",
+            "101110101010001011101",
+            "101110101100101011101",
+            "100000101011001000001",
+            "111111101010101111111",
+            "000000001000000000000",
+            "101111100101001111100",
+            "000011000101111101111",
+            "100001100110101100110",
+            "100001001001111011111",
+            "000001111110100011000",
+            "000000001100100110111",
+            "111111100111010000010",
+            "100000101110000001111",
+            "101110101101010110010",
+            "101110101011111111100",
+            "101110101000101001100",
+            "100000100011111001100",
+            "111111101010100010010",
+        ];
+        let (code, is_function) = QrCode::unmasked(b"stremio").unwrap();
+        let code = code.masked(2, &is_function);
+
+        assert_eq!(21, code.size());
+        for (y, row) in expected.iter().enumerate() {
+            let modules = (0..code.size())
+                .map(|x| if code.is_dark(x, y) { '1' } else { '0' })
+                .collect::<String>();
+            assert_eq!(*row, modules, "row {y}");
+        }
+
+        let url = QrCode::encode(b"http://192.168.1.10:11470").unwrap();
+        assert_eq!(25, url.size());
+        assert_eq!(33 * 8, url.to_image(8).width());
+        assert!(QrCode::encode(&[b'a'; 107]).is_err());
+    }
+}
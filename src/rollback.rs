@@ -0,0 +1,341 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The previous version of the service kept for reverting a failed update, see `--rollback`.
+//!
+//! Before an update is installed, the running version is kept in the [`versions`](crate::directories::Directories::versions)
+//! directory, in a sub-directory named after the version:
+//!
+//! - AppImage - a copy of the AppImage
+//! - tarball - a tarball of the installation directory, staged like the tarball of an update
+//! - macOS - a copy of the application bundle
+//! - Windows - the installer of the version, as the installed files are in use while the service runs.
+//!   It's kept when the version is installed by an update, so the first updated version can't be reverted.
+//!
+//! Only the last previous version is kept. Once it's restored, the reverted version is skipped by the updater,
+//! see [`Versions::skipped`].
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Error};
+use log::info;
+use semver::Version;
+
+use crate::{
+    staging::Staging,
+    updater::{self, Installation, TARBALL_DIR},
+};
+
+/// The version reverted last, see [`Versions::skip`]
+const SKIPPED_FILE_NAME: &str = "skipped";
+/// The installed application bundle on macOS
+const APP_BUNDLE: &str = "/Applications/StremioService.app";
+const APP_BUNDLE_NAME: &str = "StremioService.app";
+const TARBALL_NAME: &str = "stremio-service.tar.gz";
+
+/// The kept versions of the service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Versions {
+    dir: PathBuf,
+}
+
+impl Versions {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn version_dir(&self, version: &Version) -> PathBuf {
+        self.dir.join(version.to_string())
+    }
+
+    /// Keeps the running `current` version before `update` is installed with the `installer`,
+    /// the other kept versions are removed.
+    pub fn keep(
+        &self,
+        installation: &Installation,
+        current: &Version,
+        update: &Version,
+        installer: &Path,
+    ) -> Result<(), Error> {
+        let kept = match installation {
+            // the installer of the current version was kept by its update, if any
+            Installation::Installer if cfg!(windows) => {
+                let name = installer
+                    .file_name()
+                    .context(format!("Not an installer {}", installer.display()))?;
+                let dir = recreate_dir(&self.version_dir(update))?;
+                std::fs::copy(installer, dir.join(name))
+                    .context(format!("Failed to copy {}", installer.display()))?;
+
+                dir
+            }
+            Installation::Installer => {
+                let dir = recreate_dir(&self.version_dir(current))?;
+                run(Command::new("ditto")
+                    .arg(APP_BUNDLE)
+                    .arg(dir.join(APP_BUNDLE_NAME)))?;
+
+                dir
+            }
+            Installation::AppImage(appimage) => {
+                let name = appimage
+                    .file_name()
+                    .context(format!("Not an AppImage {}", appimage.display()))?;
+                let dir = recreate_dir(&self.version_dir(current))?;
+                std::fs::copy(appimage, dir.join(name))
+                    .context(format!("Failed to copy {}", appimage.display()))?;
+
+                dir
+            }
+            Installation::Tarball(install_dir) => {
+                let dir = recreate_dir(&self.version_dir(current))?;
+                archive(install_dir, &dir.join(TARBALL_NAME))?;
+
+                dir
+            }
+            Installation::Package => bail!("The package manager keeps the previous versions"),
+        };
+        info!("Kept {} for reverting the update", kept.display());
+
+        self.remove_except(&[current, update]);
+
+        Ok(())
+    }
+
+    /// The newest kept version older than `current`, the one [`Versions::restore`] reverts to.
+    pub fn previous(&self, current: &Version) -> Option<Version> {
+        std::fs::read_dir(&self.dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                std::fs::read_dir(entry.path()).is_ok_and(|mut files| files.next().is_some())
+            })
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<Version>().ok())
+            .filter(|version| version < current)
+            .max()
+    }
+
+    /// Restores the kept `version` in place of the installed one and starts it once this instance has exited.
+    ///
+    /// The tarball installation is staged and restored by the next start, see [`Staging`].
+    ///
+    /// # Errors
+    ///
+    /// When the version isn't kept for the installation or it fails to be restored,
+    /// the installed version is left as it is then.
+    pub fn restore(
+        &self,
+        installation: &Installation,
+        staging: &Staging,
+        version: &Version,
+    ) -> Result<(), Error> {
+        let dir = self.version_dir(version);
+        match installation {
+            Installation::Installer if cfg!(windows) => {
+                let installer = std::fs::read_dir(&dir)
+                    .ok()
+                    .and_then(|mut files| files.find_map(|file| Some(file.ok()?.path())))
+                    .context(format!("The installer of v{version} is not kept"))?;
+                let process = Command::new(&installer)
+                    .args([
+                        "/SILENT",
+                        "/NOCANCEL",
+                        "/FORCECLOSEAPPLICATIONS",
+                        "/TASKS=runapp",
+                    ])
+                    .spawn()
+                    .context(format!("Failed to start {}", installer.display()))?;
+                info!("Installer of v{version} started. (PID {:?})", process.id());
+            }
+            Installation::Installer => {
+                let bundle = dir.join(APP_BUNDLE_NAME);
+                if !bundle.is_dir() {
+                    bail!("The application of v{version} is not kept");
+                }
+                run(Command::new("/bin/sh")
+                    .args([
+                        "-c",
+                        &format!("rm -rf \"{APP_BUNDLE}\" && ditto \"$0\" \"{APP_BUNDLE}\""),
+                    ])
+                    .arg(bundle))?;
+                Command::new("/bin/sh")
+                    .args(["-c", &format!("sleep 5; open -n \"{APP_BUNDLE}\"")])
+                    .spawn()
+                    .context("Failed to start the restored application")?;
+            }
+            Installation::AppImage(appimage) => {
+                let kept = dir.join(appimage.file_name().unwrap_or_default());
+                if !kept.is_file() {
+                    bail!("The AppImage of v{version} is not kept");
+                }
+                updater::start_after_exit(&updater::replace_appimage(&kept, appimage)?);
+            }
+            Installation::Tarball(install_dir) => {
+                let kept = dir.join(TARBALL_NAME);
+                if !kept.is_file() {
+                    bail!("The files of v{version} are not kept");
+                }
+                staging.stage(&kept, version, install_dir)?;
+                updater::start_after_exit(&install_dir.join("stremio-service"));
+            }
+            Installation::Package => {
+                bail!("Revert the update with the package manager instead")
+            }
+        }
+        info!("Restored v{version}");
+
+        Ok(())
+    }
+
+    /// Records the `version` as reverted, the updater doesn't install it again.
+    pub fn skip(&self, version: &Version) -> Result<(), Error> {
+        let file = self.dir.join(SKIPPED_FILE_NAME);
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|()| std::fs::write(&file, version.to_string()))
+            .context(format!("Failed to write {}", file.display()))
+    }
+
+    /// The version reverted last, the updater only installs the newer ones.
+    pub fn skipped(&self) -> Option<Version> {
+        std::fs::read_to_string(self.dir.join(SKIPPED_FILE_NAME))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Removes the kept versions except the given ones, the failures are only logged.
+    fn remove_except(&self, versions: &[&Version]) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let is_kept = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<Version>().ok())
+                .is_none_or(|version| versions.contains(&&version));
+            if !is_kept {
+                if let Err(err) = std::fs::remove_dir_all(entry.path()) {
+                    log::warn!("Failed to remove {}: {err}", entry.path().display());
+                }
+            }
+        }
+    }
+}
+
+/// Removes the directory, when it exists, and creates it empty.
+fn recreate_dir(dir: &Path) -> Result<PathBuf, Error> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir).context(format!("Failed to remove {}", dir.display()))?;
+    }
+    std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+
+    Ok(dir.to_path_buf())
+}
+
+/// Writes the files of the installation directory to a tarball with the layout of the release tarball.
+fn archive(install_dir: &Path, file: &Path) -> Result<(), Error> {
+    let tarball =
+        std::fs::File::create(file).context(format!("Failed to create {}", file.display()))?;
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        tarball,
+        flate2::Compression::fast(),
+    ));
+    builder.follow_symlinks(false);
+    builder
+        .append_dir_all(TARBALL_DIR, install_dir)
+        .and_then(|()| builder.into_inner()?.finish())
+        .context(format!("Failed to archive {}", install_dir.display()))?;
+
+    Ok(())
+}
+
+fn run(command: &mut Command) -> Result<(), Error> {
+    let status = command
+        .status()
+        .context(format!("Failed to run {:?}", command.get_program()))?;
+    if !status.success() {
+        bail!("{:?} failed with {status}", command.get_program());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tarball_installation_is_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let install_dir = dir.path().join("install");
+        std::fs::create_dir_all(&install_dir).unwrap();
+        std::fs::write(install_dir.join("stremio-service"), "old").unwrap();
+        std::fs::write(install_dir.join("server.js"), "old").unwrap();
+        let installation = Installation::Tarball(install_dir.clone());
+        let versions = Versions::new(dir.path().join("versions"));
+        let (old, current, update) = (
+            Version::new(0, 1, 0),
+            Version::new(0, 2, 0),
+            Version::new(0, 3, 0),
+        );
+
+        std::fs::create_dir_all(versions.version_dir(&old)).unwrap();
+        versions
+            .keep(&installation, &current, &update, Path::new("unused"))
+            .expect("Should keep the installation");
+        assert!(
+            !versions.version_dir(&old).exists(),
+            "Only the last one is kept"
+        );
+        assert_eq!(Some(current.clone()), versions.previous(&update));
+        assert_eq!(None, versions.previous(&current));
+
+        std::fs::write(install_dir.join("stremio-service"), "new").unwrap();
+        std::fs::write(install_dir.join("server.js"), "new").unwrap();
+        let restored = restore_files(
+            &versions,
+            &dir.path().join("staging"),
+            &installation,
+            &current,
+        );
+        assert_eq!("old", restored);
+        assert_eq!(
+            "old",
+            std::fs::read_to_string(install_dir.join("server.js")).unwrap()
+        );
+
+        assert_eq!(None, versions.skipped());
+        versions.skip(&update).unwrap();
+        assert_eq!(Some(update), versions.skipped());
+    }
+
+    /// Restores the files without starting the restored executable
+    fn restore_files(
+        versions: &Versions,
+        staging_dir: &Path,
+        installation: &Installation,
+        version: &Version,
+    ) -> String {
+        let Installation::Tarball(install_dir) = installation else {
+            unreachable!()
+        };
+        let staging = Staging::new(staging_dir.to_path_buf());
+        staging
+            .stage(
+                &versions.version_dir(version).join(TARBALL_NAME),
+                version,
+                install_dir,
+            )
+            .expect("Should stage the files");
+        let executable = staging
+            .apply()
+            .expect("Should restore the files")
+            .expect("Should be staged");
+
+        std::fs::read_to_string(executable).unwrap()
+    }
+}
@@ -0,0 +1,584 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The optional confinement of server.js, as it parses untrusted torrent and media data.
+//!
+//! On Linux the file system access of server.js is restricted with [Landlock](https://docs.kernel.org/userspace-api/landlock.html):
+//! it writes only to its own data directory (the streaming cache and its temporary files included), `/dev/null` and `/dev/shm`,
+//! and it reads only the data directory, the runtime, server.js, ffmpeg and the system files they need, see [`SYSTEM_READABLE`].
+//! The network is left unrestricted.
+//! On Windows server.js runs with a low integrity token, so it can only write to its data directory,
+//! which is labeled writable at the low integrity, its temporary files are kept there too.
+//! On kernels without Landlock and on macOS server.js runs unconfined, with a warning.
+//!
+//! The distribution packages preferring AppArmor can install `resources/apparmor/stremio-service-server` instead.
+
+use std::{io, path::PathBuf};
+
+use log::warn;
+use tokio::process::Command;
+
+/// The system files read by the runtime on Linux, e.g. its shared libraries and the DNS configuration,
+/// the missing ones are skipped
+const SYSTEM_READABLE: &[&str] = &[
+    "/usr",
+    "/lib",
+    "/lib64",
+    "/bin",
+    "/etc/ld.so.cache",
+    "/etc/resolv.conf",
+    "/etc/hosts",
+    "/etc/nsswitch.conf",
+    "/etc/gai.conf",
+    "/etc/localtime",
+    "/etc/ssl",
+    "/etc/pki",
+    "/etc/ca-certificates",
+    "/proc",
+    "/sys/devices/system/cpu",
+    "/sys/fs/cgroup",
+    "/dev/urandom",
+];
+
+/// The files and directories server.js is allowed to access
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    writable: Vec<PathBuf>,
+    /// Only read and executed, the reads are restricted on Linux only
+    readable: Vec<PathBuf>,
+    /// The temporary directory of the confined command, instead of the shared one of the user
+    temp_dir: Option<PathBuf>,
+}
+
+impl Sandbox {
+    /// The sandbox allowed to write to the `writable` directories and files, and to read the system files.
+    pub fn new(writable: Vec<PathBuf>) -> Self {
+        Self {
+            writable,
+            readable: SYSTEM_READABLE.iter().map(PathBuf::from).collect(),
+            temp_dir: None,
+        }
+    }
+
+    /// The sandbox of the server.js with the given data directory, see [`data_dir`](crate::server::data_dir).
+    ///
+    /// Its temporary files are kept in a `temp` directory of the data directory, not in the shared one of the user.
+    #[cfg(not(target_os = "windows"))]
+    pub fn for_server(data_dir: PathBuf) -> Self {
+        Self {
+            temp_dir: Some(data_dir.join("temp")),
+            ..Self::new(vec![
+                data_dir,
+                PathBuf::from("/dev/null"),
+                PathBuf::from("/dev/shm"),
+            ])
+        }
+    }
+
+    /// The sandbox of the server.js with the given data directory, see [`data_dir`](crate::server::data_dir).
+    ///
+    /// The temporary directory of the user isn't writable at the low integrity, a `temp` one of the data directory is used.
+    #[cfg(target_os = "windows")]
+    pub fn for_server(data_dir: PathBuf) -> Self {
+        Self {
+            temp_dir: Some(data_dir.join("temp")),
+            ..Self::new(vec![data_dir])
+        }
+    }
+
+    /// Allows reading and executing the files, e.g. the runtime and server.js.
+    pub fn with_readable(mut self, readable: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.readable.extend(readable);
+        self
+    }
+
+    /// The directories and files server.js is allowed to write to
+    pub fn writable(&self) -> &[PathBuf] {
+        &self.writable
+    }
+
+    /// Confines the spawned command, returns whether it will be confined.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, command: &mut Command) -> bool {
+        if let Some(temp_dir) = &self.temp_dir {
+            if let Err(err) = std::fs::create_dir_all(temp_dir) {
+                warn!(
+                    "Failed to create {}, server.js runs unconfined: {err}",
+                    temp_dir.display()
+                );
+                return false;
+            }
+        }
+        let ruleset = match landlock::ruleset(&self.writable, &self.readable) {
+            Ok(Some(ruleset)) => ruleset,
+            Ok(None) => {
+                warn!("Landlock is not supported by the kernel, server.js runs unconfined");
+                return false;
+            }
+            Err(err) => {
+                warn!("Failed to create the Landlock ruleset, server.js runs unconfined: {err}");
+                return false;
+            }
+        };
+
+        if let Some(temp_dir) = &self.temp_dir {
+            command.env("TMPDIR", temp_dir);
+        }
+        // SAFETY: only async-signal-safe system calls are made in the forked child
+        unsafe {
+            command.pre_exec(move || landlock::restrict_self(&ruleset));
+        }
+
+        true
+    }
+
+    /// Confines the spawned command, returns whether it will be confined.
+    ///
+    /// The command is created suspended, it's confined and resumed by [`Sandbox::confine`] once it's spawned.
+    /// Its creation flags are replaced, it never opens a console window.
+    #[cfg(target_os = "windows")]
+    pub fn apply(&self, command: &mut Command) -> bool {
+        use windows_sys::Win32::System::Threading::{CREATE_NO_WINDOW, CREATE_SUSPENDED};
+
+        for dir in self.writable.iter().chain(&self.temp_dir) {
+            if let Err(err) = low_integrity::allow_writes(dir) {
+                warn!(
+                    "Failed to make {} writable at the low integrity, server.js runs unconfined: {err}",
+                    dir.display()
+                );
+                return false;
+            }
+        }
+        if let Some(temp_dir) = &self.temp_dir {
+            command.env("TEMP", temp_dir).env("TMP", temp_dir);
+        }
+        command.creation_flags(CREATE_NO_WINDOW | CREATE_SUSPENDED);
+
+        true
+    }
+
+    /// Confines the spawned command, returns whether it will be confined.
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    pub fn apply(&self, _command: &mut Command) -> bool {
+        warn!(
+            "Sandboxing server.js is not supported on {}, it runs unconfined",
+            std::env::consts::OS
+        );
+
+        false
+    }
+
+    /// Confines the process spawned with the command of [`Sandbox::apply`] and resumes it, on Windows,
+    /// elsewhere it's confined before it starts.
+    ///
+    /// # Errors
+    ///
+    /// When the process can't be confined, it's left suspended for the caller to kill it.
+    #[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
+    pub fn confine(&self, pid: Option<u32>) -> io::Result<()> {
+        #[cfg(target_os = "windows")]
+        {
+            let pid =
+                pid.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "It has exited"))?;
+            low_integrity::lower(pid)?;
+            low_integrity::resume(pid)
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        Ok(())
+    }
+}
+
+/// The mandatory integrity control of Windows, a low integrity process can't write to the files of a higher one
+#[cfg(target_os = "windows")]
+mod low_integrity {
+    use std::{ffi::c_void, io, os::windows::ffi::OsStrExt, path::Path, ptr};
+
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, LocalFree, BOOL, HANDLE, INVALID_HANDLE_VALUE, PSID},
+        Security::{
+            Authorization::{
+                ConvertStringSecurityDescriptorToSecurityDescriptorW, ConvertStringSidToSidW,
+                SetNamedSecurityInfoW, SDDL_REVISION_1, SE_FILE_OBJECT,
+            },
+            GetLengthSid, GetSecurityDescriptorSacl, SetTokenInformation, TokenIntegrityLevel,
+            LABEL_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, SID_AND_ATTRIBUTES,
+            TOKEN_ADJUST_DEFAULT, TOKEN_MANDATORY_LABEL,
+        },
+        System::{
+            Diagnostics::ToolHelp::{
+                CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD,
+                THREADENTRY32,
+            },
+            Threading::{
+                OpenProcess, OpenProcessToken, OpenThread, ResumeThread,
+                PROCESS_QUERY_LIMITED_INFORMATION, THREAD_SUSPEND_RESUME,
+            },
+        },
+    };
+
+    use crate::system_service::wide;
+
+    /// The low mandatory level
+    const LOW_INTEGRITY_SID: &str = "S-1-16-4096";
+    /// The low mandatory label, inherited by the files and the directories created inside
+    const LOW_INTEGRITY_LABEL: &str = "S:(ML;OICI;NW;;;LW)";
+    const SE_GROUP_INTEGRITY: u32 = 0x20;
+
+    /// Closed once it's dropped
+    struct Handle(HANDLE);
+
+    impl Handle {
+        fn new(handle: HANDLE) -> io::Result<Self> {
+            match handle {
+                0 | INVALID_HANDLE_VALUE => Err(io::Error::last_os_error()),
+                handle => Ok(Self(handle)),
+            }
+        }
+    }
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            // SAFETY: the handle is open and owned
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    fn check(result: BOOL) -> io::Result<()> {
+        match result {
+            0 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Labels the directory writable at the low integrity, the files already inside included.
+    ///
+    /// The missing directory is created.
+    pub fn allow_writes(dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+
+        let label = wide(LOW_INTEGRITY_LABEL);
+        let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+        // SAFETY: the label is NUL terminated, the descriptor is freed below
+        check(unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                label.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                ptr::null_mut(),
+            )
+        })?;
+
+        let (mut present, mut defaulted, mut sacl) = (0, 0, ptr::null_mut());
+        // SAFETY: the descriptor is valid, the SACL points into it
+        let labeled = check(unsafe {
+            GetSecurityDescriptorSacl(descriptor, &mut present, &mut sacl, &mut defaulted)
+        })
+        .and_then(|()| {
+            let path = dir
+                .as_os_str()
+                .encode_wide()
+                .chain(std::iter::once(0))
+                .collect::<Vec<_>>();
+            // SAFETY: the path is NUL terminated, only the label is set
+            match unsafe {
+                SetNamedSecurityInfoW(
+                    path.as_ptr(),
+                    SE_FILE_OBJECT,
+                    LABEL_SECURITY_INFORMATION,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null(),
+                    sacl,
+                )
+            } {
+                0 => Ok(()),
+                error => Err(io::Error::from_raw_os_error(error as i32)),
+            }
+        });
+        // SAFETY: it was allocated by ConvertStringSecurityDescriptorToSecurityDescriptorW
+        unsafe { LocalFree(descriptor) };
+
+        labeled
+    }
+
+    /// Lowers the integrity of the process, it's inherited by its children.
+    pub fn lower(pid: u32) -> io::Result<()> {
+        // SAFETY: the handles are checked and closed once they're dropped
+        let process =
+            Handle::new(unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) })?;
+        let mut token = 0;
+        check(unsafe { OpenProcessToken(process.0, TOKEN_ADJUST_DEFAULT, &mut token) })?;
+        let token = Handle::new(token)?;
+
+        let sid_string = wide(LOW_INTEGRITY_SID);
+        let mut sid: PSID = ptr::null_mut();
+        // SAFETY: the SID string is NUL terminated, the SID is freed below
+        check(unsafe { ConvertStringSidToSidW(sid_string.as_ptr(), &mut sid) })?;
+        let label = TOKEN_MANDATORY_LABEL {
+            Label: SID_AND_ATTRIBUTES {
+                Sid: sid,
+                Attributes: SE_GROUP_INTEGRITY,
+            },
+        };
+        // SAFETY: the label and its SID outlive the call, their size is given
+        let lowered = check(unsafe {
+            SetTokenInformation(
+                token.0,
+                TokenIntegrityLevel,
+                &label as *const TOKEN_MANDATORY_LABEL as *const c_void,
+                std::mem::size_of::<TOKEN_MANDATORY_LABEL>() as u32 + GetLengthSid(sid),
+            )
+        });
+        // SAFETY: it was allocated by ConvertStringSidToSidW
+        unsafe { LocalFree(sid) };
+
+        lowered
+    }
+
+    /// Resumes the threads of the process created suspended
+    pub fn resume(pid: u32) -> io::Result<()> {
+        // SAFETY: the handles are checked and closed once they're dropped, the entry size is given
+        let snapshot = Handle::new(unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) })?;
+        let mut entry = THREADENTRY32 {
+            dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+            ..unsafe { std::mem::zeroed() }
+        };
+
+        let mut resumed = false;
+        let mut found = unsafe { Thread32First(snapshot.0, &mut entry) };
+        while found != 0 {
+            if entry.th32OwnerProcessID == pid {
+                let thread = Handle::new(unsafe {
+                    OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID)
+                })?;
+                if unsafe { ResumeThread(thread.0) } == u32::MAX {
+                    return Err(io::Error::last_os_error());
+                }
+                resumed = true;
+            }
+            found = unsafe { Thread32Next(snapshot.0, &mut entry) };
+        }
+
+        match resumed {
+            true => Ok(()),
+            false => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "It has no thread to resume",
+            )),
+        }
+    }
+}
+
+/// The Landlock system calls, see `linux/landlock.h`
+#[cfg(target_os = "linux")]
+mod landlock {
+    use std::{
+        ffi::CString,
+        io,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::ffi::OsStrExt,
+        },
+        path::{Path, PathBuf},
+    };
+
+    use log::warn;
+
+    const CREATE_RULESET_VERSION: u32 = 1 << 0;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+    const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    /// Since ABI 2
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    /// Since ABI 3
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+
+    const READ_ACCESS: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+    /// The rights of a rule on a file rather than a directory
+    const FILE_ACCESS: u64 =
+        ACCESS_FS_EXECUTE | ACCESS_FS_WRITE_FILE | ACCESS_FS_READ_FILE | ACCESS_FS_TRUNCATE;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// The writing rights handled by the kernel
+    fn write_access(abi: i64) -> u64 {
+        let mut access = ACCESS_FS_WRITE_FILE
+            | ACCESS_FS_REMOVE_DIR
+            | ACCESS_FS_REMOVE_FILE
+            | ACCESS_FS_MAKE_CHAR
+            | ACCESS_FS_MAKE_DIR
+            | ACCESS_FS_MAKE_REG
+            | ACCESS_FS_MAKE_SOCK
+            | ACCESS_FS_MAKE_FIFO
+            | ACCESS_FS_MAKE_BLOCK
+            | ACCESS_FS_MAKE_SYM;
+        if abi >= 2 {
+            access |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            access |= ACCESS_FS_TRUNCATE;
+        }
+
+        access
+    }
+
+    /// The ruleset denying the writes outside of the writable paths and the reads outside of them
+    /// and the readable ones, `None` without Landlock.
+    ///
+    /// The missing writable directories are created, Landlock rules need an existing path.
+    /// The missing readable paths are skipped.
+    pub fn ruleset(writable: &[PathBuf], readable: &[PathBuf]) -> io::Result<Option<OwnedFd>> {
+        // SAFETY: the version query doesn't take a ruleset
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            return Ok(None);
+        }
+
+        let access = write_access(abi) | READ_ACCESS;
+        let attr = RulesetAttr {
+            handled_access_fs: access,
+        };
+        // SAFETY: the attribute outlives the call and its size is given
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the kernel has just returned the new file descriptor
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+        for path in writable {
+            if !path.exists() {
+                if let Err(err) = std::fs::create_dir_all(path) {
+                    warn!("server.js can't write to {}: {err}", path.display());
+                    continue;
+                }
+            }
+            add_rule(&ruleset, path, access)?;
+        }
+        for path in readable.iter().filter(|path| path.exists()) {
+            add_rule(&ruleset, path, READ_ACCESS)?;
+        }
+
+        Ok(Some(ruleset))
+    }
+
+    /// Allows the access beneath the directory, or to the file with only the rights of a file.
+    fn add_rule(ruleset: &OwnedFd, path: &Path, access: u64) -> io::Result<()> {
+        let access = match std::fs::metadata(path)?.is_dir() {
+            true => access,
+            false => access & FILE_ACCESS,
+        };
+        let path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        // SAFETY: the path is NUL terminated
+        let parent_fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if parent_fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the file descriptor has just been opened
+        let parent = unsafe { OwnedFd::from_raw_fd(parent_fd) };
+
+        let rule = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: parent.as_raw_fd(),
+        };
+        // SAFETY: the rule outlives the call
+        let added = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &rule as *const PathBeneathAttr,
+                0,
+            )
+        };
+        match added {
+            0.. => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Enforces the ruleset on the calling process, it's inherited by the executed program.
+    pub fn restrict_self(ruleset: &OwnedFd) -> io::Result<()> {
+        // SAFETY: no pointers are passed, the ruleset stays open for the call
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_access_outside_of_the_sandbox_is_denied() {
+        let dir = tempfile::tempdir().unwrap();
+        let (data_dir, denied) = (dir.path().join("data"), dir.path().join("denied"));
+        std::fs::create_dir_all(&denied).unwrap();
+        std::fs::write(denied.join("secret"), "secret").unwrap();
+
+        let sandbox = Sandbox::for_server(data_dir.clone());
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg(format!(
+            "touch {data}/file \"$TMPDIR/file\" && ! touch {denied}/file 2>/dev/null && ! cat {denied}/secret 2>/dev/null",
+            data = data_dir.display(),
+            denied = denied.display()
+        ));
+        // Landlock isn't supported by the kernel
+        if !sandbox.apply(&mut command) {
+            return;
+        }
+
+        let status = command.status().await.expect("Should run sh");
+        assert!(status.success());
+        assert!(data_dir.join("file").exists());
+        assert!(
+            data_dir.join("temp").join("file").exists(),
+            "The temporary files are kept in the data directory"
+        );
+        assert!(!denied.join("file").exists());
+    }
+}
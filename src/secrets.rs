@@ -0,0 +1,300 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::{collections::BTreeMap, fmt::Debug, io::Write, path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Error};
+use keyring::Entry;
+use log::{info, warn};
+
+use crate::constants::APP_IDENTIFIER;
+
+/// The password of the `proxyUsername`, see [`Roots::with_proxy`](crate::tls::Roots::with_proxy)
+pub const PROXY_PASSWORD: &str = "proxy-password";
+
+/// Storage for secrets (tokens, keys, PIN seeds, etc.) used by the service.
+///
+/// Secrets are kept in the OS keychain (Keychain on macOS, Credential Manager on Windows
+/// and Secret Service on Linux) using the [`keyring`] crate.
+/// When the keychain is not available (e.g. headless Linux without a Secret Service daemon)
+/// we transparently fall back to a plaintext file which is only readable by the current user.
+/// On `*nix` it's created with the `0600` mode, on Windows it inherits the ACL of the profile directory
+/// of the user it's in, which only the user, the administrators and `SYSTEM` can read.
+///
+/// Secrets found in the fallback file are migrated to the keychain as soon as it becomes available.
+#[derive(Debug, Clone)]
+pub struct Secrets {
+    /// The fallback file used when the OS keychain is not available
+    fallback_file: PathBuf,
+    keychain: Arc<dyn Keychain>,
+}
+
+/// The OS keychain, the tests replace it with one in memory.
+pub trait Keychain: Debug + Send + Sync {
+    fn get(&self, key: &str) -> Result<String, keyring::Error>;
+    fn set(&self, key: &str, secret: &str) -> Result<(), keyring::Error>;
+    fn delete(&self, key: &str) -> Result<(), keyring::Error>;
+}
+
+/// The keychain of the OS, by the [`keyring`] crate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsKeychain;
+
+impl OsKeychain {
+    fn entry(key: &str) -> Result<Entry, keyring::Error> {
+        Entry::new(APP_IDENTIFIER, key)
+    }
+}
+
+impl Keychain for OsKeychain {
+    fn get(&self, key: &str) -> Result<String, keyring::Error> {
+        Self::entry(key)?.get_password()
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<(), keyring::Error> {
+        Self::entry(key)?.set_password(secret)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), keyring::Error> {
+        Self::entry(key)?.delete_password()
+    }
+}
+
+impl Secrets {
+    pub fn new(fallback_file: PathBuf) -> Self {
+        Self::with_keychain(fallback_file, OsKeychain)
+    }
+
+    pub fn with_keychain(fallback_file: PathBuf, keychain: impl Keychain + 'static) -> Self {
+        Self {
+            fallback_file,
+            keychain: Arc::new(keychain),
+        }
+    }
+
+    /// Get a secret, first from the OS keychain and then from the fallback file.
+    pub fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        let keychain_error = match self.keychain.get(key) {
+            Ok(secret) => return Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => None,
+            Err(err) => Some(err),
+        };
+
+        let mut fallback = self.read_fallback()?;
+        let secret = match fallback.get(key) {
+            Some(secret) => secret.to_owned(),
+            None => return Ok(None),
+        };
+
+        // the keychain is reachable but the secret is still in the fallback file, migrate it.
+        if keychain_error.is_none() {
+            match self.keychain.set(key, &secret) {
+                Ok(()) => {
+                    fallback.remove(key);
+                    self.write_fallback(&fallback)?;
+                    info!("Migrated secret `{key}` to the OS keychain");
+                }
+                Err(err) => warn!("Failed to migrate secret `{key}` to the OS keychain: {err}"),
+            }
+        }
+
+        Ok(Some(secret))
+    }
+
+    /// Store a secret in the OS keychain, or in the fallback file if the keychain is not available.
+    pub fn set(&self, key: &str, secret: &str) -> Result<(), Error> {
+        match self.keychain.set(key, secret) {
+            Ok(()) => {
+                // make sure we don't leave an old copy in the fallback file
+                let mut fallback = self.read_fallback()?;
+                if fallback.remove(key).is_some() {
+                    self.write_fallback(&fallback)?;
+                }
+
+                Ok(())
+            }
+            Err(err) => {
+                warn!(
+                    "OS keychain is not available ({err}), storing secret `{key}` in {:?}",
+                    self.fallback_file
+                );
+
+                let mut fallback = self.read_fallback()?;
+                fallback.insert(key.to_owned(), secret.to_owned());
+                self.write_fallback(&fallback)
+            }
+        }
+    }
+
+    /// Remove a secret from both the OS keychain and the fallback file.
+    pub fn delete(&self, key: &str) -> Result<(), Error> {
+        match self.keychain.delete(key) {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(err) => warn!("Failed to remove secret `{key}` from the OS keychain: {err}"),
+        }
+
+        let mut fallback = self.read_fallback()?;
+        if fallback.remove(key).is_some() {
+            self.write_fallback(&fallback)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_fallback(&self) -> Result<BTreeMap<String, String>, Error> {
+        if !self.fallback_file.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let content = std::fs::read_to_string(&self.fallback_file)
+            .context(format!("Failed to read {:?}", self.fallback_file))?;
+
+        serde_json::from_str(&content)
+            .context(format!("Invalid secrets file {:?}", self.fallback_file))
+    }
+
+    fn write_fallback(&self, secrets: &BTreeMap<String, String>) -> Result<(), Error> {
+        if let Some(parent) = self.fallback_file.parent() {
+            std::fs::create_dir_all(parent).context(format!("Failed to create {:?}", parent))?;
+        }
+
+        // the secrets are written to a new file only the current user can read, which replaces the fallback file
+        let mut part = self.fallback_file.clone().into_os_string();
+        part.push(".part");
+        let part = PathBuf::from(part);
+        let write = || {
+            // a stale file would keep its permissions
+            match std::fs::remove_file(&part) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+
+                options.mode(0o600);
+            }
+            let mut file = options.open(&part)?;
+            file.write_all(&serde_json::to_vec_pretty(secrets)?)?;
+            file.sync_all()?;
+            std::fs::rename(&part, &self.fallback_file)
+        };
+
+        write().context(format!("Failed to write {:?}", self.fallback_file))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::BTreeMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use super::{Keychain, Secrets};
+
+    /// A keychain in memory, it fails like a missing Secret Service while it's unavailable
+    #[derive(Debug, Default)]
+    struct FakeKeychain {
+        secrets: Mutex<BTreeMap<String, String>>,
+        unavailable: Arc<AtomicBool>,
+    }
+
+    impl FakeKeychain {
+        fn check(&self) -> Result<(), keyring::Error> {
+            match self.unavailable.load(Ordering::SeqCst) {
+                true => Err(keyring::Error::NoStorageAccess("unavailable".into())),
+                false => Ok(()),
+            }
+        }
+    }
+
+    impl Keychain for FakeKeychain {
+        fn get(&self, key: &str) -> Result<String, keyring::Error> {
+            self.check()?;
+            self.secrets
+                .lock()
+                .unwrap()
+                .get(key)
+                .cloned()
+                .ok_or(keyring::Error::NoEntry)
+        }
+
+        fn set(&self, key: &str, secret: &str) -> Result<(), keyring::Error> {
+            self.check()?;
+            self.secrets
+                .lock()
+                .unwrap()
+                .insert(key.to_owned(), secret.to_owned());
+            Ok(())
+        }
+
+        fn delete(&self, key: &str) -> Result<(), keyring::Error> {
+            self.check()?;
+            self.secrets
+                .lock()
+                .unwrap()
+                .remove(key)
+                .map(drop)
+                .ok_or(keyring::Error::NoEntry)
+        }
+    }
+
+    #[test]
+    fn test_secrets_in_the_keychain() {
+        let dir = tempfile::tempdir().unwrap();
+        let fallback_file = dir.path().join("secrets.json");
+        let secrets = Secrets::with_keychain(fallback_file.clone(), FakeKeychain::default());
+
+        assert_eq!(None, secrets.get("token").unwrap());
+        secrets.set("token", "secret").unwrap();
+        assert_eq!(Some("secret".to_owned()), secrets.get("token").unwrap());
+        assert!(!fallback_file.exists());
+
+        secrets.delete("token").unwrap();
+        assert_eq!(None, secrets.get("token").unwrap());
+        secrets
+            .delete("token")
+            .expect("Should delete a missing secret");
+    }
+
+    #[test]
+    fn test_secrets_fall_back_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let fallback_file = dir.path().join("config").join("secrets.json");
+        let keychain = FakeKeychain::default();
+        let unavailable = keychain.unavailable.clone();
+        unavailable.store(true, Ordering::SeqCst);
+        let secrets = Secrets::with_keychain(fallback_file.clone(), keychain);
+
+        secrets.set("token", "secret").unwrap();
+        secrets.set("other", "other secret").unwrap();
+        assert_eq!(Some("secret".to_owned()), secrets.get("token").unwrap());
+        assert!(std::fs::read_to_string(&fallback_file)
+            .unwrap()
+            .contains("\"secret\""));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&fallback_file)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        secrets.delete("other").unwrap();
+        assert_eq!(None, secrets.get("other").unwrap());
+
+        // migrated once the keychain is available
+        unavailable.store(false, Ordering::SeqCst);
+        assert_eq!(Some("secret".to_owned()), secrets.get("token").unwrap());
+        assert_eq!("{}", std::fs::read_to_string(&fallback_file).unwrap());
+        assert_eq!(Some("secret".to_owned()), secrets.get("token").unwrap());
+    }
+}
@@ -1,18 +1,56 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
-use anyhow::{anyhow, bail, Error};
-use log::{error, info};
-use once_cell::sync::OnceCell;
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use log::{error, info, warn, Level};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::{
-    path::PathBuf,
+    collections::{BTreeMap, VecDeque},
+    fmt::Debug,
+    io,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
     process::{Child, Command},
-    sync::{Arc, Mutex},
+    sync::{oneshot, watch},
+    task::JoinHandle,
+};
+
+use crate::{
+    build_info::BUILD_INFO,
+    constants::{SERVER_DOWNLOAD_URL, SERVER_PORT},
+    error::ServiceError,
+    protocol::Magnet,
+    sandbox::Sandbox,
+    server_settings::ServerSettings,
+    status::{ServerInfo, ServerTrayStatus},
+    throttle::WriteLimit,
+    tls::Roots,
+    util::find_in_path,
 };
 
 #[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
-#[cfg(target_os = "windows")]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
+pub(crate) const CREATE_NO_WINDOW: u32 = 0x08000000;
+/// The checksum of the bundled server.js verified by the build script, empty without it
+const BUNDLED_SERVER_SHA256: &str = env!("STREMIO_SERVICE_SERVER_SHA256");
+/// The number of the last lines of the server.js output kept, see [`Server::log_tail`]
+pub const LOG_TAIL_LINES: usize = 1000;
+/// The environment variable of the port server.js listens on
+pub const PORT_ENV: &str = "HTTP_PORT";
+/// The environment variable of the data directory of server.js, see [`data_dir`]
+pub const DATA_DIR_ENV: &str = "APP_PATH";
+/// How long server.js has to run for its crash not to count as a consecutive one, see [`Backoff`]
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// server.js is killed when the last clone is dropped
 #[derive(Debug, Clone)]
 pub struct Server {
     inner: Arc<ServerInner>,
@@ -21,15 +59,334 @@ pub struct Server {
 #[derive(Debug)]
 struct ServerInner {
     pub config: Config,
-    pub process: Mutex<OnceCell<Child>>,
+    pub spawner: Box<dyn ProcessSpawner>,
+    /// The server.js started and its version, replaced by a server-only update, see [`Server::set_server_js`]
+    pub server_js: Mutex<(PathBuf, String)>,
+    /// The version of the server.js started last, reported in the [`ServerInfo`]
+    pub version: Mutex<String>,
+    /// The settings of the config and the ones changed since, see [`Server::update_settings`]
+    pub settings: Mutex<Option<ServerSettings>>,
+    /// The process started last, `None` when it was not started or it was stopped
+    pub process: Mutex<Option<Supervised>>,
+    /// The last lines of the output of every started process
+    pub log_tail: LogTail,
+    /// The number of the spawned processes, the generation of the last one
+    pub spawned: AtomicU64,
+    pub restarts: Mutex<Restarts>,
+    /// The last status of server.js, see [`Server::subscribe`]
+    pub status: watch::Sender<ServerInfo>,
+}
+
+/// The automatic restarts of the crashed server.js
+#[derive(Debug, Default)]
+struct Restarts {
+    /// The consecutive restarts, reset once server.js runs for [`STABLE_RUN`]
+    attempts: u32,
+    /// Whether a restart is scheduled
+    pending: bool,
+}
+
+/// How a crashed server.js is restarted, with a delay doubled after each consecutive crash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// The delay of the first restart
+    pub initial: Duration,
+    /// The longest delay
+    pub max: Duration,
+    /// The consecutive restarts before giving up
+    pub max_restarts: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(30),
+            max_restarts: 5,
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay of the restart, `attempt` starts at 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// The last [`LOG_TAIL_LINES`] lines of the server.js output
+#[derive(Debug, Clone, Default)]
+struct LogTail(Arc<Mutex<VecDeque<String>>>);
+
+impl LogTail {
+    fn push(&self, line: String) {
+        if let Ok(mut lines) = self.0.lock() {
+            if lines.len() == LOG_TAIL_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        }
+    }
+
+    /// The last `count` lines, the oldest first
+    fn last(&self, count: usize) -> Vec<String> {
+        let Ok(lines) = self.0.lock() else {
+            return vec![];
+        };
+
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The piped output of a process
+pub type Output = Box<dyn AsyncRead + Send + Unpin>;
+
+/// A spawned server.js process
+#[async_trait]
+pub trait Process: Debug + Send {
+    /// The process id, `None` once it has exited
+    fn id(&self) -> Option<u32>;
+
+    /// Kills the process and waits for it to exit.
+    async fn kill(&mut self) -> io::Result<()>;
+
+    /// Waits for the process to exit.
+    async fn wait(&mut self) -> io::Result<ExitStatus>;
+
+    /// Takes the output of the process, when it's piped
+    fn take_stdout(&mut self) -> Option<Output>;
+
+    /// Takes the error output of the process, when it's piped
+    fn take_stderr(&mut self) -> Option<Output>;
+}
+
+#[async_trait]
+impl Process for Child {
+    fn id(&self) -> Option<u32> {
+        Child::id(self)
+    }
+
+    async fn kill(&mut self) -> io::Result<()> {
+        Child::kill(self).await
+    }
+
+    async fn wait(&mut self) -> io::Result<ExitStatus> {
+        Child::wait(self).await
+    }
+
+    fn take_stdout(&mut self) -> Option<Output> {
+        self.stdout.take().map(|stdout| Box::new(stdout) as Output)
+    }
+
+    fn take_stderr(&mut self) -> Option<Output> {
+        self.stderr.take().map(|stderr| Box::new(stderr) as Output)
+    }
+}
+
+/// The state of a supervised process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessState {
+    Running,
+    /// The exit code, `None` when it was terminated by a signal or waiting for it failed
+    Exited(Option<i32>),
+}
+
+/// A spawned process and the task waiting for it to exit
+#[derive(Debug)]
+struct Supervised {
+    /// Tells apart the processes started one after another
+    generation: u64,
+    pid: Option<u32>,
+    state: watch::Receiver<ProcessState>,
+    /// Asks the task to kill the process, dropping it kills the process as well
+    kill: oneshot::Sender<()>,
+    task: JoinHandle<io::Result<()>>,
+    /// Whether it has responded to a request, it's not responding anymore when it fails after that
+    responded: bool,
+}
+
+impl Supervised {
+    /// Forwards the output of the process to the log and the tail and waits for it on the runtime.
+    fn spawn(mut process: Box<dyn Process>, generation: u64, log_tail: &LogTail) -> Self {
+        let pid = process.id();
+        for (output, level) in [
+            (process.take_stdout(), Level::Info),
+            (process.take_stderr(), Level::Warn),
+        ] {
+            if let Some(output) = output {
+                tokio::spawn(log_lines(output, level, log_tail.clone()));
+            }
+        }
+
+        let (state_sender, state) = watch::channel(ProcessState::Running);
+        let (kill, kill_requested) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let status = tokio::select! {
+                status = process.wait() => status,
+                _ = kill_requested => match process.kill().await {
+                    Ok(()) => process.wait().await,
+                    Err(err) => Err(err),
+                },
+            };
+
+            let exit_code = match &status {
+                Ok(status) => {
+                    info!("Server exited with {status}. (PID {pid:?})");
+                    status.code()
+                }
+                Err(err) => {
+                    error!("Failed to wait for the server: {err}");
+                    None
+                }
+            };
+            state_sender.send_replace(ProcessState::Exited(exit_code));
+
+            status.map(|_| ())
+        });
+
+        Self {
+            generation,
+            pid,
+            state,
+            kill,
+            task,
+            responded: false,
+        }
+    }
+
+    fn state(&self) -> ProcessState {
+        *self.state.borrow()
+    }
+}
+
+/// Restarts server.js when the process of `generation` exits on its own, see [`Backoff`].
+///
+/// Only a weak reference is kept, in order not to keep server.js running once the [`Server`] is dropped.
+async fn supervise(
+    server: Weak<ServerInner>,
+    generation: u64,
+    mut state: watch::Receiver<ProcessState>,
+    backoff: Backoff,
+) {
+    let started = Instant::now();
+    let exit_code = loop {
+        if let ProcessState::Exited(exit_code) = *state.borrow_and_update() {
+            break exit_code;
+        }
+        if state.changed().await.is_err() {
+            return;
+        }
+    };
+
+    let attempt = {
+        let Some(server) = Server::upgrade(&server) else {
+            return;
+        };
+        // stopped or started again by the service
+        if !server.is_current(generation) {
+            return;
+        }
+        let Ok(mut restarts) = server.inner.restarts.lock() else {
+            return;
+        };
+        if started.elapsed() >= STABLE_RUN {
+            restarts.attempts = 0;
+        }
+        if restarts.attempts >= backoff.max_restarts {
+            error!(
+                "server.js crashed {} times in a row, it's not restarted anymore",
+                restarts.attempts + 1
+            );
+            drop(restarts);
+            server.publish(ServerTrayStatus::Crashed { exit_code }, None);
+            return;
+        }
+        restarts.attempts += 1;
+        restarts.pending = true;
+        let attempt = restarts.attempts;
+        drop(restarts);
+        server.publish(ServerTrayStatus::Restarting { attempt }, None);
+
+        attempt
+    };
+
+    let delay = backoff.delay(attempt);
+    warn!(
+        "server.js exited unexpectedly (exit code {exit_code:?}), restarting it in {delay:?} (attempt {attempt}/{})",
+        backoff.max_restarts
+    );
+    tokio::time::sleep(delay).await;
+
+    let Some(server) = Server::upgrade(&server) else {
+        return;
+    };
+    if let Ok(mut restarts) = server.inner.restarts.lock() {
+        restarts.pending = false;
+    }
+    if server.is_current(generation) {
+        // the failure is logged, the status stays crashed
+        let _ = server.start();
+    }
+}
+
+/// Logs every line of the output, returns the number of lines when it's closed
+async fn log_lines(output: Output, level: Level, log_tail: LogTail) -> usize {
+    let mut lines = BufReader::new(output).lines();
+    let mut count = 0;
+    while let Ok(Some(line)) = lines.next_line().await {
+        log::log!(target: "server.js", level, "{line}");
+        log_tail.push(line);
+        count += 1;
+    }
+
+    count
+}
+
+/// Spawns the server.js process, the tests replace the real node with a fake process.
+pub trait ProcessSpawner: Debug + Send + Sync {
+    fn spawn(&self, command: &mut Command) -> io::Result<Box<dyn Process>>;
+}
+
+/// Spawns the command as a child process of the service
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChildSpawner;
+
+impl ProcessSpawner for ChildSpawner {
+    fn spawn(&self, command: &mut Command) -> io::Result<Box<dyn Process>> {
+        Ok(Box::new(command.spawn()?))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
     server: PathBuf,
+    /// The version of server.js, the bundled one unless it's replaced, see [`Config::with_server_js`]
+    server_version: String,
     node: PathBuf,
     ffmpeg: PathBuf,
     ffprobe: PathBuf,
+    /// Experimental features exported as environment variables
+    experiments: BTreeMap<String, String>,
+    /// Confines server.js when it's set
+    sandbox: Option<Sandbox>,
+    /// Limits the disk writes of server.js when it's set
+    write_limit: Option<WriteLimit>,
+    /// Merged into the settings file of server.js before it's started
+    settings: Option<ServerSettings>,
+    /// The SHA-256 checksum of the bundled server.js, see [`Config::repair`]
+    server_sha256: Option<String>,
+    /// The port server.js listens on
+    port: u16,
+    /// The data directory exported to server.js, its default one is used otherwise
+    data_dir: Option<PathBuf>,
+    /// Restarts the crashed server.js when it's set
+    backoff: Option<Backoff>,
 }
 
 impl Config {
@@ -38,148 +395,796 @@ impl Config {
     /// # Errors
     ///
     /// When one of the binaries required for running the server is missing.
-    pub fn new(directory: PathBuf) -> Result<Self, Error> {
+    pub fn new(directory: PathBuf) -> Result<Self, ServiceError> {
         if directory.is_dir() {
             let server = directory.join("server.js");
             let node = directory.join(Self::node_bin()?);
             let ffmpeg = directory.join(Self::ffmpeg_bin()?);
             let ffprobe = directory.join(Self::ffprobe_bin()?);
 
-            let binaries_paths = [
-                server.clone(),
-                node.clone(),
-                ffmpeg.clone(),
-                ffprobe.clone(),
-            ];
-
-            for path in binaries_paths.iter() {
-                if !path.exists() {
-                    bail!("Failed to locate the file {:?}", path)
-                }
-            }
+            let mut config = Self::from_paths(server, node, ffmpeg, ffprobe)?;
+            config.server_sha256 =
+                Some(BUNDLED_SERVER_SHA256.to_owned()).filter(|sha256| !sha256.is_empty());
 
-            Ok(Self {
-                server,
-                node,
-                ffmpeg,
-                ffprobe,
-            })
+            Ok(config)
         } else {
-            bail!(
+            Err(ServiceError::Config(anyhow!(
                 "The path '{:?}' does not exist or it is not a directory",
                 directory
-            )
+            )))
+        }
+    }
+
+    /// Create a Config from the paths of every binary, e.g. the ones installed by the system packages
+    ///
+    /// # Errors
+    ///
+    /// When one of the binaries required for running the server is missing.
+    pub fn from_paths(
+        server: PathBuf,
+        node: PathBuf,
+        ffmpeg: PathBuf,
+        ffprobe: PathBuf,
+    ) -> Result<Self, ServiceError> {
+        for path in [&server, &node, &ffmpeg, &ffprobe] {
+            if !path.exists() {
+                return Err(ServiceError::MissingBinary(path.clone()));
+            }
         }
+
+        Ok(Self {
+            server,
+            server_version: BUILD_INFO.server_version.to_owned(),
+            node,
+            ffmpeg,
+            ffprobe,
+            experiments: BTreeMap::new(),
+            sandbox: None,
+            write_limit: None,
+            settings: None,
+            server_sha256: None,
+            port: SERVER_PORT,
+            data_dir: None,
+            backoff: Some(Backoff::default()),
+        })
+    }
+
+    /// Create a Config from the system binaries, without any bundled with the service
+    ///
+    /// The runtimes default to the `node`, `ffmpeg` and `ffprobe` found in `PATH`,
+    /// while server.js has to be given, it's not an executable.
+    ///
+    /// # Errors
+    ///
+    /// When server.js is not given or one of the binaries is missing.
+    pub fn from_system(
+        server: Option<PathBuf>,
+        node: Option<PathBuf>,
+        ffmpeg: Option<PathBuf>,
+        ffprobe: Option<PathBuf>,
+    ) -> Result<Self, ServiceError> {
+        let server = server
+            .ok_or_else(|| ServiceError::Config(anyhow!("The path of server.js is required")))?;
+        let find = |path: Option<PathBuf>, name: &str| {
+            path.or_else(|| find_in_path(name))
+                .ok_or_else(|| ServiceError::MissingBinary(name.into()))
+        };
+
+        Self::from_paths(
+            server,
+            find(node, "node")?,
+            find(ffmpeg, "ffmpeg")?,
+            find(ffprobe, "ffprobe")?,
+        )
+    }
+
+    /// Set the experiments exported to server.js as environment variables
+    pub fn with_experiments(mut self, experiments: BTreeMap<String, String>) -> Self {
+        self.experiments = experiments;
+        self
+    }
+
+    /// Confine server.js with the given sandbox
+    pub fn with_sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Limit the disk writes of server.js, e.g. to its cache on a slow disk
+    pub fn with_write_limit(mut self, write_limit: WriteLimit) -> Self {
+        self.write_limit = Some(write_limit);
+        self
+    }
+
+    /// Set the settings of server.js, e.g. its cache size
+    pub fn with_settings(mut self, settings: ServerSettings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Set the data directory of server.js, e.g. the one of a profile, see [`data_dir`]
+    pub fn with_data_dir(mut self, data_dir: PathBuf) -> Self {
+        self.data_dir = Some(data_dir);
+        self
+    }
+
+    /// Set how the crashed server.js is restarted, `None` leaves it crashed
+    pub fn with_backoff(mut self, backoff: Option<Backoff>) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Run the server.js of the version at the path instead of the bundled one, e.g. the one of a server-only update.
+    ///
+    /// It's verified when it's installed, so it's not repaired.
+    pub fn with_server_js(mut self, server: PathBuf, version: String) -> Self {
+        self.server = server;
+        self.server_version = version;
+        self.server_sha256 = None;
+        self
+    }
+
+    /// Set the port server.js listens on instead of [`SERVER_PORT`]
+    ///
+    /// # Errors
+    ///
+    /// When the port is `0`, server.js has to listen on a known port.
+    pub fn with_port(mut self, port: u16) -> Result<Self, ServiceError> {
+        if port == 0 {
+            return Err(ServiceError::Config(anyhow!(
+                "The port of server.js can't be 0"
+            )));
+        }
+
+        self.port = port;
+        Ok(self)
+    }
+
+    /// The port server.js listens on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The config of another server.js with the same binaries and settings, listening on the `port`
+    /// with its own `data_dir`, see [`ServerPool`](crate::pool::ServerPool).
+    ///
+    /// # Errors
+    ///
+    /// When the port is `0`.
+    pub fn for_instance(&self, port: u16, data_dir: PathBuf) -> Result<Self, ServiceError> {
+        let mut config = self.clone().with_port(port)?;
+        config.settings = self
+            .settings
+            .as_ref()
+            .map(|settings| ServerSettings::new(&data_dir, settings.values().clone()));
+        config.sandbox = self
+            .sandbox
+            .as_ref()
+            .map(|_| Sandbox::for_server(data_dir.clone()));
+        config.write_limit = match &self.write_limit {
+            Some(write_limit) => Some(WriteLimit::new(data_dir.clone(), write_limit.rate())?),
+            None => None,
+        };
+        config.data_dir = Some(data_dir);
+
+        Ok(config)
+    }
+
+    /// The ffmpeg used by server.js
+    pub fn ffmpeg(&self) -> &Path {
+        &self.ffmpeg
+    }
+
+    /// The node runtime of server.js
+    pub fn node(&self) -> &Path {
+        &self.node
+    }
+
+    /// The local address server.js listens on, e.g. `http://127.0.0.1:11470`
+    pub fn url(&self) -> String {
+        server_url(self.port)
+    }
+
+    /// Verifies the bundled server.js and downloads it again when it's truncated or changed,
+    /// e.g. after an interrupted update, returns whether it was repaired.
+    ///
+    /// Only the bundled server.js is verified, against the checksum recorded by the build.
+    ///
+    /// # Errors
+    ///
+    /// When the download fails or server.js can't be replaced, e.g. in a read-only installation.
+    pub async fn repair(&self, tls_roots: &Roots) -> Result<bool, ServiceError> {
+        let Some(expected_sha256) = self.server_sha256.as_deref() else {
+            return Ok(false);
+        };
+
+        let server = self.server.clone();
+        let sha256 = tokio::task::spawn_blocking(move || {
+            let mut sha256 = Sha256::new();
+            io::copy(&mut std::fs::File::open(server)?, &mut sha256)?;
+            Ok::<_, io::Error>(format!("{:x}", sha256.finalize()))
+        })
+        .await
+        .map_err(|err| ServiceError::ServerRepair(err.into()))?;
+        let actual_sha256 = match sha256 {
+            Ok(sha256) if sha256.eq_ignore_ascii_case(expected_sha256) => return Ok(false),
+            Ok(sha256) => sha256,
+            Err(err) => err.to_string(),
+        };
+
+        let version = BUILD_INFO.server_version;
+        warn!(
+            "{} is corrupted, expected SHA-256 {expected_sha256} but got {actual_sha256}, downloading server.js {version} again",
+            self.server.display()
+        );
+        let download = async {
+            let url = format!("{SERVER_DOWNLOAD_URL}{version}/desktop/server.js");
+            let contents = tls_roots
+                .client_builder()
+                .build()?
+                .get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+                .context(format!("Failed to download {url}"))?;
+            let sha256 = hex::encode(Sha256::digest(&contents));
+            if !sha256.eq_ignore_ascii_case(expected_sha256) {
+                bail!("{url} failed the integrity check, got SHA-256 {sha256}");
+            }
+
+            // replaced at once, it can't be truncated again
+            let part = self.server.with_extension("js.part");
+            tokio::fs::write(&part, contents)
+                .await
+                .context(format!("Failed to write {}", part.display()))?;
+            tokio::fs::rename(&part, &self.server)
+                .await
+                .context(format!("Failed to replace {}", self.server.display()))?;
+
+            Ok(())
+        };
+        download.await.map_err(ServiceError::ServerRepair)?;
+        info!(
+            "Repaired {} with server.js {version}",
+            self.server.display()
+        );
+
+        Ok(true)
     }
-    fn node_bin() -> Result<&'static str, Error> {
+
+    fn node_bin() -> Result<&'static str, ServiceError> {
         match std::env::consts::OS {
             "linux" | "macos" => Ok("stremio-runtime"),
             "windows" => Ok("stremio-runtime.exe"),
-            os => bail!("Operating system {} is not supported", os),
+            os => Err(ServiceError::Config(anyhow!(
+                "Operating system {os} is not supported"
+            ))),
         }
     }
-    fn ffmpeg_bin() -> Result<&'static str, Error> {
+    fn ffmpeg_bin() -> Result<&'static str, ServiceError> {
         match std::env::consts::OS {
             "linux" | "macos" => Ok("ffmpeg"),
             "windows" => Ok("ffmpeg.exe"),
-            os => bail!("Operating system {} is not supported", os),
+            os => Err(ServiceError::Config(anyhow!(
+                "Operating system {os} is not supported"
+            ))),
         }
     }
-    fn ffprobe_bin() -> Result<&'static str, Error> {
+    fn ffprobe_bin() -> Result<&'static str, ServiceError> {
         match std::env::consts::OS {
             "linux" | "macos" => Ok("ffprobe"),
             "windows" => Ok("ffprobe.exe"),
-            os => bail!("Operating system {} is not supported", os),
+            os => Err(ServiceError::Config(anyhow!(
+                "Operating system {os} is not supported"
+            ))),
         }
     }
 }
 
 impl Server {
     pub fn new(config: Config) -> Self {
+        Self::with_spawner(config, ChildSpawner)
+    }
+
+    /// Create a Server spawning server.js with the given spawner instead of [`ChildSpawner`]
+    pub fn with_spawner(config: Config, spawner: impl ProcessSpawner + 'static) -> Self {
         Server {
             inner: Arc::new(ServerInner {
+                server_js: Mutex::new((config.server.clone(), config.server_version.clone())),
+                version: Mutex::new(config.server_version.clone()),
+                settings: Mutex::new(config.settings.clone()),
+                status: watch::channel(ServerInfo::new(
+                    ServerTrayStatus::Stopped,
+                    config.url(),
+                    config.server_version.clone(),
+                    None,
+                ))
+                .0,
                 config,
+                spawner: Box::new(spawner),
                 process: Default::default(),
+                log_tail: Default::default(),
+                spawned: Default::default(),
+                restarts: Default::default(),
             }),
         }
     }
 
-    pub fn start(&self) -> Result<(), Error> {
-        let mut command = Command::new(&self.inner.config.node);
+    /// Spawns server.js, unless it's already running, and returns without waiting for it.
+    ///
+    /// The output of server.js is forwarded to the log, it has to be called in a Tokio runtime.
+    pub fn start(&self) -> Result<(), ServiceError> {
+        // the running server.js keeps its settings and its command
+        let mut process = self.process()?;
+        match process.as_ref().map(Supervised::state) {
+            Some(ProcessState::Running) => {
+                info!("Only 1 instance of server can run for an instance, do nothing.");
+
+                return Ok(());
+            }
+            // a crashed server.js is started again
+            Some(ProcessState::Exited(_)) => info!("Server has exited, starting it again."),
+            None => {}
+        }
+
+        let node = &self.inner.config.node;
+        let mut command = match &self.inner.config.write_limit {
+            Some(write_limit) => match write_limit.command(node) {
+                Some(command) => {
+                    info!(
+                        "Limiting the writes of server.js to {}/s",
+                        write_limit.rate()
+                    );
+                    command
+                }
+                None => Command::new(node),
+            },
+            None => Command::new(node),
+        };
         #[cfg(target_os = "windows")]
         command.creation_flags(CREATE_NO_WINDOW);
+        // the experiments can't override the variables of the service
+        command.envs(&self.inner.config.experiments);
         command.env("FFMPEG_BIN", &self.inner.config.ffmpeg);
         command.env("FFPROBE_BIN", &self.inner.config.ffprobe);
-        command.arg(&self.inner.config.server);
+        command.env(PORT_ENV, self.inner.config.port.to_string());
+        if let Some(data_dir) = &self.inner.config.data_dir {
+            command.env(DATA_DIR_ENV, data_dir);
+        }
+        let (server_js, version) = match self.inner.server_js.lock() {
+            Ok(server_js) => server_js.clone(),
+            Err(_) => (
+                self.inner.config.server.clone(),
+                self.inner.config.server_version.clone(),
+            ),
+        };
+        command.arg(&server_js);
+        command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
 
-        info!("Starting server.js: {:#?}", command);
+        if !self.inner.config.experiments.is_empty() {
+            info!("Enabled experiments: {:?}", self.inner.config.experiments);
+        }
+        // server.js is started with its current settings then
+        let settings = match self.inner.settings.lock() {
+            Ok(settings) => settings.clone(),
+            Err(_) => self.inner.config.settings.clone(),
+        };
+        if let Some(settings) = settings.filter(|settings| !settings.values().is_empty()) {
+            match settings.apply() {
+                Ok(()) => info!(
+                    "server.js settings: {}",
+                    Value::from_iter(settings.values().clone())
+                ),
+                Err(err) => warn!("Failed to set the server.js settings: {err:#}"),
+            }
+        }
+        // inherited by server.js
+        #[cfg(unix)]
+        if let Err(err) = crate::util::raise_open_files_limit() {
+            warn!("Failed to raise the limit of open files: {err}");
+        }
+        let config = &self.inner.config;
+        let sandbox = config
+            .sandbox
+            .clone()
+            .map(|sandbox| {
+                sandbox.with_readable([
+                    config.node.clone(),
+                    server_js,
+                    config.ffmpeg.clone(),
+                    config.ffprobe.clone(),
+                ])
+            })
+            .filter(|sandbox| sandbox.apply(&mut command));
+        if let Some(sandbox) = &sandbox {
+            info!("server.js can write only to {:?}", sandbox.writable());
+        }
 
-        if self
-            .inner
-            .process
-            .lock()
-            .map_err(|_| anyhow!("Failed to lock server.js child process"))?
-            .get()
-            .is_none()
-        {
-            match command.spawn() {
-                Ok(new_process) => {
-                    let process_pid = new_process.id();
-                    info!("Server started. (PID {:?})", process_pid);
-
-                    self.inner
-                        .process
-                        .lock()
-                        .map_err(|_| anyhow!("Failed to lock server.js child process"))?
-                        .set(new_process)
-                        .expect("Should always be empty, we've just checked after all.")
-                }
-                Err(err) => {
-                    error!("Server didn't start: {err}");
+        info!("Starting server.js: {:#?}", command.as_std());
 
-                    bail!("Server didn't start: {err}")
-                }
+        let new_process = self.inner.spawner.spawn(&mut command).map_err(|err| {
+            error!("Server didn't start: {err}");
+
+            ServiceError::ServerSpawn(err)
+        })?;
+        // it's killed once it's dropped
+        if let Some(sandbox) = &sandbox {
+            sandbox.confine(new_process.id()).map_err(|err| {
+                error!("server.js can't be confined: {err}");
+
+                ServiceError::ServerSpawn(err)
+            })?;
+        }
+        info!("Server started. (PID {:?})", new_process.id());
+        if let Ok(mut current) = self.inner.version.lock() {
+            *current = version;
+        }
+        let generation = self.inner.spawned.fetch_add(1, Ordering::SeqCst) + 1;
+        let supervised = Supervised::spawn(new_process, generation, &self.inner.log_tail);
+        if let Some(backoff) = self.inner.config.backoff {
+            tokio::spawn(supervise(
+                Arc::downgrade(&self.inner),
+                generation,
+                supervised.state.clone(),
+                backoff,
+            ));
+        }
+        self.publish(ServerTrayStatus::Starting, supervised.pid);
+        *process = Some(supervised);
+
+        Ok(())
+    }
+
+    /// The status of server.js, it's sent on every change: when it's started, stopped, restarted after a crash
+    /// or left crashed, and when [`Server::info`] finds it running or not responding, e.g. from the health check.
+    ///
+    /// The tray, the control API and the embedders follow the status with it.
+    pub fn subscribe(&self) -> watch::Receiver<ServerInfo> {
+        self.inner.status.subscribe()
+    }
+
+    /// Sends the status to the subscribers, unless it's the current one.
+    fn publish(&self, status: ServerTrayStatus, pid: Option<u32>) {
+        let info = ServerInfo::new(status, self.url(), self.version(), pid);
+        self.inner.status.send_if_modified(|current| {
+            let is_modified = *current != info;
+            if is_modified {
+                *current = info;
             }
-        } else {
-            info!("Only 1 instance of server can run for an instance, do nothing.")
+
+            is_modified
+        });
+    }
+
+    fn upgrade(inner: &Weak<ServerInner>) -> Option<Self> {
+        inner.upgrade().map(|inner| Self { inner })
+    }
+
+    /// Whether the process of `generation` is the one started last and it was not stopped
+    fn is_current(&self, generation: u64) -> bool {
+        matches!(self.process().as_deref(), Ok(Some(process)) if process.generation == generation)
+    }
+
+    /// The attempt of the scheduled restart of the crashed server.js
+    fn pending_restart(&self) -> Option<u32> {
+        let restarts = self.inner.restarts.lock().ok()?;
+
+        restarts.pending.then_some(restarts.attempts)
+    }
+
+    /// Whether server.js was started by this instance and it hasn't exited since.
+    pub fn is_running(&self) -> bool {
+        matches!(self.state(), Some((_, ProcessState::Running)))
+    }
+
+    /// The local address server.js listens on
+    pub fn url(&self) -> String {
+        self.inner.config.url()
+    }
+
+    /// The port server.js listens on
+    pub fn port(&self) -> u16 {
+        self.inner.config.port
+    }
+
+    /// The version of the server.js started last, or the one to be started
+    pub fn version(&self) -> String {
+        match self.inner.version.lock() {
+            Ok(version) => version.clone(),
+            Err(_) => self.inner.config.server_version.clone(),
         }
+    }
+
+    /// The current value of the server.js setting, see [`update_settings`](Self::update_settings)
+    pub fn setting(&self, key: &str) -> Option<Value> {
+        let settings = self.inner.settings.lock().ok()?;
+
+        settings.as_ref()?.values().get(key).cloned()
+    }
+
+    /// Changes the settings of server.js, they're kept when it's restarted.
+    ///
+    /// The running server.js applies them at once, e.g. a new download speed limit,
+    /// without restarting it.
+    ///
+    /// # Errors
+    ///
+    /// When the running server.js doesn't accept them, they're set on its next start anyway.
+    pub async fn update_settings(
+        &self,
+        values: BTreeMap<String, Value>,
+    ) -> Result<(), ServiceError> {
+        if let Ok(mut settings) = self.inner.settings.lock() {
+            if let Some(settings) = settings.as_mut() {
+                settings.extend(values.clone());
+            }
+        }
+        if !self.is_running() {
+            return Ok(());
+        }
+
+        local_client()
+            .post(format!("{}/settings", self.url()))
+            .json(&values)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("Failed to change the server.js settings")
+            .map_err(ServiceError::Other)?;
+        info!(
+            "Changed the server.js settings: {}",
+            Value::from_iter(values)
+        );
 
         Ok(())
     }
 
-    pub fn stop(&mut self) -> Result<(), Error> {
-        match self
-            .inner
+    /// The status of the server.js started by this instance.
+    ///
+    /// A running server.js which doesn't respond is [`ServerTrayStatus::Unhealthy`] once it has responded before,
+    /// until then it's still starting.
+    pub async fn info(&self) -> ServerInfo {
+        let url = self.url();
+        let version = self.version();
+        let info = match self.state() {
+            None => ServerInfo::new(ServerTrayStatus::Stopped, url, version, None),
+            // the id of an exited process may be reused
+            Some((_, ProcessState::Exited(exit_code))) => match self.pending_restart() {
+                Some(attempt) => {
+                    ServerInfo::new(ServerTrayStatus::Restarting { attempt }, url, version, None)
+                }
+                None => {
+                    ServerInfo::new(ServerTrayStatus::Crashed { exit_code }, url, version, None)
+                }
+            },
+            Some((pid, ProcessState::Running)) => {
+                // the running process is the one spawned last
+                let generation = self.inner.spawned.load(Ordering::SeqCst);
+                let status = if is_ready(&url).await {
+                    self.set_responded(generation);
+                    ServerTrayStatus::Running
+                } else if self.has_responded() {
+                    ServerTrayStatus::Unhealthy
+                } else {
+                    ServerTrayStatus::Starting
+                };
+
+                ServerInfo::new(status, url, version, pid)
+            }
+        };
+        self.publish(info.status, info.pid);
+
+        info
+    }
+
+    /// The id and the state of the process started last, `None` when it was not started or it was stopped.
+    fn state(&self) -> Option<(Option<u32>, ProcessState)> {
+        self.process()
+            .ok()?
+            .as_ref()
+            .map(|process| (process.pid, process.state()))
+    }
+
+    /// Whether the process started last has responded to a request, see [`Server::info`]
+    fn has_responded(&self) -> bool {
+        matches!(self.process().as_deref(), Ok(Some(process)) if process.responded)
+    }
+
+    fn set_responded(&self, generation: u64) {
+        if let Ok(Some(process)) = self.process().as_deref_mut() {
+            if process.generation == generation {
+                process.responded = true;
+            }
+        }
+    }
+
+    fn process(&self) -> Result<MutexGuard<'_, Option<Supervised>>, ServiceError> {
+        self.inner
             .process
             .lock()
-            .map_err(|_| anyhow!("Failed to lock server.js child process"))?
-            .take()
-        {
-            Some(mut child_process) => {
-                child_process
-                    .kill()
-                    .expect("Failed to stop the server process.");
-                info!("Server was shut down. (PID #{})", child_process.id());
-            }
-            None => info!("Server was not running, do nothing."),
+            .map_err(|_| ServiceError::Other(anyhow!("Failed to lock server.js child process")))
+    }
+
+    /// Kills server.js and waits for it to exit.
+    pub async fn stop(&self) -> Result<(), ServiceError> {
+        let process = self.process()?.take();
+        if let Ok(mut restarts) = self.inner.restarts.lock() {
+            *restarts = Restarts::default();
         }
+        let Some(Supervised {
+            pid, kill, task, ..
+        }) = process
+        else {
+            info!("Server was not running, do nothing.");
+            self.publish(ServerTrayStatus::Stopped, None);
+
+            return Ok(());
+        };
+
+        // the task has already finished when server.js has exited on its own
+        let _ = kill.send(());
+        task.await
+            .map_err(io::Error::other)
+            .and_then(|killed| killed)
+            .map_err(ServiceError::ServerStop)?;
+        info!("Server was shut down. (PID #{pid:?})");
+        self.publish(ServerTrayStatus::Stopped, None);
 
         Ok(())
     }
+
+    /// Stops server.js, when it's running, and starts it again.
+    pub async fn restart(&self) -> Result<(), ServiceError> {
+        self.stop().await?;
+        self.start()
+    }
+
+    /// Runs the given server.js of the version from now on, the running one is restarted with it.
+    ///
+    /// A stopped or a crashed server.js is left as it is.
+    pub async fn set_server_js(
+        &self,
+        server_js: PathBuf,
+        version: String,
+    ) -> Result<(), ServiceError> {
+        if let Ok(mut current) = self.inner.server_js.lock() {
+            *current = (server_js, version);
+        }
+
+        if self.is_running() {
+            self.restart().await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The last `count` lines of the output of server.js, at most [`LOG_TAIL_LINES`], the oldest first.
+    ///
+    /// The output of the previous processes is kept after a crash or a restart.
+    pub fn log_tail(&self, count: usize) -> Vec<String> {
+        self.inner.log_tail.last(count)
+    }
 }
 
-impl Drop for Server {
-    fn drop(&mut self) {
-        match self.stop() {
-            Ok(()) => {}
-            Err(err) => error!("Failed to stop server on Drop, reason: {err}"),
+/// The directory of the settings and the cache of server.js.
+///
+/// It's `APP_PATH` when set, by default `~/.stremio-server`.
+/// The one of a profile has a `-<profile>` suffix, e.g. `~/.stremio-server-beta`.
+pub fn data_dir(home: &Path, profile: Option<&str>) -> PathBuf {
+    let data_dir = std::env::var_os(DATA_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".stremio-server"));
+
+    match profile {
+        Some(profile) => {
+            let mut data_dir = data_dir.into_os_string();
+            data_dir.push(format!("-{profile}"));
+            data_dir.into()
         }
+        None => data_dir,
     }
 }
 
+/// The local address of a server.js listening on the port
+pub fn server_url(port: u16) -> String {
+    format!("http://127.0.0.1:{port}")
+}
+
+/// The client of the requests to server.js, they never go through the proxies of the environment
+fn local_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .expect("Should build the client")
+}
+
+/// Whether the server.js at `url` is up and responding to requests, see [`Server::url`].
+///
+/// This checks any running server.js, not only the one started by this instance.
+pub async fn is_ready(url: &str) -> bool {
+    let response = local_client()
+        .get(format!("{url}/settings"))
+        .timeout(Duration::from_secs(2))
+        .send()
+        .await;
+
+    matches!(response, Ok(response) if response.status().is_success())
+}
+
+/// Whether the server.js at `url` is not downloading any torrent, `None` when it can't tell.
+///
+/// Its `/stats.json` has the stats of every torrent it streams, e.g. their `downloadSpeed`.
+pub async fn is_idle(url: &str) -> Option<bool> {
+    let stats = local_client()
+        .get(format!("{url}/stats.json"))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .ok()?
+        .json::<Value>()
+        .await
+        .ok()?;
+    let mut torrents: Box<dyn Iterator<Item = &Value>> = match &stats {
+        Value::Object(torrents) => Box::new(torrents.values()),
+        Value::Array(torrents) => Box::new(torrents.iter()),
+        _ => return None,
+    };
+
+    Some(torrents.all(|torrent| {
+        torrent
+            .get("downloadSpeed")
+            .and_then(Value::as_f64)
+            .is_none_or(|speed| speed <= 0.0)
+    }))
+}
+
+/// Asks the server.js at `url` to start fetching the torrent of the magnet link from its peer sources,
+/// so it's ready sooner when the player requests it.
+pub async fn create_torrent(url: &str, magnet: &Magnet) -> Result<(), anyhow::Error> {
+    let body = serde_json::json!({
+        "torrent": { "infoHash": magnet.info_hash },
+        "peerSearch": { "sources": magnet.announce(), "min": 40, "max": 200 },
+    });
+    local_client()
+        .post(format!("{url}/{}/create", magnet.info_hash))
+        .json(&body)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .context(format!("Failed to create the torrent {}", magnet.info_hash))?;
+
+    Ok(())
+}
+
+/// Waits for the server.js at `url` to respond to requests.
+///
+/// Returns `false` if it's still not ready after the timeout.
+pub async fn wait_until_ready(url: &str, timeout: Duration) -> bool {
+    let health_check = async {
+        while !is_ready(url).await {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    };
+
+    tokio::time::timeout(timeout, health_check).await.is_ok()
+}
+
 #[cfg(test)]
 mod test {
-    use super::Server;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use tokio::sync::Notify;
+
+    use super::*;
+    use crate::server_settings::{DOWNLOAD_SPEED_LIMIT, SETTINGS_FILE_NAME};
 
     fn is_sync<T: Sync>() {}
     fn is_send<T: Send>() {}
@@ -189,4 +1194,561 @@ mod test {
         is_sync::<Server>();
         is_send::<Server>();
     }
+
+    #[derive(Debug, Default)]
+    struct FakeProcess {
+        killed: Arc<AtomicBool>,
+        exit: Arc<Notify>,
+    }
+
+    #[async_trait]
+    impl Process for FakeProcess {
+        fn id(&self) -> Option<u32> {
+            Some(42)
+        }
+
+        async fn kill(&mut self) -> io::Result<()> {
+            self.killed.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn wait(&mut self) -> io::Result<ExitStatus> {
+            if !self.killed.load(Ordering::SeqCst) {
+                self.exit.notified().await;
+            }
+            Ok(ExitStatus::default())
+        }
+
+        fn take_stdout(&mut self) -> Option<Output> {
+            Some(Box::new(io::Cursor::new("EngineFS server started\n")))
+        }
+
+        fn take_stderr(&mut self) -> Option<Output> {
+            None
+        }
+    }
+
+    /// Records the spawned commands, fails to spawn when there's no node
+    #[derive(Debug, Default, Clone)]
+    struct FakeSpawner {
+        spawned: Arc<Mutex<Vec<String>>>,
+        killed: Arc<AtomicBool>,
+        /// Exits the spawned process
+        exit: Arc<Notify>,
+    }
+
+    impl ProcessSpawner for FakeSpawner {
+        fn spawn(&self, command: &mut Command) -> io::Result<Box<dyn Process>> {
+            let command = command.as_std();
+            if command.get_program().is_empty() {
+                return Err(io::ErrorKind::NotFound.into());
+            }
+
+            let envs = command
+                .get_envs()
+                .map(|(key, value)| {
+                    format!(
+                        "{}={}",
+                        key.to_string_lossy(),
+                        value.unwrap_or_default().to_string_lossy()
+                    )
+                })
+                .collect::<Vec<_>>();
+            self.spawned.lock().unwrap().push(envs.join(" "));
+
+            self.killed.store(false, Ordering::SeqCst);
+            Ok(Box::new(FakeProcess {
+                killed: self.killed.clone(),
+                exit: self.exit.clone(),
+            }))
+        }
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Timed out");
+    }
+
+    fn config(node: &str) -> Config {
+        Config {
+            server: "server.js".into(),
+            server_version: BUILD_INFO.server_version.to_owned(),
+            node: node.into(),
+            ffmpeg: "ffmpeg".into(),
+            ffprobe: "ffprobe".into(),
+            experiments: BTreeMap::from([
+                ("EXPERIMENT".to_owned(), "1".to_owned()),
+                ("FFMPEG_BIN".to_owned(), "/tmp/evil".to_owned()),
+            ]),
+            sandbox: None,
+            write_limit: None,
+            settings: None,
+            server_sha256: None,
+            port: 11480,
+            data_dir: None,
+            backoff: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_starts_once_and_stops() {
+        let spawner = FakeSpawner::default();
+        let server = Server::with_spawner(config("node"), spawner.clone());
+
+        server.start().expect("Should start");
+        server.start().expect("Should do nothing");
+        assert_eq!(
+            vec!["EXPERIMENT=1 FFMPEG_BIN=ffmpeg FFPROBE_BIN=ffprobe HTTP_PORT=11480".to_owned()],
+            *spawner.spawned.lock().unwrap()
+        );
+
+        server.stop().await.expect("Should stop");
+        assert!(spawner.killed.load(Ordering::SeqCst));
+        assert!(!server.is_running());
+
+        server.start().expect("Should start again");
+        assert_eq!(2, spawner.spawned.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_instance_config() {
+        let values = BTreeMap::from([("cacheSize".to_owned(), Value::from(0))]);
+        let config = config("node")
+            .with_settings(ServerSettings::new(
+                Path::new("/home/user/.stremio-server"),
+                values.clone(),
+            ))
+            .with_sandbox(Sandbox::for_server("/home/user/.stremio-server".into()));
+        let data_dir = PathBuf::from("/home/user/.stremio-server-11490");
+
+        let instance = config
+            .for_instance(11490, data_dir.clone())
+            .expect("Should use the port");
+        assert_eq!(11490, instance.port());
+        assert_eq!(Some(&data_dir), instance.data_dir.as_ref());
+        assert_eq!(
+            Some(ServerSettings::new(&data_dir, values)),
+            instance.settings,
+            "The settings are merged into the file of the instance"
+        );
+        assert_eq!(
+            Some(&data_dir),
+            instance
+                .sandbox
+                .as_ref()
+                .map(|sandbox| &sandbox.writable()[0])
+        );
+        assert_eq!(11480, config.port(), "The main config is kept");
+        assert!(config.for_instance(0, data_dir).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_profile_data_dir_is_exported() {
+        let spawner = FakeSpawner::default();
+        let home = Path::new("/home/user");
+        let data_dir = data_dir(home, Some("beta"));
+        if std::env::var_os(DATA_DIR_ENV).is_none() {
+            assert_eq!(home.join(".stremio-server-beta"), data_dir);
+        }
+        let server = Server::with_spawner(
+            config("node").with_data_dir(data_dir.clone()),
+            spawner.clone(),
+        );
+
+        server.start().expect("Should start");
+        assert!(spawner.spawned.lock().unwrap()[0]
+            .starts_with(&format!("APP_PATH={} ", data_dir.display())));
+        server.stop().await.expect("Should stop");
+    }
+
+    #[tokio::test]
+    async fn test_updated_settings_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config("node").with_settings(ServerSettings::new(dir.path(), BTreeMap::new()));
+        let server = Server::with_spawner(config, FakeSpawner::default());
+
+        let limit = (DOWNLOAD_SPEED_LIMIT.to_owned(), Value::from(1 << 20));
+        server
+            .update_settings(BTreeMap::from([limit]))
+            .await
+            .expect("Should be set on the next start");
+        assert_eq!(
+            Some(Value::from(1 << 20)),
+            server.setting(DOWNLOAD_SPEED_LIMIT)
+        );
+
+        server.start().expect("Should start");
+        let settings: Value =
+            serde_json::from_slice(&std::fs::read(dir.path().join(SETTINGS_FILE_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(
+            serde_json::json!({ DOWNLOAD_SPEED_LIMIT: 1 << 20 }),
+            settings
+        );
+
+        // the running server.js changed them itself
+        std::fs::write(dir.path().join(SETTINGS_FILE_NAME), "{}").unwrap();
+        server.start().expect("Should do nothing");
+        assert_eq!(
+            "{}",
+            std::fs::read_to_string(dir.path().join(SETTINGS_FILE_NAME)).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dropped_server_is_killed() {
+        let spawner = FakeSpawner::default();
+        let server = Server::with_spawner(config("node"), spawner.clone());
+
+        server.start().expect("Should start");
+        drop(server.clone());
+        assert!(server.is_running());
+
+        drop(server);
+        wait_for(|| spawner.killed.load(Ordering::SeqCst)).await;
+    }
+
+    #[tokio::test]
+    async fn test_version_of_the_started_server_js_is_reported() {
+        let server = Server::with_spawner(config("node"), FakeSpawner::default());
+        assert_eq!(
+            BUILD_INFO.server_version,
+            server.subscribe().borrow().version
+        );
+
+        server
+            .set_server_js("updated/server.js".into(), "v4.20.9".to_owned())
+            .await
+            .expect("Should be started on the next start");
+        assert_eq!(
+            BUILD_INFO.server_version,
+            server.version(),
+            "Not started yet"
+        );
+
+        server.start().expect("Should start");
+        assert_eq!("v4.20.9", server.subscribe().borrow().version);
+    }
+
+    #[tokio::test]
+    async fn test_output_is_logged() {
+        let output = Box::new(io::Cursor::new("EngineFS server started\nlistening\n"));
+        let log_tail = LogTail::default();
+
+        assert_eq!(2, log_lines(output, Level::Info, log_tail.clone()).await);
+        assert_eq!(vec!["listening".to_owned()], log_tail.last(1));
+        assert_eq!(2, log_tail.last(10).len());
+
+        for line in 0..LOG_TAIL_LINES {
+            log_tail.push(line.to_string());
+        }
+        let lines = log_tail.last(LOG_TAIL_LINES + 1);
+        assert_eq!(LOG_TAIL_LINES, lines.len());
+        assert_eq!("0", lines[0]);
+    }
+
+    #[tokio::test]
+    async fn test_exited_server_starts_again() {
+        let spawner = FakeSpawner::default();
+        let server = Server::with_spawner(config("node"), spawner.clone());
+
+        server.start().expect("Should start");
+        assert!(server.is_running());
+
+        spawner.exit.notify_one();
+        wait_for(|| !server.is_running()).await;
+        let info = server.info().await;
+        assert_eq!(
+            ServerTrayStatus::Crashed { exit_code: Some(0) },
+            info.status
+        );
+        assert_eq!(None, info.pid);
+
+        server.start().expect("Should start again");
+        assert!(server.is_running());
+        assert_eq!(Some(42), server.info().await.pid);
+        assert_eq!(2, spawner.spawned.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_crashed_server_is_restarted() {
+        let spawner = FakeSpawner::default();
+        let backoff = Backoff {
+            initial: Duration::from_millis(10),
+            max: Duration::from_millis(10),
+            max_restarts: 2,
+        };
+        let server =
+            Server::with_spawner(config("node").with_backoff(Some(backoff)), spawner.clone());
+        let spawned = || spawner.spawned.lock().unwrap().len();
+
+        server.start().expect("Should start");
+        for restarts in 1..=2 {
+            spawner.exit.notify_one();
+            wait_for(|| spawned() == restarts + 1 && server.is_running()).await;
+        }
+
+        // gives up after the last restart
+        spawner.exit.notify_one();
+        wait_for(|| !server.is_running()).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(3, spawned());
+        assert_eq!(
+            ServerTrayStatus::Crashed { exit_code: Some(0) },
+            server.info().await.status
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_is_published() {
+        let spawner = FakeSpawner::default();
+        let backoff = Backoff {
+            initial: Duration::from_millis(50),
+            max: Duration::from_millis(50),
+            max_restarts: 1,
+        };
+        let server =
+            Server::with_spawner(config("node").with_backoff(Some(backoff)), spawner.clone());
+        let mut status = server.subscribe();
+        assert_eq!(ServerTrayStatus::Stopped, status.borrow().status);
+
+        server.start().expect("Should start");
+        let info = next_status(&mut status).await;
+        assert_eq!(
+            (ServerTrayStatus::Starting, Some(42)),
+            (info.status, info.pid)
+        );
+
+        spawner.exit.notify_one();
+        assert_eq!(
+            ServerTrayStatus::Restarting { attempt: 1 },
+            next_status(&mut status).await.status
+        );
+        assert_eq!(
+            ServerTrayStatus::Starting,
+            next_status(&mut status).await.status
+        );
+
+        spawner.exit.notify_one();
+        assert_eq!(
+            ServerTrayStatus::Crashed { exit_code: Some(0) },
+            next_status(&mut status).await.status
+        );
+
+        server.stop().await.expect("Should stop");
+        assert_eq!(
+            ServerTrayStatus::Stopped,
+            next_status(&mut status).await.status
+        );
+    }
+
+    async fn next_status(status: &mut watch::Receiver<ServerInfo>) -> ServerInfo {
+        tokio::time::timeout(Duration::from_secs(5), status.changed())
+            .await
+            .expect("Timed out")
+            .expect("Should be sent");
+
+        status.borrow().clone()
+    }
+
+    #[tokio::test]
+    async fn test_stopped_server_is_not_restarted() {
+        let spawner = FakeSpawner::default();
+        let backoff = Backoff {
+            initial: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let server =
+            Server::with_spawner(config("node").with_backoff(Some(backoff)), spawner.clone());
+
+        server.start().expect("Should start");
+        spawner.exit.notify_one();
+        wait_for(|| !server.is_running()).await;
+        wait_for(|| server.pending_restart() == Some(1)).await;
+        assert_eq!(
+            ServerTrayStatus::Restarting { attempt: 1 },
+            server.info().await.status
+        );
+
+        server.stop().await.expect("Should stop");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(1, spawner.spawned.lock().unwrap().len());
+        assert_eq!(ServerTrayStatus::Stopped, server.info().await.status);
+    }
+
+    #[tokio::test]
+    async fn test_hanging_server_is_unhealthy() {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let responding = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+                    .await;
+            }
+        });
+
+        let spawner = FakeSpawner::default();
+        let server = Server::with_spawner(config("node").with_port(port).unwrap(), spawner);
+        server.start().expect("Should start");
+        assert_eq!(ServerTrayStatus::Running, server.info().await.status);
+
+        // the process keeps running, but it doesn't respond anymore
+        responding.abort();
+        let _ = responding.await;
+        assert_eq!(ServerTrayStatus::Unhealthy, server.info().await.status);
+
+        // a new process is starting until it responds
+        server.restart().await.expect("Should restart");
+        assert_eq!(ServerTrayStatus::Starting, server.info().await.status);
+    }
+
+    #[tokio::test]
+    async fn test_idle_server() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        async fn serve(stats: &'static str) -> String {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let _ = stream.read(&mut [0; 4096]).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{stats}",
+                    stats.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            });
+
+            url
+        }
+
+        assert_eq!(Some(true), is_idle(&serve("{}").await).await);
+        assert_eq!(
+            Some(false),
+            is_idle(&serve(r#"{ "08ada5a7": { "downloadSpeed": 2048.5 } }"#).await).await
+        );
+        assert_eq!(
+            Some(true),
+            is_idle(&serve(r#"[{ "downloadSpeed": 0 }, { "peers": 3 }]"#).await).await
+        );
+        assert_eq!(None, is_idle(&serve("null").await).await);
+    }
+
+    #[tokio::test]
+    async fn test_magnet_torrent_is_created() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let request = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 4096];
+            while !request.ends_with(b"}}") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+                .await
+                .unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        let magnet = Magnet {
+            info_hash: "08ada5a7a6183aae1e09d831df6748d566095a10".to_owned(),
+            name: None,
+            trackers: vec!["udp://explodie.org:6969".to_owned()],
+        };
+        create_torrent(&url, &magnet)
+            .await
+            .expect("Should create the torrent");
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /08ada5a7a6183aae1e09d831df6748d566095a10/create "));
+        assert!(request.contains("\"tracker:udp://explodie.org:6969\""));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff::default();
+
+        assert_eq!(Duration::from_secs(1), backoff.delay(1));
+        assert_eq!(Duration::from_secs(4), backoff.delay(3));
+        assert_eq!(Duration::from_secs(30), backoff.delay(6));
+        assert_eq!(Duration::from_secs(30), backoff.delay(u32::MAX));
+    }
+
+    #[test]
+    fn test_config_from_system() {
+        let dir = tempfile::tempdir().unwrap();
+        let [server_js, node, ffmpeg, ffprobe] =
+            ["server.js", "node", "ffmpeg", "ffprobe"].map(|file| dir.path().join(file));
+        for file in [&server_js, &node, &ffmpeg, &ffprobe] {
+            std::fs::write(file, "").unwrap();
+        }
+
+        let config = Config::from_system(
+            Some(server_js.clone()),
+            Some(node.clone()),
+            Some(ffmpeg.clone()),
+            Some(ffprobe.clone()),
+        )
+        .expect("Should use the given paths");
+        assert_eq!(node, config.node);
+        assert_eq!(ffprobe, config.ffprobe);
+
+        let err = Config::from_system(None, Some(node.clone()), Some(ffmpeg), Some(ffprobe))
+            .expect_err("Should require server.js");
+        assert!(
+            matches!(&err, ServiceError::Config(err) if err.to_string() == "The path of server.js is required")
+        );
+
+        std::fs::remove_file(&node).unwrap();
+        let err = Config::from_paths(
+            server_js,
+            node.clone(),
+            dir.path().join("ffmpeg"),
+            dir.path().join("ffprobe"),
+        )
+        .expect_err("Should require node");
+        assert!(matches!(err, ServiceError::MissingBinary(missing) if missing == node));
+    }
+
+    #[tokio::test]
+    async fn test_intact_server_is_not_repaired() {
+        let dir = tempfile::tempdir().unwrap();
+        let server_js = dir.path().join("server.js");
+        std::fs::write(&server_js, "console.log('server')").unwrap();
+
+        let mut config = config("node");
+        config.server = server_js;
+        assert!(!config
+            .repair(&Roots::default())
+            .await
+            .expect("Should skip without a checksum"));
+
+        config.server_sha256 = Some(hex::encode(Sha256::digest("console.log('server')")));
+        assert!(!config
+            .repair(&Roots::default())
+            .await
+            .expect("Should verify the checksum"));
+    }
+
+    #[tokio::test]
+    async fn test_server_fails_to_start() {
+        let server = Server::with_spawner(config(""), FakeSpawner::default());
+
+        assert!(server.start().is_err());
+        assert!(server.inner.process.lock().unwrap().is_none());
+    }
 }
@@ -0,0 +1,183 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The settings of server.js set by the service, see the `serverSettings` setting.
+//!
+//! server.js keeps its settings (e.g. the cache size, the transcoding profile or the torrent speed limits)
+//! in the [`SETTINGS_FILE_NAME`] of its data directory, they're changed from the settings of Stremio Web.
+//! The ones set by the service are merged into that file before server.js is started, so they're set
+//! after a fresh install as well, while the other keys of the file are kept.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use serde_json::{Map, Value};
+
+/// The settings file of server.js in its data directory
+pub const SETTINGS_FILE_NAME: &str = "server-settings.json";
+
+/// The size of the torrent cache in bytes, `0` disables the cache
+pub const CACHE_SIZE: &str = "cacheSize";
+/// The transcoding profile, e.g. of a hardware encoder
+pub const TRANSCODE_PROFILE: &str = "transcodeProfile";
+/// Whether server.js transcodes with the hardware encoders, see [`HwTranscoding`](crate::transcoding::HwTranscoding)
+pub const HARDWARE_ACCEL: &str = "transcodeHardwareAccel";
+/// The maximum download speed of the torrents in bytes per second
+pub const DOWNLOAD_SPEED_LIMIT: &str = "btDownloadSpeedHardLimit";
+/// The local address of the `https://*.stremio.rocks` certificate of server.js for the other devices
+pub const REMOTE_HTTPS: &str = "remoteHttps";
+
+/// The settings merged into the settings file of server.js
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerSettings {
+    file: PathBuf,
+    values: BTreeMap<String, Value>,
+}
+
+impl ServerSettings {
+    /// The settings of the server.js with the given data directory, see [`data_dir`](crate::server::data_dir)
+    pub fn new(data_dir: &Path, values: BTreeMap<String, Value>) -> Self {
+        Self {
+            file: data_dir.join(SETTINGS_FILE_NAME),
+            values,
+        }
+    }
+
+    pub fn values(&self) -> &BTreeMap<String, Value> {
+        &self.values
+    }
+
+    /// Sets the values, replacing the ones with the same keys
+    pub fn extend(&mut self, values: BTreeMap<String, Value>) {
+        self.values.extend(values);
+    }
+
+    /// Merges the settings into the settings file, which is created when it doesn't exist yet.
+    ///
+    /// # Errors
+    ///
+    /// When the file can't be written or it's not a JSON object, it's left as it is then.
+    pub fn apply(&self) -> Result<(), anyhow::Error> {
+        let mut settings = match std::fs::read(&self.file) {
+            Ok(contents) => match serde_json::from_slice(&contents) {
+                Ok(Value::Object(settings)) => settings,
+                _ => bail!("{} is not a JSON object", self.file.display()),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Map::new(),
+            Err(err) => return Err(err).context(format!("Failed to read {}", self.file.display())),
+        };
+        let changed = self
+            .values
+            .iter()
+            .filter(|(key, value)| settings.get(*key) != Some(value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        settings.extend(changed);
+
+        if let Some(dir) = self.file.parent() {
+            std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&self.file, serde_json::to_vec_pretty(&settings)?)
+            .context(format!("Failed to write {}", self.file.display()))
+    }
+}
+
+/// Parses a size in bytes, with an optional `K`, `M`, `G` or `T` suffix of the powers of 1024, e.g. `10G`.
+pub fn parse_size(size: &str) -> Result<u64, anyhow::Error> {
+    let (digits, unit) = match size.strip_suffix(['K', 'M', 'G', 'T']) {
+        Some(digits) => (digits, &size[digits.len()..]),
+        None => (size, ""),
+    };
+    let multiplier: u64 = match unit {
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => 1,
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .filter(|_| digits.chars().all(|char| char.is_ascii_digit()))
+        .and_then(|digits| digits.checked_mul(multiplier))
+        .with_context(|| format!("`{size}` is not a size, e.g. `10G` for 10 GiB"))
+}
+
+/// Parses a `KEY=VALUE` setting, the value is parsed as JSON and kept as a string otherwise.
+pub fn parse_setting(setting: &str) -> Result<(String, Value), anyhow::Error> {
+    let Some((key, value)) = setting.split_once('=').filter(|(key, _)| !key.is_empty()) else {
+        bail!("`{setting}` is not a KEY=VALUE setting");
+    };
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()));
+
+    Ok((key.to_owned(), value))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_settings_are_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = ServerSettings::new(
+            dir.path(),
+            BTreeMap::from([
+                (CACHE_SIZE.to_owned(), json!(0)),
+                (REMOTE_HTTPS.to_owned(), json!("192.168.1.2")),
+            ]),
+        );
+
+        settings.apply().expect("Should create the file");
+        let file = dir.path().join(SETTINGS_FILE_NAME);
+        let read = || serde_json::from_slice::<Value>(&std::fs::read(&file).unwrap()).unwrap();
+        assert_eq!(
+            json!({ "cacheSize": 0, "remoteHttps": "192.168.1.2" }),
+            read()
+        );
+
+        std::fs::write(
+            &file,
+            r#"{ "cacheSize": 2147483648, "btMaxConnections": 55 }"#,
+        )
+        .unwrap();
+        settings.apply().expect("Should merge the settings");
+        assert_eq!(
+            json!({ "cacheSize": 0, "remoteHttps": "192.168.1.2", "btMaxConnections": 55 }),
+            read()
+        );
+
+        std::fs::write(&file, "[]").unwrap();
+        assert!(settings.apply().is_err(), "Not a JSON object");
+        assert_eq!(json!([]), read());
+    }
+
+    #[test]
+    fn test_parse_size_and_setting() {
+        assert_eq!(10 << 30, parse_size("10G").unwrap());
+        assert_eq!(0, parse_size("0").unwrap());
+        assert_eq!(1536, parse_size("1536").unwrap());
+        for size in ["", "G", "10GB", "-1", "1.5G", "+1"] {
+            assert!(parse_size(size).is_err(), "`{size}` should be invalid");
+        }
+
+        assert_eq!(
+            ("btMaxConnections".to_owned(), json!(55)),
+            parse_setting("btMaxConnections=55").unwrap()
+        );
+        assert_eq!(
+            ("transcodeProfile".to_owned(), json!("vaapi")),
+            parse_setting("transcodeProfile=vaapi").unwrap()
+        );
+        assert!(parse_setting("=1").is_err());
+        assert!(parse_setting("cacheSize").is_err());
+    }
+}
@@ -0,0 +1,734 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+use std::{
+    collections::BTreeMap,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    browser::Browser,
+    constants::{CONTROL_API_PORT, HTTPS_PORT, LOCAL_WEB_UI_PORT, SERVER_PORT, STREMIO_URL},
+    error::ServiceError,
+    profile::Profile,
+    transcoding::HwTranscoding,
+    updater::{Channel, UpdateMode},
+};
+
+/// The persisted service settings.
+///
+/// Every value can be overridden by the CLI [`Args`](crate::args::Args).
+/// Secrets are never part of the settings, they are kept in [`Secrets`](crate::secrets::Secrets),
+/// which makes the settings file portable between machines.
+///
+/// Missing values default to the platform [`Profile`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// Whether the service should register itself to start on login
+    pub autostart: bool,
+    /// Whether or not to skip the updater
+    pub skip_updater: bool,
+    /// The endpoint to use for the updater
+    pub updater_endpoint: Option<Url>,
+    /// Always use the same updater endpoint instead of a random one on each launch.
+    ///
+    /// The endpoint is picked once and stored in `pinned_updater_endpoint`.
+    pub pin_updater_endpoint: bool,
+    /// The pinned updater endpoint, used only when `pin_updater_endpoint` is set
+    ///
+    /// It can be set manually to any of the [`UPDATE_ENDPOINT`](crate::constants::UPDATE_ENDPOINT)s.
+    pub pinned_updater_endpoint: Option<Url>,
+    /// Updates the app to the latest release candidate.
+    ///
+    /// Kept for the older settings files, it's the same as the beta [`Channel`].
+    pub release_candidate: bool,
+    /// The release channel the app is updated from, see [`Settings::update_channel`]
+    pub update_channel: Channel,
+    /// When the downloaded updates are installed, see [`UpdateMode`]
+    pub update_mode: UpdateMode,
+    /// The hour of the local time the `scheduled` updates are installed at, from 0 to 23
+    pub update_hour: u32,
+    /// How often the updates are checked while the service runs, in hours, `0` only checks on start
+    pub update_check_interval: u32,
+    /// The saved Web UI endpoints, see [`Settings::default_web_ui`]
+    pub web_uis: Vec<WebUi>,
+    /// Experimental server features, every key is exported to server.js as an environment variable of the same name
+    pub experiments: BTreeMap<String, Value>,
+    /// The settings of server.js, e.g. `cacheSize`, see [`ServerSettings`](crate::server_settings::ServerSettings)
+    pub server_settings: BTreeMap<String, Value>,
+    /// Whether server.js transcodes with the hardware encoders, see [`transcoding`](crate::transcoding)
+    pub hardware_transcoding: HwTranscoding,
+    /// Allow `stremio://` addon installation links from any host,
+    /// otherwise only the Stremio hosts are allowed, see [`is_stremio_host`](crate::deeplink::is_stremio_host).
+    pub allow_any_addon_host: bool,
+    /// Confine server.js to its own data directory, see [`Sandbox`](crate::sandbox::Sandbox)
+    pub sandbox_server: bool,
+    /// A PEM bundle of certificate authorities trusted besides the OS certificate store, see [`Roots`](crate::tls::Roots)
+    pub ca_bundle: Option<PathBuf>,
+    /// The HTTP proxy of the requests to the internet, e.g. of the updater, see [`Roots::with_proxy`](crate::tls::Roots::with_proxy)
+    pub proxy: Option<Url>,
+    /// The username of the `proxy`, its password is kept in the [`Secrets`](crate::secrets::Secrets)
+    pub proxy_username: Option<String>,
+    /// Limit the disk writes of server.js to its cache, in bytes per second, see [`WriteLimit`](crate::throttle::WriteLimit)
+    pub cache_write_limit: Option<String>,
+    /// Throttle the downloads of server.js while the system is under memory or I/O pressure, see [`pressure`](crate::pressure)
+    pub pressure_watchdog: bool,
+    /// Serve the local control API, see [`ControlApi`](crate::control::ControlApi)
+    pub control_api: bool,
+    /// The port of the control API on the loopback interface
+    pub control_api_port: u16,
+    /// Advertise server.js on the local network, see [`Discovery`](crate::discovery::Discovery)
+    pub lan_discovery: bool,
+    /// The PEM certificate server.js is served with over HTTPS on the local network, see [`HttpsProxy`](crate::https::HttpsProxy)
+    pub https_certificate: Option<PathBuf>,
+    /// The PEM PKCS#8 private key of `https_certificate`
+    pub https_key: Option<PathBuf>,
+    /// The port server.js is served over HTTPS on
+    pub https_port: u16,
+    /// A build directory of Stremio Web served and opened instead of the Web UIs, see [`LocalWebUi`](crate::web::LocalWebUi)
+    pub local_web_ui: Option<PathBuf>,
+    /// The port the `local_web_ui` is served on
+    pub local_web_ui_port: u16,
+    /// Serve the `local_web_ui` on the local network too, e.g. for the TVs
+    pub local_web_ui_lan: bool,
+    /// Show the desktop notifications about the updates and the crashes of server.js, see [`notifications`](crate::notifications)
+    pub notifications: bool,
+    /// Write a report on a crash and ask to send it on the next start, see [`crash`](crate::crash)
+    pub crash_reports: bool,
+    /// Where the crash reports are sent, they're only kept locally without one
+    pub crash_report_endpoint: Option<Url>,
+    /// The port server.js listens on
+    pub server_port: u16,
+    /// The server.js instances run besides the main one, see [`ServerPool`](crate::pool::ServerPool)
+    pub additional_servers: Vec<AdditionalServer>,
+    /// Open the default Web UI once server.js is ready, when the service starts
+    pub open_on_start: bool,
+    /// Open the Web UIs from the tray in an app window instead of the browser, see [`window`](crate::window)
+    pub open_in_window: bool,
+    /// The browser Stremio Web is opened in, see [`Browser`]
+    pub browser: Browser,
+    /// The command of [`Browser::Custom`], its `%u` is replaced by the url, otherwise it's appended
+    pub browser_command: Option<String>,
+    /// The log filter, e.g. `debug` or `info,server.js=warn`, `RUST_LOG` takes precedence
+    pub log_level: String,
+}
+
+/// A named Stremio Web UI endpoint (official, staging, self-hosted, local, etc.)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebUi {
+    pub name: String,
+    pub url: Url,
+    /// Whether this is the Web UI opened by default
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default: bool,
+}
+
+impl Default for WebUi {
+    fn default() -> Self {
+        Self {
+            name: "Stremio Web".to_owned(),
+            url: STREMIO_URL.parse().expect("Should always be a valid url"),
+            default: true,
+        }
+    }
+}
+
+/// A server.js run besides the main one, with its own port and data directory, e.g. for another TV
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdditionalServer {
+    /// Shown in the tray and the log
+    pub name: String,
+    pub port: u16,
+}
+
+/// The description of a single settings key, see [`Settings::describe`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingDescription {
+    /// The key as found in the settings file
+    pub name: &'static str,
+    /// The type of the value
+    pub kind: &'static str,
+    /// The default value for the current platform [`Profile`]
+    pub default: Value,
+    pub help: &'static str,
+}
+
+/// The experiments which name an environment variable of the service or of the runtime of server.js,
+/// they're rejected so an experiment can't override them
+const RESERVED_EXPERIMENTS: &[&str] = &[
+    "FFMPEG_BIN",
+    "FFPROBE_BIN",
+    crate::server::PORT_ENV,
+    crate::server::DATA_DIR_ENV,
+    "NODE_OPTIONS",
+    "NODE_PATH",
+    "PATH",
+    "LD_PRELOAD",
+    "LD_LIBRARY_PATH",
+    "DYLD_INSERT_LIBRARIES",
+    "DYLD_LIBRARY_PATH",
+];
+
+/// The name, type and help text of every [`Settings`] key.
+///
+/// Keep in sync with the [`Settings`] fields, it's checked by the tests.
+const SETTINGS_KEYS: &[(&str, &str, &str)] = &[
+    (
+        "autostart",
+        "bool",
+        "Whether the service should register itself to start on login",
+    ),
+    ("skipUpdater", "bool", "Whether or not to skip the updater"),
+    (
+        "updaterEndpoint",
+        "url (optional)",
+        "The endpoint to use for the updater, overrides the default endpoints, a `file://` URL of a local update descriptor is supported too",
+    ),
+    (
+        "pinUpdaterEndpoint",
+        "bool",
+        "Always use the same updater endpoint instead of a random one on each launch",
+    ),
+    (
+        "pinnedUpdaterEndpoint",
+        "url (optional)",
+        "The pinned updater endpoint, used only when `pinUpdaterEndpoint` is set",
+    ),
+    (
+        "releaseCandidate",
+        "bool",
+        "Deprecated, the same as the `beta` update channel",
+    ),
+    (
+        "updateChannel",
+        "`stable`, `beta` or `nightly`",
+        "The release channel the app is updated from, it can be changed from the tray too",
+    ),
+    (
+        "updateMode",
+        "`idle`, `quit` or `scheduled`",
+        "When the downloaded update is installed: once server.js is idle, when the service quits or at `updateHour` once server.js is idle",
+    ),
+    (
+        "updateHour",
+        "hour",
+        "The hour of the local time, from 0 to 23, the `scheduled` updates are installed at",
+    ),
+    (
+        "updateCheckInterval",
+        "hours",
+        "How often the updates are checked while the service runs, `0` only checks them on start",
+    ),
+    (
+        "webUis",
+        "list of { name, url, default }",
+        "The saved Web UI endpoints, the one marked as `default` is opened from the tray",
+    ),
+    (
+        "experiments",
+        "table",
+        "Experimental server features, every key is exported to server.js as an environment variable of the same name",
+    ),
+    (
+        "serverSettings",
+        "table",
+        "The settings of server.js, e.g. `{ \"cacheSize\": 0 }`, merged into its `server-settings.json` before it's started",
+    ),
+    (
+        "hardwareTranscoding",
+        "`auto`, `on` or `off`",
+        "Whether server.js transcodes with the GPU, `auto` only when one of its encoders works with the bundled ffmpeg",
+    ),
+    (
+        "allowAnyAddonHost",
+        "bool",
+        "Allow `stremio://` addon installation links from any host, not only the Stremio ones",
+    ),
+    (
+        "sandboxServer",
+        "bool",
+        "Confine server.js to its own data directory (Linux with Landlock and Windows)",
+    ),
+    (
+        "caBundle",
+        "path (optional)",
+        "A PEM bundle of certificate authorities trusted besides the OS certificate store, e.g. of a private updater mirror",
+    ),
+    (
+        "proxy",
+        "url (optional)",
+        "The `http://` or `https://` proxy of the updater and of the other requests to the internet, instead of `HTTP_PROXY` and `HTTPS_PROXY`",
+    ),
+    (
+        "proxyUsername",
+        "string (optional)",
+        "The username of `proxy`, the password is set with `--set-proxy-password` and kept in the OS keychain",
+    ),
+    (
+        "cacheWriteLimit",
+        "string (optional)",
+        "Limit the disk writes of server.js to its cache in bytes per second, e.g. `20M`, for slow disks (Linux with systemd only)",
+    ),
+    (
+        "pressureWatchdog",
+        "bool",
+        "Throttle the downloads of server.js while the system is stalled on the memory or the disk (Linux only)",
+    ),
+    (
+        "controlApi",
+        "bool",
+        "Serve the local HTTP API controlling the service on `127.0.0.1:11471`",
+    ),
+    (
+        "controlApiPort",
+        "port",
+        "The port of the control API, e.g. for the instance of another profile",
+    ),
+    (
+        "lanDiscovery",
+        "bool",
+        "Advertise server.js on the local network with mDNS (`_stremio-server._tcp`), for the TVs and the phones to find it",
+    ),
+    (
+        "httpsCertificate",
+        "path (optional)",
+        "A PEM certificate for serving server.js over HTTPS on the local network, e.g. for Stremio Web on the TVs and the phones",
+    ),
+    (
+        "httpsKey",
+        "path (optional)",
+        "The PEM PKCS#8 private key of `httpsCertificate`",
+    ),
+    (
+        "httpsPort",
+        "port",
+        "The port server.js is served over HTTPS on, when `httpsCertificate` is set",
+    ),
+    (
+        "localWebUi",
+        "path (optional)",
+        "A build of Stremio Web (the `build` directory of `npm run build`) served on `http://127.0.0.1:11473/web/` and opened instead of `https://web.stremio.com`, so it works offline. The builds with the `embedded-web` feature serve their own without it",
+    ),
+    (
+        "localWebUiPort",
+        "port",
+        "The port `localWebUi` is served on",
+    ),
+    (
+        "localWebUiLan",
+        "bool",
+        "Serve `localWebUi` on the local network too, for the TVs which can't reach `web.stremio.com`",
+    ),
+    (
+        "notifications",
+        "bool",
+        "Show the desktop notifications about the updates and the crashes of server.js",
+    ),
+    (
+        "crashReports",
+        "bool",
+        "Write a report with the backtrace and the last log lines when the service crashes, in the `crashes` state directory",
+    ),
+    (
+        "crashReportEndpoint",
+        "url (optional)",
+        "Where the crash reports are sent, the user is asked on the next start, they're only kept locally without one",
+    ),
+    (
+        "serverPort",
+        "port",
+        "The port server.js listens on, e.g. when another app uses the default one",
+    ),
+    (
+        "additionalServers",
+        "list of { name, port }",
+        "More server.js instances, each with its own port, cache and settings, e.g. one per TV",
+    ),
+    (
+        "openOnStart",
+        "bool",
+        "Open the default Web UI once server.js is ready, when the service starts",
+    ),
+    (
+        "openInWindow",
+        "bool",
+        "Open Stremio Web from the tray in an app window instead of the browser, in the builds with the `webview` feature",
+    ),
+    (
+        "browser",
+        "string",
+        "The browser Stremio Web is opened in, it's chosen from the tray too: `default`, `chrome`, `firefox` or `custom` for `browserCommand`",
+    ),
+    (
+        "browserCommand",
+        "string (optional)",
+        "The command of the `custom` browser, e.g. `flatpak run com.google.Chrome`, `%u` is replaced by the url, otherwise it's appended",
+    ),
+    (
+        "logLevel",
+        "string",
+        "The log filter, e.g. `debug` or `info,server.js=warn`, the `RUST_LOG` environment variable takes precedence",
+    ),
+];
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::from(Profile::current())
+    }
+}
+
+impl From<Profile> for Settings {
+    fn from(profile: Profile) -> Self {
+        Self {
+            autostart: profile.autostart,
+            skip_updater: profile.skip_updater,
+            updater_endpoint: None,
+            pin_updater_endpoint: false,
+            pinned_updater_endpoint: None,
+            release_candidate: false,
+            update_channel: Channel::Stable,
+            update_mode: UpdateMode::Idle,
+            update_hour: 4,
+            update_check_interval: 12,
+            web_uis: vec![WebUi::default()],
+            experiments: BTreeMap::new(),
+            server_settings: BTreeMap::new(),
+            hardware_transcoding: HwTranscoding::Auto,
+            allow_any_addon_host: false,
+            sandbox_server: false,
+            ca_bundle: None,
+            proxy: None,
+            proxy_username: None,
+            cache_write_limit: None,
+            pressure_watchdog: false,
+            control_api: true,
+            control_api_port: CONTROL_API_PORT,
+            lan_discovery: false,
+            https_certificate: None,
+            https_key: None,
+            https_port: HTTPS_PORT,
+            local_web_ui: None,
+            local_web_ui_port: LOCAL_WEB_UI_PORT,
+            local_web_ui_lan: false,
+            notifications: true,
+            crash_reports: false,
+            crash_report_endpoint: None,
+            server_port: SERVER_PORT,
+            additional_servers: vec![],
+            open_on_start: false,
+            open_in_window: false,
+            browser: Browser::Default,
+            browser_command: None,
+            log_level: "info".to_owned(),
+        }
+    }
+}
+
+impl Settings {
+    /// Describe every settings key with its type, default value and help text.
+    ///
+    /// Used by `stremio-service config --list` and for generating the configuration docs.
+    pub fn describe() -> Vec<SettingDescription> {
+        let defaults = serde_json::to_value(Self::default()).expect("Settings should serialize");
+
+        SETTINGS_KEYS
+            .iter()
+            .map(|&(name, kind, help)| SettingDescription {
+                name,
+                kind,
+                default: defaults.get(name).cloned().unwrap_or(Value::Null),
+                help,
+            })
+            .collect()
+    }
+
+    /// The release channel, the beta one when only the older `release_candidate` is set.
+    pub fn update_channel(&self) -> Channel {
+        match self.update_channel {
+            Channel::Stable if self.release_candidate => Channel::Beta,
+            channel => channel,
+        }
+    }
+
+    /// The Web UI marked as default.
+    ///
+    /// If none is marked as default, it's the first saved Web UI or the official Stremio Web.
+    pub fn default_web_ui(&self) -> WebUi {
+        self.web_uis
+            .iter()
+            .find(|web_ui| web_ui.default)
+            .or_else(|| self.web_uis.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Find a saved Web UI by its name (case insensitive).
+    pub fn find_web_ui(&self, name: &str) -> Option<&WebUi> {
+        self.web_uis
+            .iter()
+            .find(|web_ui| web_ui.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The local address of the control API, `None` when it's disabled
+    pub fn control_api_address(&self) -> Option<SocketAddr> {
+        self.control_api
+            .then(|| SocketAddr::from((Ipv4Addr::LOCALHOST, self.control_api_port)))
+    }
+
+    /// The experiments as environment variables for server.js, every key is exported as written.
+    ///
+    /// Keys which are not valid environment variable names or which name a reserved variable,
+    /// e.g. `NODE_OPTIONS`, are skipped.
+    pub fn experiments_env(&self) -> BTreeMap<String, String> {
+        self.experiments
+            .iter()
+            .filter_map(|(key, value)| {
+                let is_valid = key.chars().enumerate().all(|(index, char)| {
+                    char == '_'
+                        || char.is_ascii_alphabetic()
+                        || (index > 0 && char.is_ascii_digit())
+                });
+                if key.is_empty() || !is_valid {
+                    log::warn!(
+                        "Skipping experiment `{key}`, it is not a valid environment variable name"
+                    );
+                    return None;
+                }
+                if RESERVED_EXPERIMENTS
+                    .iter()
+                    .any(|reserved| key.eq_ignore_ascii_case(reserved))
+                {
+                    log::warn!(
+                        "Skipping experiment `{key}`, it is a reserved environment variable"
+                    );
+                    return None;
+                }
+
+                let value = match value {
+                    Value::String(value) => value.to_owned(),
+                    value => value.to_string(),
+                };
+
+                Some((key.to_owned(), value))
+            })
+            .collect()
+    }
+
+    /// Renders [`Settings::describe`] as a Markdown table.
+    pub fn describe_markdown() -> String {
+        let mut markdown =
+            String::from("| Key | Type | Default | Description |\n|---|---|---|---|\n");
+        for description in Self::describe() {
+            markdown.push_str(&format!(
+                "| `{}` | {} | `{}` | {} |\n",
+                description.name, description.kind, description.default, description.help
+            ));
+        }
+
+        markdown
+    }
+
+    /// Load the settings from the given file.
+    ///
+    /// If the file does not exist yet, the default settings are returned.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ServiceError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read settings {:?}", path))
+            .map_err(ServiceError::Settings)?;
+
+        serde_json::from_str(&content)
+            .context(format!("Invalid settings file {:?}", path))
+            .map_err(ServiceError::Settings)
+    }
+
+    /// Save the settings to the given file, creating the parent directories if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ServiceError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create {:?}", parent))
+                .map_err(ServiceError::Settings)?;
+        }
+
+        let content =
+            serde_json::to_vec_pretty(self).map_err(|err| ServiceError::Settings(err.into()))?;
+        std::fs::write(path, content)
+            .context(format!("Failed to write settings {:?}", path))
+            .map_err(ServiceError::Settings)
+    }
+
+    /// Export the settings stored at `settings_file` to the portable file `destination`.
+    pub fn export(
+        settings_file: impl AsRef<Path>,
+        destination: impl AsRef<Path>,
+    ) -> Result<(), ServiceError> {
+        Self::load(settings_file)?.save(destination)
+    }
+
+    /// Import the portable settings file `source` and store them in `settings_file`.
+    ///
+    /// The imported file is validated before replacing the current settings.
+    pub fn import(
+        settings_file: impl AsRef<Path>,
+        source: impl AsRef<Path>,
+    ) -> Result<Self, ServiceError> {
+        let source = source.as_ref();
+        if !source.is_file() {
+            return Err(ServiceError::Settings(anyhow!(
+                "The settings file {:?} does not exist",
+                source
+            )));
+        }
+
+        let settings = Self::load(source)?;
+        settings.save(settings_file)?;
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AdditionalServer, Browser, Channel, HwTranscoding, Settings, UpdateMode, WebUi};
+
+    #[test]
+    fn test_settings_describe_all_keys() {
+        let settings = serde_json::to_value(Settings::default()).unwrap();
+        let mut keys = settings
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let mut described = Settings::describe()
+            .into_iter()
+            .map(|description| description.name)
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        described.sort_unstable();
+
+        assert_eq!(keys, described);
+    }
+
+    #[test]
+    fn test_settings_export_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings_file = dir.path().join("settings.json");
+        let exported = dir.path().join("exported.json");
+        let imported_file = dir.path().join("imported").join("settings.json");
+
+        let settings = Settings {
+            autostart: false,
+            skip_updater: true,
+            updater_endpoint: Some("https://example.com/updater".parse().unwrap()),
+            pin_updater_endpoint: false,
+            pinned_updater_endpoint: None,
+            release_candidate: false,
+            update_channel: Channel::Nightly,
+            update_mode: UpdateMode::Scheduled,
+            update_hour: 3,
+            update_check_interval: 24,
+            web_uis: vec![
+                WebUi::default(),
+                WebUi {
+                    name: "Local".to_owned(),
+                    url: "http://127.0.0.1:8080".parse().unwrap(),
+                    default: false,
+                },
+            ],
+            experiments: [("NEW_FEATURE".to_owned(), true.into())].into(),
+            server_settings: [("cacheSize".to_owned(), 0.into())].into(),
+            allow_any_addon_host: true,
+            hardware_transcoding: HwTranscoding::Off,
+            sandbox_server: true,
+            ca_bundle: Some("/etc/ssl/certs/corporate.pem".into()),
+            proxy: Some("http://proxy.example.com:3128".parse().unwrap()),
+            proxy_username: Some("user".to_owned()),
+            cache_write_limit: Some("20M".to_owned()),
+            pressure_watchdog: true,
+            control_api: false,
+            control_api_port: 11481,
+            lan_discovery: true,
+            https_certificate: Some("/etc/stremio/cert.pem".into()),
+            https_key: Some("/etc/stremio/key.pem".into()),
+            https_port: 12480,
+            local_web_ui: Some("/opt/stremio-web/build".into()),
+            local_web_ui_port: 12473,
+            local_web_ui_lan: true,
+            notifications: false,
+            crash_reports: true,
+            crash_report_endpoint: Some("https://example.com/crashes".parse().unwrap()),
+            server_port: 11480,
+            additional_servers: vec![AdditionalServer {
+                name: "Bedroom TV".to_owned(),
+                port: 11490,
+            }],
+            open_on_start: true,
+            open_in_window: true,
+            browser: Browser::Custom,
+            browser_command: Some("flatpak run com.google.Chrome --app=%u".to_owned()),
+            log_level: "info,server.js=warn".to_owned(),
+        };
+        settings.save(&settings_file).unwrap();
+
+        Settings::export(&settings_file, &exported).unwrap();
+        let imported = Settings::import(&imported_file, &exported).unwrap();
+
+        assert_eq!(settings, imported);
+        assert_eq!(settings, Settings::load(&imported_file).unwrap());
+    }
+
+    #[test]
+    fn test_release_candidate_is_the_beta_channel() {
+        let mut settings = Settings {
+            release_candidate: true,
+            ..Default::default()
+        };
+        assert_eq!(Channel::Beta, settings.update_channel());
+
+        settings.update_channel = Channel::Nightly;
+        assert_eq!(Channel::Nightly, settings.update_channel());
+    }
+
+    #[test]
+    fn test_reserved_experiments_are_skipped() {
+        let settings = Settings {
+            experiments: [
+                ("newFeature".to_owned(), true.into()),
+                ("CACHE_MODE".to_owned(), "disk".into()),
+                ("NODE_OPTIONS".to_owned(), "--require /tmp/evil.js".into()),
+                ("ffmpeg_bin".to_owned(), "/tmp/evil".into()),
+                ("1INVALID".to_owned(), 1.into()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            std::collections::BTreeMap::from([
+                ("CACHE_MODE".to_owned(), "disk".to_owned()),
+                ("newFeature".to_owned(), "true".to_owned()),
+            ]),
+            settings.experiments_env()
+        );
+    }
+
+    #[test]
+    fn test_settings_missing_file_is_default() {
+        let settings = Settings::load("/this/path/does/not/exist/settings.json").unwrap();
+
+        assert_eq!(Settings::default(), settings);
+    }
+}
@@ -0,0 +1,693 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The updates of the Linux tarball installation, staged before they replace the installed files, see [`Staging`].
+//!
+//! Extracting a tarball over the installation directory leaves a mix of the old and the new files when it's interrupted,
+//! e.g. by a power loss. The update is extracted to the [`staging`](crate::directories::Directories::staging) directory
+//! instead, and it's swapped with the installation directory on the next start of the service, before anything runs from it:
+//!
+//! 1. the staged files are moved next to the installation directory as `.<name>.new`, they're copied from another file system
+//! 2. the two directories are exchanged at once by `renameat2(RENAME_EXCHANGE)`, by two renames where it's not supported
+//! 3. the old files are removed
+//!
+//! The step is recorded in the [`JOURNAL_FILE_NAME`] before it's taken, so a swap interrupted at any step is completed
+//! on the next start. When the parent of the installation directory isn't writable, the staged files are moved inside it
+//! and merged over the installed ones one by one instead, the installed files the update doesn't have are removed then.
+//! It isn't atomic, so the journal is kept when it fails and the merge is retried on the next start.
+
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Error};
+use log::{info, warn};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::updater::TARBALL_DIR;
+
+/// The record of the staged update and of the step of its swap
+const JOURNAL_FILE_NAME: &str = "journal.json";
+/// The version of the staged files, it tells whether the installation directory was swapped already
+const VERSION_FILE_NAME: &str = ".stremio-service-version";
+/// The executable of the installation directory
+const EXECUTABLE_NAME: &str = "stremio-service";
+/// The staged files inside the installation directory, when its parent isn't writable
+const IN_PLACE_DIR: &str = ".update";
+
+/// The staged update
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Journal {
+    version: String,
+    /// The installation directory the update replaces
+    install_dir: PathBuf,
+    step: Step,
+    /// The paths of the staged files relative to the installation directory, for the [`Step::SwappingFiles`]
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    files: BTreeSet<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Step {
+    /// The files are extracted to the staging directory
+    Staged,
+    /// The files are next to the installation directory, they're exchanged
+    SwappingDirectory,
+    /// The files are inside the installation directory, they're merged over the installed ones
+    SwappingFiles,
+}
+
+/// The staging directory of the updates, with one sub-directory of the version staged last
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Staging {
+    dir: PathBuf,
+}
+
+impl Staging {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Extracts the release tarball of the version, it replaces the installation directory on the next start,
+    /// see [`Staging::apply`]. The update staged before, if any, is dropped.
+    ///
+    /// # Errors
+    ///
+    /// When the tarball fails to be extracted or it's not a release tarball, nothing is staged then.
+    pub fn stage(
+        &self,
+        tarball: &Path,
+        version: &Version,
+        install_dir: &Path,
+    ) -> Result<(), Error> {
+        remove_dir(&self.dir)?;
+        let part = self.dir.join(format!("{version}.part"));
+        std::fs::create_dir_all(&part).context(format!("Failed to create {}", part.display()))?;
+
+        let staged = self.dir.join(version.to_string());
+        let extract = || {
+            let file = std::fs::File::open(tarball)
+                .context(format!("Failed to open {}", tarball.display()))?;
+            tar::Archive::new(flate2::read::GzDecoder::new(file))
+                .unpack(&part)
+                .context(format!("Failed to extract {}", tarball.display()))?;
+            let files = part.join(TARBALL_DIR);
+            if !files.join(EXECUTABLE_NAME).is_file() {
+                bail!("The tarball has no {TARBALL_DIR}/{EXECUTABLE_NAME}");
+            }
+            std::fs::write(files.join(VERSION_FILE_NAME), version.to_string())?;
+            std::fs::rename(&files, &staged)
+                .context(format!("Failed to stage {}", staged.display()))?;
+
+            Ok(())
+        };
+        let extracted = extract();
+        let _ = std::fs::remove_dir_all(&part);
+        extracted?;
+
+        self.write_journal(&Journal {
+            version: version.to_string(),
+            install_dir: install_dir.to_path_buf(),
+            step: Step::Staged,
+            files: BTreeSet::new(),
+        })?;
+        info!(
+            "Staged v{version} in {}, it's installed on the next start",
+            staged.display()
+        );
+
+        Ok(())
+    }
+
+    /// Swaps the installation directory with the staged update, or completes the swap interrupted before,
+    /// and returns the updated executable. `None` when no update is staged.
+    ///
+    /// It's called on start while the lockfile is held, as the updated files replace the ones this instance runs from.
+    ///
+    /// # Errors
+    ///
+    /// When the files fail to be swapped, the update is dropped and the installed files are kept then.
+    /// The files merged by the [`Step::SwappingFiles`] can't be restored, the journal is kept instead
+    /// and the merge is retried on the next start.
+    pub fn apply(&self) -> Result<Option<PathBuf>, Error> {
+        let Some(mut journal) = self.read_journal()? else {
+            return Ok(None);
+        };
+        info!(
+            "Installing the staged v{} to {}",
+            journal.version,
+            journal.install_dir.display()
+        );
+
+        let swapped = self.swap(&mut journal);
+        if swapped.is_err() {
+            match journal.step {
+                Step::SwappingFiles => warn!(
+                    "The staged v{} is installed partly, it's retried on the next start",
+                    journal.version
+                ),
+                _ => self.abort(&journal),
+            }
+        }
+        let executable = swapped?;
+        if let Err(err) = remove_dir(&self.dir) {
+            warn!("{err:#}");
+        }
+        info!("Installed v{}", journal.version);
+
+        Ok(Some(executable))
+    }
+
+    fn swap(&self, journal: &mut Journal) -> Result<PathBuf, Error> {
+        let install_dir = journal.install_dir.clone();
+        let new_dir = sibling(&install_dir, "new")?;
+        let in_place_dir = install_dir.join(IN_PLACE_DIR);
+
+        if journal.step == Step::Staged {
+            let staged = self.dir.join(&journal.version);
+            journal.step = match (staged.is_dir(), new_dir.is_dir(), in_place_dir.is_dir()) {
+                // moved by the start interrupted before
+                (false, true, _) => Step::SwappingDirectory,
+                (false, _, true) => {
+                    journal.files = files(&in_place_dir)?;
+                    Step::SwappingFiles
+                }
+                (false, ..) => bail!("The staged files are missing"),
+                (true, ..) => match move_dir(&staged, &new_dir) {
+                    Ok(()) => Step::SwappingDirectory,
+                    Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                        warn!("{} can't be replaced at once: {err}", install_dir.display());
+                        move_dir(&staged, &in_place_dir)
+                            .context(format!("Failed to move {}", staged.display()))?;
+                        journal.files = files(&in_place_dir)?;
+
+                        Step::SwappingFiles
+                    }
+                    Err(err) => {
+                        return Err(err).context(format!("Failed to move {}", staged.display()))
+                    }
+                },
+            };
+            self.write_journal(journal)?;
+        }
+
+        match journal.step {
+            Step::Staged => unreachable!("The staged files were moved"),
+            Step::SwappingDirectory => {
+                let old_dir = sibling(&install_dir, "old")?;
+                let version = std::fs::read_to_string(install_dir.join(VERSION_FILE_NAME)).ok();
+                if version.as_deref() != Some(journal.version.as_str()) {
+                    match install_dir.exists() {
+                        // between the two renames
+                        false => std::fs::rename(&new_dir, &install_dir),
+                        true => exchange(&install_dir, &new_dir).or_else(|err| {
+                            warn!("The directories can't be exchanged at once: {err}");
+                            std::fs::rename(&install_dir, &old_dir)?;
+                            std::fs::rename(&new_dir, &install_dir)
+                        }),
+                    }
+                    .context(format!("Failed to replace {}", install_dir.display()))?;
+                }
+                for dir in [new_dir, old_dir] {
+                    if let Err(err) = remove_dir(&dir) {
+                        warn!("{err:#}");
+                    }
+                }
+            }
+            Step::SwappingFiles => {
+                if in_place_dir.is_dir() {
+                    merge_dir(&in_place_dir, &install_dir)?;
+                    remove_dir(&in_place_dir)?;
+                }
+                // the journals written before the paths were recorded
+                if !journal.files.is_empty() {
+                    prune_dir(&install_dir, &install_dir, &journal.files)?;
+                }
+            }
+        }
+
+        Ok(install_dir.join(EXECUTABLE_NAME))
+    }
+
+    /// Drops the update after a failed swap, the old installation directory is restored when it was moved.
+    fn abort(&self, journal: &Journal) {
+        let install_dir = &journal.install_dir;
+        let Ok(old_dir) = sibling(install_dir, "old") else {
+            return;
+        };
+        if !install_dir.exists() && old_dir.is_dir() {
+            if let Err(err) = std::fs::rename(&old_dir, install_dir) {
+                warn!("Failed to restore {}: {err}", install_dir.display());
+            }
+        }
+        let new_dir = sibling(install_dir, "new").ok();
+        for dir in new_dir.iter().chain([&self.dir]) {
+            if let Err(err) = remove_dir(dir) {
+                warn!("{err:#}");
+            }
+        }
+    }
+
+    fn read_journal(&self) -> Result<Option<Journal>, Error> {
+        let file = self.dir.join(JOURNAL_FILE_NAME);
+        let journal = match std::fs::read(&file) {
+            Ok(journal) => journal,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).context(format!("Failed to read {}", file.display())),
+        };
+
+        match serde_json::from_slice(&journal) {
+            Ok(journal) => Ok(Some(journal)),
+            Err(err) => {
+                // a journal is only written by a rename, it was changed by something else
+                remove_dir(&self.dir)?;
+                Err(err).context(format!(
+                    "Dropped the staged update, invalid {}",
+                    file.display()
+                ))
+            }
+        }
+    }
+
+    /// Replaces the journal at once, it's synced to the disk before the step is taken
+    fn write_journal(&self, journal: &Journal) -> Result<(), Error> {
+        let file = self.dir.join(JOURNAL_FILE_NAME);
+        let part = self.dir.join(format!("{JOURNAL_FILE_NAME}.part"));
+        let write = || {
+            std::fs::create_dir_all(&self.dir)?;
+            std::fs::write(&part, serde_json::to_vec_pretty(journal)?)?;
+            std::fs::File::open(&part)?.sync_all()?;
+            std::fs::rename(&part, &file)
+        };
+
+        write().context(format!("Failed to write {}", file.display()))
+    }
+}
+
+/// Runs the executable in place of this process with the same arguments, e.g. the updated one.
+///
+/// On `*nix` it only returns on failure, elsewhere the executable is started and this process should exit.
+pub fn restart(executable: &Path) -> io::Result<()> {
+    let mut command = Command::new(executable);
+    command.args(std::env::args_os().skip(1));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        Err(command.exec())
+    }
+    #[cfg(not(unix))]
+    command.spawn().map(|_| ())
+}
+
+/// The hidden directory next to the installation directory, e.g. `.stremio-service.new`
+fn sibling(install_dir: &Path, suffix: &str) -> Result<PathBuf, Error> {
+    let name = install_dir
+        .file_name()
+        .context(format!(
+            "Invalid installation directory {}",
+            install_dir.display()
+        ))?
+        .to_string_lossy();
+
+    Ok(install_dir.with_file_name(format!(".{name}.{suffix}")))
+}
+
+/// Moves the directory by a rename, it's copied first from another file system.
+fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+    if to.exists() {
+        std::fs::remove_dir_all(to)?;
+    }
+    match std::fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            let mut part = to.as_os_str().to_owned();
+            part.push(".part");
+            let part = PathBuf::from(part);
+            if part.exists() {
+                std::fs::remove_dir_all(&part)?;
+            }
+            copy_dir(from, &part)?;
+            std::fs::rename(&part, to)?;
+
+            std::fs::remove_dir_all(from)
+        }
+        moved => moved,
+    }
+}
+
+/// The paths of the files and directories in the directory, relative to it
+fn files(dir: &Path) -> Result<BTreeSet<PathBuf>, Error> {
+    fn walk(dir: &Path, base: &Path, files: &mut BTreeSet<PathBuf>) -> io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if let Ok(relative) = path.strip_prefix(base) {
+                files.insert(relative.to_path_buf());
+            }
+            if entry.file_type()?.is_dir() {
+                walk(&path, base, files)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = BTreeSet::new();
+    walk(dir, dir, &mut files).context(format!("Failed to list {}", dir.display()))?;
+
+    Ok(files)
+}
+
+/// Moves the files of the directory over the ones in `to`, the sub-directories are merged the same way.
+///
+/// The moved files are gone from `from`, so a merge interrupted before continues where it stopped.
+fn merge_dir(from: &Path, to: &Path) -> Result<(), Error> {
+    for entry in std::fs::read_dir(from).context(format!("Failed to read {}", from.display()))? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        let dest_is_dir = std::fs::symlink_metadata(&dest).map(|metadata| metadata.is_dir());
+        if entry.file_type()?.is_dir() {
+            match dest_is_dir {
+                Ok(true) => {
+                    merge_dir(&entry.path(), &dest)?;
+                    std::fs::remove_dir(entry.path())
+                        .context(format!("Failed to remove {}", entry.path().display()))?;
+                    continue;
+                }
+                Ok(false) => std::fs::remove_file(&dest)
+                    .context(format!("Failed to remove {}", dest.display()))?,
+                Err(_) => {}
+            }
+        } else if let Ok(true) = dest_is_dir {
+            remove_dir(&dest)?;
+        }
+        std::fs::rename(entry.path(), &dest)
+            .context(format!("Failed to replace {}", dest.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Removes the files of the installation directory the update doesn't have, except the staged files inside it
+fn prune_dir(dir: &Path, install_dir: &Path, files: &BTreeSet<PathBuf>) -> Result<(), Error> {
+    for entry in std::fs::read_dir(dir).context(format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let Ok(relative) = path.strip_prefix(install_dir) else {
+            continue;
+        };
+        if relative == Path::new(IN_PLACE_DIR) {
+            continue;
+        }
+        let is_dir = std::fs::symlink_metadata(&path)?.is_dir();
+        match (files.contains(relative), is_dir) {
+            (true, true) => prune_dir(&path, install_dir, files)?,
+            (true, false) => {}
+            (false, true) => remove_dir(&path)?,
+            (false, false) => std::fs::remove_file(&path)
+                .context(format!("Failed to remove {}", path.display()))?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the directory with its permissions and symbolic links
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::create_dir(to)?;
+    std::fs::set_permissions(to, std::fs::metadata(from)?.permissions())?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &dest)?;
+            #[cfg(not(unix))]
+            std::fs::copy(entry.path(), &dest).map(|_| ())?;
+        } else {
+            std::fs::copy(entry.path(), &dest)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the directory and everything in it, it's fine when it doesn't exist.
+fn remove_dir(dir: &Path) -> Result<(), Error> {
+    match std::fs::remove_dir_all(dir) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).context(format!("Failed to remove {}", dir.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Exchanges the two paths at once.
+#[cfg(target_os = "linux")]
+fn exchange(a: &Path, b: &Path) -> io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let a = CString::new(a.as_os_str().as_bytes())?;
+    let b = CString::new(b.as_os_str().as_bytes())?;
+    // the syscall, as the `renameat2` function needs glibc 2.28
+    // SAFETY: the paths are NUL terminated
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+
+    match result {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exchange(_a: &Path, _b: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Writes a release tarball with the contents of its `stremio-service` and `server.js`
+    fn tarball(file: &Path, contents: &str) {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            std::fs::File::create(file).unwrap(),
+            flate2::Compression::default(),
+        ));
+        for name in [EXECUTABLE_NAME, "server.js"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            builder
+                .append_data(
+                    &mut header,
+                    format!("{TARBALL_DIR}/{name}"),
+                    contents.as_bytes(),
+                )
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn installed(install_dir: &Path) -> (String, String) {
+        (
+            std::fs::read_to_string(install_dir.join(EXECUTABLE_NAME)).unwrap(),
+            std::fs::read_to_string(install_dir.join("server.js")).unwrap(),
+        )
+    }
+
+    fn test_dir() -> (TempDir, PathBuf, Staging) {
+        let dir = tempfile::tempdir().unwrap();
+        let install_dir = dir.path().join("stremio-service");
+        std::fs::create_dir_all(&install_dir).unwrap();
+        std::fs::write(install_dir.join(EXECUTABLE_NAME), "old").unwrap();
+        std::fs::write(install_dir.join("server.js"), "old").unwrap();
+
+        let staging = Staging::new(dir.path().join("staging"));
+
+        (dir, install_dir, staging)
+    }
+
+    #[test]
+    fn test_staged_update_is_swapped() {
+        let (dir, install_dir, staging) = test_dir();
+        let file = dir.path().join("stremio-service-x86_64.tar.gz");
+        std::fs::write(&file, "not a tarball").unwrap();
+        assert!(staging
+            .stage(&file, &Version::new(2, 0, 0), &install_dir)
+            .is_err());
+        assert_eq!(None, staging.apply().unwrap(), "Nothing is staged");
+
+        tarball(&file, "new");
+        staging
+            .stage(&file, &Version::new(2, 0, 0), &install_dir)
+            .expect("Should stage the update");
+        assert_eq!(
+            ("old".to_owned(), "old".to_owned()),
+            installed(&install_dir),
+            "The update waits for the next start"
+        );
+
+        let executable = staging.apply().expect("Should swap the files");
+        assert_eq!(Some(install_dir.join(EXECUTABLE_NAME)), executable);
+        assert_eq!(
+            ("new".to_owned(), "new".to_owned()),
+            installed(&install_dir)
+        );
+        assert_eq!(
+            vec![install_dir.clone(), file],
+            std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            "The old and the staged files are removed"
+        );
+        assert_eq!(None, staging.apply().unwrap());
+    }
+
+    #[test]
+    fn test_interrupted_swap_is_completed() {
+        let (dir, install_dir, staging) = test_dir();
+        let file = dir.path().join("stremio-service-x86_64.tar.gz");
+        tarball(&file, "new");
+        let version = Version::new(2, 0, 0);
+        staging.stage(&file, &version, &install_dir).unwrap();
+
+        // the power was lost between the two renames
+        let new_dir = sibling(&install_dir, "new").unwrap();
+        std::fs::rename(staging.dir.join(version.to_string()), &new_dir).unwrap();
+        std::fs::rename(&install_dir, sibling(&install_dir, "old").unwrap()).unwrap();
+        staging
+            .write_journal(&Journal {
+                version: version.to_string(),
+                install_dir: install_dir.clone(),
+                step: Step::SwappingDirectory,
+                files: BTreeSet::new(),
+            })
+            .unwrap();
+
+        staging.apply().expect("Should complete the swap");
+        assert_eq!(
+            ("new".to_owned(), "new".to_owned()),
+            installed(&install_dir)
+        );
+        assert!(!new_dir.exists());
+        assert!(!sibling(&install_dir, "old").unwrap().exists());
+
+        // the power was lost after the exchange, before the old files were removed
+        std::fs::create_dir_all(&new_dir).unwrap();
+        staging
+            .write_journal(&Journal {
+                version: version.to_string(),
+                install_dir: install_dir.clone(),
+                step: Step::SwappingDirectory,
+                files: BTreeSet::new(),
+            })
+            .unwrap();
+        staging.apply().unwrap();
+        assert_eq!(
+            ("new".to_owned(), "new".to_owned()),
+            installed(&install_dir)
+        );
+        assert!(!new_dir.exists());
+
+        // the files inside the installation directory were renamed partly
+        let in_place_dir = install_dir.join(IN_PLACE_DIR);
+        std::fs::create_dir_all(&in_place_dir).unwrap();
+        std::fs::write(in_place_dir.join("server.js"), "newer").unwrap();
+        staging
+            .write_journal(&Journal {
+                version: "2.0.1".to_owned(),
+                install_dir: install_dir.clone(),
+                step: Step::SwappingFiles,
+                files: [EXECUTABLE_NAME, "server.js", VERSION_FILE_NAME]
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .collect(),
+            })
+            .unwrap();
+        staging.apply().unwrap();
+        assert_eq!(
+            ("new".to_owned(), "newer".to_owned()),
+            installed(&install_dir)
+        );
+        assert!(!in_place_dir.exists());
+    }
+
+    #[test]
+    fn test_in_place_swap_merges_directories() {
+        let (_dir, install_dir, staging) = test_dir();
+        std::fs::create_dir_all(install_dir.join("lib/old")).unwrap();
+        std::fs::write(install_dir.join("lib/libold.so"), "old").unwrap();
+        std::fs::write(install_dir.join("lib/libkept.so"), "old").unwrap();
+        std::fs::write(install_dir.join("lib/old/file"), "old").unwrap();
+        std::fs::write(install_dir.join("dropped"), "old").unwrap();
+
+        // the parent of the installation directory wasn't writable, the staged files were moved inside it
+        let in_place_dir = install_dir.join(IN_PLACE_DIR);
+        std::fs::create_dir_all(in_place_dir.join("lib/new")).unwrap();
+        for file in [
+            EXECUTABLE_NAME,
+            "server.js",
+            "lib/libkept.so",
+            "lib/new/file",
+        ] {
+            std::fs::write(in_place_dir.join(file), "new").unwrap();
+        }
+        staging
+            .write_journal(&Journal {
+                version: "2.0.0".to_owned(),
+                install_dir: install_dir.clone(),
+                step: Step::Staged,
+                files: BTreeSet::new(),
+            })
+            .unwrap();
+
+        staging.apply().expect("Should merge the files");
+        assert_eq!(
+            ("new".to_owned(), "new".to_owned()),
+            installed(&install_dir)
+        );
+        assert_eq!(
+            [
+                "lib",
+                "lib/libkept.so",
+                "lib/new",
+                "lib/new/file",
+                "server.js",
+                EXECUTABLE_NAME
+            ]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect::<BTreeSet<_>>(),
+            files(&install_dir).unwrap(),
+            "The files the update doesn't have are removed"
+        );
+        assert_eq!(
+            "new",
+            std::fs::read_to_string(install_dir.join("lib/libkept.so")).unwrap()
+        );
+        assert_eq!(None, staging.read_journal().unwrap());
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The status of the service, shared by the tray, the CLI JSON output and the embedders.
+//!
+//! # Stability
+//!
+//! The JSON representation is part of the public API:
+//! new fields and states may be added in minor releases, but the existing ones keep their names and meaning.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::build_info::BUILD_INFO;
+
+/// The state of server.js, as shown in the tray
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum ServerTrayStatus {
+    /// server.js was started but it doesn't respond yet
+    Starting,
+    /// server.js responds to requests
+    Running,
+    /// server.js is running but it has stopped responding to requests, it's restarted if it doesn't recover
+    Unhealthy,
+    /// server.js was not started or it was stopped by the service
+    Stopped,
+    /// server.js exited on its own and it's restarted after a delay
+    Restarting {
+        /// The number of the consecutive restarts, starting at 1
+        attempt: u32,
+    },
+    /// server.js exited on its own and it's not restarted
+    Crashed {
+        /// `None` when it was terminated by a signal
+        #[serde(rename = "exitCode")]
+        exit_code: Option<i32>,
+    },
+}
+
+impl ServerTrayStatus {
+    /// Whether server.js can stream
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Running)
+    }
+}
+
+impl fmt::Display for ServerTrayStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Starting => write!(f, "Starting"),
+            Self::Running => write!(f, "Running"),
+            Self::Unhealthy => write!(f, "Not responding"),
+            Self::Stopped => write!(f, "Stopped"),
+            Self::Restarting { attempt } => write!(f, "Restarting (attempt {attempt})"),
+            Self::Crashed {
+                exit_code: Some(exit_code),
+            } => write!(f, "Crashed (exit code {exit_code})"),
+            Self::Crashed { exit_code: None } => write!(f, "Crashed"),
+        }
+    }
+}
+
+/// The server.js started by the service, see [`Server::info`](crate::server::Server::info)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    pub status: ServerTrayStatus,
+    /// The address server.js listens on
+    pub url: String,
+    /// The version of the server.js started last, the bundled one or the one of a server-only update
+    pub version: String,
+    /// The process id, only while it's running
+    pub pid: Option<u32>,
+}
+
+impl ServerInfo {
+    /// The server.js of the version listening on `url` with the given status
+    pub fn new(status: ServerTrayStatus, url: String, version: String, pid: Option<u32>) -> Self {
+        Self {
+            status,
+            url,
+            version,
+            pid,
+        }
+    }
+}
+
+/// Everything the tray shows about the service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrayStatus {
+    /// The version of the service
+    pub version: String,
+    pub server: ServerTrayStatus,
+}
+
+impl TrayStatus {
+    /// The text of the tray status item, e.g. `Server: Running`
+    pub fn label(&self) -> String {
+        format!("Server: {}", self.server)
+    }
+}
+
+impl From<&ServerInfo> for TrayStatus {
+    fn from(server: &ServerInfo) -> Self {
+        Self {
+            version: BUILD_INFO.version.to_owned(),
+            server: server.status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::SERVER_URL;
+
+    #[test]
+    fn test_status_json() {
+        let info = ServerInfo::new(
+            ServerTrayStatus::Crashed { exit_code: Some(1) },
+            SERVER_URL.to_owned(),
+            "v4.20.9".to_owned(),
+            None,
+        );
+        let json = serde_json::to_value(&info).expect("Should serialize");
+        assert_eq!(
+            serde_json::json!({ "state": "crashed", "exitCode": 1 }),
+            json["status"]
+        );
+        assert_eq!(SERVER_URL, json["url"]);
+        assert_eq!("v4.20.9", json["version"]);
+        assert_eq!(
+            info,
+            serde_json::from_value(json).expect("Should deserialize")
+        );
+
+        let tray = TrayStatus::from(&ServerInfo::new(
+            ServerTrayStatus::Running,
+            SERVER_URL.to_owned(),
+            BUILD_INFO.server_version.to_owned(),
+            Some(42),
+        ));
+        assert_eq!(
+            serde_json::json!({ "version": BUILD_INFO.version, "server": { "state": "running" } }),
+            serde_json::to_value(&tray).expect("Should serialize")
+        );
+        assert_eq!("Server: Running", tray.label());
+
+        assert_eq!(
+            serde_json::json!({ "state": "restarting", "attempt": 2 }),
+            serde_json::to_value(ServerTrayStatus::Restarting { attempt: 2 })
+                .expect("Should serialize")
+        );
+    }
+}
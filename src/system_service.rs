@@ -0,0 +1,435 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Running the service as a Windows Service, started by the Service Control Manager on boot.
+//!
+//! `stremio-service --install-service` (as an administrator) registers it to start on boot as `NT AUTHORITY\LocalService`,
+//! so server.js is available before any user logs in and after they log off. It runs like
+//! [`Application::run_headless`], without the tray, the protocol handler and the autostart,
+//! with the settings, the logs and the server.js data of the profile of that account.
+//! `stremio-service --uninstall-service` stops and removes it.
+//!
+//! The account runs the executable it was registered with on every boot, so it's only registered from the Program Files,
+//! which only the administrators can write, see [`check_install_dir`]. Otherwise any user could replace it, e.g. in
+//! a per-user installation, and run their code as `LocalService`. For the same reason the service doesn't update itself,
+//! it's updated by the installers run by an administrator.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Error};
+
+use crate::app::Application;
+
+/// The name of the Windows Service
+pub const SERVICE_NAME: &str = crate::constants::APP_NAME;
+/// The argument the Service Control Manager starts the service with, see [`run`]
+pub const RUN_SERVICE_ARG: &str = "--run-service";
+
+/// Registers the current executable as a Windows Service started on boot and starts it.
+#[cfg(windows)]
+pub fn install() -> Result<(), Error> {
+    windows::install()
+}
+
+/// Stops and removes the Windows Service registered by [`install`], nothing is done when there's none.
+#[cfg(windows)]
+pub fn uninstall() -> Result<(), Error> {
+    windows::uninstall()
+}
+
+/// Runs the application as the Windows Service until it's stopped by the Service Control Manager.
+///
+/// It blocks the calling thread, the application runs on the given runtime.
+///
+/// # Errors
+///
+/// When the process wasn't started by the Service Control Manager.
+#[cfg(windows)]
+pub fn run(application: Application, runtime: tokio::runtime::Handle) -> Result<(), Error> {
+    windows::run(application, runtime)
+}
+
+/// Checks that the executable is inside one of the `protected_dirs`, only the administrators can write them.
+///
+/// Both are expected to be canonical, see [`std::fs::canonicalize`].
+#[cfg_attr(not(windows), allow(dead_code))]
+fn check_install_dir(exe: &Path, protected_dirs: &[PathBuf]) -> Result<(), Error> {
+    if !protected_dirs.iter().any(|dir| exe.starts_with(dir)) {
+        bail!(
+            "{} is not in the Program Files, only a machine-wide installation can be installed as a Windows Service",
+            exe.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// A NUL terminated UTF-16 string
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(not(windows))]
+pub fn install() -> Result<(), Error> {
+    bail!(
+        "Windows Services are not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(not(windows))]
+pub fn uninstall() -> Result<(), Error> {
+    bail!(
+        "Windows Services are not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(not(windows))]
+pub fn run(_application: Application, _runtime: tokio::runtime::Handle) -> Result<(), Error> {
+    bail!(
+        "Windows Services are not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{ffi::OsString, path::PathBuf, sync::Mutex, time::Duration};
+
+    use anyhow::{bail, Context, Error};
+    use log::{error, info, warn};
+    use windows_service::{
+        define_windows_service,
+        service::{
+            ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl,
+            ServiceExitCode, ServiceInfo, ServiceStartType, ServiceState, ServiceStatus,
+            ServiceType,
+        },
+        service_control_handler::{self, ServiceControlHandlerResult},
+        service_dispatcher,
+        service_manager::{ServiceManager, ServiceManagerAccess},
+    };
+    use windows_sys::Win32::{
+        Foundation::{
+            ERROR_SERVICE_DOES_NOT_EXIST, ERROR_SERVICE_EXISTS, ERROR_SERVICE_NOT_ACTIVE,
+        },
+        System::Com::CoTaskMemFree,
+        UI::Shell::{
+            FOLDERID_ProgramFiles, FOLDERID_ProgramFilesX86, SHGetKnownFolderPath, KF_FLAG_DEFAULT,
+        },
+    };
+
+    use super::{check_install_dir, RUN_SERVICE_ARG, SERVICE_NAME};
+    use crate::{app::Application, handle::ServiceHandle, server};
+
+    const DISPLAY_NAME: &str = "Stremio Service";
+    const DESCRIPTION: &str =
+        "Runs the Stremio streaming server (server.js) without a logged in user";
+    /// The least privileged account with network access
+    const ACCOUNT: &str = r"NT AUTHORITY\LocalService";
+    /// How long starting server.js may take between two reports of the progress
+    const START_WAIT_HINT: Duration = Duration::from_secs(10);
+    /// How long the service waits for server.js to respond before it's reported running anyway
+    const START_TIMEOUT: Duration = Duration::from_secs(120);
+    /// How long stopping server.js may take
+    const STOP_WAIT_HINT: Duration = Duration::from_secs(30);
+    /// The exit code of a service failing to start or to stop server.js
+    const SERVICE_FAILED: ServiceExitCode = ServiceExitCode::ServiceSpecific(1);
+
+    /// The application run by [`service_main`] on the runtime, the dispatcher doesn't pass any context
+    static SERVICE: Mutex<Option<(Application, tokio::runtime::Handle)>> = Mutex::new(None);
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    fn open_manager(access: ServiceManagerAccess) -> Result<ServiceManager, Error> {
+        ServiceManager::local_computer(None::<&str>, access)
+            .context("Failed to open the Service Control Manager, it requires an administrator")
+    }
+
+    /// Whether the error of the Service Control Manager is the given Win32 error
+    fn is_error(err: &windows_service::Error, code: u32) -> bool {
+        matches!(err, windows_service::Error::Winapi(err) if err.raw_os_error() == Some(code as i32))
+    }
+
+    /// The canonical Program Files directories, 64-bit and 32-bit
+    fn program_files() -> Vec<PathBuf> {
+        [FOLDERID_ProgramFiles, FOLDERID_ProgramFilesX86]
+            .iter()
+            .filter_map(|folder| {
+                let mut path = std::ptr::null_mut();
+                // SAFETY: the path is allocated by the call, it's freed even when it fails
+                let result =
+                    unsafe { SHGetKnownFolderPath(folder, KF_FLAG_DEFAULT as u32, 0, &mut path) };
+                let dir = (result == 0).then(|| {
+                    // SAFETY: the path is NUL terminated
+                    let len = (0..).take_while(|&i| unsafe { *path.add(i) } != 0).count();
+                    PathBuf::from(String::from_utf16_lossy(unsafe {
+                        std::slice::from_raw_parts(path, len)
+                    }))
+                });
+                unsafe { CoTaskMemFree(path as *const _) };
+
+                dir.and_then(|dir| std::fs::canonicalize(dir).ok())
+            })
+            .collect()
+    }
+
+    pub fn install() -> Result<(), Error> {
+        let exe = std::env::current_exe()
+            .and_then(std::fs::canonicalize)
+            .context("Failed to get current executable")?;
+        check_install_dir(&exe, &program_files())?;
+
+        let manager = open_manager(ServiceManagerAccess::CREATE_SERVICE)?;
+        let service_info = ServiceInfo {
+            name: SERVICE_NAME.into(),
+            display_name: DISPLAY_NAME.into(),
+            service_type: ServiceType::OWN_PROCESS,
+            start_type: ServiceStartType::AutoStart,
+            error_control: ServiceErrorControl::Normal,
+            executable_path: exe.clone(),
+            launch_arguments: vec![RUN_SERVICE_ARG.into()],
+            dependencies: vec![],
+            account_name: Some(ACCOUNT.into()),
+            account_password: None,
+        };
+        let service = match manager.create_service(
+            &service_info,
+            ServiceAccess::CHANGE_CONFIG | ServiceAccess::START,
+        ) {
+            Err(err) if is_error(&err, ERROR_SERVICE_EXISTS) => {
+                bail!("The {SERVICE_NAME} service is already installed")
+            }
+            service => service.context("Failed to create the service")?,
+        };
+        if let Err(err) = service.set_description(DESCRIPTION) {
+            warn!("Failed to set the description of the service: {err}");
+        }
+        info!(
+            "Installed the {SERVICE_NAME} service running {}",
+            exe.display()
+        );
+
+        service
+            .start::<OsString>(&[])
+            .context("The service is installed but it failed to start")?;
+        info!("Started the {SERVICE_NAME} service");
+
+        Ok(())
+    }
+
+    pub fn uninstall() -> Result<(), Error> {
+        let manager = open_manager(ServiceManagerAccess::CONNECT)?;
+        let service = match manager.open_service(
+            SERVICE_NAME,
+            ServiceAccess::STOP | ServiceAccess::QUERY_STATUS | ServiceAccess::DELETE,
+        ) {
+            Err(err) if is_error(&err, ERROR_SERVICE_DOES_NOT_EXIST) => return Ok(()),
+            service => service.context("Failed to open the service")?,
+        };
+
+        match service.stop() {
+            Err(err) if !is_error(&err, ERROR_SERVICE_NOT_ACTIVE) => {
+                warn!("Failed to stop the service: {err}")
+            }
+            _ => {}
+        }
+        // removed once it's stopped
+        service.delete().context("Failed to remove the service")?;
+        info!("Removed the {SERVICE_NAME} service");
+
+        Ok(())
+    }
+
+    pub fn run(application: Application, runtime: tokio::runtime::Handle) -> Result<(), Error> {
+        *SERVICE.lock().expect("Should lock the service") = Some((application, runtime));
+
+        // it returns once the service is stopped
+        service_dispatcher::start(SERVICE_NAME, ffi_service_main).context(format!(
+            "Not started by the Service Control Manager, `{RUN_SERVICE_ARG}` is only used by it"
+        ))
+    }
+
+    /// Runs the application on the thread started by the Service Control Manager.
+    ///
+    /// It's reported starting until server.js responds, then running until it's stopped.
+    fn service_main(_arguments: Vec<OsString>) {
+        let Some((application, runtime)) =
+            SERVICE.lock().ok().and_then(|mut service| service.take())
+        else {
+            error!("The service was started twice");
+            return;
+        };
+        let shutdown = application.shutdown_handle();
+        let stop = shutdown.clone();
+        let status_handle =
+            match service_control_handler::register(SERVICE_NAME, move |control| match control {
+                ServiceControl::Stop | ServiceControl::Shutdown => {
+                    stop.shutdown();
+                    ServiceControlHandlerResult::NoError
+                }
+                ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+                _ => ServiceControlHandlerResult::NotImplemented,
+            }) {
+                Ok(status_handle) => status_handle,
+                Err(err) => {
+                    error!("Failed to register the service control handler: {err}");
+                    return;
+                }
+            };
+        let set_status = |state, exit_code, checkpoint| {
+            if let Err(err) =
+                status_handle.set_service_status(service_status(state, exit_code, checkpoint))
+            {
+                warn!("Failed to set the status of the service: {err}");
+            }
+        };
+        set_status(ServiceState::StartPending, ServiceExitCode::Win32(0), 0);
+
+        let served = runtime.block_on(async {
+            let service = ServiceHandle::start(application).await?;
+
+            let url = service.info().await.url;
+            let ready = server::wait_until_ready(&url, START_TIMEOUT);
+            tokio::pin!(ready);
+            let mut progress = tokio::time::interval(START_WAIT_HINT / 2);
+            let mut checkpoint = 0;
+            loop {
+                tokio::select! {
+                    is_ready = &mut ready => {
+                        if !is_ready {
+                            warn!("server.js is not ready after {START_TIMEOUT:?}, the service is reported running anyway");
+                        }
+                        set_status(ServiceState::Running, ServiceExitCode::Win32(0), 0);
+                        break;
+                    }
+                    _ = progress.tick() => {
+                        checkpoint += 1;
+                        set_status(ServiceState::StartPending, ServiceExitCode::Win32(0), checkpoint);
+                    }
+                    _ = shutdown.wait() => break,
+                }
+            }
+
+            shutdown.wait().await;
+            set_status(ServiceState::StopPending, ServiceExitCode::Win32(0), 0);
+            service.stop().await
+        });
+        match served {
+            Ok(()) => set_status(ServiceState::Stopped, ServiceExitCode::Win32(0), 0),
+            Err(err) => {
+                error!("{err}");
+                set_status(ServiceState::Stopped, SERVICE_FAILED, 0);
+            }
+        }
+    }
+
+    /// The status reported to the Service Control Manager, it only accepts the controls while it's running
+    fn service_status(
+        state: ServiceState,
+        exit_code: ServiceExitCode,
+        checkpoint: u32,
+    ) -> ServiceStatus {
+        ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted: match state {
+                ServiceState::Running => {
+                    ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+                }
+                _ => ServiceControlAccept::empty(),
+            },
+            exit_code,
+            checkpoint,
+            wait_hint: match state {
+                ServiceState::StartPending => START_WAIT_HINT,
+                ServiceState::StopPending => STOP_WAIT_HINT,
+                _ => Duration::ZERO,
+            },
+            process_id: None,
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_service_status() {
+            let starting = service_status(ServiceState::StartPending, ServiceExitCode::Win32(0), 2);
+            assert_eq!(ServiceType::OWN_PROCESS, starting.service_type);
+            assert_eq!(ServiceControlAccept::empty(), starting.controls_accepted);
+            assert_eq!(2, starting.checkpoint);
+            assert_eq!(START_WAIT_HINT, starting.wait_hint);
+
+            let running = service_status(ServiceState::Running, ServiceExitCode::Win32(0), 0);
+            assert_eq!(
+                ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+                running.controls_accepted
+            );
+            assert_eq!(Duration::ZERO, running.wait_hint);
+
+            let stopping = service_status(ServiceState::StopPending, ServiceExitCode::Win32(0), 0);
+            assert_eq!(ServiceControlAccept::empty(), stopping.controls_accepted);
+            assert_eq!(STOP_WAIT_HINT, stopping.wait_hint);
+
+            let failed = service_status(ServiceState::Stopped, SERVICE_FAILED, 0);
+            assert_eq!(ServiceControlAccept::empty(), failed.controls_accepted);
+            assert_eq!(SERVICE_FAILED, failed.exit_code);
+        }
+
+        #[test]
+        fn test_program_files_are_found() {
+            assert!(!program_files().is_empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{check_install_dir, wide};
+
+    #[test]
+    fn test_wide_is_nul_terminated() {
+        assert_eq!(vec![0], wide(""));
+        assert_eq!(
+            vec![0x53, 0x74, 0xe9, 0xd83c, 0xdfac, 0],
+            wide("St\u{e9}\u{1f3ac}")
+        );
+    }
+
+    #[test]
+    fn test_only_program_files_are_installed() {
+        let program_files = [
+            PathBuf::from("/ProgramFiles"),
+            PathBuf::from("/ProgramFilesX86"),
+        ];
+
+        assert!(check_install_dir(
+            Path::new("/ProgramFiles/Stremio Service/stremio-service.exe"),
+            &program_files
+        )
+        .is_ok());
+        assert!(check_install_dir(
+            Path::new("/ProgramFilesX86/Stremio Service/stremio-service.exe"),
+            &program_files
+        )
+        .is_ok());
+        // a per-user installation
+        assert!(check_install_dir(
+            Path::new("/Users/user/AppData/Local/Programs/Stremio Service/stremio-service.exe"),
+            &program_files
+        )
+        .is_err());
+        // only whole components
+        assert!(check_install_dir(
+            Path::new("/ProgramFilesEvil/stremio-service.exe"),
+            &program_files
+        )
+        .is_err());
+        assert!(check_install_dir(Path::new("/ProgramFiles/stremio-service.exe"), &[]).is_err());
+    }
+}
@@ -0,0 +1,358 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Running the service as a systemd user unit on Linux.
+//!
+//! `stremio-service service install-systemd` writes the [`UNIT_NAME`] unit to the user units directory
+//! and enables it, it then starts the service instead of the autostart desktop entry (see [`autostart`](crate::autostart)).
+//! The unit is of `Type=notify`, the service tells systemd it's ready once server.js responds (see [`notify_ready`])
+//! and pings its [`Watchdog`] from the health check, systemd restarts the service when the pings stop.
+//! Inside Flatpak and snap the unit can only be installed from the host, it's printed with `--print` then.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Error};
+
+use crate::{constants::SYSTEMD_USER_UNITS_PATH, profile::Profile};
+
+/// The name of the user unit
+pub const UNIT_NAME: &str = "stremio-service.service";
+/// The delay before a failed service is restarted
+const RESTART_SEC: u32 = 5;
+/// The seconds without a [`Watchdog`] ping before the service is restarted
+const WATCHDOG_SEC: u32 = 60;
+/// The seconds until server.js responds, it may be repaired first
+const TIMEOUT_START_SEC: u32 = 300;
+
+/// The user unit file, in the `systemd/user` directory of the XDG config directory
+pub fn unit_file(home_dir: &Path) -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|config_home| !config_home.is_empty() && !Profile::is_flatpak())
+        .map(|config_home| PathBuf::from(config_home).join("systemd").join("user"))
+        .unwrap_or_else(|| home_dir.join(SYSTEMD_USER_UNITS_PATH))
+        .join(UNIT_NAME)
+}
+
+/// Whether the unit is installed for the user
+pub fn is_installed(home_dir: &Path) -> bool {
+    unit_file(home_dir).exists()
+}
+
+/// The contents of the unit starting the current executable, with `--headless` when `headless`.
+///
+/// The headless unit starts with the user manager, on boot when the user lingers.
+/// The other one starts with the graphical session, as the tray does.
+pub fn unit(headless: bool) -> Result<String, Error> {
+    let mut command = command()?;
+    if headless {
+        command.push("--headless".to_owned());
+    }
+    let exec_start = command
+        .iter()
+        .map(|argument| quote(argument))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let (session, wanted_by) = match headless {
+        true => ("", "default.target"),
+        false => (
+            "PartOf=graphical-session.target\nAfter=graphical-session.target\n",
+            "graphical-session.target",
+        ),
+    };
+
+    Ok(format!(
+        "[Unit]
+Description=Stremio Service
+Documentation={repository}
+{session}
+[Service]
+Type=notify
+NotifyAccess=main
+ExecStart={exec_start}
+TimeoutStartSec={TIMEOUT_START_SEC}
+WatchdogSec={WATCHDOG_SEC}
+Restart=on-failure
+RestartSec={RESTART_SEC}
+
+[Install]
+WantedBy={wanted_by}
+",
+        repository = env!("CARGO_PKG_REPOSITORY"),
+    ))
+}
+
+/// The command starting this installation of the service from the host
+fn command() -> Result<Vec<String>, Error> {
+    if let Some(flatpak_id) = std::env::var_os("FLATPAK_ID").filter(|_| Profile::is_flatpak()) {
+        return Ok(vec![
+            "/usr/bin/flatpak".to_owned(),
+            "run".to_owned(),
+            flatpak_id.to_string_lossy().into_owned(),
+        ]);
+    }
+    if let Some(snap_name) = std::env::var_os("SNAP_NAME").filter(|_| Profile::is_snap()) {
+        return Ok(vec![format!("/snap/bin/{}", snap_name.to_string_lossy())]);
+    }
+    // the executable of an AppImage is in its mount point, which changes on every start
+    let exe = match std::env::var_os("APPIMAGE") {
+        Some(appimage) => PathBuf::from(appimage),
+        None => std::env::current_exe().context("Failed to get current executable")?,
+    };
+
+    Ok(vec![exe.to_string_lossy().into_owned()])
+}
+
+/// Quotes an argument of `ExecStart`, escaping the quotes and the specifiers
+fn quote(argument: &str) -> String {
+    format!(
+        "\"{}\"",
+        argument
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+    )
+}
+
+/// Writes the unit, enables and starts it and removes the autostart desktop entry it replaces.
+///
+/// # Errors
+///
+/// Inside Flatpak and snap, where the user manager can't be reached.
+#[cfg(target_os = "linux")]
+pub fn install(home_dir: &Path, headless: bool) -> Result<(), Error> {
+    use log::{info, warn};
+
+    if Profile::is_flatpak() || Profile::is_snap() {
+        bail!(
+            "The unit can't be installed from the {} sandbox, install the output of `service install-systemd --print` to ~/{SYSTEMD_USER_UNITS_PATH}/{UNIT_NAME} instead",
+            Profile::current_name()
+        );
+    }
+
+    let file = unit_file(home_dir);
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(&file, unit(headless)?)
+        .context(format!("Failed to write {}", file.display()))?;
+    info!("Installed {}", file.display());
+
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", UNIT_NAME])?;
+    info!("Enabled and started {UNIT_NAME}");
+
+    // both would start the service on login
+    if let Err(err) = crate::autostart::remove_desktop_entry(home_dir) {
+        warn!("{err:#}");
+    }
+
+    Ok(())
+}
+
+/// Stops, disables and removes the unit, nothing is done when it's not installed.
+#[cfg(target_os = "linux")]
+pub fn uninstall(home_dir: &Path) -> Result<(), Error> {
+    let file = unit_file(home_dir);
+    if !file.exists() {
+        return Ok(());
+    }
+
+    systemctl(&["disable", "--now", UNIT_NAME])?;
+    std::fs::remove_file(&file).context(format!("Failed to remove {}", file.display()))?;
+    systemctl(&["daemon-reload"])?;
+    log::info!("Removed {}", file.display());
+
+    Ok(())
+}
+
+/// Whether the installed unit is enabled
+#[cfg(target_os = "linux")]
+pub fn is_enabled() -> bool {
+    std::process::Command::new("systemctl")
+        .args(["--user", "--quiet", "is-enabled", UNIT_NAME])
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Enables or disables the installed unit, without starting or stopping it.
+#[cfg(target_os = "linux")]
+pub fn set_enabled(enabled: bool) -> Result<(), Error> {
+    match enabled {
+        true => systemctl(&["enable", UNIT_NAME]),
+        false => systemctl(&["disable", UNIT_NAME]),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn systemctl(arguments: &[&str]) -> Result<(), Error> {
+    let output = std::process::Command::new("systemctl")
+        .arg("--user")
+        .args(arguments)
+        .output()
+        .context("Failed to run systemctl")?;
+    if !output.status.success() {
+        bail!(
+            "`systemctl --user {}` failed: {}",
+            arguments.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Tells systemd the service is ready, when it's started by a `Type=notify` unit.
+///
+/// Nothing is done outside of systemd, the errors are only logged.
+pub fn notify_ready() {
+    if let Err(err) = notify("READY=1") {
+        log::warn!("Failed to notify systemd: {err:#}");
+    }
+}
+
+/// The watchdog of the unit which started the service, see `WatchdogSec`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchdog {
+    timeout: Duration,
+}
+
+impl Watchdog {
+    /// The watchdog from the `WATCHDOG_USEC` environment variable, `None` when it's not enabled
+    /// or when it's meant for another process.
+    pub fn from_env() -> Option<Self> {
+        Self::from_vars(
+            std::env::var("WATCHDOG_USEC").ok().as_deref(),
+            std::env::var("WATCHDOG_PID").ok().as_deref(),
+            std::process::id(),
+        )
+    }
+
+    /// The watchdog of the `WATCHDOG_USEC` and `WATCHDOG_PID` values for the process `pid`
+    fn from_vars(usec: Option<&str>, watchdog_pid: Option<&str>, pid: u32) -> Option<Self> {
+        let timeout = usec?.parse::<u64>().ok().filter(|timeout| *timeout > 0)?;
+        if let Some(watchdog_pid) = watchdog_pid {
+            if watchdog_pid.parse::<u32>().ok()? != pid {
+                return None;
+            }
+        }
+
+        Some(Self {
+            timeout: Duration::from_micros(timeout),
+        })
+    }
+
+    /// The time without a ping before systemd restarts the service
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Tells systemd the service is alive, the errors are only logged.
+    pub fn ping(&self) {
+        if let Err(err) = notify("WATCHDOG=1") {
+            log::warn!("Failed to ping the systemd watchdog: {err:#}");
+        }
+    }
+}
+
+/// Sends the state to the `NOTIFY_SOCKET` of systemd, if any
+#[cfg(target_os = "linux")]
+fn notify(state: &str) -> Result<(), Error> {
+    match std::env::var_os("NOTIFY_SOCKET") {
+        Some(socket) => notify_socket(&socket, state),
+        None => Ok(()),
+    }
+}
+
+/// Sends the state to the socket, a path or an abstract name starting with `@`
+#[cfg(target_os = "linux")]
+fn notify_socket(socket: &std::ffi::OsStr, state: &str) -> Result<(), Error> {
+    use std::os::{
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram},
+    };
+
+    let socket = socket.to_string_lossy();
+    let address = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(socket.as_ref()),
+    }
+    .context(format!("Invalid NOTIFY_SOCKET {socket}"))?;
+
+    UnixDatagram::unbound()?
+        .send_to_addr(state.as_bytes(), &address)
+        .context(format!("Failed to send {state} to {socket}"))?;
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn notify(_state: &str) -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn install(_home_dir: &Path, _headless: bool) -> Result<(), Error> {
+    bail!(
+        "systemd units are not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn uninstall(_home_dir: &Path) -> Result<(), Error> {
+    bail!(
+        "systemd units are not supported on {}",
+        std::env::consts::OS
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exec_start_is_quoted() {
+        assert_eq!(
+            r#""/opt/Stremio Service/stremio-service""#,
+            quote("/opt/Stremio Service/stremio-service")
+        );
+        assert_eq!(r#""/home/100%%/a\"b""#, quote(r#"/home/100%/a"b"#));
+    }
+
+    #[test]
+    fn test_watchdog_from_vars() {
+        assert_eq!(
+            Some(Duration::from_secs(60)),
+            Watchdog::from_vars(Some("60000000"), Some("42"), 42)
+                .map(|watchdog| watchdog.timeout())
+        );
+        assert_eq!(
+            Some(Duration::from_secs(60)),
+            Watchdog::from_vars(Some("60000000"), None, 42).map(|watchdog| watchdog.timeout())
+        );
+        assert_eq!(
+            None,
+            Watchdog::from_vars(Some("60000000"), Some("1"), 42),
+            "Meant for another process"
+        );
+        assert_eq!(None, Watchdog::from_vars(Some("0"), None, 42));
+        assert_eq!(None, Watchdog::from_vars(None, None, 42));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_notify_sends_the_state() {
+        use std::os::unix::net::UnixDatagram;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        notify_socket(path.as_os_str(), "READY=1").expect("Should notify");
+
+        let mut buffer = [0; 16];
+        let length = socket.recv(&mut buffer).unwrap();
+        assert_eq!(b"READY=1", &buffer[..length]);
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The optional limit of the disk writes of server.js.
+//!
+//! The heavy sequential torrent writes to a slow HDD or SD card can stall the whole system while streaming.
+//! On Linux server.js runs in a transient systemd scope with `IOWriteBandwidthMax`,
+//! enforced with the cgroup `io.max` of the disk of its cache.
+//! systemd ignores it when the `io` controller is not delegated to the user manager.
+//! Without `systemd-run`, e.g. inside Flatpak, and on the other platforms the writes are not limited, with a warning.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use tokio::process::Command;
+
+use crate::error::ServiceError;
+
+/// The write throughput of server.js to the disk of a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteLimit {
+    path: PathBuf,
+    rate: String,
+}
+
+impl WriteLimit {
+    /// Limits the writes to the disk of the path to the rate, in bytes per second.
+    ///
+    /// The rate may have a `K`, `M`, `G` or `T` suffix, as in systemd, e.g. `20M`.
+    ///
+    /// # Errors
+    ///
+    /// When the rate is invalid.
+    pub fn new(path: PathBuf, rate: &str) -> Result<Self, ServiceError> {
+        let digits = rate.strip_suffix(['K', 'M', 'G', 'T']).unwrap_or(rate);
+        if digits.is_empty() || !digits.chars().all(|char| char.is_ascii_digit()) {
+            return Err(ServiceError::Config(anyhow!(
+                "`{rate}` is not a write limit, e.g. `20M` for 20 MiB/s"
+            )));
+        }
+
+        Ok(Self {
+            path,
+            rate: rate.to_owned(),
+        })
+    }
+
+    pub fn rate(&self) -> &str {
+        &self.rate
+    }
+
+    /// The systemd resource control property of the limit
+    pub fn property(&self) -> String {
+        format!("IOWriteBandwidthMax={} {}", self.path.display(), self.rate)
+    }
+
+    /// The command running the program with the limit, `None` when it can't be limited.
+    #[cfg(target_os = "linux")]
+    pub fn command(&self, program: &Path) -> Option<Command> {
+        let Some(systemd_run) = crate::util::find_in_path("systemd-run") else {
+            log::warn!("systemd-run is not found, the writes of server.js are not limited");
+            return None;
+        };
+        // the property is split on the whitespace
+        if self.path.to_string_lossy().contains(char::is_whitespace) {
+            log::warn!(
+                "The writes to {} can't be limited, the path has whitespace",
+                self.path.display()
+            );
+            return None;
+        }
+        // systemd looks up the disk of an existing path
+        if let Err(err) = std::fs::create_dir_all(&self.path) {
+            log::warn!(
+                "Failed to create {}, the writes of server.js are not limited: {err}",
+                self.path.display()
+            );
+            return None;
+        }
+
+        // the scope executes the program in place, it keeps the process id
+        let mut command = Command::new(systemd_run);
+        command
+            .args(["--user", "--scope", "--quiet", "--collect", "-p"])
+            .arg(self.property())
+            .arg("--")
+            .arg(program);
+
+        Some(command)
+    }
+
+    /// The command running the program with the limit, `None` when it can't be limited.
+    #[cfg(not(target_os = "linux"))]
+    pub fn command(&self, _program: &Path) -> Option<Command> {
+        log::warn!(
+            "Limiting the writes of server.js is not supported on {}",
+            std::env::consts::OS
+        );
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_limit_rate() {
+        let limit =
+            WriteLimit::new("/home/user/.stremio-server".into(), "20M").expect("Should be valid");
+        assert_eq!(
+            "IOWriteBandwidthMax=/home/user/.stremio-server 20M",
+            limit.property()
+        );
+        assert!(WriteLimit::new("/".into(), "1048576").is_ok());
+
+        for rate in ["", "M", "20MB", "-1", "1.5G"] {
+            assert!(
+                matches!(
+                    WriteLimit::new("/".into(), rate),
+                    Err(ServiceError::Config(_))
+                ),
+                "`{rate}` should be invalid"
+            );
+        }
+    }
+}
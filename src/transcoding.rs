@@ -0,0 +1,193 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The hardware transcoding of server.js, see the `hardwareTranscoding` setting.
+//!
+//! server.js transcodes the videos the player can't play with the software encoders of ffmpeg,
+//! which can keep every core busy on a slow machine. The hardware encoders are probed with the ffmpeg
+//! of server.js by encoding a single frame with each of them, so a driver that's missing or broken is found
+//! before server.js relies on it. With `auto` the hardware acceleration of server.js is enabled only when
+//! one of them works, `on` and `off` force it.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// How long an encoder is given to encode the frame, a hanging driver is not waited for
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// The frame encoded by the probes
+const TEST_SOURCE: &str = "color=black:size=256x256";
+
+/// Whether server.js transcodes with the hardware encoders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HwTranscoding {
+    /// Only when one of the hardware encoders works, see [`probe`]
+    #[default]
+    Auto,
+    /// Always, even when none of them is found to work
+    On,
+    /// Never, the hardware encoders are not probed either
+    Off,
+}
+
+impl HwTranscoding {
+    /// Whether the hardware acceleration of server.js is enabled with the `available` encoders
+    pub fn is_enabled(&self, available: &[HwAccel]) -> bool {
+        match self {
+            Self::Auto => !available.is_empty(),
+            Self::On => true,
+            Self::Off => false,
+        }
+    }
+}
+
+/// A hardware video encoder of ffmpeg
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HwAccel {
+    /// The Video Acceleration API of the Linux drivers, e.g. of the AMD and Intel GPUs
+    Vaapi,
+    /// The encoder of the NVIDIA GPUs
+    Nvenc,
+    /// The encoder of macOS
+    VideoToolbox,
+    /// The Quick Sync Video of the Intel GPUs
+    Qsv,
+}
+
+impl HwAccel {
+    /// The encoders probed on the current OS
+    pub fn candidates() -> &'static [Self] {
+        match std::env::consts::OS {
+            "linux" => &[Self::Vaapi, Self::Nvenc, Self::Qsv],
+            "windows" => &[Self::Nvenc, Self::Qsv],
+            "macos" => &[Self::VideoToolbox],
+            _ => &[],
+        }
+    }
+
+    /// The name shown in the tray
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Vaapi => "VAAPI",
+            Self::Nvenc => "NVENC",
+            Self::VideoToolbox => "VideoToolbox",
+            Self::Qsv => "Quick Sync",
+        }
+    }
+
+    /// The H.264 encoder of ffmpeg
+    fn encoder(&self) -> &'static str {
+        match self {
+            Self::Vaapi => "h264_vaapi",
+            Self::Nvenc => "h264_nvenc",
+            Self::VideoToolbox => "h264_videotoolbox",
+            Self::Qsv => "h264_qsv",
+        }
+    }
+
+    /// The ffmpeg command encoding a frame, `None` when the encoder can't work anyway
+    fn command(&self, ffmpeg: &Path) -> Option<Command> {
+        let mut command = Command::new(ffmpeg);
+        command.args(["-hide_banner", "-loglevel", "error"]);
+        match self {
+            Self::Vaapi => {
+                command.arg("-vaapi_device").arg(render_node()?);
+            }
+            Self::Nvenc | Self::VideoToolbox | Self::Qsv => {}
+        }
+        command.args(["-f", "lavfi", "-i", TEST_SOURCE, "-frames:v", "1"]);
+        match self {
+            // the frame is uploaded to the GPU
+            Self::Vaapi => command.args(["-vf", "format=nv12,hwupload"]),
+            Self::Qsv => command.args(["-vf", "format=nv12"]),
+            Self::Nvenc | Self::VideoToolbox => &mut command,
+        };
+        command
+            .args(["-c:v", self.encoder(), "-f", "null", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(target_os = "windows")]
+        command.creation_flags(crate::server::CREATE_NO_WINDOW);
+
+        Some(command)
+    }
+}
+
+/// The first DRM render node of the GPUs, the device of VAAPI
+fn render_node() -> Option<PathBuf> {
+    let mut nodes = std::fs::read_dir("/dev/dri")
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("renderD"))
+        })
+        .collect::<Vec<_>>();
+    nodes.sort();
+
+    nodes.into_iter().next()
+}
+
+/// The hardware encoders of the [`candidates`](HwAccel::candidates) which encode a frame with `ffmpeg`.
+pub async fn probe(ffmpeg: &Path) -> Vec<HwAccel> {
+    let mut available = vec![];
+    for &accel in HwAccel::candidates() {
+        let Some(mut command) = accel.command(ffmpeg) else {
+            debug!("{} has no device", accel.label());
+            continue;
+        };
+        match tokio::time::timeout(PROBE_TIMEOUT, command.output()).await {
+            Ok(Ok(output)) if output.status.success() => available.push(accel),
+            Ok(Ok(output)) => debug!(
+                "{} failed to encode: {}",
+                accel.label(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Ok(Err(err)) => debug!("Failed to run {}: {err}", ffmpeg.display()),
+            Err(_) => debug!("{} didn't encode in {PROBE_TIMEOUT:?}", accel.label()),
+        }
+    }
+
+    available
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hw_transcoding_is_enabled() {
+        assert!(HwTranscoding::Auto.is_enabled(&[HwAccel::Nvenc]));
+        assert!(!HwTranscoding::Auto.is_enabled(&[]));
+        assert!(HwTranscoding::On.is_enabled(&[]));
+        assert!(!HwTranscoding::Off.is_enabled(&[HwAccel::Nvenc]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_probe_finds_the_working_encoders() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ffmpeg = dir.path().join("ffmpeg");
+        // only NVENC works
+        std::fs::write(
+            &ffmpeg,
+            "#!/bin/sh\ncase \"$*\" in *h264_nvenc*) exit 0;; *) echo 'No device' >&2; exit 1;; esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(vec![HwAccel::Nvenc], probe(&ffmpeg).await);
+        assert!(probe(Path::new("/nonexistent/ffmpeg")).await.is_empty());
+    }
+}
@@ -1,30 +1,348 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
-use std::{io::Write, path::PathBuf, process::Command};
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
 
-use anyhow::{anyhow, Context};
-use log::{error, info};
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::{error, info, warn};
 use semver::{Version, VersionReq};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use tokio::io::AsyncWriteExt;
+use tokio::{sync::mpsc, task::JoinHandle};
 use url::Url;
 
-use crate::app::Config;
+use crate::{
+    app::Config, build_info::BUILD_INFO, error::ServiceError, minisign::PublicKey,
+    profile::Profile, rollback::Versions, staging::Staging, tls::Roots, util::get_current_exe_dir,
+};
 
-pub struct Update {
-    /// The new version that we update to
-    pub version: Version,
-    pub file: PathBuf,
+/// The top directory of the Linux release tarball, with the same files as the AppImage
+pub(crate) const TARBALL_DIR: &str = "stremio-service";
+/// The descriptor of the offline update bundles, see [`offline_provider`]
+const OFFLINE_DESCRIPTOR_NAME: &str = "descriptor.json";
+/// The record of the server.js installed by a server-only update, see [`ServerUpdate`]
+const SERVER_UPDATE_FILE: &str = "server.json";
+
+/// A downloaded and verified update
+pub enum Update {
+    /// The installer of a new version of the service, see [`Updater::run_updater_setup`]
+    Installer {
+        /// The new version that we update to
+        version: Version,
+        file: PathBuf,
+    },
+    /// Only a new server.js for the current version of the service, already installed to `file`
+    Server { version: String, file: PathBuf },
 }
 
 #[derive(Debug)]
 pub struct Updater {
     pub current_version: Version,
     pub next_version: VersionReq,
-    pub endpoint: Url,
+    /// Where the releases are checked and downloaded from
+    pub provider: Box<dyn UpdateProvider>,
     pub skip_update: bool,
     pub force_update: bool,
+    /// The directory where the installers are downloaded
+    pub download_dir: PathBuf,
+    /// How the update is downloaded and installed
+    pub installation: Installation,
+    /// The channel of the releases, already in the query of the endpoint
+    pub channel: Channel,
+    /// The key the installers are signed with, see [`PublicKey::embedded`]
+    pub public_key: Option<PublicKey>,
+    /// The version of the server.js run by the service,
+    /// `None` disables the server-only updates, e.g. of the system server.js
+    pub server_version: Option<String>,
+    /// Where the server.js of the server-only updates is installed, see [`ServerUpdate`]
+    pub server_dir: PathBuf,
+    /// When the downloaded installer is run
+    pub mode: UpdateMode,
+    /// The hour of the local time the [`UpdateMode::Scheduled`] updates are installed at
+    pub install_hour: u32,
+    /// Where the running version is kept before an update, see [`Updater::keep_current_version`]
+    pub versions: Versions,
+    /// Where the tarball of an update is extracted before it's installed, see [`staging`](crate::staging)
+    pub staging: Staging,
+    /// Told how much of the installer is downloaded, see [`Updater::with_progress`]
+    pub progress: ProgressHandler,
+}
+
+/// How much of an installer is downloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    /// The downloaded bytes
+    pub downloaded: u64,
+    /// The size of the installer, `None` when the server doesn't tell it
+    pub total: Option<u64>,
+}
+
+impl DownloadProgress {
+    /// The downloaded part, `None` without the size of the installer
+    pub fn percent(&self) -> Option<u64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| (self.downloaded.min(total) * 100) / total)
+    }
+}
+
+/// Called with the [`DownloadProgress`] while an installer is downloaded and with `None` once the download ended,
+/// whether it succeeded or not, e.g. to show it in the tray.
+///
+/// It's called from the runtime, for every chunk of the download, so it shouldn't block.
+#[derive(Clone, Default)]
+pub struct ProgressHandler(Option<Arc<dyn Fn(Option<DownloadProgress>) + Send + Sync>>);
+
+impl ProgressHandler {
+    pub fn new(on_progress: impl Fn(Option<DownloadProgress>) + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(on_progress)))
+    }
+
+    /// Renders the progress on the terminal, e.g. for `update apply`
+    pub fn terminal() -> Self {
+        Self::new(|progress| {
+            match progress {
+                Some(progress) => match progress.percent() {
+                    Some(percent) => print!("\rProgress: {percent}%"),
+                    None => print!("\rDownloaded: {} KiB", progress.downloaded / 1024),
+                },
+                None => println!(),
+            }
+            std::io::stdout().flush().ok();
+        })
+    }
+
+    pub fn report(&self, progress: Option<DownloadProgress>) {
+        if let Some(on_progress) = &self.0 {
+            on_progress(progress);
+        }
+    }
+}
+
+impl std::fmt::Debug for ProgressHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ProgressHandler")
+            .field(&self.0.as_ref().map(|_| "Fn"))
+            .finish()
+    }
+}
+
+/// The server.js installed by a server-only update.
+///
+/// It's run instead of the bundled one until the service itself is updated,
+/// as the installers bring their own server.js.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerUpdate {
+    /// The server.js version
+    pub version: String,
+    /// The version of the service it was installed for
+    pub service_version: String,
+    /// The SHA-256 checksum of the installed server.js
+    pub sha256: String,
+}
+
+impl ServerUpdate {
+    /// The installed server.js and its update, when it's for this version of the service and it's intact.
+    pub fn load(dir: &Path) -> Option<(PathBuf, Self)> {
+        let record = std::fs::read(dir.join(SERVER_UPDATE_FILE)).ok()?;
+        let update = serde_json::from_slice::<Self>(&record).ok()?;
+        if update.service_version != BUILD_INFO.version {
+            return None;
+        }
+
+        let server_js = dir.join("server.js");
+        let mut sha256 = Sha256::new();
+        io::copy(&mut std::fs::File::open(&server_js).ok()?, &mut sha256).ok()?;
+        if !format!("{:x}", sha256.finalize()).eq_ignore_ascii_case(&update.sha256) {
+            warn!(
+                "{} is corrupted, running the bundled server.js",
+                server_js.display()
+            );
+            return None;
+        }
+
+        Some((server_js, update))
+    }
+
+    /// The version of the server.js run by the service, the one installed to the directory or the bundled one.
+    ///
+    /// `None` when the service runs a server.js of the system, see the `no-bundled-server` feature.
+    pub fn installed_version(dir: &Path) -> Option<String> {
+        (!cfg!(feature = "no-bundled-server")).then(|| {
+            Self::load(dir).map_or_else(
+                || BUILD_INFO.server_version.to_owned(),
+                |(_, update)| update.version,
+            )
+        })
+    }
+
+    /// Installs the verified server.js to the directory and returns its path.
+    ///
+    /// The previous one is replaced at once and the record is written last,
+    /// so an interrupted update leaves a server.js which doesn't match it and the bundled one is run instead.
+    fn install(dir: &Path, file: &Path, update: &Self) -> Result<PathBuf, anyhow::Error> {
+        std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+        let server_js = dir.join("server.js");
+        let part = dir.join("server.js.part");
+        std::fs::copy(file, &part).context(format!("Failed to copy {}", file.display()))?;
+        std::fs::rename(&part, &server_js)
+            .context(format!("Failed to replace {}", server_js.display()))?;
+        std::fs::write(
+            dir.join(SERVER_UPDATE_FILE),
+            serde_json::to_vec_pretty(update)?,
+        )
+        .context("Failed to write the server.js update")?;
+        let _ = std::fs::remove_file(file);
+
+        Ok(server_js)
+    }
+}
+
+/// The release channel the service is updated from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// The releases
+    #[default]
+    Stable,
+    /// The release candidates
+    Beta,
+    /// The builds of the development branch
+    Nightly,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+            Self::Nightly => "nightly",
+        }
+    }
+
+    /// The name shown in the tray
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Stable => "Stable",
+            Self::Beta => "Beta",
+            Self::Nightly => "Nightly",
+        }
+    }
+
+    /// The updater endpoint with the channel in its query, the `file://` descriptors are left as they are.
+    ///
+    /// The beta channel keeps the `rc=true` of the release candidates as well,
+    /// which the endpoints understood before the channels.
+    pub fn endpoint(&self, endpoint: &Url) -> Url {
+        let mut endpoint = endpoint.clone();
+        if endpoint.scheme() == "file" {
+            return endpoint;
+        }
+
+        endpoint
+            .query_pairs_mut()
+            .append_pair("channel", self.as_str());
+        if *self == Self::Beta {
+            endpoint.query_pairs_mut().append_pair("rc", "true");
+        }
+
+        endpoint
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// When the downloaded installer of an update is run, see [`Updater::run_installer`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateMode {
+    /// Once server.js is idle
+    #[default]
+    Idle,
+    /// When the service quits, without starting it again
+    Quit,
+    /// At the install hour, once server.js is idle
+    Scheduled,
+}
+
+impl UpdateMode {
+    /// When the update is installed, as told to the user
+    pub fn when(&self) -> &'static str {
+        match self {
+            Self::Idle => "once nothing streams",
+            Self::Quit => "when the service quits",
+            Self::Scheduled => "at the scheduled hour once nothing streams",
+        }
+    }
+}
+
+/// The time from `now` until the next `hour` starts, zero during it.
+pub fn until_hour(now: chrono::NaiveDateTime, hour: u32) -> std::time::Duration {
+    use chrono::Timelike;
+
+    if now.hour() == hour {
+        return std::time::Duration::ZERO;
+    }
+    let mut next = now
+        .date()
+        .and_hms_opt(hour, 0, 0)
+        .expect("The hour should be validated");
+    if next < now {
+        next += chrono::Duration::days(1);
+    }
+
+    (next - now).to_std().unwrap_or_default()
+}
+
+/// How the running service was installed, it decides the file of the release and how it's installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Installation {
+    /// The setup of Windows or the DMG of macOS
+    Installer,
+    /// The AppImage at the path, replaced by the new AppImage
+    AppImage(PathBuf),
+    /// The release tarball extracted to the directory, it's replaced by the staged new one on the next start
+    Tarball(PathBuf),
+    /// A distribution, Flatpak or snap package, updated by the package manager
+    Package,
+}
+
+impl Installation {
+    /// The installation of the running service, by the platform [`Profile`].
+    pub fn current() -> Self {
+        match Profile::current_name() {
+            "windows" | "macos" => Self::Installer,
+            "appimage" => std::env::var_os("APPIMAGE")
+                .map(PathBuf::from)
+                .map_or(Self::Package, Self::AppImage),
+            "tarball" => Self::Tarball(get_current_exe_dir()),
+            _ => Self::Package,
+        }
+    }
+
+    /// Whether the file of the release installs this installation
+    fn accepts(&self, file_item: &FileItem) -> bool {
+        let path = file_item.url.path();
+
+        file_item.os == std::env::consts::OS
+            && match self {
+                Self::AppImage(_) => path.ends_with(".AppImage"),
+                Self::Tarball(_) => path.ends_with(".tar.gz"),
+                Self::Installer | Self::Package => true,
+            }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,6 +359,20 @@ pub struct FileItem {
     pub url: Url,
     pub checksum: String,
     os: String,
+    /// The minisign signature of the installer, the contents of its `.minisig`
+    pub signature: Option<String>,
+}
+
+/// The server.js of a release, for the server-only updates
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerFile {
+    /// The server.js version, e.g. `v4.20.8`
+    pub version: String,
+    pub url: Url,
+    pub checksum: String,
+    /// The minisign signature of server.js
+    pub signature: Option<String>,
 }
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -49,55 +381,200 @@ struct Descriptor {
     // tag: String,
     // released: String,
     files: Vec<FileItem>,
+    server: Option<ServerFile>,
 }
 
-impl Updater {
-    pub fn new(current_version: Version, config: &Config) -> Self {
-        Self {
-            next_version: VersionReq::parse(&format!(">{current_version}"))
-                .expect("Version is type-safe"),
-            current_version,
-            endpoint: config.updater_endpoint.clone(),
-            skip_update: config.skip_update,
-            force_update: config.force_update,
-        }
+impl Descriptor {
+    /// The release with the installer for this OS and installation
+    fn release(self, installation: &Installation) -> Result<Release, anyhow::Error> {
+        let installer = self
+            .files
+            .into_iter()
+            .find(|file_item| installation.accepts(file_item))
+            .context("No update for this OS")?;
+
+        Ok(Release {
+            version: Version::parse(&self.version)?,
+            installer,
+            server: self.server,
+        })
     }
+}
 
-    /// Updates the service only for non-linux OS and returns whether an update was made.
-    pub async fn prompt_and_update(&self) -> bool {
-        if self.skip_update {
-            info!("Skipping update check");
-            return false;
+/// What [`Updater::check`] found, responded by `GET /update` of the [`ControlApi`](crate::control::ControlApi)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub channel: Channel,
+    /// The newer version of the channel, `None` when the service is up to date
+    pub available_version: Option<String>,
+}
+
+/// The latest release found by an [`UpdateProvider`]
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: Version,
+    /// The installer for this OS
+    pub installer: FileItem,
+    /// The server.js of the release, when the descriptor has it
+    pub server: Option<ServerFile>,
+}
+
+/// Where the releases are checked, downloaded and verified from.
+///
+/// The [`Updater`] uses [`HttpProvider`] for the `http(s)://` endpoints and [`FileProvider`]
+/// for the `file://` ones, other sources (e.g. the GitHub releases or an internal mirror
+/// with its own API) are set with [`Updater::with_provider`].
+#[async_trait]
+pub trait UpdateProvider: std::fmt::Debug + Send + Sync {
+    /// Fetches the latest release, whether it's newer is decided by the [`Updater`].
+    async fn check(&self) -> Result<Release, anyhow::Error>;
+
+    /// Downloads the installer of the release to `download_dir` and returns its path,
+    /// the `progress` is reported as it's downloaded.
+    async fn download(
+        &self,
+        release: &Release,
+        download_dir: &Path,
+        progress: &ProgressHandler,
+    ) -> Result<Download, anyhow::Error>;
+
+    /// Verifies the downloaded installer, by default against the SHA-256 checksum of the release.
+    ///
+    /// The file is hashed again only when its checksum wasn't calculated while downloading.
+    async fn verify(&self, release: &Release, download: &Download) -> Result<(), anyhow::Error> {
+        let actual_sha256 = match &download.sha256 {
+            Some(sha256) => sha256.clone(),
+            None => {
+                let file = download.file.clone();
+                tokio::task::spawn_blocking(move || {
+                    let mut sha256 = Sha256::new();
+                    std::io::copy(&mut std::fs::File::open(file)?, &mut sha256)?;
+                    Ok::<_, io::Error>(format!("{:x}", sha256.finalize()))
+                })
+                .await??
+            }
+        };
+        if !actual_sha256.eq_ignore_ascii_case(&release.installer.checksum) {
+            bail!("Checksum verification failed");
         }
 
-        #[cfg(not(target_os = "linux"))]
-        {
-            info!("Fetching updates for >v{}", self.current_version);
+        Ok(())
+    }
+}
 
-            match self.autoupdate().await {
-                Ok(Some(update)) => {
-                    info!("Found update v{}", update.version.to_string());
+/// An installer downloaded by an [`UpdateProvider`]
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub file: PathBuf,
+    /// The SHA-256 checksum of the file, when it was calculated while downloading (see [`InstallerWriter`])
+    pub sha256: Option<String>,
+}
 
-                    self.run_updater_setup(update.file);
-                    return true;
-                }
-                Ok(None) => info!("No new updates found"),
-                Err(e) => error!("Failed to fetch updates: {e}"),
+/// The buffer of the installer file, the chunks of the responses are much smaller
+const WRITE_BUFFER_SIZE: usize = 1024 * 1024;
+/// How many chunks are queued for the writer before the download waits for it
+const QUEUED_CHUNKS: usize = 64;
+
+/// Writes a downloaded installer and calculates its SHA-256 checksum on a blocking worker.
+///
+/// The runtime only hands the chunks over, instead of hashing each one itself
+/// and waiting for a blocking file write per chunk, which is slow on the low-end devices.
+#[derive(Debug)]
+pub struct InstallerWriter {
+    chunks: mpsc::Sender<Bytes>,
+    worker: JoinHandle<io::Result<String>>,
+}
+
+impl InstallerWriter {
+    /// Creates, or truncates, the file and starts the worker writing to it.
+    pub fn create(file: PathBuf) -> Self {
+        let (chunks, mut queue) = mpsc::channel::<Bytes>(QUEUED_CHUNKS);
+        let worker = tokio::task::spawn_blocking(move || {
+            let mut writer =
+                std::io::BufWriter::with_capacity(WRITE_BUFFER_SIZE, std::fs::File::create(file)?);
+            let mut sha256 = Sha256::new();
+            while let Some(chunk) = queue.blocking_recv() {
+                sha256.update(&chunk);
+                writer.write_all(&chunk)?;
             }
+            writer.flush()?;
+
+            Ok(format!("{:x}", sha256.finalize()))
+        });
+
+        Self { chunks, worker }
+    }
+
+    /// Queues the chunk, it waits only when the worker is behind.
+    ///
+    /// # Errors
+    ///
+    /// When the worker has stopped, [`InstallerWriter::finish`] returns the reason.
+    pub async fn write(&self, chunk: Bytes) -> io::Result<()> {
+        self.chunks
+            .send(chunk)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The installer writer stopped"))
+    }
+
+    /// Waits for the queued chunks to be written and returns the SHA-256 checksum of the file.
+    pub async fn finish(self) -> io::Result<String> {
+        drop(self.chunks);
+
+        self.worker.await.map_err(io::Error::other)?
+    }
+}
+
+/// The releases of the Stremio update endpoints, or a mirror with the same API
+#[derive(Debug, Clone)]
+pub struct HttpProvider {
+    pub endpoint: Url,
+    /// Trusted besides the OS certificate store
+    pub tls_roots: Roots,
+}
+
+impl HttpProvider {
+    pub fn new(endpoint: Url) -> Self {
+        Self {
+            endpoint,
+            tls_roots: Roots::default(),
         }
+    }
+
+    /// Trust the given roots as well, e.g. of a mirror with a private certificate authority
+    pub fn with_tls_roots(mut self, tls_roots: Roots) -> Self {
+        self.tls_roots = tls_roots;
+        self
+    }
 
-        false
+    fn client(&self) -> Result<reqwest::Client, anyhow::Error> {
+        // the build details help triaging the reports from the different builds of a version
+        self.tls_roots
+            .client_builder()
+            .default_headers(BUILD_INFO.headers())
+            .build()
+            .context("Failed to build the updater client")
     }
+}
 
-    async fn check_for_update(&self) -> Result<(FileItem, Version), anyhow::Error> {
+#[async_trait]
+impl UpdateProvider for HttpProvider {
+    async fn check(&self) -> Result<Release, anyhow::Error> {
         info!("Using updater endpoint {}", &self.endpoint);
-        let update_response = reqwest::get(self.endpoint.clone())
+        let client = self.client()?;
+        let update_response = client
+            .get(self.endpoint.clone())
+            .send()
             .await
             .context("Cannot fetch response from the updater endpoint")?
             .json::<UpdateResponse>()
             .await
             .context("Invalid response from the updater endpoint")?;
-        let update_descriptor = reqwest::get(update_response.version_desc)
+        let update_descriptor = client
+            .get(update_response.version_desc)
+            .send()
             .await
             .context("Cannot fetch the update descriptor")?
             .json::<Descriptor>()
@@ -107,118 +584,1076 @@ impl Updater {
         if update_response.version != update_descriptor.version {
             return Err(anyhow!("Mismatched update versions"));
         }
-        let installer = update_descriptor
-            .files
-            .iter()
-            .find(|file_item| file_item.os == std::env::consts::OS)
-            .context("No update for this OS")?;
-        let version = Version::parse(update_descriptor.version.as_str())?;
-        if !self.force_update && !self.next_version.matches(&version) {
-            return Err(anyhow!(
-                "No new releases found that match the requirement of `{}`",
-                self.next_version
-            ));
-        }
-        Ok((installer.clone(), version))
+
+        update_descriptor.release(&Installation::current())
     }
 
-    async fn download_and_verify_installer(
+    async fn download(
         &self,
-        url: Url,
-        expected_sha256: &str,
-    ) -> Result<PathBuf, anyhow::Error> {
-        let mut installer_response = reqwest::get(url.clone()).await?;
-        let size = installer_response.content_length();
+        release: &Release,
+        download_dir: &Path,
+        progress: &ProgressHandler,
+    ) -> Result<Download, anyhow::Error> {
+        let url = release.installer.url.clone();
+        // an error page isn't written as the installer
+        let mut installer_response = self
+            .client()?
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        let total = installer_response.content_length();
         let mut downloaded: u64 = 0;
-        let mut sha256 = Sha256::new();
-        let temp_dir = std::env::temp_dir();
-        let file_name = std::path::Path::new(url.path())
-            .file_name()
-            .context("Invalid file name")?
-            .to_str()
-            .context("The path is not valid UTF-8")?
-            .to_string();
-        let dest = temp_dir.join(&file_name);
-
-        println!("Downloading {} to {}", url, dest.display());
-
-        let mut file = tokio::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(dest.clone())
-            .await?;
+        let dest = download_dir.join(file_name(&url)?);
+
+        info!("Downloading {} to {}", url, dest.display());
+
+        let writer = InstallerWriter::create(dest.clone());
         while let Some(chunk) = installer_response.chunk().await? {
-            sha256.update(&chunk);
-            file.write_all(&chunk).await?;
-            if let Some(size) = size {
-                downloaded += chunk.len() as u64;
-                print!("\rProgress: {}%", downloaded * 100 / size);
-            } else {
-                print!(".");
+            let chunk_size = chunk.len() as u64;
+            if writer.write(chunk).await.is_err() {
+                break;
             }
-            std::io::stdout().flush().ok();
+            downloaded += chunk_size;
+            progress.report(Some(DownloadProgress { downloaded, total }));
+        }
+        let sha256 = writer
+            .finish()
+            .await
+            .context(format!("Failed to write {}", dest.display()))?;
+
+        Ok(Download {
+            file: dest,
+            sha256: Some(sha256),
+        })
+    }
+}
+
+/// The releases of a local descriptor, e.g. `file:///srv/stremio-service/descriptor.json`
+///
+/// The descriptor has the format of the `versionDesc` of the update endpoints and its installers
+/// are `file://` URLs as well, for the tests and the machines without access to the endpoints.
+#[derive(Debug, Clone)]
+pub struct FileProvider {
+    pub descriptor: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new(descriptor: PathBuf) -> Self {
+        Self { descriptor }
+    }
+}
+
+#[async_trait]
+impl UpdateProvider for FileProvider {
+    async fn check(&self) -> Result<Release, anyhow::Error> {
+        info!("Using update descriptor {}", self.descriptor.display());
+        let descriptor = tokio::fs::read(&self.descriptor).await.context(format!(
+            "Cannot read the update descriptor {}",
+            self.descriptor.display()
+        ))?;
+
+        serde_json::from_slice::<Descriptor>(&descriptor)
+            .context("Invalid update descriptor")?
+            .release(&Installation::current())
+    }
+
+    /// Copies the local installer of the release, the ones of the other URLs are expected next to the descriptor,
+    /// e.g. when the descriptor of the endpoint was copied with its installers.
+    async fn download(
+        &self,
+        release: &Release,
+        download_dir: &Path,
+        _progress: &ProgressHandler,
+    ) -> Result<Download, anyhow::Error> {
+        let url = &release.installer.url;
+        let source = match url.scheme() {
+            "file" => url
+                .to_file_path()
+                .map_err(|_| anyhow!("The installer {url} is not a local file"))?,
+            _ => self
+                .descriptor
+                .parent()
+                .unwrap_or(Path::new("."))
+                .join(file_name(url)?),
+        };
+
+        copy_installer(&source, download_dir).await
+    }
+}
+
+/// The installer of a release given as a local file, e.g. on an air-gapped machine, see [`offline_provider`]
+#[derive(Debug)]
+pub struct InstallerProvider {
+    release: Release,
+}
+
+impl InstallerProvider {
+    /// The installer of the `version`, verified against the `sha256` checksum
+    /// and the signature of the `.minisig` next to it, the installer is refused without one.
+    ///
+    /// # Errors
+    ///
+    /// When the installer doesn't exist or it doesn't install the `installation`, e.g. an AppImage of the tarball.
+    pub fn new(
+        file: &Path,
+        version: Version,
+        sha256: String,
+        installation: &Installation,
+    ) -> Result<Self, anyhow::Error> {
+        let file =
+            std::fs::canonicalize(file).context(format!("Failed to find {}", file.display()))?;
+        let url = Url::from_file_path(&file)
+            .map_err(|()| anyhow!("Invalid installer path {}", file.display()))?;
+        let mut signature_file = file.clone().into_os_string();
+        signature_file.push(".minisig");
+        let installer = FileItem {
+            url,
+            checksum: sha256,
+            os: std::env::consts::OS.to_owned(),
+            signature: std::fs::read_to_string(signature_file).ok(),
+        };
+        if !installation.accepts(&installer) {
+            bail!("{} doesn't install this installation", file.display());
+        }
+
+        Ok(Self {
+            release: Release {
+                version,
+                installer,
+                server: None,
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl UpdateProvider for InstallerProvider {
+    async fn check(&self) -> Result<Release, anyhow::Error> {
+        Ok(self.release.clone())
+    }
+
+    async fn download(
+        &self,
+        release: &Release,
+        download_dir: &Path,
+        _progress: &ProgressHandler,
+    ) -> Result<Download, anyhow::Error> {
+        let url = &release.installer.url;
+        let source = url
+            .to_file_path()
+            .map_err(|_| anyhow!("The installer {url} is not a local file"))?;
+
+        copy_installer(&source, download_dir).await
+    }
+}
+
+/// The provider of a local update for `update apply`, by the kind of the `file`:
+///
+/// - `.zip` - a bundle with the `descriptor.json` of the endpoint and the installers it lists, extracted to `download_dir`
+/// - `.json` - an update descriptor, see [`FileProvider`]
+/// - an installer, its `version` and `sha256` checksum have to be given, see [`InstallerProvider`]
+pub fn offline_provider(
+    file: &Path,
+    version: Option<Version>,
+    sha256: Option<String>,
+    download_dir: &Path,
+) -> Result<Box<dyn UpdateProvider>, anyhow::Error> {
+    let extension = file
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("zip") => {
+            let bundle_dir = download_dir.join("offline");
+            if bundle_dir.exists() {
+                std::fs::remove_dir_all(&bundle_dir)
+                    .context(format!("Failed to remove {}", bundle_dir.display()))?;
+            }
+            let bundle =
+                std::fs::File::open(file).context(format!("Failed to open {}", file.display()))?;
+            zip_extract::extract(bundle, &bundle_dir, true)
+                .context(format!("Failed to extract {}", file.display()))?;
+            let descriptor = bundle_dir.join(OFFLINE_DESCRIPTOR_NAME);
+            if !descriptor.is_file() {
+                bail!("{} has no {OFFLINE_DESCRIPTOR_NAME}", file.display());
+            }
+
+            Ok(Box::new(FileProvider::new(descriptor)))
         }
-        println!();
-        let actual_sha256 = format!("{:x}", sha256.finalize());
-        if actual_sha256 != expected_sha256 {
-            tokio::fs::remove_file(dest).await?;
-            return Err(anyhow::anyhow!("Checksum verification failed"));
+        Some("json") => Ok(Box::new(FileProvider::new(file.to_owned()))),
+        _ => {
+            let (Some(version), Some(sha256)) = (version, sha256) else {
+                bail!("The version and the SHA-256 checksum of the installer are required to verify it");
+            };
+
+            Ok(Box::new(InstallerProvider::new(
+                file,
+                version,
+                sha256,
+                &Installation::current(),
+            )?))
         }
-        println!("Checksum verified.");
-        Ok(dest)
     }
+}
+
+/// Copies the local installer to the download directory, its checksum is calculated by [`UpdateProvider::verify`].
+async fn copy_installer(source: &Path, download_dir: &Path) -> Result<Download, anyhow::Error> {
+    let dest = download_dir.join(
+        source
+            .file_name()
+            .context(format!("Not an installer {}", source.display()))?,
+    );
+
+    info!("Copying {} to {}", source.display(), dest.display());
+    tokio::fs::copy(&source, &dest)
+        .await
+        .context(format!("Failed to copy {}", source.display()))?;
+
+    Ok(Download {
+        file: dest,
+        sha256: None,
+    })
+}
+
+/// The provider of the endpoint, by its scheme
+fn provider_for(endpoint: &Url, tls_roots: &Roots) -> Box<dyn UpdateProvider> {
+    match endpoint.to_file_path() {
+        Ok(descriptor) if endpoint.scheme() == "file" => Box::new(FileProvider::new(descriptor)),
+        _ => Box::new(HttpProvider::new(endpoint.clone()).with_tls_roots(tls_roots.clone())),
+    }
+}
+
+/// The name of the downloaded installer
+fn file_name(url: &Url) -> Result<String, anyhow::Error> {
+    Ok(Path::new(url.path())
+        .file_name()
+        .context("Invalid file name")?
+        .to_str()
+        .context("The path is not valid UTF-8")?
+        .to_string())
+}
+
+impl Updater {
+    /// The updater of the configured endpoint and channel.
+    ///
+    /// The version reverted last is skipped, see [`Versions::skipped`].
+    pub fn new(current_version: Version, config: &Config) -> Self {
+        let versions = Versions::new(config.directories.versions());
+        let skipped = versions
+            .skipped()
+            .filter(|skipped| *skipped > current_version);
+        if let Some(skipped) = &skipped {
+            info!("Skipping the updates up to the reverted v{skipped}");
+        }
+
+        Self {
+            next_version: VersionReq::parse(&format!(
+                ">{}",
+                skipped.as_ref().unwrap_or(&current_version)
+            ))
+            .expect("Version is type-safe"),
+            current_version,
+            provider: provider_for(
+                &config.update_channel.endpoint(&config.updater_endpoint),
+                &config.tls_roots,
+            ),
+            skip_update: config.skip_update,
+            force_update: config.force_update,
+            download_dir: config.directories.updates(),
+            installation: Installation::current(),
+            channel: config.update_channel,
+            public_key: PublicKey::embedded(),
+            server_version: ServerUpdate::installed_version(&config.directories.server_update()),
+            server_dir: config.directories.server_update(),
+            mode: config.update_mode,
+            install_hour: config.update_hour,
+            versions,
+            staging: Staging::new(config.directories.staging()),
+            progress: ProgressHandler::default(),
+        }
+    }
+
+    /// Checks and downloads the releases from the given provider instead of the updater endpoint.
+    pub fn with_provider(mut self, provider: impl UpdateProvider + 'static) -> Self {
+        self.provider = Box::new(provider);
+        self
+    }
+
+    /// Reports the progress of the installer downloads to the handler, they're only logged otherwise.
+    pub fn with_progress(mut self, progress: ProgressHandler) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Downloads the update, if any, for [`Updater::run_updater_setup`].
+    ///
+    /// The failures are only logged, the service keeps running the current version.
+    /// Whether the updater is skipped by default depends on the platform [`Profile`](crate::profile::Profile).
+    pub async fn fetch_update(&self) -> Option<Update> {
+        if self.skip_update {
+            info!("Skipping update check");
+            return None;
+        }
+
+        info!(
+            "Fetching updates for >v{} on the {} channel",
+            self.current_version, self.channel
+        );
+
+        match self.autoupdate().await {
+            Ok(Some(update)) => {
+                match &update {
+                    Update::Installer { version, .. } => info!("Found update v{version}"),
+                    Update::Server { version, .. } => info!("Installed server.js {version}"),
+                }
+
+                return Some(update);
+            }
+            Ok(None) => info!("No new updates found"),
+            Err(e) => error!("Failed to fetch updates: {e}"),
+        }
+
+        None
+    }
+
+    /// The release of only the server.js, when the service is up to date but its server.js is not
+    fn server_release(&self, release: &Release) -> Option<Release> {
+        let server_version = self.server_version.as_deref()?;
+        let server = release.server.as_ref().filter(|server| {
+            release.version == self.current_version && server.version != server_version
+        })?;
+
+        Some(Release {
+            version: release.version.clone(),
+            installer: FileItem {
+                url: server.url.clone(),
+                checksum: server.checksum.clone(),
+                os: std::env::consts::OS.to_owned(),
+                signature: server.signature.clone(),
+            },
+            server: None,
+        })
+    }
+
+    async fn download_and_verify_installer(
+        &self,
+        release: &Release,
+    ) -> Result<PathBuf, anyhow::Error> {
+        tokio::fs::create_dir_all(&self.download_dir).await?;
+        let download = self
+            .provider
+            .download(release, &self.download_dir, &self.progress)
+            .await;
+        self.progress.report(None);
+        let download = download?;
+        let verified = match self.provider.verify(release, &download).await {
+            Ok(()) => self.verify_signature(release, &download.file).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = verified {
+            tokio::fs::remove_file(download.file).await?;
+            return Err(err);
+        }
+        info!("Checksum verified");
+        Ok(download.file)
+    }
+
+    /// Verifies the signature of the installer against the [`Updater::public_key`],
+    /// unlike the checksum it doesn't come from the endpoint the installer came from.
+    ///
+    /// It's verified whatever the provider, the builds without a public key refuse every installer.
+    /// The signature has to be of the version of the release, see [`minisign`](crate::minisign).
+    async fn verify_signature(&self, release: &Release, file: &Path) -> Result<(), anyhow::Error> {
+        let Some(public_key) = self.public_key.clone() else {
+            bail!("This build has no update public key, the installer can't be verified");
+        };
+        let signature = release
+            .installer
+            .signature
+            .clone()
+            .context("The installer is not signed")?;
+
+        let file = file.to_owned();
+        let version = release.version.to_string();
+        tokio::task::spawn_blocking(move || public_key.verify(&file, &signature, &version))
+            .await??;
+        info!("Signature verified");
+
+        Ok(())
+    }
+
+    /// Checks whether there's a newer release, without downloading it.
+    ///
+    /// The check is done even when the updates are skipped, e.g. for a packaged service.
+    pub async fn check(&self) -> Result<UpdateCheck, ServiceError> {
+        let release = self.provider.check().await.map_err(ServiceError::Update)?;
+
+        Ok(UpdateCheck {
+            current_version: self.current_version.to_string(),
+            channel: self.channel,
+            available_version: self
+                .next_version
+                .matches(&release.version)
+                .then(|| release.version.to_string()),
+        })
+    }
+
+    /// Fetches the latest update from the update provider.
+    ///
+    /// When only the server.js of the current version is newer, just server.js is downloaded and installed.
+    pub async fn autoupdate(&self) -> Result<Option<Update>, ServiceError> {
+        let release = self.provider.check().await.map_err(ServiceError::Update)?;
+        if !self.force_update && !self.next_version.matches(&release.version) {
+            let Some(server_release) = self.server_release(&release) else {
+                return Err(ServiceError::Update(anyhow!(
+                    "No new releases found that match the requirement of `{}`",
+                    self.next_version
+                )));
+            };
+
+            let install = async {
+                let server = release.server.context("The release has no server.js")?;
+                let file = self.download_and_verify_installer(&server_release).await?;
+                let update = ServerUpdate {
+                    version: server.version,
+                    service_version: BUILD_INFO.version.to_owned(),
+                    sha256: server.checksum,
+                };
+                let server_js = ServerUpdate::install(&self.server_dir, &file, &update)?;
+
+                Ok(Update::Server {
+                    version: update.version,
+                    file: server_js,
+                })
+            };
+
+            return install.await.map(Some).map_err(ServiceError::Update);
+        }
 
-    /// Fetches the latest update from the update server.
-    pub async fn autoupdate(&self) -> Result<Option<Update>, anyhow::Error> {
-        let (installer, version) = self.check_for_update().await?;
         let dest = self
-            .download_and_verify_installer(installer.url, &installer.checksum)
-            .await?;
-        let update = Some(Update {
-            version,
+            .download_and_verify_installer(&release)
+            .await
+            .map_err(ServiceError::Update)?;
+        let update = Some(Update::Installer {
+            version: release.version,
             file: dest,
         });
         Ok(update)
     }
 
-    pub fn run_updater_setup(&self, file_path: PathBuf) {
+    /// Keeps the running version for reverting the update to the given version, see [`Versions::keep`].
+    ///
+    /// The failures are only logged, the update is installed anyway.
+    pub fn keep_current_version(&self, update: &Version, installer: &Path) {
+        if let Err(err) =
+            self.versions
+                .keep(&self.installation, &self.current_version, update, installer)
+        {
+            warn!("The update can't be reverted: {err:#}");
+        }
+    }
+
+    /// Runs the downloaded installer of the version and returns whether it was started successfully.
+    ///
+    /// The AppImage is replaced in place instead and the updated executable is started.
+    /// The tarball is staged and this executable is started again, it installs the staged files first, see [`Staging`].
+    pub fn run_updater_setup(&self, version: &Version, file_path: PathBuf) -> bool {
+        self.run_installer(version, file_path, true)
+    }
+
+    /// Runs the downloaded installer like [`Updater::run_updater_setup`], the updated version is started
+    /// only when `relaunch` is set, e.g. not when the update is installed on quit.
+    pub fn run_installer(&self, version: &Version, file_path: PathBuf, relaunch: bool) -> bool {
         match std::env::consts::OS {
             "windows" => {
                 let mut command = Command::new(file_path);
-                command.args([
-                    "/SILENT",
-                    "/NOCANCEL",
-                    "/FORCECLOSEAPPLICATIONS",
-                    "/TASKS=runapp",
-                ]);
+                command.args(["/SILENT", "/NOCANCEL", "/FORCECLOSEAPPLICATIONS"]);
+                if relaunch {
+                    command.arg("/TASKS=runapp");
+                }
 
                 match command.spawn() {
-                    Ok(process) => info!("Updater started. (PID {:?})", process.id()),
-                    Err(err) => error!("Updater couldn't be started: {err}"),
-                };
+                    Ok(process) => {
+                        info!("Updater started. (PID {:?})", process.id());
+                        true
+                    }
+                    Err(err) => {
+                        error!("Updater couldn't be started: {err}");
+                        false
+                    }
+                }
             }
             "macos" => {
+                // The app is notarized and stapled, so Gatekeeper accepts it despite the quarantine of the download
                 let mut command = Command::new("/bin/sh");
-                command.args(["-c", format!("DMG=\"{}\" && NEW=/Applications/$(date +%s).app && MNT=\"/Volumes/StremioService$(date +%s)\" && hdiutil attach \"$DMG\" -nobrowse -noautoopen && cp -R \"$MNT\"/*.app \"$NEW\" && rm -rf /Applications/StremioService.app && mv \"$NEW\" \"/Applications/StremioService.app\" && xattr -d com.apple.quarantine /Applications/StremioService.app; hdiutil detach \"$MNT\"", file_path.display()).as_str()]);
+                // the disk image is passed as `$0`, it's never parsed by the shell
+                command
+                    .args(["-c", "NEW=/Applications/$(date +%s).app && MNT=\"/Volumes/StremioService$(date +%s)\" && hdiutil attach \"$0\" -mountpoint \"$MNT\" -nobrowse -noautoopen && cp -R \"$MNT\"/*.app \"$NEW\" && rm -rf /Applications/StremioService.app && mv \"$NEW\" \"/Applications/StremioService.app\"; hdiutil detach \"$MNT\""])
+                    .arg(file_path);
                 match command.status() {
                     Ok(status) => {
-                        if status.success() {
+                        if status.success() && !relaunch {
+                            info!("Updater finished.");
+                            true
+                        } else if status.success() {
                             info!("Updater finished. Running updated app...");
                             let mut command = Command::new("/bin/sh");
-                            command.args(["-c", "sleep 5; open -n /Applications/Stremio.app"]);
+                            command
+                                .args(["-c", "sleep 5; open -n /Applications/StremioService.app"]);
                             match command.spawn() {
                                 Ok(_) => info!("Updated app started."),
                                 Err(err) => error!("Updated app couldn't be started: {err}"),
                             };
+                            true
                         } else {
                             error!("Updater errored with status: {status}");
+                            false
                         }
                     }
-                    Err(err) => error!("Updater couldn't be started: {err}"),
+                    Err(err) => {
+                        error!("Updater couldn't be started: {err}");
+                        false
+                    }
+                }
+            }
+            _ => {
+                let installed = match &self.installation {
+                    Installation::AppImage(appimage) => replace_appimage(&file_path, appimage),
+                    Installation::Tarball(install_dir) => self
+                        .staging
+                        .stage(&file_path, version, install_dir)
+                        .map(|()| install_dir.join("stremio-service")),
+                    _ => {
+                        error!("Updates aren't supported on: {}", std::env::consts::OS);
+                        return false;
+                    }
+                };
+
+                match installed {
+                    Ok(_) if !relaunch => {
+                        info!("Update installed.");
+                        true
+                    }
+                    Ok(executable) => {
+                        info!("Update installed. Running updated app...");
+                        start_after_exit(&executable);
+                        true
+                    }
+                    Err(err) => {
+                        error!("Failed to install the update: {err:#}");
+                        false
+                    }
                 }
             }
-            _ => error!("Updates aren't supported on: {}", std::env::consts::OS),
         }
     }
 }
+
+/// Starts the executable once this instance has exited and released the lockfile, the failures are only logged.
+pub(crate) fn start_after_exit(executable: &Path) {
+    let mut command = Command::new("/bin/sh");
+    command.args(["-c", "sleep 5; exec \"$0\""]).arg(executable);
+    match command.spawn() {
+        Ok(_) => info!("Updated app started."),
+        Err(err) => error!("Updated app couldn't be started: {err}"),
+    };
+}
+
+/// Replaces the AppImage with the downloaded one and returns the path to start it.
+///
+/// The new AppImage is copied next to the old one first, so it's swapped by a rename on the same file system.
+pub(crate) fn replace_appimage(file: &Path, appimage: &Path) -> Result<PathBuf, anyhow::Error> {
+    let file_name = appimage
+        .file_name()
+        .context("Invalid AppImage path")?
+        .to_string_lossy();
+    let staged = appimage.with_file_name(format!(".{file_name}.update"));
+
+    std::fs::copy(file, &staged).context(format!("Failed to copy {}", file.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))
+            .context(format!("Failed to make {} executable", staged.display()))?;
+    }
+    if let Err(err) = std::fs::rename(&staged, appimage) {
+        let _ = std::fs::remove_file(&staged);
+        return Err(err).context(format!("Failed to replace {}", appimage.display()));
+    }
+
+    Ok(appimage.to_path_buf())
+}
+
+/// Records the version running now to the file and returns the one run before when it's older,
+/// i.e. when the service was updated since.
+pub fn record_version(file: &Path, current_version: &Version) -> Option<Version> {
+    let last_version = std::fs::read_to_string(file)
+        .ok()
+        .and_then(|version| version.trim().parse::<Version>().ok());
+    if last_version.as_ref() != Some(current_version) {
+        if let Err(err) = std::fs::write(file, current_version.to_string()) {
+            warn!("Failed to write {}: {err}", file.display());
+        }
+    }
+
+    last_version.filter(|last_version| last_version < current_version)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const UPDATE_ENDPOINT: &str = crate::constants::UPDATE_ENDPOINT[0];
+
+    /// Writes an installer and its descriptor, returns the updater using them
+    fn local_updater(checksum: Option<&str>) -> (Updater, TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+
+        let installer = dir.path().join("StremioServiceSetup.exe");
+        std::fs::write(&installer, b"installer").unwrap();
+        std::fs::write(
+            dir.path().join("StremioServiceSetup.exe.minisig"),
+            crate::minisign::test::sign(b"installer", "StremioServiceSetup.exe", "99.0.0"),
+        )
+        .unwrap();
+        let checksum = checksum
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| hex::encode(Sha256::digest(b"installer")));
+        let descriptor = dir.path().join("descriptor.json");
+        let contents = serde_json::json!({
+            "version": "99.0.0",
+            "files": [{
+                "url": Url::from_file_path(&installer).unwrap(),
+                "checksum": checksum,
+                "os": std::env::consts::OS,
+                "signature": crate::minisign::test::sign(b"installer", "StremioServiceSetup.exe", "99.0.0"),
+            }],
+        });
+        std::fs::write(&descriptor, contents.to_string()).unwrap();
+
+        let updater = Updater {
+            current_version: Version::new(0, 1, 0),
+            next_version: VersionReq::parse(">0.1.0").unwrap(),
+            provider: provider_for(
+                &Url::from_file_path(&descriptor).unwrap(),
+                &Roots::default(),
+            ),
+            skip_update: false,
+            force_update: false,
+            download_dir: dir.path().join("updates"),
+            installation: Installation::Installer,
+            channel: Channel::Stable,
+            public_key: Some(crate::minisign::test::public_key()),
+            server_version: Some("v4.20.8".to_owned()),
+            server_dir: dir.path().join("server"),
+            mode: UpdateMode::Idle,
+            install_hour: 4,
+            versions: Versions::new(dir.path().join("versions")),
+            staging: Staging::new(dir.path().join("staging")),
+            progress: ProgressHandler::default(),
+        };
+
+        (updater, dir)
+    }
+
+    #[tokio::test]
+    async fn test_file_provider_update() {
+        let (updater, dir) = local_updater(None);
+
+        let update = updater
+            .autoupdate()
+            .await
+            .expect("Should update")
+            .expect("Should find the update");
+        let Update::Installer { version, file } = update else {
+            panic!("Should be an installer update");
+        };
+        assert_eq!(Version::new(99, 0, 0), version);
+        assert_eq!(
+            dir.path().join("updates").join("StremioServiceSetup.exe"),
+            file
+        );
+        assert_eq!(b"installer".as_slice(), std::fs::read(&file).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_download_progress_is_reported() {
+        let (updater, _dir) = local_updater(None);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let installer_server =
+            hyper::Server::from_tcp(listener)
+                .unwrap()
+                .serve(hyper::service::make_service_fn(|_| async {
+                    Ok::<_, std::convert::Infallible>(hyper::service::service_fn(|_| async {
+                        Ok::<_, std::convert::Infallible>(hyper::Response::new(hyper::Body::from(
+                            "installer",
+                        )))
+                    }))
+                }));
+        tokio::spawn(installer_server);
+
+        let reports = Arc::new(std::sync::Mutex::new(vec![]));
+        let progress = ProgressHandler::new({
+            let reports = reports.clone();
+            move |progress| reports.lock().unwrap().push(progress)
+        });
+        let url = Url::parse(&format!("http://{address}/StremioServiceSetup.exe")).unwrap();
+        let release = Release {
+            version: Version::new(99, 0, 0),
+            installer: FileItem {
+                url: url.clone(),
+                checksum: hex::encode(Sha256::digest(b"installer")),
+                os: std::env::consts::OS.to_owned(),
+                signature: None,
+            },
+            server: None,
+        };
+        std::fs::create_dir_all(&updater.download_dir).unwrap();
+        HttpProvider::new(url)
+            .download(&release, &updater.download_dir, &progress)
+            .await
+            .expect("Should download it");
+
+        let last = reports.lock().unwrap().last().copied().flatten().unwrap();
+        assert_eq!(
+            DownloadProgress {
+                downloaded: 9,
+                total: Some(9)
+            },
+            last
+        );
+        assert_eq!(Some(100), last.percent());
+        assert_eq!(
+            None,
+            DownloadProgress {
+                downloaded: 9,
+                total: None
+            }
+            .percent()
+        );
+
+        // the end of the download is reported by the updater, whatever the provider
+        let updater = updater.with_progress(progress);
+        updater.autoupdate().await.expect("Should update");
+        assert_eq!(Some(&None), reports.lock().unwrap().last());
+    }
+
+    #[tokio::test]
+    async fn test_error_page_is_not_downloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let installer_server =
+            hyper::Server::from_tcp(listener)
+                .unwrap()
+                .serve(hyper::service::make_service_fn(|_| async {
+                    Ok::<_, std::convert::Infallible>(hyper::service::service_fn(|_| async {
+                        hyper::Response::builder()
+                            .status(hyper::StatusCode::NOT_FOUND)
+                            .body(hyper::Body::from("Not Found"))
+                    }))
+                }));
+        tokio::spawn(installer_server);
+
+        let url = Url::parse(&format!("http://{address}/StremioServiceSetup.exe")).unwrap();
+        let release = Release {
+            version: Version::new(99, 0, 0),
+            installer: FileItem {
+                url: url.clone(),
+                checksum: hex::encode(Sha256::digest(b"installer")),
+                os: std::env::consts::OS.to_owned(),
+                signature: None,
+            },
+            server: None,
+        };
+        assert!(HttpProvider::new(url)
+            .download(&release, dir.path(), &ProgressHandler::default())
+            .await
+            .is_err());
+        assert!(!dir.path().join("StremioServiceSetup.exe").exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_verification_removes_the_installer() {
+        let (updater, dir) = local_updater(Some(&"0".repeat(64)));
+
+        let err = updater.autoupdate().await.err().expect("Should not verify");
+        assert!(
+            matches!(&err, ServiceError::Update(err) if err.to_string() == "Checksum verification failed")
+        );
+        assert!(!dir
+            .path()
+            .join("updates")
+            .join("StremioServiceSetup.exe")
+            .exists());
+    }
+
+    #[tokio::test]
+    async fn test_signature_is_verified() {
+        let (mut updater, dir) = local_updater(None);
+
+        updater
+            .autoupdate()
+            .await
+            .expect("Should verify the signature")
+            .expect("Should find the update");
+
+        // the builds without a public key don't install anything
+        updater.public_key = None;
+        let err = updater
+            .autoupdate()
+            .await
+            .err()
+            .expect("Should not accept it unverified");
+        assert!(
+            matches!(&err, ServiceError::Update(err) if err.to_string().starts_with("This build has no update public key"))
+        );
+        assert!(!dir
+            .path()
+            .join("updates")
+            .join("StremioServiceSetup.exe")
+            .exists());
+        updater.public_key = Some(crate::minisign::test::public_key());
+
+        // a compromised endpoint serves the checksum of its installer, but it can't sign it
+        let descriptor = dir.path().join("descriptor.json");
+        let mut contents: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&descriptor).unwrap()).unwrap();
+        contents["files"][0]["signature"] = serde_json::Value::Null;
+        std::fs::write(&descriptor, contents.to_string()).unwrap();
+
+        let err = updater.autoupdate().await.err().expect("Should not verify");
+        assert!(
+            matches!(&err, ServiceError::Update(err) if err.to_string() == "The installer is not signed")
+        );
+        assert!(!dir
+            .path()
+            .join("updates")
+            .join("StremioServiceSetup.exe")
+            .exists());
+    }
+
+    #[tokio::test]
+    async fn test_local_installer_is_verified() {
+        let (mut updater, dir) = local_updater(None);
+        let installer = dir.path().join("StremioServiceSetup.exe");
+        let checksum = hex::encode(Sha256::digest(b"installer"));
+        updater.provider = Box::new(
+            InstallerProvider::new(
+                &installer,
+                Version::new(2, 0, 0),
+                checksum,
+                &Installation::Installer,
+            )
+            .expect("Should accept the installer"),
+        );
+
+        let Some(Update::Installer { version, file }) =
+            updater.autoupdate().await.expect("Should verify it")
+        else {
+            panic!("Should be an installer update");
+        };
+        assert_eq!(Version::new(2, 0, 0), version);
+        assert_eq!(
+            dir.path().join("updates").join("StremioServiceSetup.exe"),
+            file
+        );
+
+        updater.provider = Box::new(
+            InstallerProvider::new(
+                &installer,
+                Version::new(2, 0, 0),
+                "0".repeat(64),
+                &Installation::Installer,
+            )
+            .unwrap(),
+        );
+        assert!(updater.autoupdate().await.is_err(), "Wrong checksum");
+        assert!(
+            InstallerProvider::new(
+                &installer,
+                Version::new(2, 0, 0),
+                "0".repeat(64),
+                &Installation::Tarball(dir.path().to_path_buf()),
+            )
+            .is_err(),
+            "Not a tarball"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_older_release_is_not_downloaded() {
+        let (mut updater, dir) = local_updater(None);
+        updater.next_version = VersionReq::parse(">99.0.0").unwrap();
+
+        assert!(updater.autoupdate().await.is_err());
+        assert!(!dir.path().join("updates").exists());
+    }
+
+    #[tokio::test]
+    async fn test_only_server_js_is_updated() {
+        let (mut updater, dir) = local_updater(None);
+        updater.current_version = Version::new(99, 0, 0);
+        updater.next_version = VersionReq::parse(">99.0.0").unwrap();
+
+        let server_js = dir.path().join("server.js");
+        std::fs::write(&server_js, b"server.js").unwrap();
+        let descriptor = dir.path().join("descriptor.json");
+        let mut contents: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&descriptor).unwrap()).unwrap();
+        contents["server"] = serde_json::json!({
+            "version": "v4.20.9",
+            "url": Url::from_file_path(&server_js).unwrap(),
+            "checksum": hex::encode(Sha256::digest(b"server.js")),
+            "signature": crate::minisign::test::sign(b"server.js", "server.js", "99.0.0"),
+        });
+        std::fs::write(&descriptor, contents.to_string()).unwrap();
+
+        let update = updater
+            .autoupdate()
+            .await
+            .expect("Should update")
+            .expect("Should find the update");
+        let Update::Server { version, file } = update else {
+            panic!("Should be a server.js update");
+        };
+        assert_eq!("v4.20.9", version);
+        assert_eq!(dir.path().join("server").join("server.js"), file);
+        assert!(!dir.path().join("updates").join("server.js").exists());
+
+        let (loaded, update) =
+            ServerUpdate::load(&updater.server_dir).expect("Should load the update");
+        assert_eq!(file, loaded);
+        assert_eq!("v4.20.9", update.version);
+
+        // the same server.js is not downloaded again
+        updater.server_version = Some(update.version);
+        assert!(updater.autoupdate().await.is_err());
+
+        // a corrupted server.js is not run
+        std::fs::write(&file, b"corrupted").unwrap();
+        assert_eq!(None, ServerUpdate::load(&updater.server_dir));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_installer_writer_hashes_the_written_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("StremioServiceSetup.exe");
+        let contents = Bytes::from(
+            (0..3 * WRITE_BUFFER_SIZE)
+                .map(|i| i as u8)
+                .collect::<Vec<_>>(),
+        );
+
+        let writer = InstallerWriter::create(file.clone());
+        for chunk in contents.chunks(16 * 1024) {
+            writer
+                .write(contents.slice_ref(chunk))
+                .await
+                .expect("Should queue the chunk");
+        }
+        let sha256 = writer.finish().await.expect("Should write the file");
+
+        assert_eq!(hex::encode(Sha256::digest(&contents)), sha256);
+        assert_eq!(contents, std::fs::read(&file).unwrap());
+    }
+
+    #[test]
+    fn test_record_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("last-version");
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert_eq!(None, record_version(&file, &version("0.1.13")), "First run");
+        assert_eq!(None, record_version(&file, &version("0.1.13")));
+        assert_eq!(
+            Some(version("0.1.13")),
+            record_version(&file, &version("0.1.14")),
+            "Updated"
+        );
+        assert_eq!(None, record_version(&file, &version("0.1.14")));
+        assert_eq!(
+            None,
+            record_version(&file, &version("0.1.12")),
+            "Downgraded"
+        );
+    }
+
+    #[test]
+    fn test_channel_endpoint() {
+        let endpoint = Url::parse(UPDATE_ENDPOINT).unwrap();
+
+        assert_eq!(
+            format!("{UPDATE_ENDPOINT}&channel=stable"),
+            Channel::Stable.endpoint(&endpoint).as_str()
+        );
+        assert_eq!(
+            format!("{UPDATE_ENDPOINT}&channel=beta&rc=true"),
+            Channel::Beta.endpoint(&endpoint).as_str()
+        );
+        assert_eq!(
+            format!("{UPDATE_ENDPOINT}&channel=nightly"),
+            Channel::Nightly.endpoint(&endpoint).as_str()
+        );
+
+        let descriptor = Url::parse("file:///srv/stremio-service/descriptor.json").unwrap();
+        assert_eq!(descriptor, Channel::Nightly.endpoint(&descriptor));
+    }
+
+    #[test]
+    fn test_until_hour() {
+        let at = |hour, minute| {
+            chrono::NaiveDate::from_ymd_opt(2026, 3, 1)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap()
+        };
+
+        assert_eq!(Duration::from_secs(90 * 60), until_hour(at(2, 30), 4));
+        assert_eq!(Duration::ZERO, until_hour(at(4, 59), 4), "During the hour");
+        assert_eq!(
+            Duration::from_secs(23 * 60 * 60),
+            until_hour(at(5, 0), 4),
+            "The next day"
+        );
+    }
+
+    #[test]
+    fn test_release_for_the_installation() {
+        let file_item = |name: &str| FileItem {
+            url: Url::parse(&format!(
+                "https://dl.strem.io/stremio-service/v99.0.0/{name}"
+            ))
+            .unwrap(),
+            checksum: String::new(),
+            os: std::env::consts::OS.to_string(),
+            signature: None,
+        };
+        let descriptor = || Descriptor {
+            version: "99.0.0".to_string(),
+            files: vec![
+                file_item("StremioService-x86_64.AppImage"),
+                file_item("stremio-service-x86_64.tar.gz"),
+            ],
+            server: None,
+        };
+
+        let release = |installation| {
+            descriptor()
+                .release(&installation)
+                .unwrap()
+                .installer
+                .url
+                .path()
+                .to_string()
+        };
+        assert!(release(Installation::AppImage(PathBuf::new())).ends_with(".AppImage"));
+        assert!(release(Installation::Tarball(PathBuf::new())).ends_with(".tar.gz"));
+    }
+}
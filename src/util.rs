@@ -1,5 +1,6 @@
-// Copyright (C) 2017-2024 Smart Code OOD 203358507
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
 
+use anyhow::Context;
 use log::error;
 use std::{
     env,
@@ -7,17 +8,14 @@ use std::{
 };
 use tao::system_tray;
 
-pub fn load_icon(buffer: &[u8]) -> system_tray::Icon {
-    let (icon_rgba, icon_width, icon_height) = {
-        let image = image::load_from_memory(buffer)
-            .expect("Failed to open icon path")
-            .into_rgba8();
-        let (width, height) = image.dimensions();
-        let rgba = image.into_raw();
+/// Decodes the PNG, only the RGBA is kept by the icon
+pub fn load_icon(buffer: &[u8]) -> Result<system_tray::Icon, anyhow::Error> {
+    let image = image::load_from_memory(buffer)
+        .context("Failed to decode the icon")?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
 
-        (rgba, width, height)
-    };
-    system_tray::Icon::from_rgba(icon_rgba, icon_width, icon_height).expect("Failed to open icon")
+    system_tray::Icon::from_rgba(image.into_raw(), width, height).context("Failed to open icon")
 }
 
 pub fn get_current_exe_dir() -> PathBuf {
@@ -30,6 +28,15 @@ pub fn get_current_exe_dir() -> PathBuf {
     PathBuf::from(current_exe_dir)
 }
 
+/// Finds the executable in the directories of the `PATH` environment variable
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let executable = format!("{name}{}", env::consts::EXE_SUFFIX);
+
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&executable))
+        .find(|path| path.is_file())
+}
+
 pub fn create_dir_if_does_not_exists(path: &Path) {
     if !path.exists() {
         if let Err(e) = std::fs::create_dir_all(path) {
@@ -37,3 +44,73 @@ pub fn create_dir_if_does_not_exists(path: &Path) {
         }
     }
 }
+
+/// The open files limit wanted for the torrent swarms of server.js
+#[cfg(unix)]
+const OPEN_FILES_LIMIT: libc::rlim_t = 65536;
+
+/// Raises the soft limit of open files of the service, inherited by server.js, and returns it.
+///
+/// Some distributions default to 1024, which large torrent swarms exhaust and the streams fail.
+/// The limit is raised up to the hard one, which only root can raise.
+#[cfg(unix)]
+pub fn raise_open_files_limit() -> std::io::Result<libc::rlim_t> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: the limit outlives the call
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    // macOS refuses a soft limit above OPEN_MAX (10240 in `sys/syslimits.h`), even when the hard one is unlimited
+    #[cfg(target_os = "macos")]
+    let hard_limit = limit.rlim_max.min(10240);
+    #[cfg(not(target_os = "macos"))]
+    let hard_limit = limit.rlim_max;
+
+    let wanted = OPEN_FILES_LIMIT.min(hard_limit);
+    if limit.rlim_cur >= wanted {
+        return Ok(limit.rlim_cur);
+    }
+    if wanted < OPEN_FILES_LIMIT {
+        log::warn!(
+            "The hard limit of open files is {hard_limit}, large torrents may fail to stream"
+        );
+    }
+
+    let previous = limit.rlim_cur;
+    limit.rlim_cur = wanted;
+    // SAFETY: the limit outlives the call
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    log::info!("Raised the limit of open files from {previous} to {wanted}");
+
+    Ok(wanted)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_open_files_limit_is_raised() {
+        let raised = raise_open_files_limit().expect("Should raise the limit");
+
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        assert_eq!(0, unsafe {
+            libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit)
+        });
+        assert_eq!(raised, limit.rlim_cur);
+        assert!(raised >= OPEN_FILES_LIMIT.min(limit.rlim_max));
+        assert_eq!(
+            raised,
+            raise_open_files_limit().expect("Should keep the limit")
+        );
+    }
+}
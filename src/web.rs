@@ -0,0 +1,383 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! Serving a local build of Stremio Web, see the `localWebUi` setting.
+//!
+//! The files of a [stremio-web](https://github.com/Stremio/stremio-web) build directory are served at
+//! `http://127.0.0.1:11473/web/`, which is opened instead of `https://web.stremio.com`. Stremio works offline then,
+//! and the page isn't served over HTTPS, so its requests to the plain HTTP server.js aren't blocked as mixed content.
+//! The routes of Stremio Web are in the fragment of its url, so only the files of the build are served.
+//!
+//! The builds with the `embedded-web` feature embed the build directory given by `STREMIO_WEB_BUILD` at build time,
+//! it's served without `localWebUi`, see [`LocalWebUi::embedded`].
+
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use log::{info, warn};
+use url::Url;
+
+use crate::error::ServiceError;
+
+/// The path of the Web UI on the server
+pub const WEB_PATH: &str = "/web/";
+const INDEX_FILE_NAME: &str = "index.html";
+
+/// A stremio-web build served on the address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalWebUi {
+    files: Files,
+    address: SocketAddr,
+}
+
+/// Where the files of the build are read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Files {
+    /// The build directory
+    Dir(PathBuf),
+    /// The build embedded in the binary, see [`EmbeddedBuild`]
+    #[cfg(feature = "embedded-web")]
+    Embedded,
+}
+
+/// The build directory of `STREMIO_WEB_BUILD`, embedded with the `embedded-web` feature
+#[cfg(feature = "embedded-web")]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "$STREMIO_WEB_BUILD"]
+struct EmbeddedBuild;
+
+impl std::fmt::Display for Files {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Files::Dir(dir) => write!(f, "{}", dir.display()),
+            #[cfg(feature = "embedded-web")]
+            Files::Embedded => write!(f, "the embedded build"),
+        }
+    }
+}
+
+impl LocalWebUi {
+    /// Serves the build directory on the loopback interface, or on all the interfaces for the other devices with `lan`.
+    ///
+    /// # Errors
+    ///
+    /// When the directory has no `index.html`, e.g. it's not a stremio-web build.
+    pub fn new(dir: PathBuf, port: u16, lan: bool) -> Result<Self, ServiceError> {
+        if !dir.join(INDEX_FILE_NAME).is_file() {
+            return Err(ServiceError::Config(anyhow::anyhow!(
+                "{} has no {INDEX_FILE_NAME}, it's not a build of Stremio Web",
+                dir.display()
+            )));
+        }
+        let ip = match lan {
+            true => Ipv4Addr::UNSPECIFIED,
+            false => Ipv4Addr::LOCALHOST,
+        };
+
+        Ok(Self {
+            files: Files::Dir(dir),
+            address: SocketAddr::from((ip, port)),
+        })
+    }
+
+    /// Serves the build embedded in the binary, like [`LocalWebUi::new`].
+    #[cfg(feature = "embedded-web")]
+    pub fn embedded(port: u16, lan: bool) -> Self {
+        let ip = match lan {
+            true => Ipv4Addr::UNSPECIFIED,
+            false => Ipv4Addr::LOCALHOST,
+        };
+
+        Self {
+            files: Files::Embedded,
+            address: SocketAddr::from((ip, port)),
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    /// The local url of the Web UI, e.g. `http://127.0.0.1:11473/web/`
+    pub fn url(&self) -> Url {
+        format!("http://127.0.0.1:{}{WEB_PATH}", self.address.port())
+            .parse()
+            .expect("Should be a valid url")
+    }
+
+    /// Serves the files until the `shutdown` future resolves.
+    ///
+    /// # Errors
+    ///
+    /// When the address can't be listened on, e.g. it's used by another program.
+    pub async fn serve(self, shutdown: impl Future<Output = ()>) -> Result<(), ServiceError> {
+        let address = self.address;
+        let builder = hyper::Server::try_bind(&address)
+            .context(format!("Failed to listen on {address}"))
+            .map_err(ServiceError::Other)?;
+
+        let files = self.files.clone();
+        let make_service = make_service_fn(move |_| {
+            let files = files.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let files = files.clone();
+                    async move { Ok::<_, Infallible>(handle(&files, request).await) }
+                }))
+            }
+        });
+
+        info!(
+            "Serving Stremio Web from {} on http://{address}{WEB_PATH}",
+            self.files
+        );
+        builder
+            .serve(make_service)
+            .with_graceful_shutdown(shutdown)
+            .await
+            .context("Serving Stremio Web failed")
+            .map_err(ServiceError::Other)
+    }
+}
+
+/// Responds with the file of the build at the path of the request.
+async fn handle(files: &Files, request: Request<Body>) -> Response<Body> {
+    if !matches!(request.method(), &Method::GET | &Method::HEAD) {
+        return respond(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed");
+    }
+    let path = request.uri().path();
+    if path == "/" || path == WEB_PATH.trim_end_matches('/') {
+        return Response::builder()
+            .status(StatusCode::MOVED_PERMANENTLY)
+            .header(header::LOCATION, WEB_PATH)
+            .body(Body::empty())
+            .expect("Should be a valid response");
+    }
+
+    let segments = match path.strip_prefix(WEB_PATH).map(file_segments) {
+        Some(Ok(segments)) => segments,
+        Some(Err(err)) => {
+            warn!("Refused to serve {path}: {err}");
+            return respond(StatusCode::NOT_FOUND, "Not found");
+        }
+        None => return respond(StatusCode::NOT_FOUND, "Not found"),
+    };
+    let (file, contents) = match read(files, &segments).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return respond(StatusCode::NOT_FOUND, "Not found"),
+        Err(err) => {
+            warn!("Failed to read {path}: {err}");
+            return respond(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read the file");
+        }
+    };
+
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, content_type(&file))
+        .header(header::CONTENT_LENGTH, contents.len())
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+    let body = match request.method() {
+        &Method::HEAD => Body::empty(),
+        _ => Body::from(contents),
+    };
+
+    response.body(body).expect("Should be a valid response")
+}
+
+/// The file names of the percent-encoded path.
+///
+/// # Errors
+///
+/// When the path isn't inside the build, e.g. with a `..`.
+fn file_segments(path: &str) -> Result<Vec<String>, anyhow::Error> {
+    let path = urlencoding::decode(path).context("Not an UTF-8 path")?;
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            let is_normal = matches!(
+                Path::new(segment).components().collect::<Vec<_>>()[..],
+                [Component::Normal(_)]
+            );
+            if !is_normal || segment.contains(['\\', ':']) {
+                bail!("`{segment}` is not a file name");
+            }
+
+            Ok(segment.to_owned())
+        })
+        .collect()
+}
+
+/// The name and the contents of the file of the build, the `index.html` of the sub-directories.
+/// `None` when there's no such file.
+async fn read(
+    files: &Files,
+    segments: &[String],
+) -> Result<Option<(PathBuf, Vec<u8>)>, std::io::Error> {
+    match files {
+        Files::Dir(dir) => {
+            let mut file = dir.to_path_buf();
+            file.extend(segments);
+            if file.is_dir() {
+                file.push(INDEX_FILE_NAME);
+            }
+
+            match tokio::fs::read(&file).await {
+                Ok(contents) => Ok(Some((file, contents))),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+        #[cfg(feature = "embedded-web")]
+        Files::Embedded => {
+            let name = segments.join("/");
+            let index = match name.is_empty() {
+                true => INDEX_FILE_NAME.to_owned(),
+                false => format!("{name}/{INDEX_FILE_NAME}"),
+            };
+
+            Ok([name, index].into_iter().find_map(|name| {
+                EmbeddedBuild::get(&name).map(|file| (PathBuf::from(name), file.data.into_owned()))
+            }))
+        }
+    }
+}
+
+fn content_type(file: &Path) -> &'static str {
+    let extension = file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match extension.as_str() {
+        "html" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+fn respond(status: StatusCode, message: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from(message))
+        .expect("Should be a valid response")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn get(dir: &Path, path: &str) -> Response<Body> {
+        handle(
+            &Files::Dir(dir.to_path_buf()),
+            Request::get(path)
+                .body(Body::empty())
+                .expect("Should be a valid request"),
+        )
+        .await
+    }
+
+    async fn body(response: Response<Body>) -> String {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_build_files_are_served() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = dir.path().join("build");
+        std::fs::create_dir_all(build.join("scripts")).unwrap();
+        std::fs::write(build.join(INDEX_FILE_NAME), "<html>").unwrap();
+        std::fs::write(build.join("scripts").join("main.js"), "main()").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        assert!(
+            LocalWebUi::new(dir.path().to_path_buf(), 11473, false).is_err(),
+            "No index.html"
+        );
+        let web_ui = LocalWebUi::new(build.clone(), 11473, false).unwrap();
+        assert_eq!("http://127.0.0.1:11473/web/", web_ui.url().as_str());
+
+        let index = get(&build, "/web/").await;
+        assert_eq!(StatusCode::OK, index.status());
+        assert_eq!(
+            "text/html; charset=utf-8",
+            index.headers()[header::CONTENT_TYPE]
+        );
+        assert_eq!("<html>", body(index).await);
+        let script = get(&build, "/web/scripts/main.js").await;
+        assert_eq!(
+            "text/javascript; charset=utf-8",
+            script.headers()[header::CONTENT_TYPE]
+        );
+        assert_eq!("main()", body(script).await);
+        assert_eq!(
+            StatusCode::MOVED_PERMANENTLY,
+            get(&build, "/web").await.status()
+        );
+
+        for path in [
+            "/web/missing.js",
+            "/web/../secret.txt",
+            "/web/%2E%2E/secret.txt",
+            "/web/scripts/..%2F..%2Fsecret.txt",
+            "/secret.txt",
+        ] {
+            assert_eq!(
+                StatusCode::NOT_FOUND,
+                get(&build, path).await.status(),
+                "{path}"
+            );
+        }
+    }
+
+    #[cfg(feature = "embedded-web")]
+    #[tokio::test]
+    async fn test_embedded_build_is_served() {
+        let get = |path: &'static str| {
+            handle(
+                &Files::Embedded,
+                Request::get(path)
+                    .body(Body::empty())
+                    .expect("Should be a valid request"),
+            )
+        };
+
+        let index = get("/web/").await;
+        assert_eq!(StatusCode::OK, index.status());
+        assert_eq!(
+            "text/html; charset=utf-8",
+            index.headers()[header::CONTENT_TYPE]
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            get("/web/../Cargo.toml").await.status()
+        );
+        assert_eq!(
+            "http://127.0.0.1:11473/web/",
+            LocalWebUi::embedded(11473, false).url().as_str()
+        );
+    }
+}
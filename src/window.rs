@@ -0,0 +1,234 @@
+// Copyright (C) 2017-2026 Smart Code OOD 203358507
+
+//! The app window of Stremio Web, opened from the tray instead of the browser with the `openInWindow` setting.
+//!
+//! It's the webview of the system (WebView2 on Windows, WKWebView on macOS, WebKitGTK on Linux),
+//! only in the builds with the `webview` feature, see [`WebUiWindow`].
+//! Its size and position are remembered in the state directory, see [`WindowGeometry`].
+
+use std::path::Path;
+
+use anyhow::{Context, Error};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// The smallest size of the window Stremio Web is usable in, in logical pixels
+pub const MIN_SIZE: (f64, f64) = (400.0, 300.0);
+
+/// The size and the position of the window, in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGeometry {
+    /// The size of the content, without the decorations
+    pub width: f64,
+    pub height: f64,
+    /// The position of the window, `None` when the platform doesn't tell it, e.g. on Wayland
+    pub position: Option<(f64, f64)>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            width: 1280.0,
+            height: 800.0,
+            position: None,
+        }
+    }
+}
+
+impl WindowGeometry {
+    /// The saved geometry, the default one without a file or when it's invalid.
+    pub fn load(file: &Path) -> Self {
+        let json = match std::fs::read(file) {
+            Ok(json) => json,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                warn!("Failed to read {}: {err}", file.display());
+                return Self::default();
+            }
+        };
+
+        match serde_json::from_slice::<Self>(&json) {
+            Ok(geometry) if geometry.is_valid() => geometry,
+            Ok(_) => {
+                warn!("Ignoring the invalid window geometry in {}", file.display());
+                Self::default()
+            }
+            Err(err) => {
+                warn!("Failed to parse {}: {err}", file.display());
+                Self::default()
+            }
+        }
+    }
+
+    /// Saves the geometry for the next window.
+    ///
+    /// # Errors
+    ///
+    /// When the file can't be written.
+    pub fn save(&self, file: &Path) -> Result<(), Error> {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_vec_pretty(self).context("Failed to serialize the geometry")?;
+
+        std::fs::write(file, json).context(format!("Failed to write {}", file.display()))
+    }
+
+    fn is_valid(&self) -> bool {
+        let position = self.position.unwrap_or_default();
+
+        self.width.is_finite()
+            && self.height.is_finite()
+            && self.width >= MIN_SIZE.0
+            && self.height >= MIN_SIZE.1
+            && position.0.is_finite()
+            && position.1.is_finite()
+    }
+}
+
+#[cfg(feature = "webview")]
+pub use webview::WebUiWindow;
+
+#[cfg(feature = "webview")]
+mod webview {
+    use std::path::PathBuf;
+
+    use anyhow::{Context, Error};
+    use log::error;
+    use tao::{
+        dpi::{LogicalPosition, LogicalSize},
+        event::{Event, WindowEvent},
+        event_loop::EventLoopWindowTarget,
+        window::WindowBuilder,
+    };
+    use url::Url;
+    use wry::webview::{WebView, WebViewBuilder};
+
+    use super::{WindowGeometry, MIN_SIZE};
+
+    /// The app window, created by [`WebUiWindow::open`] and dropped once the user closes it
+    pub struct WebUiWindow {
+        /// Where the [`WindowGeometry`] is saved
+        geometry_file: PathBuf,
+        webview: Option<WebView>,
+    }
+
+    impl WebUiWindow {
+        pub fn new(geometry_file: PathBuf) -> Self {
+            Self {
+                geometry_file,
+                webview: None,
+            }
+        }
+
+        /// Opens the url in the window, it's created with the saved geometry when it's not open.
+        ///
+        /// # Errors
+        ///
+        /// When the window or the webview can't be created, e.g. without the webview of the system.
+        pub fn open<T>(
+            &mut self,
+            event_loop: &EventLoopWindowTarget<T>,
+            url: &Url,
+        ) -> Result<(), Error> {
+            if let Some(webview) = &self.webview {
+                webview.load_url(url.as_str());
+                let window = webview.window();
+                window.set_minimized(false);
+                window.set_visible(true);
+                window.set_focus();
+
+                return Ok(());
+            }
+
+            let geometry = WindowGeometry::load(&self.geometry_file);
+            let mut builder = WindowBuilder::new()
+                .with_title("Stremio")
+                .with_inner_size(LogicalSize::new(geometry.width, geometry.height))
+                .with_min_inner_size(LogicalSize::new(MIN_SIZE.0, MIN_SIZE.1));
+            if let Some((x, y)) = geometry.position {
+                builder = builder.with_position(LogicalPosition::new(x, y));
+            }
+            let window = builder
+                .build(event_loop)
+                .context("Failed to create the window")?;
+            let webview = WebViewBuilder::new(window)
+                .and_then(|builder| builder.with_url(url.as_str()))
+                .and_then(WebViewBuilder::build)
+                .context("Failed to create the webview")?;
+            self.webview = Some(webview);
+
+            Ok(())
+        }
+
+        /// Closes the window when the user asks to or the event loop exits, its geometry is saved then.
+        pub fn handle<T>(&mut self, event: &Event<T>) {
+            match event {
+                Event::WindowEvent {
+                    window_id,
+                    event: WindowEvent::CloseRequested,
+                    ..
+                } if self.webview.as_ref().map(|webview| webview.window().id())
+                    == Some(*window_id) =>
+                {
+                    self.close()
+                }
+                Event::LoopDestroyed => self.close(),
+                _ => {}
+            }
+        }
+
+        fn close(&mut self) {
+            let Some(webview) = self.webview.take() else {
+                return;
+            };
+
+            let window = webview.window();
+            let scale_factor = window.scale_factor();
+            let size = window.inner_size().to_logical::<f64>(scale_factor);
+            let position = window
+                .outer_position()
+                .ok()
+                .map(|position| position.to_logical::<f64>(scale_factor))
+                .map(|position| (position.x, position.y));
+            let geometry = WindowGeometry {
+                width: size.width,
+                height: size.height,
+                position,
+            };
+            if let Err(err) = geometry.save(&self.geometry_file) {
+                error!("{err:#}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_window_geometry_is_remembered() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("window.json");
+        assert_eq!(WindowGeometry::default(), WindowGeometry::load(&file));
+
+        let geometry = WindowGeometry {
+            width: 1024.0,
+            height: 640.0,
+            position: Some((-1600.0, 120.0)),
+        };
+        geometry.save(&file).unwrap();
+        assert_eq!(geometry, WindowGeometry::load(&file));
+
+        WindowGeometry {
+            width: 0.0,
+            ..geometry
+        }
+        .save(&file)
+        .unwrap();
+        assert_eq!(WindowGeometry::default(), WindowGeometry::load(&file));
+    }
+}
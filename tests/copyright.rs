@@ -1,4 +1,4 @@
-//! Copyright (C) 2017-2024 Smart Code OOD 203358507
+//! Copyright (C) 2017-2026 Smart Code OOD 203358507
 
 use std::{
     env, fs,
@@ -11,7 +11,7 @@ use walkdir::WalkDir;
 
 #[test]
 fn copyright() {
-    let include_dirs = vec!["src", "tests", ".github/workflows"];
+    let include_dirs = ["src", "tests", ".github/workflows"];
     let project_root = env!("CARGO_MANIFEST_DIR");
     let current_year = Utc::now().year().to_string();
     let regex_pattern = format!(
@@ -42,7 +42,7 @@ fn copyright() {
                 .unwrap_or(false);
 
             if parent_dir_included {
-                if let Ok(file) = fs::File::open(&path) {
+                if let Ok(file) = fs::File::open(path) {
                     let reader = io::BufReader::new(file);
                     if let Some(first_line) = reader.lines().next() {
                         let line = first_line.unwrap();
@@ -0,0 +1,210 @@
+//! Copyright (C) 2017-2026 Smart Code OOD 203358507
+//!
+//! The lifecycle of server.js with a stub in place of the real one, without the Stremio binaries.
+//!
+//! The test binary is its own stub: it's copied as the runtime of a temporary bins directory
+//! and, when started by the [`Server`] with [`STUB_ENV`], it answers the health checks
+//! on the port of server.js until it's asked to crash with `GET /exit`.
+//! It listens on [`STUB_PORT`], not to be confused with a real server.js.
+//! The same stub is then run by an embedded service, see [`ServiceHandle`].
+
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{ensure, Context};
+use clap::Parser;
+use stremio_service::{
+    app,
+    args::Args,
+    directories::Directories,
+    server::{self, Config, Server, PORT_ENV},
+    status::ServerTrayStatus,
+    Application, ServiceHandle,
+};
+use tempfile::TempDir;
+
+/// Set for the stub by the test, as an experiment exported to server.js
+const STUB_ENV: &str = "STREMIO_SERVICE_STUB_SERVER";
+const STUB_PORT: u16 = 11490;
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+fn main() -> anyhow::Result<()> {
+    if std::env::var_os(STUB_ENV).is_some() {
+        return stub_server();
+    }
+
+    tokio::runtime::Runtime::new()?.block_on(lifecycle())?;
+    println!("test lifecycle ... ok");
+
+    Ok(())
+}
+
+/// Answers every request with `200 OK` and exits with `1` on `GET /exit`
+fn stub_server() -> anyhow::Result<()> {
+    let port = std::env::var(PORT_ENV).context(format!("{PORT_ENV} is not set"))?;
+    let address = format!("127.0.0.1:{port}");
+    let listener = TcpListener::bind(&address).context(format!("Cannot listen on {address}"))?;
+
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let exit = read_request(&stream)?.starts_with("GET /exit ");
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}")?;
+        stream.flush()?;
+        if exit {
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the request and returns its request line
+fn read_request(stream: &TcpStream) -> anyhow::Result<String> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    Ok(request_line)
+}
+
+async fn lifecycle() -> anyhow::Result<()> {
+    let url = server::server_url(STUB_PORT);
+    if server::is_ready(&url).await {
+        println!("test lifecycle ... ignored, another server is running on {url}");
+        return Ok(());
+    }
+
+    let bins_dir = stub_bins_dir()?;
+    let config = Config::new(bins_dir.path().to_path_buf())?
+        .with_experiments(BTreeMap::from([(STUB_ENV.to_owned(), "1".to_owned())]))
+        .with_port(STUB_PORT)?;
+    let server = Server::new(config);
+    ensure!(server.url() == url);
+
+    // start → health check
+    server.start()?;
+    ensure!(
+        server::wait_until_ready(&url, TIMEOUT).await,
+        "The stub is not ready"
+    );
+    ensure!(server.is_running(), "The stub should be running");
+    ensure!(server.info().await.status == ServerTrayStatus::Running);
+
+    // crash
+    reqwest::get(format!("{url}/exit")).await?;
+    wait_for(|| !server.is_running()).await?;
+    ensure!(
+        !server::is_ready(&url).await,
+        "The crashed stub is still ready"
+    );
+    let status = server.info().await.status;
+    ensure!(
+        status == ServerTrayStatus::Restarting { attempt: 1 },
+        "Unexpected status {status}"
+    );
+
+    // automatic restart
+    ensure!(
+        server::wait_until_ready(&url, TIMEOUT).await,
+        "The restarted stub is not ready"
+    );
+
+    // stop
+    server.stop().await?;
+    ensure!(!server.is_running(), "The stub should be stopped");
+    ensure!(server.info().await.status == ServerTrayStatus::Stopped);
+    let deadline = Instant::now() + TIMEOUT;
+    while server::is_ready(&url).await {
+        ensure!(Instant::now() < deadline, "The stopped stub is still ready");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    embedded(bins_dir.path()).await?;
+
+    Ok(())
+}
+
+/// Starts and stops the stub with the service embedded in-process
+async fn embedded(bins_dir: &Path) -> anyhow::Result<()> {
+    let dir = bins_dir.join("service");
+    let mut directories = Directories::new(
+        Some(dir.join("config")),
+        Some(dir.join("state")),
+        Some(dir.join("cache")),
+        None,
+    )?;
+    directories.home = dir.join("home");
+    // the experiments of the settings replace the ones of the server config
+    std::fs::create_dir_all(&directories.config)?;
+    std::fs::write(
+        directories.settings_file(),
+        serde_json::json!({ "experiments": { STUB_ENV: "1" } }).to_string(),
+    )?;
+    let args = Args::parse_from([
+        "stremio-service",
+        "--skip-updater",
+        "--no-control-api",
+        "--server-port",
+        &STUB_PORT.to_string(),
+    ]);
+    let config = app::Config::new(args, directories, Config::new(bins_dir.to_owned())?)?;
+
+    // start → the status of the health check
+    let service = ServiceHandle::start(Application::new(config)).await?;
+    let mut status = service.subscribe_status();
+    tokio::time::timeout(TIMEOUT, async {
+        while status.borrow_and_update().status != ServerTrayStatus::Running {
+            status.changed().await?;
+        }
+
+        Ok::<_, anyhow::Error>(())
+    })
+    .await
+    .context("The embedded stub is not running")??;
+    ensure!(service.info().await.url == server::server_url(STUB_PORT));
+
+    // stop
+    service.stop().await?;
+    ensure!(status.borrow().status == ServerTrayStatus::Stopped);
+
+    Ok(())
+}
+
+/// The bins directory of the [`Config`], with this test as the runtime and empty files for the rest
+fn stub_bins_dir() -> anyhow::Result<TempDir> {
+    let dir = tempfile::tempdir()?;
+
+    let exe_suffix = std::env::consts::EXE_SUFFIX;
+    std::fs::copy(
+        std::env::current_exe()?,
+        dir.path().join(format!("stremio-runtime{exe_suffix}")),
+    )?;
+    for file in [
+        "server.js".to_owned(),
+        format!("ffmpeg{exe_suffix}"),
+        format!("ffprobe{exe_suffix}"),
+    ] {
+        std::fs::write(dir.path().join(file), "")?;
+    }
+
+    Ok(dir)
+}
+
+async fn wait_for(condition: impl Fn() -> bool) -> anyhow::Result<()> {
+    let deadline = Instant::now() + TIMEOUT;
+    while !condition() {
+        ensure!(Instant::now() < deadline, "Timed out after {TIMEOUT:?}");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    Ok(())
+}